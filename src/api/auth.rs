@@ -0,0 +1,42 @@
+//! Bearer-token guard for the administrative endpoints.
+
+use crate::api::handlers::AppError;
+use crate::api::node::GlobalNode;
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+
+/// Extractor admitting only requests that carry
+/// `Authorization: Bearer <NodeConfiguration.admin_token>`. Every request
+/// is refused while no token is configured.
+pub struct AdminAuth;
+
+#[async_trait]
+impl FromRequestParts<GlobalNode> for AdminAuth {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, node: &GlobalNode) -> Result<Self, AppError> {
+        let Some(expected) = node.config.admin_token.as_deref() else {
+            return Err(AppError::Forbidden(
+                "Admin endpoints are disabled; set BDLD_ADMIN_TOKEN".to_string(),
+            ));
+        };
+        let presented = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match presented {
+            Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(AdminAuth),
+            _ => Err(AppError::Unauthorized(
+                "Missing or invalid bearer token".to_string(),
+            )),
+        }
+    }
+}
+
+/// Compares every byte so timing does not reveal the matching prefix.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -1,16 +1,26 @@
+use crate::api::auth::AdminAuth;
 use crate::api::node::GlobalNode;
 use crate::api::types::{
-    ApplyLaborRequest, ApplyLaborResponse, BtcPegResponse, DividendRequest, DividendResponse,
-    ErrorResponse, HealthResponse, LaborHistoryResponse, LaborStateResponse, LaborValueResponse,
-    NodeConfig, ParticipantState, PayoutExecuteRequest, PayoutHistoryResponse, PoolBalanceResponse,
-    RBIComponents, RBIResponse, StatusResponse, VelocityResponse, VolatilityResponse,
+    AddParticipantAddressRequest, ApplyLaborRequest, ApplyLaborResponse, BtcPegResponse,
+    CosignersResponse, DividendRequest, DividendResponse, ErrorResponse, FeeBumpRequest,
+    HealthResponse, LaborHistoryResponse, LaborStateResponse, LaborValueResponse, NodeConfig,
+    ParticipantState, PayoutExecuteRequest, PayoutHistoryResponse, PayoutTransitionsResponse,
+    PoolBalanceResponse, PoolUtxosRequest, PoolUtxosResponse, RBIComponents, RBIResponse,
+    RegisterParticipantRequest, SignedPsbtRequest, SignedPsbtResponse, StatusResponse,
+    VelocityResponse, VolatilityResponse,
+};
+use crate::audit_log::{AuditChainStatus, AuditEntry};
+use crate::disbursement::{
+    decode_psbt, parse_address, AddressRole, BatchPayoutRequest, BatchPayoutResult,
+    DisbursementError, PayoutStatus, PayoutTransactionResult, PoolUtxo, PoolUtxoRecord,
 };
-use crate::disbursement::PayoutTransactionResult;
 use crate::rbi_engine::DistributionPoolState;
 use crate::simulation::state::SimulationParticipant;
+use crate::storage::{ParticipantPage, ParticipantRecord, RegistryError};
+use crate::sybil_clustering::ParticipantSybilRisk;
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header::WWW_AUTHENTICATE, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
@@ -43,6 +53,7 @@ pub async fn get_status(State(node): State<GlobalNode>) -> Json<StatusResponse>
             rate_limit_per_minute: config.rate_limit_per_minute,
             jwt_auth_enabled: config.jwt_auth_enabled,
             cors_enabled: config.cors_enabled,
+            admin_api_enabled: config.admin_token.is_some(),
         },
     })
 }
@@ -54,7 +65,7 @@ pub async fn get_rbi(State(node): State<GlobalNode>) -> Result<Json<RBIResponse>
         total_distributed_sats: node.get_pool_balance(),
         average_participant_velocity: 1.0,
         epoch_duration_days: 14,
-        participants: vec![],
+        participants: node.get_participant_snapshots(),
     };
 
     let mut engine = node
@@ -63,7 +74,7 @@ pub async fn get_rbi(State(node): State<GlobalNode>) -> Result<Json<RBIResponse>
         .map_err(|_| AppError::Internal("Failed to acquire RBI engine lock".to_string()))?;
 
     let snapshot = engine
-        .calculate_rbi(&pool_state, 800_000)
+        .calculate_rbi(&pool_state, node.get_block_height())
         .map_err(|e| AppError::Internal(format!("Failed to compute RBI: {}", e)))?;
 
     Ok(Json(RBIResponse {
@@ -91,6 +102,38 @@ pub async fn get_pool_balance(State(node): State<GlobalNode>) -> Json<PoolBalanc
     })
 }
 
+/// List the spendable pool UTXOs payouts are funded from
+pub async fn get_pool_utxos_handler(
+    _: AdminAuth,
+    State(node): State<GlobalNode>,
+) -> Json<PoolUtxosResponse> {
+    Json(pool_utxos_response(&node))
+}
+
+/// Replace the spendable pool UTXO set; the pool balance follows its total
+pub async fn set_pool_utxos_handler(
+    _: AdminAuth,
+    State(node): State<GlobalNode>,
+    Json(req): Json<PoolUtxosRequest>,
+) -> Result<Json<PoolUtxosResponse>, AppError> {
+    let utxos = req
+        .utxos
+        .into_iter()
+        .map(PoolUtxo::try_from)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::Disbursement(DisbursementError::InvalidUtxo(e)))?;
+    node.set_pool_utxos(utxos)?;
+    Ok(Json(pool_utxos_response(&node)))
+}
+
+fn pool_utxos_response(node: &GlobalNode) -> PoolUtxosResponse {
+    let utxos = node.get_pool_utxos();
+    PoolUtxosResponse {
+        total_sats: utxos.iter().map(|u| u.value_sats).sum(),
+        utxos: utxos.iter().map(PoolUtxoRecord::from).collect(),
+    }
+}
+
 /// Calculate dividend for a participant
 pub async fn get_participant_dividend(
     State(node): State<GlobalNode>,
@@ -125,10 +168,22 @@ pub async fn get_participant_dividend(
 
 /// Execute payout request and generate PSBT / raw transaction
 pub async fn execute_payout_handler(
+    _: AdminAuth,
     State(node): State<GlobalNode>,
     Json(req): Json<PayoutExecuteRequest>,
 ) -> Result<Json<PayoutTransactionResult>, AppError> {
-    let result = node.execute_payout(&req).map_err(AppError::InvalidInput)?;
+    let result = node.execute_payout(&req)?;
+
+    Ok(Json(result))
+}
+
+/// Build batched payout transactions for an epoch's participant allocations
+pub async fn execute_batch_payout_handler(
+    _: AdminAuth,
+    State(node): State<GlobalNode>,
+    Json(req): Json<BatchPayoutRequest>,
+) -> Result<Json<BatchPayoutResult>, AppError> {
+    let result = node.execute_batch_payout(&req)?;
 
     Ok(Json(result))
 }
@@ -145,6 +200,92 @@ pub async fn get_payout_handler(
     Ok(Json(payout))
 }
 
+/// Submit a (partially) signed PSBT for a payout
+pub async fn submit_signed_psbt_handler(
+    _: AdminAuth,
+    State(node): State<GlobalNode>,
+    Path(payout_id): Path<String>,
+    Json(req): Json<SignedPsbtRequest>,
+) -> Result<Json<SignedPsbtResponse>, AppError> {
+    node.get_payout(&payout_id)
+        .ok_or_else(|| AppError::NotFound(format!("Payout with id '{}' not found", payout_id)))?;
+
+    let psbt = decode_psbt(&req.psbt_base64)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid PSBT: {}", e)))?;
+
+    let payout = node
+        .submit_signed_psbt(&payout_id, &psbt)
+        .map_err(AppError::InvalidInput)?;
+
+    let txid = payout.final_tx_hex.is_some().then(|| payout.txid.clone());
+    Ok(Json(SignedPsbtResponse {
+        payout_id: payout.payout_id,
+        status: payout.status,
+        raw_tx_hex: payout.final_tx_hex,
+        txid,
+    }))
+}
+
+/// Broadcast a finalized payout through the configured backend
+pub async fn broadcast_payout_handler(
+    _: AdminAuth,
+    State(node): State<GlobalNode>,
+    Path(payout_id): Path<String>,
+) -> Result<Json<PayoutTransactionResult>, AppError> {
+    let payout = node
+        .get_payout(&payout_id)
+        .ok_or_else(|| AppError::NotFound(format!("Payout with id '{}' not found", payout_id)))?;
+    if payout.status != PayoutStatus::Finalized {
+        return Err(AppError::InvalidInput(format!(
+            "Payout '{}' is {}, not finalized",
+            payout_id, payout.status
+        )));
+    }
+    let broadcaster = node.broadcaster.clone().ok_or_else(|| {
+        AppError::Unavailable("No broadcast backend configured; set BDLD_ESPLORA_URL".to_string())
+    })?;
+
+    // Permanent rejections come back as a failed payout; anything else left
+    // it finalized and is worth retrying
+    let payout = tokio::task::spawn_blocking(move || {
+        node.broadcast_payout(&payout_id, broadcaster.as_ref())
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("Broadcast task failed: {}", e)))?
+    .map_err(AppError::Unavailable)?;
+
+    Ok(Json(payout))
+}
+
+/// Create an unsigned RBF replacement (or CPFP child) for a stuck payout
+pub async fn bump_payout_fee_handler(
+    _: AdminAuth,
+    State(node): State<GlobalNode>,
+    Path(payout_id): Path<String>,
+    Json(req): Json<FeeBumpRequest>,
+) -> Result<Json<PayoutTransactionResult>, AppError> {
+    node.get_payout(&payout_id)
+        .ok_or_else(|| AppError::NotFound(format!("Payout with id '{}' not found", payout_id)))?;
+
+    let bump = node.bump_payout_fee(&payout_id, req.fee_rate_sats_per_vbyte)?;
+
+    Ok(Json(bump))
+}
+
+/// Get the lifecycle transition log of a payout
+pub async fn get_payout_transitions_handler(
+    State(node): State<GlobalNode>,
+    Path(payout_id): Path<String>,
+) -> Result<Json<PayoutTransitionsResponse>, AppError> {
+    node.get_payout(&payout_id)
+        .ok_or_else(|| AppError::NotFound(format!("Payout with id '{}' not found", payout_id)))?;
+
+    Ok(Json(PayoutTransitionsResponse {
+        transitions: node.get_payout_transitions(&payout_id),
+        payout_id,
+    }))
+}
+
 /// Get payout history
 pub async fn get_payout_history_handler(
     State(node): State<GlobalNode>,
@@ -158,6 +299,33 @@ pub async fn get_payout_history_handler(
     })
 }
 
+/// List pool cosigners with the payouts each still has to sign
+pub async fn get_cosigners_handler(State(node): State<GlobalNode>) -> Json<CosignersResponse> {
+    Json(CosignersResponse {
+        threshold: node
+            .disbursement_engine
+            .config
+            .pool_descriptor
+            .as_ref()
+            .map(|d| d.threshold()),
+        cosigners: node.cosigner_queues(),
+    })
+}
+
+/// Payouts awaiting a signature from one cosigner, by master key fingerprint
+pub async fn get_cosigner_payouts_handler(
+    State(node): State<GlobalNode>,
+    Path(fingerprint): Path<String>,
+) -> Json<PayoutHistoryResponse> {
+    let payouts = node.payouts_awaiting_cosigner(&fingerprint);
+    let total_count = payouts.len();
+
+    Json(PayoutHistoryResponse {
+        payouts,
+        total_count,
+    })
+}
+
 /// Get velocity data for a participant
 pub async fn get_participant_velocity(
     State(_node): State<GlobalNode>,
@@ -392,23 +560,280 @@ pub async fn get_btc_peg(State(node): State<GlobalNode>) -> Json<BtcPegResponse>
     })
 }
 
+/// Participant listing query
+#[derive(Debug, Deserialize)]
+pub struct ParticipantListQuery {
+    #[serde(default)]
+    pub page: u32,
+    #[serde(default = "default_page_size")]
+    pub page_size: u32,
+    #[serde(default)]
+    pub include_inactive: bool,
+}
+
+/// List registered participants, one page at a time
+pub async fn list_participants_handler(
+    _: AdminAuth,
+    State(node): State<GlobalNode>,
+    Query(query): Query<ParticipantListQuery>,
+) -> Result<Json<ParticipantPage>, AppError> {
+    let page = node.registry()?.list_participants(
+        query.page,
+        query.page_size.min(100),
+        query.include_inactive,
+    )?;
+    Ok(Json(page))
+}
+
+/// Register a participant and its addresses
+pub async fn register_participant_handler(
+    _: AdminAuth,
+    State(node): State<GlobalNode>,
+    Json(req): Json<RegisterParticipantRequest>,
+) -> Result<(StatusCode, Json<ParticipantRecord>), AppError> {
+    for address in &req.addresses {
+        check_participant_address(&node, address)?;
+    }
+    let record = node
+        .registry()?
+        .register_participant(&req.participant_id, &req.addresses)?;
+    Ok((StatusCode::CREATED, Json(record)))
+}
+
+/// Get a registered participant, including deactivated ones
+pub async fn get_participant_handler(
+    _: AdminAuth,
+    State(node): State<GlobalNode>,
+    Path(participant_id): Path<String>,
+) -> Result<Json<ParticipantRecord>, AppError> {
+    node.registry()?
+        .get_participant(&participant_id)?
+        .map(Json)
+        .ok_or(AppError::Registry(RegistryError::ParticipantNotFound(
+            participant_id,
+        )))
+}
+
+/// Add an address to a participant
+pub async fn add_participant_address_handler(
+    _: AdminAuth,
+    State(node): State<GlobalNode>,
+    Path(participant_id): Path<String>,
+    Json(req): Json<AddParticipantAddressRequest>,
+) -> Result<Json<ParticipantRecord>, AppError> {
+    check_participant_address(&node, &req.address)?;
+    let record =
+        node.registry()?
+            .add_participant_address(&participant_id, &req.address, req.position)?;
+    Ok(Json(record))
+}
+
+/// Remove an address from a participant
+pub async fn remove_participant_address_handler(
+    _: AdminAuth,
+    State(node): State<GlobalNode>,
+    Path((participant_id, address)): Path<(String, String)>,
+) -> Result<Json<ParticipantRecord>, AppError> {
+    let record = node
+        .registry()?
+        .remove_participant_address(&participant_id, &address)?;
+    Ok(Json(record))
+}
+
+/// Deactivate a participant from the current block height on; its
+/// addresses stay reserved
+pub async fn deactivate_participant_handler(
+    _: AdminAuth,
+    State(node): State<GlobalNode>,
+    Path(participant_id): Path<String>,
+) -> Result<Json<ParticipantRecord>, AppError> {
+    let record = node
+        .registry()?
+        .deactivate_participant(&participant_id, node.get_block_height())?;
+    Ok(Json(record))
+}
+
+/// Sybil clustering result for an active participant: linked participants,
+/// the co-spends linking them and the risk score
+pub async fn get_participant_sybil_handler(
+    _: AdminAuth,
+    State(node): State<GlobalNode>,
+    Path(participant_id): Path<String>,
+) -> Result<Json<ParticipantSybilRisk>, AppError> {
+    node.sybil_report()
+        .get(&participant_id)
+        .cloned()
+        .map(Json)
+        .ok_or(AppError::Registry(RegistryError::ParticipantNotFound(
+            participant_id,
+        )))
+}
+
+/// Audit log query: entries after `after_seq`, oldest first
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    #[serde(default)]
+    pub after_seq: u64,
+    #[serde(default = "default_page_size")]
+    pub limit: u32,
+}
+
+/// Page through the audit log
+pub async fn get_audit_log_handler(
+    _: AdminAuth,
+    State(node): State<GlobalNode>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Json<Vec<AuditEntry>>, AppError> {
+    let entries = node
+        .registry()?
+        .audit_entries(query.after_seq, query.limit.min(1000))?;
+    Ok(Json(entries))
+}
+
+/// A chain head recorded from an earlier verification, given as
+/// `?entries=42&head_hash=...`
+#[derive(Debug, Deserialize)]
+pub struct AuditVerifyQuery {
+    pub entries: Option<u64>,
+    pub head_hash: Option<String>,
+}
+
+/// Recompute the audit hash chain, which must still contain the given and
+/// the anchored heads; 500 `audit_chain_broken` names the first tampered
+/// entry
+pub async fn verify_audit_log_handler(
+    _: AdminAuth,
+    State(node): State<GlobalNode>,
+    Query(query): Query<AuditVerifyQuery>,
+) -> Result<Json<AuditChainStatus>, AppError> {
+    let published = match (query.entries, query.head_hash) {
+        (Some(entries), Some(head_hash)) => Some(AuditChainStatus { entries, head_hash }),
+        (None, None) => None,
+        _ => {
+            return Err(AppError::InvalidInput(
+                "entries and head_hash must be given together".into(),
+            ))
+        }
+    };
+    Ok(Json(node.verify_audit_chain(published.as_ref())?))
+}
+
+/// Participant addresses receive payouts, so they must be valid on the
+/// pool's network
+fn check_participant_address(node: &GlobalNode, address: &str) -> Result<(), AppError> {
+    let network = node.disbursement_engine.config.network;
+    parse_address(address, network, AddressRole::Recipient)
+        .map(|_| ())
+        .map_err(|e| AppError::Disbursement(e.into()))
+}
+
 /// Application error type
 pub enum AppError {
     NotFound(String),
     Internal(String),
     InvalidInput(String),
+    Unauthorized(String),
+    Forbidden(String),
+    /// A backend the request depends on is missing or failed; retry later
+    Unavailable(String),
+    Disbursement(DisbursementError),
+    Registry(RegistryError),
+}
+
+impl From<DisbursementError> for AppError {
+    fn from(err: DisbursementError) -> Self {
+        AppError::Disbursement(err)
+    }
+}
+
+impl From<RegistryError> for AppError {
+    fn from(err: RegistryError) -> Self {
+        AppError::Registry(err)
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
-            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
-            AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
-            AppError::InvalidInput(msg) => (StatusCode::BAD_REQUEST, msg),
+        let challenge = matches!(self, AppError::Unauthorized(_));
+        let body = match self {
+            AppError::NotFound(msg) => error_body(StatusCode::NOT_FOUND, "not_found", msg),
+            AppError::Internal(msg) => {
+                error_body(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", msg)
+            }
+            AppError::InvalidInput(msg) => {
+                error_body(StatusCode::BAD_REQUEST, "invalid_input", msg)
+            }
+            AppError::Unauthorized(msg) => {
+                error_body(StatusCode::UNAUTHORIZED, "unauthorized", msg)
+            }
+            AppError::Forbidden(msg) => error_body(StatusCode::FORBIDDEN, "forbidden", msg),
+            AppError::Unavailable(msg) => {
+                error_body(StatusCode::SERVICE_UNAVAILABLE, "unavailable", msg)
+            }
+            AppError::Registry(err) => {
+                error_body(registry_status(&err), err.code(), err.to_string())
+            }
+            AppError::Disbursement(err) => {
+                let status = disbursement_status(&err);
+                let (status, Json(body)) = error_body(status, err.code(), err.to_string());
+                match err {
+                    DisbursementError::PolicyRejected { findings, .. } => {
+                        (status, Json(body.with_findings(findings)))
+                    }
+                    _ => (status, Json(body)),
+                }
+            }
         };
 
-        let body = Json(ErrorResponse::new(status.to_string(), message));
+        let mut response = body.into_response();
+        if challenge {
+            response
+                .headers_mut()
+                .insert(WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        }
+        response
+    }
+}
+
+fn error_body(
+    status: StatusCode,
+    code: &str,
+    message: String,
+) -> (StatusCode, Json<ErrorResponse>) {
+    (status, Json(ErrorResponse::new(status, code, message)))
+}
+
+/// HTTP status for a disbursement failure: refusals the caller can fix by
+/// changing the request are 4xx, a PSBT that cannot be assembled is ours.
+fn disbursement_status(err: &DisbursementError) -> StatusCode {
+    match err {
+        DisbursementError::PolicyRejected { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        DisbursementError::InsufficientFunds { .. } => StatusCode::CONFLICT,
+        DisbursementError::InvalidAddress(_)
+        | DisbursementError::InvalidUtxo(_)
+        | DisbursementError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+        DisbursementError::PsbtBuild(_) | DisbursementError::Storage(_) => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
 
-        (status, body).into_response()
+/// HTTP status for a refused participant registry operation.
+fn registry_status(err: &RegistryError) -> StatusCode {
+    match err {
+        RegistryError::Unavailable | RegistryError::ReadOnly => StatusCode::SERVICE_UNAVAILABLE,
+        RegistryError::ParticipantExists(_)
+        | RegistryError::ParticipantInactive(_)
+        | RegistryError::AddressInUse { .. }
+        | RegistryError::PositionInUse { .. }
+        | RegistryError::StakeExists(_)
+        | RegistryError::UtxoReserved { .. } => StatusCode::CONFLICT,
+        RegistryError::ParticipantNotFound(_)
+        | RegistryError::AddressNotFound { .. }
+        | RegistryError::StakeNotFound(_) => StatusCode::NOT_FOUND,
+        RegistryError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+        RegistryError::AuditChainBroken(_) | RegistryError::Storage(_) => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
     }
 }
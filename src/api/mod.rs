@@ -1,3 +1,4 @@
+pub mod auth;
 pub mod handlers;
 pub mod node;
 pub mod server;
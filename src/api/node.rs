@@ -1,14 +1,25 @@
-use crate::api::types::LaborHistoryEntry;
+use crate::api::types::{CosignerQueue, LaborHistoryEntry};
+use crate::audit_log::{AuditChainStatus, AuditEvent};
 use crate::disbursement::{
-    DisbursementConfig, DisbursementEngine, PayoutRequest, PayoutTransactionResult,
+    BatchPayoutRequest, BatchPayoutResult, Broadcaster, ChangeIndexStore, ConfirmationSource,
+    DisbursementConfig, DisbursementEngine, DisbursementError, FeeBumpKind, FeeBumpLink,
+    LifecycleError, PayoutAllocation, PayoutHistory, PayoutRequest, PayoutStatus,
+    PayoutTransactionResult, PayoutTransition, PoolUtxo,
 };
 use crate::economic_oracle::MockEconomicDataProvider;
-use crate::rbi_engine::RBIEngine;
+use crate::rbi_engine::{ParticipantSnapshot, RBIEngine};
 use crate::simulation::state::SimulationParticipant;
 use crate::sqlite_participant_registry::SqliteParticipantRegistry;
+use crate::storage::{AuditAnchorFile, PayoutLock, RegistryError, RegistryStorage};
+use crate::sybil_clustering::{
+    analyze_clusters, apply_sybil_weighting, CoSpend, CoSpendSource, SybilConfig, SybilReport,
+};
+use crate::velocity_analyzer::VelocityError;
+use bitcoin::psbt::PartiallySignedTransaction;
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex, RwLock};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
 
 /// GlobalNode provides centralized access to all core protocol components.
 /// This structure holds the state and services needed for the REST API.
@@ -18,7 +29,7 @@ pub struct GlobalNode {
     pub rbi_engine: Arc<Mutex<RBIEngine<MockEconomicDataProvider>>>,
 
     /// Participant registry for looking up participant data
-    pub participant_registry: Option<Arc<SqliteParticipantRegistry>>,
+    pub participant_registry: Option<Arc<dyn RegistryStorage>>,
 
     /// Disbursement engine for payout transaction generation & AILEE safety checks
     pub disbursement_engine: Arc<DisbursementEngine>,
@@ -26,9 +37,23 @@ pub struct GlobalNode {
     /// In-memory payouts store (when sqlite registry is absent or read-only)
     pub in_memory_payouts: Arc<RwLock<HashMap<String, PayoutTransactionResult>>>,
 
+    /// In-memory payout transition log, mirroring the registry's
+    pub in_memory_transitions: Arc<RwLock<Vec<PayoutTransition>>>,
+
+    /// Serializes payout creation and lifecycle updates in this process;
+    /// the registry's payout lock covers other nodes sharing it
+    payout_update_lock: Arc<Mutex<()>>,
+
     /// Current pool balance in satoshis
     pub pool_balance: Arc<RwLock<u64>>,
 
+    /// Spendable pool wallet UTXOs used to fund payouts
+    pub pool_utxos: Arc<RwLock<Vec<PoolUtxo>>>,
+
+    /// Pool UTXOs reserved by unfinished payouts, by payout id, when there
+    /// is no registry to keep the reservations
+    pool_reservations: Arc<RwLock<HashMap<String, Vec<PoolUtxo>>>>,
+
     /// Node startup time for uptime calculation
     pub startup_time: Arc<DateTime<Utc>>,
 
@@ -41,8 +66,22 @@ pub struct GlobalNode {
     /// Labor history tracking
     pub labor_history: Arc<RwLock<Vec<LaborHistoryEntry>>>,
 
-    /// In-memory participant state
+    /// In-memory participant state from `/labor/apply`; persisted stakes
+    /// come from the registry
     pub participants: Arc<RwLock<Vec<SimulationParticipant>>>,
+
+    /// Observed transaction input sets used for Sybil clustering
+    pub co_spends: Arc<RwLock<Vec<CoSpend>>>,
+
+    /// Sybil flagging threshold and distribution weighting
+    pub sybil_config: Arc<SybilConfig>,
+
+    /// Backend relaying finalized payouts; broadcasting is disabled without one
+    pub broadcaster: Option<Arc<dyn Broadcaster>>,
+
+    /// Audit chain head kept outside the registry, checked and advanced on
+    /// each verification
+    pub audit_anchor: Option<Arc<AuditAnchorFile>>,
 }
 
 /// Node configuration settings
@@ -53,6 +92,9 @@ pub struct NodeConfiguration {
     pub rate_limit_per_minute: u32,
     pub jwt_auth_enabled: bool,
     pub cors_enabled: bool,
+    /// Bearer token for the participant management endpoints; they are
+    /// disabled when unset
+    pub admin_token: Option<String>,
 }
 
 impl Default for NodeConfiguration {
@@ -63,6 +105,7 @@ impl Default for NodeConfiguration {
             rate_limit_per_minute: 100,
             jwt_auth_enabled: false,
             cors_enabled: true,
+            admin_token: None,
         }
     }
 }
@@ -77,52 +120,537 @@ impl GlobalNode {
         let rbi_engine = RBIEngine::new(provider);
         let disbursement_engine = DisbursementEngine::new(DisbursementConfig::default());
 
-        Self {
+        let node = Self {
             rbi_engine: Arc::new(Mutex::new(rbi_engine)),
             participant_registry: None,
             disbursement_engine: Arc::new(disbursement_engine),
             in_memory_payouts: Arc::new(RwLock::new(HashMap::new())),
+            in_memory_transitions: Arc::new(RwLock::new(Vec::new())),
+            payout_update_lock: Arc::new(Mutex::new(())),
             pool_balance: Arc::new(RwLock::new(0)),
+            pool_utxos: Arc::new(RwLock::new(Vec::new())),
+            pool_reservations: Arc::new(RwLock::new(HashMap::new())),
             startup_time: Arc::new(Utc::now()),
             config: Arc::new(NodeConfiguration::default()),
             current_block_height: Arc::new(RwLock::new(800_000)),
             labor_history: Arc::new(RwLock::new(Vec::new())),
             participants: Arc::new(RwLock::new(Vec::new())),
-        }
+            co_spends: Arc::new(RwLock::new(Vec::new())),
+            sybil_config: Arc::new(SybilConfig::default()),
+            broadcaster: None,
+            audit_anchor: None,
+        };
+        node.with_disbursement_config(DisbursementConfig::default())
     }
 
     /// Create a GlobalNode with a specific participant registry
-    pub fn with_registry(mut self, registry: SqliteParticipantRegistry) -> Self {
-        self.participant_registry = Some(Arc::new(registry));
+    pub fn with_registry(self, registry: SqliteParticipantRegistry) -> Self {
+        self.with_storage(Arc::new(registry))
+    }
+
+    /// Create a GlobalNode backed by any registry storage, e.g. a shared
+    /// PostgreSQL database
+    pub fn with_storage(mut self, storage: Arc<dyn RegistryStorage>) -> Self {
+        // A stored pool UTXO set outlives restarts; the cache starts from it
+        if let Ok(utxos) = storage.pool_utxos() {
+            if !utxos.is_empty() {
+                self.cache_pool_utxos(utxos);
+            }
+        }
+        self.participant_registry = Some(storage);
+        let engine = self.build_engine(self.disbursement_engine.config.clone());
+        self.disbursement_engine = Arc::new(engine);
         self
     }
 
     /// Create a GlobalNode with custom configuration
+    pub fn with_disbursement_config(mut self, config: DisbursementConfig) -> Self {
+        self.disbursement_engine = Arc::new(self.build_engine(config));
+        self
+    }
+
+    /// Trust rules see the registry's payout history (the in-memory store
+    /// without one) and the live pool balance. Change indexes come from the
+    /// registry so they survive restarts.
+    fn build_engine(&self, config: DisbursementConfig) -> DisbursementEngine {
+        let engine = DisbursementEngine::new(config).with_pool_balance(self.pool_balance.clone());
+        match &self.participant_registry {
+            Some(registry) => engine
+                .with_payout_history(registry.clone() as Arc<dyn PayoutHistory>)
+                .with_change_index_store(registry.clone() as Arc<dyn ChangeIndexStore>),
+            None => engine.with_payout_history(self.in_memory_payouts.clone()),
+        }
+    }
+
     pub fn with_config(mut self, config: NodeConfiguration) -> Self {
         self.config = Arc::new(config);
         self
     }
 
+    /// Create a GlobalNode with a Sybil clustering policy
+    pub fn with_sybil_config(mut self, config: SybilConfig) -> Self {
+        self.sybil_config = Arc::new(config);
+        self
+    }
+
+    /// Create a GlobalNode relaying finalized payouts through `broadcaster`
+    pub fn with_broadcaster(mut self, broadcaster: Arc<dyn Broadcaster>) -> Self {
+        self.broadcaster = Some(broadcaster);
+        self
+    }
+
+    /// Create a GlobalNode anchoring audit verification in a file
+    pub fn with_audit_anchor(mut self, anchor: AuditAnchorFile) -> Self {
+        self.audit_anchor = Some(Arc::new(anchor));
+        self
+    }
+
+    /// Recompute the audit chain. It must still contain `published`, a head
+    /// an auditor recorded earlier, and the anchor file's head, which then
+    /// advances to the current one
+    pub fn verify_audit_chain(
+        &self,
+        published: Option<&AuditChainStatus>,
+    ) -> Result<AuditChainStatus, RegistryError> {
+        let registry = self.registry()?;
+        if let Some(published) = published {
+            registry.verify_audit_chain_from(published)?;
+        }
+        match &self.audit_anchor {
+            Some(anchor) => anchor.advance(registry),
+            None => registry.verify_audit_chain(),
+        }
+    }
+
     /// Execute / generate a payout request
-    pub fn execute_payout(&self, req: &PayoutRequest) -> Result<PayoutTransactionResult, String> {
+    pub fn execute_payout(
+        &self,
+        req: &PayoutRequest,
+    ) -> Result<PayoutTransactionResult, DisbursementError> {
         let payout_id = format!("payout-{}", uuid::Uuid::new_v4());
-        let res = self
-            .disbursement_engine
-            .create_unsigned_payout(payout_id, req)?;
+        // Serialized so concurrent requests cannot both fit under a
+        // velocity limit or select the same pool UTXOs
+        let _guard = self.lock_payouts().map_err(DisbursementError::Storage)?;
+        // Refreshed first: trust rules read the pool balance it sets
+        let pool_utxos = self
+            .current_pool_utxos()
+            .map_err(DisbursementError::Storage)?;
+
+        let engine = &self.disbursement_engine;
+        let trust_audit = engine.evaluate_ailee_trust_policy(req);
+        if engine.blocks_payout(&trust_audit) {
+            let (rejected, transition) = engine.rejected_payout(payout_id, req, trust_audit);
+            self.store_payout(&rejected, &[transition])
+                .map_err(DisbursementError::Storage)?;
+            return Err(DisbursementError::PolicyRejected {
+                recipient_address: rejected.recipient_address,
+                findings: rejected.trust_audit.findings,
+            });
+        }
+
+        let res = engine.create_audited_payout(payout_id, req, &pool_utxos, trust_audit)?;
+        // Selected inputs are no longer available to later payouts
+        if !res.is_dry_run {
+            self.reserve_pool_utxos(&res, None)
+                .map_err(DisbursementError::Storage)?;
+        }
+        self.store_payout(&res, &[])
+            .map_err(|err| self.abandon_payouts(std::slice::from_ref(&res), err))
+            .map_err(DisbursementError::Storage)?;
+
+        Ok(res)
+    }
+
+    /// Holds this process's payout lock, then the registry's, so payouts
+    /// are evaluated and stored one at a time across every node sharing
+    /// the registry. The process lock guards no data, so a poisoned one is
+    /// safe to reuse.
+    fn lock_payouts(&self) -> Result<(Option<PayoutLock<'_>>, MutexGuard<'_, ()>), String> {
+        let local = self
+            .payout_update_lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let shared = match self.participant_registry {
+            Some(ref registry) => Some(
+                registry
+                    .lock_payouts()
+                    .map_err(|e| format!("Failed to take the payout lock: {}", e))?,
+            ),
+            None => None,
+        };
+        Ok((shared, local))
+    }
 
-        // Persist to registry if available
+    /// Persist a payout and its new transitions to the registry (if
+    /// available) and the in-memory cache
+    fn store_payout(
+        &self,
+        payout: &PayoutTransactionResult,
+        transitions: &[PayoutTransition],
+    ) -> Result<(), String> {
         if let Some(ref registry) = self.participant_registry {
-            let _ = registry.save_payout(&res);
+            registry
+                .save_payout_transitions(payout, transitions)
+                .map_err(|e| format!("Failed to store payout '{}': {}", payout.payout_id, e))?;
         }
 
-        // Always update in-memory cache
         if let Ok(mut payouts) = self.in_memory_payouts.write() {
-            payouts.insert(res.payout_id.clone(), res.clone());
+            payouts.insert(payout.payout_id.clone(), payout.clone());
+        }
+        if let Ok(mut log) = self.in_memory_transitions.write() {
+            log.extend_from_slice(transitions);
+        }
+        Ok(())
+    }
+
+    /// Apply `update` to a stored payout and persist the transitions it returns
+    fn update_payout<F>(
+        &self,
+        payout_id: &str,
+        update: F,
+    ) -> Result<PayoutTransactionResult, String>
+    where
+        F: FnOnce(&mut PayoutTransactionResult) -> Result<Vec<PayoutTransition>, LifecycleError>,
+    {
+        let _guard = self.lock_payouts()?;
+        self.update_payout_locked(payout_id, update)
+    }
+
+    /// [`Self::update_payout`] for a caller already holding the payout lock
+    fn update_payout_locked<F>(
+        &self,
+        payout_id: &str,
+        update: F,
+    ) -> Result<PayoutTransactionResult, String>
+    where
+        F: FnOnce(&mut PayoutTransactionResult) -> Result<Vec<PayoutTransition>, LifecycleError>,
+    {
+        let mut payout = self
+            .get_payout(payout_id)
+            .ok_or_else(|| format!("Payout with id '{}' not found", payout_id))?;
+        let was_abandoned = payout.status.is_abandoned();
+        let transitions = update(&mut payout).map_err(|e| e.to_string())?;
+        self.store_payout(&payout, &transitions)?;
+        // Its inputs were never spent, so later payouts may use them
+        if payout.status.is_abandoned() && !was_abandoned {
+            self.release_pool_utxos(payout_id)?;
+        }
+        Ok(payout)
+    }
+
+    /// Move a payout to `to`, enforcing the lifecycle transition rules
+    pub fn transition_payout(
+        &self,
+        payout_id: &str,
+        to: PayoutStatus,
+        detail: Option<String>,
+    ) -> Result<PayoutTransactionResult, String> {
+        self.update_payout(payout_id, |payout| Ok(vec![payout.transition(to, detail)?]))
+    }
+
+    /// Record a (partially) signed PSBT for a payout
+    pub fn submit_signed_psbt(
+        &self,
+        payout_id: &str,
+        psbt: &PartiallySignedTransaction,
+    ) -> Result<PayoutTransactionResult, String> {
+        self.update_payout(payout_id, |payout| {
+            Ok(vec![payout.apply_signed_psbt(psbt)?])
+        })
+    }
+
+    /// Broadcast a finalized payout and record the outcome. Fails, with the
+    /// payout broadcast, if an RBF original cannot be marked replaced.
+    pub fn broadcast_payout(
+        &self,
+        payout_id: &str,
+        broadcaster: &dyn Broadcaster,
+    ) -> Result<PayoutTransactionResult, String> {
+        let payout = self.update_payout(payout_id, |payout| {
+            Ok(vec![payout.broadcast_with(broadcaster)?])
+        })?;
+
+        // An accepted RBF replacement has evicted the payout it replaces
+        if let (PayoutStatus::Broadcast, Some(link)) = (&payout.status, &payout.fee_bump_of) {
+            if link.kind == FeeBumpKind::Rbf {
+                let detail = Some(format!("replaced by {}", payout.payout_id));
+                self.transition_payout(&link.payout_id, PayoutStatus::Replaced, detail)
+                    .map_err(|err| {
+                        format!(
+                            "Payout '{}' was broadcast, but '{}' could not be marked replaced: {}",
+                            payout.payout_id, link.payout_id, err
+                        )
+                    })?;
+            }
+        }
+        Ok(payout)
+    }
+
+    /// Create an unsigned fee bump for a stuck broadcast payout: an RBF
+    /// replacement when possible, otherwise a CPFP child
+    pub fn bump_payout_fee(
+        &self,
+        payout_id: &str,
+        fee_rate_sats_per_vbyte: u64,
+    ) -> Result<PayoutTransactionResult, DisbursementError> {
+        let _guard = self.lock_payouts().map_err(DisbursementError::Storage)?;
+        let original = self.get_payout(payout_id).ok_or_else(|| {
+            DisbursementError::InvalidRequest(format!("Payout with id '{}' not found", payout_id))
+        })?;
+        let pool_utxos = self
+            .current_pool_utxos()
+            .map_err(DisbursementError::Storage)?;
+        let bump = self.disbursement_engine.bump_fee(
+            format!("payout-{}", uuid::Uuid::new_v4()),
+            &original,
+            fee_rate_sats_per_vbyte,
+            &pool_utxos,
+        )?;
+
+        self.reserve_pool_utxos(&bump, Some(&original))
+            .map_err(DisbursementError::Storage)?;
+        self.store_payout(&bump, &[])
+            .map_err(|err| self.abandon_payouts(std::slice::from_ref(&bump), err))
+            .map_err(DisbursementError::Storage)?;
+
+        // Linked only once the replacement is stored, so the original never
+        // points at a payout that does not exist
+        let link = bump.fee_bump_of.as_ref().map(|link| FeeBumpLink {
+            payout_id: bump.payout_id.clone(),
+            kind: link.kind,
+        });
+        if let Err(err) = self.update_payout_locked(payout_id, |original| {
+            original.fee_bumped_by = link;
+            Ok(Vec::new())
+        }) {
+            let detail = Some(format!("linking {} failed: {}", payout_id, err));
+            let err = match self.update_payout_locked(&bump.payout_id, |bump| {
+                Ok(vec![bump.transition(PayoutStatus::Failed, detail)?])
+            }) {
+                Ok(_) => err,
+                Err(failed) => self
+                    .abandon_payouts(std::slice::from_ref(&bump), format!("{}; {}", err, failed)),
+            };
+            return Err(DisbursementError::Storage(err));
+        }
+        Ok(bump)
+    }
+
+    /// Poll the chain backend for every broadcast or confirmed payout and
+    /// record confirmation progress. Returns the transitions taken.
+    pub fn track_confirmations(&self, source: &dyn ConfirmationSource) -> Vec<PayoutTransition> {
+        let final_confirmations = self.disbursement_engine.config.final_confirmations;
+        let mut taken = Vec::new();
+        for payout in self.list_payouts() {
+            if !matches!(
+                payout.status,
+                PayoutStatus::Broadcast | PayoutStatus::Confirmed
+            ) {
+                continue;
+            }
+            let Ok(txid) = bitcoin::Txid::from_str(&payout.txid) else {
+                continue;
+            };
+            let Ok(status) = source.transaction_status(&txid) else {
+                continue;
+            };
+            let mut transitions = Vec::new();
+            // Only stored transitions count; the next poll retries the rest
+            let stored = self.update_payout(&payout.payout_id, |payout| {
+                transitions = payout.observe_chain(status, final_confirmations)?;
+                Ok(transitions.clone())
+            });
+            if stored.is_ok() {
+                taken.extend(transitions);
+            }
+        }
+        taken
+    }
+
+    /// Transition log of a payout, oldest first
+    pub fn get_payout_transitions(&self, payout_id: &str) -> Vec<PayoutTransition> {
+        if let Some(ref registry) = self.participant_registry {
+            if let Ok(transitions) = registry.get_payout_transitions(payout_id) {
+                if !transitions.is_empty() {
+                    return transitions;
+                }
+            }
+        }
+
+        self.in_memory_transitions
+            .read()
+            .map(|log| {
+                log.iter()
+                    .filter(|t| t.payout_id == payout_id)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Build unsigned batch transactions for an epoch's allocations
+    pub fn execute_batch_payout(
+        &self,
+        req: &BatchPayoutRequest,
+    ) -> Result<BatchPayoutResult, DisbursementError> {
+        let batch_id = format!("batch-{}", uuid::Uuid::new_v4());
+        // Same serialization as `execute_payout`: the batch total must fit
+        // under the pool-wide limits together with concurrent payouts.
+        let _guard = self.lock_payouts().map_err(DisbursementError::Storage)?;
+
+        let pool_utxos = self
+            .current_pool_utxos()
+            .map_err(DisbursementError::Storage)?;
+        let res = self
+            .disbursement_engine
+            .create_batch_payout(batch_id, req, &pool_utxos)?;
+        let records = res.payout_records();
+        if !res.is_dry_run {
+            for (n, record) in records.iter().enumerate() {
+                self.reserve_pool_utxos(record, None)
+                    .map_err(|err| self.abandon_payouts(&records[..n], err))
+                    .map_err(DisbursementError::Storage)?;
+            }
+        }
+        for (n, record) in records.iter().enumerate() {
+            self.store_payout(record, &[])
+                .map_err(|err| self.abandon_payouts(&records[n..], err))
+                .map_err(DisbursementError::Storage)?;
+        }
+
+        if !res.is_dry_run {
+            if let Some(ref registry) = self.participant_registry {
+                let sum = |allocations: &[PayoutAllocation]| -> u64 {
+                    allocations.iter().map(|a| a.amount_sats).sum()
+                };
+                let closed = registry.record_audit_event(&AuditEvent::EpochClosed {
+                    batch_id: res.batch_id.clone(),
+                    block_height: self.get_block_height(),
+                    txids: res.transactions.iter().map(|tx| tx.txid.clone()).collect(),
+                    total_paid_sats: res.total_paid_sats,
+                    total_fee_sats: res.total_fee_sats,
+                    dropped_sats: sum(&res.dropped),
+                    rolled_over_sats: sum(&res.rolled_over),
+                });
+                // A batch missing from the audit log must not be signed
+                if let Err(e) = closed {
+                    let err = format!("Failed to audit batch '{}': {}", res.batch_id, e);
+                    return Err(DisbursementError::Storage(self.fail_payouts(&records, err)));
+                }
+            }
         }
 
         Ok(res)
     }
 
+    /// Pool UTXOs to select from under the payout lock: the registry's
+    /// set, which other nodes sharing it may have spent from
+    fn current_pool_utxos(&self) -> Result<Vec<PoolUtxo>, String> {
+        if let Some(ref registry) = self.participant_registry {
+            let utxos = registry
+                .pool_utxos()
+                .map_err(|e| format!("Failed to load pool UTXOs: {}", e))?;
+            self.cache_pool_utxos(utxos);
+        }
+        Ok(self.get_pool_utxos())
+    }
+
+    /// Reserve a payout's inputs before the payout is stored, so a failure
+    /// here cannot leave them selectable by a later payout. Inputs shared
+    /// with `replaces`, the payout an RBF bump replaces, stay reserved by it.
+    fn reserve_pool_utxos(
+        &self,
+        payout: &PayoutTransactionResult,
+        replaces: Option<&PayoutTransactionResult>,
+    ) -> Result<(), String> {
+        let mut spent = spent_outpoints(&payout.raw_tx_hex).ok_or_else(|| {
+            format!(
+                "Payout '{}' has no decodable transaction to reserve inputs for",
+                payout.payout_id
+            )
+        })?;
+        if let Some(original) = replaces {
+            let kept = spent_outpoints(&original.raw_tx_hex).unwrap_or_default();
+            spent.retain(|outpoint| !kept.contains(outpoint));
+        }
+        if let Some(ref registry) = self.participant_registry {
+            registry
+                .reserve_pool_utxos(&payout.payout_id, &spent)
+                .map_err(|e| format!("Failed to reserve pool UTXOs: {}", e))?;
+        }
+        let (reserved, remaining): (Vec<_>, Vec<_>) = self
+            .get_pool_utxos()
+            .into_iter()
+            .partition(|u| spent.contains(&u.outpoint));
+        if self.participant_registry.is_none() {
+            if let Ok(mut reservations) = self.pool_reservations.write() {
+                reservations
+                    .entry(payout.payout_id.clone())
+                    .or_default()
+                    .extend(reserved);
+            }
+        }
+        self.cache_pool_utxos(remaining);
+        Ok(())
+    }
+
+    /// Return the inputs reserved by a payout that will not be broadcast,
+    /// because it failed or was never stored
+    fn release_pool_utxos(&self, payout_id: &str) -> Result<(), String> {
+        let release_failed =
+            |e: RegistryError| format!("Failed to release pool UTXOs of '{}': {}", payout_id, e);
+        match self.participant_registry {
+            Some(ref registry) => {
+                registry
+                    .release_pool_utxos(payout_id)
+                    .map_err(release_failed)?;
+                let utxos = registry.pool_utxos().map_err(release_failed)?;
+                self.cache_pool_utxos(utxos);
+            }
+            None => {
+                let released = self
+                    .pool_reservations
+                    .write()
+                    .ok()
+                    .and_then(|mut reservations| reservations.remove(payout_id))
+                    .unwrap_or_default();
+                if !released.is_empty() {
+                    let mut utxos = self.get_pool_utxos();
+                    utxos.extend(released);
+                    self.cache_pool_utxos(utxos);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Release the inputs of payouts that could not be stored, adding any
+    /// failure to do so to `err`
+    fn abandon_payouts(&self, payouts: &[PayoutTransactionResult], err: String) -> String {
+        payouts
+            .iter()
+            .filter_map(|payout| self.release_pool_utxos(&payout.payout_id).err())
+            .fold(err, |err, release| format!("{}; {}", err, release))
+    }
+
+    /// Moves stored `payouts` to `Failed` with `err` as the reason, which
+    /// releases their inputs; returns `err` with any failure appended.
+    fn fail_payouts(&self, payouts: &[PayoutTransactionResult], err: String) -> String {
+        let failures: Vec<String> = payouts
+            .iter()
+            .filter_map(|payout| {
+                let detail = Some(err.clone());
+                self.update_payout_locked(&payout.payout_id, |payout| {
+                    Ok(vec![payout.transition(PayoutStatus::Failed, detail)?])
+                })
+                .err()
+            })
+            .collect();
+        failures
+            .into_iter()
+            .fold(err, |err, failed| format!("{}; {}", err, failed))
+    }
+
     /// Retrieve payout by ID
     pub fn get_payout(&self, payout_id: &str) -> Option<PayoutTransactionResult> {
         if let Some(ref registry) = self.participant_registry {
@@ -157,6 +685,63 @@ impl GlobalNode {
         Vec::new()
     }
 
+    /// Payouts still waiting for the cosigner with master key `fingerprint`
+    /// to sign, newest first
+    pub fn payouts_awaiting_cosigner(&self, fingerprint: &str) -> Vec<PayoutTransactionResult> {
+        self.list_payouts()
+            .into_iter()
+            .filter(|p| {
+                awaits_signatures(p)
+                    && p.signing
+                        .as_ref()
+                        .is_some_and(|s| s.awaiting().any(|f| f.eq_ignore_ascii_case(fingerprint)))
+            })
+            .collect()
+    }
+
+    /// Every known cosigner (pool descriptor keys first) with the payouts it
+    /// still has to sign
+    pub fn cosigner_queues(&self) -> Vec<CosignerQueue> {
+        let mut queues: Vec<CosignerQueue> = self
+            .disbursement_engine
+            .config
+            .pool_descriptor
+            .iter()
+            .flat_map(|d| d.cosigners())
+            .map(|fingerprint| CosignerQueue {
+                fingerprint: fingerprint.to_string(),
+                awaiting_payout_ids: Vec::new(),
+            })
+            .collect();
+
+        for payout in self.list_payouts() {
+            let Some(ref signing) = payout.signing else {
+                continue;
+            };
+            for cosigner in &signing.cosigners {
+                let index = match queues
+                    .iter()
+                    .position(|q| q.fingerprint == cosigner.fingerprint)
+                {
+                    Some(index) => index,
+                    None => {
+                        queues.push(CosignerQueue {
+                            fingerprint: cosigner.fingerprint.clone(),
+                            awaiting_payout_ids: Vec::new(),
+                        });
+                        queues.len() - 1
+                    }
+                };
+                if !cosigner.signed && awaits_signatures(&payout) {
+                    queues[index]
+                        .awaiting_payout_ids
+                        .push(payout.payout_id.clone());
+                }
+            }
+        }
+        queues
+    }
+
     /// Set the pool balance
     pub fn set_pool_balance(&self, balance: u64) {
         if let Ok(mut pool) = self.pool_balance.write() {
@@ -169,6 +754,43 @@ impl GlobalNode {
         self.pool_balance.read().map(|b| *b).unwrap_or(0)
     }
 
+    /// Replace the spendable pool UTXO set, persisting it to the registry
+    /// (if available). The pool balance becomes the set's total value.
+    pub fn set_pool_utxos(&self, mut utxos: Vec<PoolUtxo>) -> Result<(), RegistryError> {
+        // Outpoints that stay in the set keep their reservations
+        if let Some(ref registry) = self.participant_registry {
+            registry.replace_pool_utxos(&utxos)?;
+            utxos = registry.pool_utxos()?;
+        } else if let Ok(mut reservations) = self.pool_reservations.write() {
+            for reserved in reservations.values_mut() {
+                reserved.retain(|r| utxos.iter().any(|u| u.outpoint == r.outpoint));
+            }
+            utxos.retain(|u| {
+                !reservations
+                    .values()
+                    .flatten()
+                    .any(|r| r.outpoint == u.outpoint)
+            });
+        }
+        self.cache_pool_utxos(utxos);
+        Ok(())
+    }
+
+    fn cache_pool_utxos(&self, utxos: Vec<PoolUtxo>) {
+        self.set_pool_balance(utxos.iter().map(|u| u.value_sats).sum());
+        if let Ok(mut pool) = self.pool_utxos.write() {
+            *pool = utxos;
+        }
+    }
+
+    /// Get the spendable pool UTXO set
+    pub fn get_pool_utxos(&self) -> Vec<PoolUtxo> {
+        self.pool_utxos
+            .read()
+            .map(|u| u.clone())
+            .unwrap_or_default()
+    }
+
     /// Get uptime in seconds
     pub fn get_uptime_seconds(&self) -> u64 {
         let now = Utc::now();
@@ -231,12 +853,158 @@ impl GlobalNode {
         }
     }
 
-    /// Get all participants
+    /// The configured participant registry, for the management endpoints
+    pub fn registry(&self) -> Result<&dyn RegistryStorage, RegistryError> {
+        self.participant_registry
+            .as_deref()
+            .ok_or(RegistryError::Unavailable)
+    }
+
+    /// Get all participants: the registry's stakers at the current block
+    /// height, then in-memory labor entries for ids the registry lacks
     pub fn get_participants(&self) -> Vec<SimulationParticipant> {
-        self.participants
-            .read()
-            .map(|p| p.clone())
+        let mut participants: Vec<SimulationParticipant> = self
+            .participant_registry
+            .as_ref()
+            .and_then(|r| r.participant_snapshots(self.get_block_height()).ok())
             .unwrap_or_default()
+            .into_iter()
+            .map(|s| SimulationParticipant {
+                participant_id: s.participant_id,
+                stake_sats: s.stake_amount_sats,
+                trust_coefficient: s.trust_coefficient,
+                addresses: vec![],
+            })
+            .collect();
+
+        if let Ok(in_memory) = self.participants.read() {
+            for participant in in_memory.iter() {
+                if !participants
+                    .iter()
+                    .any(|p| p.participant_id == participant.participant_id)
+                {
+                    participants.push(participant.clone());
+                }
+            }
+        }
+        participants
+    }
+
+    /// Participant snapshots for RBI and distribution, with flagged Sybil
+    /// groups weighted per the configured policy
+    pub fn get_participant_snapshots(&self) -> Vec<ParticipantSnapshot> {
+        let snapshots = self
+            .get_participants()
+            .into_iter()
+            .map(|p| ParticipantSnapshot {
+                participant_id: p.participant_id,
+                stake_amount_sats: p.stake_sats,
+                trust_coefficient: p.trust_coefficient,
+            })
+            .collect();
+        apply_sybil_weighting(snapshots, &self.sybil_report(), self.sybil_config.weighting)
+    }
+
+    /// Log the distribution parameters this node runs with to the audit
+    /// log; values unchanged since they were last logged are skipped
+    pub fn record_parameters(&self) -> Result<(), RegistryError> {
+        let registry = self.registry()?;
+        let config = &self.disbursement_engine.config;
+        let parameters = [
+            ("network", config.network.to_string()),
+            (
+                "max_single_payout_sats",
+                config.max_single_payout_sats.to_string(),
+            ),
+            (
+                "require_ailee_trust_pass",
+                config.require_ailee_trust_pass.to_string(),
+            ),
+            (
+                "final_confirmations",
+                config.final_confirmations.to_string(),
+            ),
+            ("trust_policy", config.trust_policy.version().to_string()),
+            ("sybil_weighting", self.sybil_config.weighting.to_string()),
+        ];
+        for (parameter, value) in parameters {
+            registry.record_parameter(parameter, &value)?;
+        }
+        Ok(())
+    }
+
+    /// Record observed transaction input sets. Returns how many were new.
+    pub fn add_co_spends(&self, co_spends: impl IntoIterator<Item = CoSpend>) -> usize {
+        let Ok(mut known) = self.co_spends.write() else {
+            return 0;
+        };
+        let before = known.len();
+        for co_spend in co_spends {
+            if !known.iter().any(|c| c.txid == co_spend.txid) {
+                known.push(co_spend);
+            }
+        }
+        known.len() - before
+    }
+
+    /// Fetch co-spends of every clustered participant's addresses within
+    /// the Sybil lookback window ending at the current block height.
+    /// Returns how many were new.
+    pub fn refresh_co_spends(&self, source: &dyn CoSpendSource) -> Result<usize, VelocityError> {
+        let addresses: Vec<String> = self
+            .clustered_participants()
+            .into_iter()
+            .flat_map(|(_, addresses)| addresses)
+            .collect();
+        if addresses.is_empty() {
+            return Ok(0);
+        }
+        let end_height = self.get_block_height();
+        let start_height = end_height.saturating_sub(self.sybil_config.lookback_blocks);
+        let co_spends = source.co_spends_for_addresses(&addresses, start_height, end_height)?;
+        Ok(self.add_co_spends(co_spends))
+    }
+
+    /// Cluster active registered participants, and in-memory ones the
+    /// registry lacks, by their addresses and the recorded co-spends
+    pub fn sybil_report(&self) -> SybilReport {
+        let co_spends = self.co_spends.read().map(|c| c.clone()).unwrap_or_default();
+        analyze_clusters(
+            &self.clustered_participants(),
+            &co_spends,
+            &self.sybil_config,
+        )
+    }
+
+    /// Active registered participants and in-memory ones the registry
+    /// lacks, with their addresses
+    fn clustered_participants(&self) -> Vec<(String, Vec<String>)> {
+        let mut participants: Vec<(String, Vec<String>)> = self
+            .participant_registry
+            .as_ref()
+            .and_then(|r| r.list_participants(0, u32::MAX, false).ok())
+            .map(|page| page.participants)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| {
+                let addresses = p.addresses.into_iter().map(|a| a.address).collect();
+                (p.participant_id, addresses)
+            })
+            .collect();
+        if let Ok(in_memory) = self.participants.read() {
+            for participant in in_memory.iter() {
+                if !participants
+                    .iter()
+                    .any(|(id, _)| *id == participant.participant_id)
+                {
+                    participants.push((
+                        participant.participant_id.clone(),
+                        participant.addresses.clone(),
+                    ));
+                }
+            }
+        }
+        participants
     }
 }
 
@@ -245,3 +1013,19 @@ impl Default for GlobalNode {
         Self::new()
     }
 }
+
+/// Whether a payout can still collect signatures
+fn awaits_signatures(payout: &PayoutTransactionResult) -> bool {
+    !payout.is_dry_run
+        && matches!(
+            payout.status,
+            PayoutStatus::UnsignedCreated | PayoutStatus::PartiallySigned
+        )
+}
+
+/// Outpoints a raw transaction spends; `None` if it does not decode.
+fn spent_outpoints(raw_tx_hex: &str) -> Option<Vec<bitcoin::OutPoint>> {
+    let bytes = hex::decode(raw_tx_hex).ok()?;
+    let tx: bitcoin::Transaction = bitcoin::consensus::deserialize(&bytes).ok()?;
+    Some(tx.input.iter().map(|i| i.previous_output).collect())
+}
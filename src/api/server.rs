@@ -1,12 +1,18 @@
 use crate::api::handlers::{
-    apply_labor, execute_payout_handler, get_btc_peg, get_labor_history, get_labor_state,
-    get_labor_value, get_participant_dividend, get_participant_velocity, get_payout_handler,
-    get_payout_history_handler, get_pool_balance, get_rbi, get_status, get_volatility,
-    health_check, root,
+    add_participant_address_handler, apply_labor, broadcast_payout_handler,
+    bump_payout_fee_handler, deactivate_participant_handler, execute_batch_payout_handler,
+    execute_payout_handler, get_audit_log_handler, get_btc_peg, get_cosigner_payouts_handler,
+    get_cosigners_handler, get_labor_history, get_labor_state, get_labor_value,
+    get_participant_dividend, get_participant_handler, get_participant_sybil_handler,
+    get_participant_velocity, get_payout_handler, get_payout_history_handler,
+    get_payout_transitions_handler, get_pool_balance, get_pool_utxos_handler, get_rbi, get_status,
+    get_volatility, health_check, list_participants_handler, register_participant_handler,
+    remove_participant_address_handler, root, set_pool_utxos_handler, submit_signed_psbt_handler,
+    verify_audit_log_handler,
 };
 use crate::api::node::GlobalNode;
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
 use tower_http::cors::CorsLayer;
@@ -27,10 +33,59 @@ pub fn create_router(node: GlobalNode) -> Router {
         .route("/labor/volatility", get(get_volatility))
         // BTC peg endpoint
         .route("/btc/peg", get(get_btc_peg))
-        // Payout endpoints
+        // Payout endpoints (bearer admin token for writes)
         .route("/api/v1/payouts/execute", post(execute_payout_handler))
+        .route("/api/v1/payouts/batch", post(execute_batch_payout_handler))
         .route("/api/v1/payouts/history", get(get_payout_history_handler))
         .route("/api/v1/payouts/:id", get(get_payout_handler))
+        .route("/api/v1/payouts/:id/bump", post(bump_payout_fee_handler))
+        .route(
+            "/api/v1/payouts/:id/broadcast",
+            post(broadcast_payout_handler),
+        )
+        .route(
+            "/api/v1/payouts/:id/signed",
+            post(submit_signed_psbt_handler),
+        )
+        .route(
+            "/api/v1/payouts/:id/transitions",
+            get(get_payout_transitions_handler),
+        )
+        // Pool wallet UTXOs funding payouts (bearer admin token)
+        .route(
+            "/api/v1/pool/utxos",
+            get(get_pool_utxos_handler).put(set_pool_utxos_handler),
+        )
+        // Multisig cosigner signing queues
+        .route("/api/v1/cosigners", get(get_cosigners_handler))
+        .route(
+            "/api/v1/cosigners/:fingerprint/payouts",
+            get(get_cosigner_payouts_handler),
+        )
+        // Participant management (bearer admin token)
+        .route(
+            "/api/v1/participants",
+            get(list_participants_handler).post(register_participant_handler),
+        )
+        .route("/api/v1/participants/:id", get(get_participant_handler))
+        .route(
+            "/api/v1/participants/:id/addresses",
+            post(add_participant_address_handler),
+        )
+        .route(
+            "/api/v1/participants/:id/addresses/:address",
+            delete(remove_participant_address_handler),
+        )
+        .route(
+            "/api/v1/participants/:id/deactivate",
+            post(deactivate_participant_handler),
+        )
+        .route(
+            "/api/v1/participants/:id/sybil",
+            get(get_participant_sybil_handler),
+        )
+        .route("/api/v1/audit", get(get_audit_log_handler))
+        .route("/api/v1/audit/verify", get(verify_audit_log_handler))
         // Legacy API v1 routes (maintained for backward compatibility)
         .route("/api/v1/rbi", get(get_rbi))
         .route("/api/v1/pool/balance", get(get_pool_balance))
@@ -1,4 +1,7 @@
-use crate::disbursement::{PayoutRequest, PayoutTransactionResult};
+use crate::disbursement::{
+    PayoutRequest, PayoutStatus, PayoutTransactionResult, PayoutTransition, PoolUtxoRecord,
+    TrustFinding,
+};
 use crate::rbi_engine::RbiStatus;
 use serde::{Deserialize, Serialize};
 
@@ -24,6 +27,7 @@ pub struct NodeConfig {
     pub rate_limit_per_minute: u32,
     pub jwt_auth_enabled: bool,
     pub cors_enabled: bool,
+    pub admin_api_enabled: bool,
 }
 
 /// Response for the RBI status endpoint
@@ -51,6 +55,19 @@ pub struct PoolBalanceResponse {
     pub balance_btc: f64,
 }
 
+/// Spendable pool wallet UTXOs, e.g. from the wallet's `listunspent`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PoolUtxosRequest {
+    pub utxos: Vec<PoolUtxoRecord>,
+}
+
+/// Spendable pool UTXO set and its total value
+#[derive(Debug, Serialize)]
+pub struct PoolUtxosResponse {
+    pub utxos: Vec<PoolUtxoRecord>,
+    pub total_sats: u64,
+}
+
 /// Response for dividend calculation
 #[derive(Debug, Serialize)]
 pub struct DividendResponse {
@@ -75,16 +92,28 @@ pub struct VelocityResponse {
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
     pub error: String,
+    /// Machine-readable error code, e.g. `policy_rejected`
+    pub code: String,
     pub message: String,
+    /// Trust-layer findings behind a `policy_rejected` error
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<TrustFinding>,
 }
 
 impl ErrorResponse {
-    pub fn new(error: impl ToString, message: impl ToString) -> Self {
+    pub fn new(error: impl ToString, code: impl ToString, message: impl ToString) -> Self {
         Self {
             error: error.to_string(),
+            code: code.to_string(),
             message: message.to_string(),
+            findings: Vec::new(),
         }
     }
+
+    pub fn with_findings(mut self, findings: Vec<TrustFinding>) -> Self {
+        self.findings = findings;
+        self
+    }
 }
 
 /// Request for calculating dividend
@@ -184,9 +213,70 @@ pub struct BtcPegResponse {
 /// Payout execute request
 pub type PayoutExecuteRequest = PayoutRequest;
 
+/// Signed PSBT submitted by an offline signer
+#[derive(Debug, Deserialize)]
+pub struct SignedPsbtRequest {
+    pub psbt_base64: String,
+}
+
+/// Result of submitting a signed PSBT; the raw transaction and final txid
+/// are present once every input is finalized
+#[derive(Debug, Serialize)]
+pub struct SignedPsbtResponse {
+    pub payout_id: String,
+    pub status: PayoutStatus,
+    pub raw_tx_hex: Option<String>,
+    pub txid: Option<String>,
+}
+
+/// Fee bump request for a stuck payout
+#[derive(Debug, Deserialize)]
+pub struct FeeBumpRequest {
+    pub fee_rate_sats_per_vbyte: u64,
+}
+
+/// Payout lifecycle transition log response
+#[derive(Debug, Serialize)]
+pub struct PayoutTransitionsResponse {
+    pub payout_id: String,
+    pub transitions: Vec<PayoutTransition>,
+}
+
 /// Payout list history response
 #[derive(Debug, Serialize)]
 pub struct PayoutHistoryResponse {
     pub payouts: Vec<PayoutTransactionResult>,
     pub total_count: usize,
 }
+
+/// Payouts a pool cosigner still has to sign
+#[derive(Debug, Clone, Serialize)]
+pub struct CosignerQueue {
+    /// Master key fingerprint, hex
+    pub fingerprint: String,
+    pub awaiting_payout_ids: Vec<String>,
+}
+
+/// Pool cosigners and their signing queues
+#[derive(Debug, Serialize)]
+pub struct CosignersResponse {
+    /// Signatures each payout input needs; `None` without a pool descriptor
+    pub threshold: Option<usize>,
+    pub cosigners: Vec<CosignerQueue>,
+}
+
+/// Register a participant with its initial addresses
+#[derive(Debug, Deserialize)]
+pub struct RegisterParticipantRequest {
+    pub participant_id: String,
+    #[serde(default)]
+    pub addresses: Vec<String>,
+}
+
+/// Add an address to a participant; appended when no position is given
+#[derive(Debug, Deserialize)]
+pub struct AddParticipantAddressRequest {
+    pub address: String,
+    #[serde(default)]
+    pub position: Option<u32>,
+}
@@ -0,0 +1,320 @@
+//! Append-only, hash-chained log of economic state changes.
+//!
+//! Every entry stores its event as JSON together with the hash of the
+//! previous entry, and its own hash commits to both. Rewriting, removing or
+//! reordering any entry changes every hash after it, which
+//! [`verify_chain`] detects.
+
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use serde::{Deserialize, Serialize};
+
+/// `prev_hash` of the first entry.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// An economic state change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    PayoutCreated {
+        payout_id: String,
+        recipient_address: String,
+        amount_sats: u64,
+        fee_sats: u64,
+        status: String,
+        is_dry_run: bool,
+    },
+    /// A stored payout rewritten without a status change, e.g. signatures
+    /// or fee bump links added.
+    PayoutUpdated {
+        payout_id: String,
+        status: String,
+        txid: String,
+    },
+    PayoutStatusChanged {
+        payout_id: String,
+        from: String,
+        to: String,
+        confirmations: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+    },
+    ParticipantRegistered {
+        participant_id: String,
+        addresses: Vec<String>,
+    },
+    ParticipantAddressAdded {
+        participant_id: String,
+        address: String,
+        position: u32,
+    },
+    ParticipantAddressRemoved {
+        participant_id: String,
+        address: String,
+    },
+    ParticipantDeactivated {
+        participant_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        effective_height: Option<u64>,
+    },
+    StakeRecorded {
+        outpoint: String,
+        participant_id: String,
+        amount_sats: u64,
+        lock_height: u32,
+    },
+    StakeUpdated {
+        outpoint: String,
+        verification: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        confirmed_height: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        spent_height: Option<u64>,
+    },
+    /// A protocol parameter, or a participant's when `participant_id` is set.
+    ParameterChanged {
+        parameter: String,
+        value: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        participant_id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        effective_height: Option<u64>,
+    },
+    /// An epoch's distribution was committed to batch transactions.
+    EpochClosed {
+        batch_id: String,
+        block_height: u64,
+        txids: Vec<String>,
+        total_paid_sats: u64,
+        total_fee_sats: u64,
+        dropped_sats: u64,
+        rolled_over_sats: u64,
+    },
+}
+
+impl AuditEvent {
+    /// The `event` tag of the JSON form.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AuditEvent::PayoutCreated { .. } => "payout_created",
+            AuditEvent::PayoutUpdated { .. } => "payout_updated",
+            AuditEvent::PayoutStatusChanged { .. } => "payout_status_changed",
+            AuditEvent::ParticipantRegistered { .. } => "participant_registered",
+            AuditEvent::ParticipantAddressAdded { .. } => "participant_address_added",
+            AuditEvent::ParticipantAddressRemoved { .. } => "participant_address_removed",
+            AuditEvent::ParticipantDeactivated { .. } => "participant_deactivated",
+            AuditEvent::StakeRecorded { .. } => "stake_recorded",
+            AuditEvent::StakeUpdated { .. } => "stake_updated",
+            AuditEvent::ParameterChanged { .. } => "parameter_changed",
+            AuditEvent::EpochClosed { .. } => "epoch_closed",
+        }
+    }
+}
+
+/// A stored log entry. `payload_json` is kept verbatim since the hash
+/// commits to its exact bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditEntry {
+    /// 1-based and contiguous.
+    pub seq: u64,
+    pub recorded_at: String,
+    pub event_type: String,
+    pub payload_json: String,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl AuditEntry {
+    pub fn event(&self) -> Result<AuditEvent, serde_json::Error> {
+        serde_json::from_str(&self.payload_json)
+    }
+}
+
+/// An intact chain. Recorded outside the log, it anchors later checks
+/// with [`verify_chain_extends`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditChainStatus {
+    pub entries: u64,
+    /// Hash of the last entry; [`GENESIS_HASH`] when empty.
+    pub head_hash: String,
+}
+
+/// Where verification first failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditChainError {
+    pub seq: u64,
+    pub reason: String,
+}
+
+impl std::fmt::Display for AuditChainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "audit entry {}: {}", self.seq, self.reason)
+    }
+}
+
+impl std::error::Error for AuditChainError {}
+
+/// Hex SHA-256 over the previous hash, the big-endian sequence number, then
+/// the length-prefixed timestamp, event type and payload.
+pub fn entry_hash(
+    seq: u64,
+    recorded_at: &str,
+    event_type: &str,
+    payload_json: &str,
+    prev_hash: &str,
+) -> String {
+    let mut engine = sha256::Hash::engine();
+    engine.input(prev_hash.as_bytes());
+    engine.input(&seq.to_be_bytes());
+    for field in [recorded_at, event_type, payload_json] {
+        engine.input(&(field.len() as u64).to_be_bytes());
+        engine.input(field.as_bytes());
+    }
+    sha256::Hash::from_engine(engine).to_string()
+}
+
+/// Checks `entries`, in log order, form an unbroken chain from
+/// [`GENESIS_HASH`] with contiguous sequence numbers and matching hashes.
+pub fn verify_chain<'a>(
+    entries: impl IntoIterator<Item = &'a AuditEntry>,
+) -> Result<AuditChainStatus, AuditChainError> {
+    let mut head = GENESIS_HASH.to_string();
+    let mut count = 0u64;
+    for entry in entries {
+        let broken = |reason: String| AuditChainError {
+            seq: entry.seq,
+            reason,
+        };
+        if entry.seq != count + 1 {
+            return Err(broken(format!("expected sequence number {}", count + 1)));
+        }
+        if entry.prev_hash != head {
+            return Err(broken("previous hash does not match".into()));
+        }
+        let kind = entry
+            .event()
+            .map_err(|e| broken(format!("unreadable payload: {e}")))?
+            .kind();
+        if kind != entry.event_type {
+            return Err(broken(format!(
+                "event type {} does not match payload {kind}",
+                entry.event_type
+            )));
+        }
+        let expected = entry_hash(
+            entry.seq,
+            &entry.recorded_at,
+            &entry.event_type,
+            &entry.payload_json,
+            &entry.prev_hash,
+        );
+        if entry.hash != expected {
+            return Err(broken("hash does not match contents".into()));
+        }
+        head = entry.hash.clone();
+        count += 1;
+    }
+    Ok(AuditChainStatus {
+        entries: count,
+        head_hash: head,
+    })
+}
+
+/// [`verify_chain`], and the chain must still contain `anchor`: its entry
+/// at `anchor.entries` hashes to `anchor.head_hash`. A log cut back past the
+/// anchor, or rewritten up to it, is otherwise a valid chain.
+pub fn verify_chain_extends(
+    entries: &[AuditEntry],
+    anchor: &AuditChainStatus,
+) -> Result<AuditChainStatus, AuditChainError> {
+    let status = verify_chain(entries)?;
+    let anchored = match anchor.entries {
+        0 => Some(GENESIS_HASH),
+        n => entries.get(n as usize - 1).map(|e| e.hash.as_str()),
+    };
+    match anchored {
+        None => Err(AuditChainError {
+            seq: status.entries + 1,
+            reason: format!("log ends before anchored entry {}", anchor.entries),
+        }),
+        Some(hash) if hash != anchor.head_hash => Err(AuditChainError {
+            seq: anchor.entries,
+            reason: "hash does not match the anchored head".into(),
+        }),
+        Some(_) => Ok(status),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(events: &[AuditEvent]) -> Vec<AuditEntry> {
+        let mut prev_hash = GENESIS_HASH.to_string();
+        let mut entries = Vec::new();
+        for (seq, event) in (1..).zip(events) {
+            let payload_json = serde_json::to_string(event).unwrap();
+            let recorded_at = format!("2024-01-01T00:00:0{seq}+00:00");
+            let hash = entry_hash(seq, &recorded_at, event.kind(), &payload_json, &prev_hash);
+            entries.push(AuditEntry {
+                seq,
+                recorded_at,
+                event_type: event.kind().to_string(),
+                payload_json,
+                prev_hash,
+                hash: hash.clone(),
+            });
+            prev_hash = hash;
+        }
+        entries
+    }
+
+    #[test]
+    fn verify_detects_edits_and_removals() {
+        let deactivated = |id: &str| AuditEvent::ParticipantDeactivated {
+            participant_id: id.to_string(),
+            effective_height: None,
+        };
+        let entries = chain(&[
+            deactivated("alice"),
+            deactivated("bob"),
+            deactivated("carol"),
+        ]);
+        let status = verify_chain(&entries).unwrap();
+        assert_eq!(status.entries, 3);
+        assert_eq!(status.head_hash, entries[2].hash);
+        assert_eq!(verify_chain(&[]).unwrap().head_hash, GENESIS_HASH);
+
+        let mut edited = entries.clone();
+        edited[1].payload_json = serde_json::to_string(&deactivated("mallory")).unwrap();
+        assert_eq!(verify_chain(&edited).unwrap_err().seq, 2);
+
+        let removed = [entries[0].clone(), entries[2].clone()];
+        assert_eq!(verify_chain(&removed).unwrap_err().seq, 3);
+    }
+
+    #[test]
+    fn anchors_catch_truncation_and_rebuilt_chains() {
+        let deactivated = |id: &str| AuditEvent::ParticipantDeactivated {
+            participant_id: id.to_string(),
+            effective_height: None,
+        };
+        let entries = chain(&[deactivated("alice"), deactivated("bob")]);
+        let anchor = verify_chain(&entries[..1]).unwrap();
+
+        // Appending keeps the anchor; so does an empty anchor
+        assert_eq!(verify_chain_extends(&entries, &anchor).unwrap().entries, 2);
+        let genesis = verify_chain(&[]).unwrap();
+        assert!(verify_chain_extends(&entries, &genesis).is_ok());
+
+        // Both of these are valid chains on their own
+        let anchor = verify_chain(&entries).unwrap();
+        assert_eq!(
+            verify_chain_extends(&entries[..1], &anchor)
+                .unwrap_err()
+                .seq,
+            2
+        );
+        let rebuilt = chain(&[deactivated("alice"), deactivated("mallory")]);
+        assert_eq!(verify_chain_extends(&rebuilt, &anchor).unwrap_err().seq, 2);
+    }
+}
@@ -1,5 +1,19 @@
 use bitcoin_digital_labor_derivative::api::{create_router, GlobalNode};
+use bitcoin_digital_labor_derivative::disbursement::{
+    ConfirmationSource, DisbursementConfig, TrustPolicy, TrustPolicyConfig,
+};
+#[cfg(feature = "esplora")]
+use bitcoin_digital_labor_derivative::esplora_broadcaster::EsploraBroadcaster;
+#[cfg(feature = "postgres")]
+use bitcoin_digital_labor_derivative::postgres_registry::PostgresParticipantRegistry;
+use bitcoin_digital_labor_derivative::sqlite_participant_registry::SqliteParticipantRegistry;
+use bitcoin_digital_labor_derivative::storage::AuditAnchorFile;
+#[cfg(feature = "esplora")]
+use bitcoin_digital_labor_derivative::sybil_clustering::CoSpendSource;
+use bitcoin_digital_labor_derivative::sybil_clustering::{SybilConfig, SybilWeighting};
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() {
@@ -22,20 +36,175 @@ async fn main() {
     let environment = std::env::var("BDLD_ENV").unwrap_or_else(|_| "production".to_string());
 
     // Create the global node with configuration
+    // Bearer token for the participant management endpoints
+    let admin_token = std::env::var("BDLD_ADMIN_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty());
+    if admin_token.is_none() {
+        tracing::info!("BDLD_ADMIN_TOKEN not set; participant management endpoints disabled");
+    }
+
     let config = bitcoin_digital_labor_derivative::api::node::NodeConfiguration {
         node_id: node_id.clone(),
         environment: environment.clone(),
+        admin_token,
         ..Default::default()
     };
 
-    let node = GlobalNode::new().with_config(config);
+    let mut node = GlobalNode::new().with_config(config);
+    let mut disbursement = DisbursementConfig::default();
+
+    // Participant registry and payout store (SQLite, migrated on open)
+    if let Ok(path) = std::env::var("BDLD_REGISTRY_PATH") {
+        match SqliteParticipantRegistry::open_read_write(&path) {
+            Ok(registry) => node = node.with_registry(registry.with_network(disbursement.network)),
+            Err(e) => tracing::warn!("Ignoring BDLD_REGISTRY_PATH {}: {}", path, e),
+        }
+    }
+
+    // Shared PostgreSQL registry for several replicas; replaces the SQLite one
+    #[cfg(feature = "postgres")]
+    if let Ok(url) = std::env::var("BDLD_DATABASE_URL") {
+        match PostgresParticipantRegistry::connect(&url) {
+            Ok(registry) => {
+                node = node.with_storage(Arc::new(registry.with_network(disbursement.network)))
+            }
+            Err(e) => tracing::warn!("Ignoring BDLD_DATABASE_URL: {}", e),
+        }
+    }
+    #[cfg(not(feature = "postgres"))]
+    if std::env::var("BDLD_DATABASE_URL").is_ok() {
+        tracing::warn!("Ignoring BDLD_DATABASE_URL: built without the postgres feature");
+    }
+
+    // Weighting of participants linked by Sybil clustering, and how far
+    // back co-spends are scanned
+    let mut sybil = SybilConfig::default();
+    if let Ok(weighting) = std::env::var("BDLD_SYBIL_WEIGHTING") {
+        let config = weighting
+            .parse::<SybilWeighting>()
+            .map(|weighting| SybilConfig {
+                weighting,
+                ..sybil.clone()
+            })
+            .and_then(|config| config.validate().map(|_| config));
+        match config {
+            Ok(config) => sybil = config,
+            Err(e) => tracing::warn!("Ignoring BDLD_SYBIL_WEIGHTING: {}", e),
+        }
+    }
+    if let Ok(blocks) = std::env::var("BDLD_SYBIL_LOOKBACK_BLOCKS") {
+        match blocks.parse::<u64>() {
+            Ok(blocks) if blocks > 0 => sybil.lookback_blocks = blocks,
+            _ => tracing::warn!("Ignoring invalid BDLD_SYBIL_LOOKBACK_BLOCKS: {}", blocks),
+        }
+    }
+    node = node.with_sybil_config(sybil);
+
+    // Pool descriptor for signable payout PSBTs (key origins, change keys)
+    if let Ok(descriptor) = std::env::var("BDLD_POOL_DESCRIPTOR") {
+        match descriptor.parse() {
+            Ok(pool_descriptor) => disbursement.pool_descriptor = Some(pool_descriptor),
+            Err(e) => tracing::warn!("Ignoring invalid BDLD_POOL_DESCRIPTOR: {}", e),
+        }
+    }
+
+    // Pool change address, needed without a pool descriptor
+    if let Ok(address) = std::env::var("BDLD_CHANGE_ADDRESS") {
+        disbursement.change_address = Some(address);
+    } else if disbursement.pool_descriptor.is_none() {
+        tracing::warn!(
+            "Neither BDLD_POOL_DESCRIPTOR nor BDLD_CHANGE_ADDRESS set; payouts must name a change address"
+        );
+    }
+
+    // Ceiling on fee bump rates, against a mistyped rate draining the pool
+    if let Ok(rate) = std::env::var("BDLD_MAX_FEE_RATE") {
+        match rate.parse::<u64>() {
+            Ok(rate) if rate > 0 => disbursement.max_fee_rate = rate,
+            _ => tracing::warn!("Ignoring invalid BDLD_MAX_FEE_RATE: {}", rate),
+        }
+    }
+
+    // AILEE Trust Layer rule set (JSON); the standard rules otherwise
+    if let Ok(path) = std::env::var("BDLD_TRUST_POLICY_FILE") {
+        match load_trust_policy(&path) {
+            Ok(policy) => {
+                tracing::info!("Loaded trust policy {} from {}", policy.version(), path);
+                disbursement.trust_policy = Arc::new(policy);
+            }
+            Err(e) => tracing::warn!("Ignoring BDLD_TRUST_POLICY_FILE: {}", e),
+        }
+    }
+
+    node = node.with_disbursement_config(disbursement);
+
+    // Parameter changes since the last start go to the audit log
+    if node.participant_registry.is_some() {
+        if let Err(e) = node.record_parameters() {
+            tracing::warn!("Failed to record parameters in the audit log: {}", e);
+        }
+    }
 
-    // Set an example pool balance (10 BTC = 1,000,000,000 sats)
-    node.set_pool_balance(1_000_000_000);
+    // Audit chain head kept outside the database; the log must still
+    // contain it at every verification
+    if let Ok(path) = std::env::var("BDLD_AUDIT_ANCHOR_PATH") {
+        node = node.with_audit_anchor(AuditAnchorFile::new(path));
+        match node.verify_audit_chain(None) {
+            Ok(status) => tracing::info!(
+                "Audit log verified: {} entries, head {}",
+                status.entries,
+                status.head_hash
+            ),
+            Err(e) => tracing::error!("Audit log verification failed: {}", e),
+        }
+    }
+
+    // Without a stored pool UTXO set, set an example pool balance
+    // (10 BTC = 1,000,000,000 sats) until one is loaded via /api/v1/pool/utxos
+    if node.get_pool_utxos().is_empty() {
+        node.set_pool_balance(1_000_000_000);
+    }
 
     // Set current block height
     node.set_block_height(800_000);
 
+    // Esplora backend relaying finalized payouts and tracking them until
+    // they are final, and reporting participants' co-spends
+    #[cfg(feature = "esplora")]
+    let confirmation_source = std::env::var("BDLD_ESPLORA_URL").ok().map(|url| {
+        let esplora = Arc::new(EsploraBroadcaster::new(url, Duration::from_secs(30)));
+        node = node.clone().with_broadcaster(esplora.clone());
+        let interval = std::env::var("BDLD_SYBIL_POLL_SECS")
+            .ok()
+            .and_then(|secs| secs.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(3600);
+        spawn_co_spend_tracking(node.clone(), esplora.clone(), Duration::from_secs(interval));
+        esplora as Arc<dyn ConfirmationSource>
+    });
+    #[cfg(not(feature = "esplora"))]
+    let confirmation_source: Option<Arc<dyn ConfirmationSource>> = {
+        if std::env::var("BDLD_ESPLORA_URL").is_ok() {
+            tracing::warn!("Ignoring BDLD_ESPLORA_URL: built without the esplora feature");
+        }
+        None
+    };
+    match confirmation_source {
+        Some(source) => {
+            let interval = std::env::var("BDLD_CONFIRMATION_POLL_SECS")
+                .ok()
+                .and_then(|secs| secs.parse::<u64>().ok())
+                .filter(|secs| *secs > 0)
+                .unwrap_or(60);
+            spawn_confirmation_tracking(node.clone(), source, Duration::from_secs(interval));
+        }
+        None => tracing::info!(
+            "BDLD_ESPLORA_URL not set; payouts are not broadcast or tracked for confirmations, \
+             and no co-spends are fetched for Sybil clustering"
+        ),
+    }
+
     tracing::info!("Node ID: {}", node_id);
     tracing::info!("Environment: {}", environment);
     tracing::info!("Pool balance: {} sats", node.get_pool_balance());
@@ -68,6 +237,19 @@ async fn main() {
     println!("  GET  /api/v1/pool/balance               - Pool balance");
     println!("  GET  /api/v1/participants/:id/dividend  - Calculate dividend");
     println!("  GET  /api/v1/participants/:id/velocity  - Velocity data");
+    println!("\nParticipant Management (Authorization: Bearer $BDLD_ADMIN_TOKEN):");
+    println!("  GET    /api/v1/participants                        - List participants");
+    println!("  POST   /api/v1/participants                        - Register participant");
+    println!("  GET    /api/v1/participants/:id                    - Get participant");
+    println!("  POST   /api/v1/participants/:id/addresses          - Add address");
+    println!("  DELETE /api/v1/participants/:id/addresses/:address - Remove address");
+    println!("  POST   /api/v1/participants/:id/deactivate         - Deactivate participant");
+    println!("  GET    /api/v1/participants/:id/sybil              - Sybil clustering risk");
+    println!("  GET    /api/v1/pool/utxos                          - Pool UTXO set");
+    println!("  PUT    /api/v1/pool/utxos                          - Replace pool UTXO set");
+    println!("  POST   /api/v1/payouts/:id/broadcast               - Broadcast finalized payout");
+    println!("  GET    /api/v1/audit                               - Audit log entries");
+    println!("  GET    /api/v1/audit/verify                        - Verify audit hash chain");
     println!("\n{}", "=".repeat(60));
     println!("Press Ctrl+C to stop the server\n");
 
@@ -79,6 +261,57 @@ async fn main() {
     axum::serve(listener, app).await.expect("Server error");
 }
 
+/// Polls `source` for every broadcast or confirmed payout each `interval`.
+fn spawn_confirmation_tracking(
+    node: GlobalNode,
+    source: Arc<dyn ConfirmationSource>,
+    interval: Duration,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let (node, source) = (node.clone(), source.clone());
+            // Chain lookups block; keep them off the async workers
+            match tokio::task::spawn_blocking(move || node.track_confirmations(source.as_ref()))
+                .await
+            {
+                Ok(taken) if !taken.is_empty() => {
+                    tracing::info!("Recorded {} payout confirmation transitions", taken.len())
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Confirmation tracking failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Fetches participants' co-spends from `source` each `interval`.
+#[cfg(feature = "esplora")]
+fn spawn_co_spend_tracking(node: GlobalNode, source: Arc<dyn CoSpendSource>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let (node, source) = (node.clone(), source.clone());
+            match tokio::task::spawn_blocking(move || node.refresh_co_spends(source.as_ref())).await
+            {
+                Ok(Ok(added)) if added > 0 => tracing::info!("Recorded {} new co-spends", added),
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => tracing::warn!("Co-spend lookup failed: {}", e),
+                Err(e) => tracing::warn!("Co-spend lookup failed: {}", e),
+            }
+        }
+    });
+}
+
+fn load_trust_policy(path: &str) -> Result<TrustPolicy, String> {
+    let json = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let config: TrustPolicyConfig =
+        serde_json::from_str(&json).map_err(|e| format!("{path}: {e}"))?;
+    TrustPolicy::from_config(&config)
+}
+
 fn print_startup_banner() {
     let banner = r#"
 ╔══════════════════════════════════════════════════════════════╗
@@ -1,8 +1,11 @@
+use crate::disbursement::{
+    classify_rejection, BroadcastError, Broadcaster, ConfirmationSource, TxChainStatus,
+};
 use crate::utxo_scoring::UtxoEntry;
 use crate::velocity_analyzer::{ChainDataSource, TxActivity, VelocityError};
 use bitcoin::address::NetworkUnchecked;
 use bitcoin::amount::Amount;
-use bitcoin::Address;
+use bitcoin::{Address, Transaction, Txid};
 use bitcoincore_rpc::json::GetTransactionResultDetailCategory;
 use bitcoincore_rpc::{Client, RpcApi};
 use std::collections::{HashMap, HashSet};
@@ -370,6 +373,59 @@ impl ChainDataSource for BitcoinCoreChainDataSource {
     }
 }
 
+impl ConfirmationSource for BitcoinCoreChainDataSource {
+    /// Transactions outside the mempool and wallet require `-txindex`.
+    fn transaction_status(&self, txid: &Txid) -> Result<TxChainStatus, VelocityError> {
+        match self.client.get_raw_transaction_info(txid, None) {
+            Ok(info) => Ok(match info.confirmations {
+                Some(confirmations) if confirmations > 0 => {
+                    TxChainStatus::Confirmed { confirmations }
+                }
+                _ => TxChainStatus::InMempool,
+            }),
+            Err(err)
+                if err
+                    .to_string()
+                    .to_lowercase()
+                    .contains("no such mempool or blockchain transaction") =>
+            {
+                Ok(TxChainStatus::Unknown)
+            }
+            Err(err) => Err(Self::map_rpc_error(err)),
+        }
+    }
+}
+
+impl Broadcaster for BitcoinCoreChainDataSource {
+    /// Runs `testmempoolaccept` first so policy rejections come back with
+    /// their reason before anything is relayed.
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, BroadcastError> {
+        let results = self
+            .client
+            .test_mempool_accept(&[tx])
+            .map_err(map_broadcast_error)?;
+        if let Some(result) = results.first() {
+            if !result.allowed {
+                return Err(classify_rejection(
+                    result.reject_reason.as_deref().unwrap_or("rejected"),
+                ));
+            }
+        }
+        self.client
+            .send_raw_transaction(tx)
+            .map_err(map_broadcast_error)
+    }
+}
+
+fn map_broadcast_error(error: bitcoincore_rpc::Error) -> BroadcastError {
+    match error {
+        bitcoincore_rpc::Error::JsonRpc(bitcoincore_rpc::jsonrpc::Error::Rpc(rpc)) => {
+            classify_rejection(&rpc.message)
+        }
+        other => BroadcastError::Transport(other.to_string()),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RpcConfig {
     pub timeout: Duration,
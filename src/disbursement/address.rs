@@ -0,0 +1,138 @@
+//! Address validation against the engine's network.
+//!
+//! Every address a payout touches (recipient, change, and the pool keys that
+//! fund it or receive its change) must belong to `DisbursementConfig.network`;
+//! mainnet transactions paying testnet addresses are refused rather than
+//! warned about.
+
+use bitcoin::address::NetworkChecked;
+use bitcoin::{Address, Network};
+use std::str::FromStr;
+
+/// What an address is used for in a payout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressRole {
+    Recipient,
+    Change,
+    /// Pool keys spent by the payout.
+    Funding,
+}
+
+impl std::fmt::Display for AddressRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressRole::Recipient => write!(f, "recipient"),
+            AddressRole::Change => write!(f, "change"),
+            AddressRole::Funding => write!(f, "funding"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+    /// Not a valid address on any network.
+    Malformed {
+        role: AddressRole,
+        address: String,
+        reason: String,
+    },
+    /// Valid, but for another network.
+    NetworkMismatch {
+        role: AddressRole,
+        address: String,
+        expected: Network,
+    },
+    /// The pool descriptor's extended key is for another network.
+    PoolKeyNetworkMismatch {
+        role: AddressRole,
+        xpub: String,
+        expected: Network,
+    },
+}
+
+impl AddressError {
+    pub fn role(&self) -> AddressRole {
+        match self {
+            AddressError::Malformed { role, .. }
+            | AddressError::NetworkMismatch { role, .. }
+            | AddressError::PoolKeyNetworkMismatch { role, .. } => *role,
+        }
+    }
+}
+
+impl std::fmt::Display for AddressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressError::Malformed {
+                role,
+                address,
+                reason,
+            } => write!(f, "invalid {role} address '{address}': {reason}"),
+            AddressError::NetworkMismatch {
+                role,
+                address,
+                expected,
+            } => write!(
+                f,
+                "{role} address {address} is not valid for network {expected}"
+            ),
+            AddressError::PoolKeyNetworkMismatch {
+                role,
+                xpub,
+                expected,
+            } => write!(
+                f,
+                "pool {role} key {xpub} is not valid for network {expected}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+/// Parses `address` and requires it to belong to `network`.
+pub fn parse_address(
+    address: &str,
+    network: Network,
+    role: AddressRole,
+) -> Result<Address<NetworkChecked>, AddressError> {
+    let unchecked = Address::from_str(address).map_err(|e| AddressError::Malformed {
+        role,
+        address: address.to_string(),
+        reason: e.to_string(),
+    })?;
+    unchecked
+        .require_network(network)
+        .map_err(|_| AddressError::NetworkMismatch {
+            role,
+            address: address.to_string(),
+            expected: network,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_addresses_for_other_networks() {
+        let testnet = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+        assert!(parse_address(testnet, Network::Testnet, AddressRole::Recipient).is_ok());
+        assert!(parse_address(testnet, Network::Signet, AddressRole::Recipient).is_ok());
+        assert_eq!(
+            parse_address(testnet, Network::Bitcoin, AddressRole::Change),
+            Err(AddressError::NetworkMismatch {
+                role: AddressRole::Change,
+                address: testnet.to_string(),
+                expected: Network::Bitcoin,
+            })
+        );
+        assert!(matches!(
+            parse_address("bc1qnotanaddress", Network::Bitcoin, AddressRole::Recipient),
+            Err(AddressError::Malformed {
+                role: AddressRole::Recipient,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,444 @@
+use super::{
+    decode_psbt, ensure_supported_inputs, estimate_vsize, fund_outputs, parse_address,
+    signing_progress, unsigned_transaction, AddressRole, AileeTrustAudit, DisbursementEngine,
+    DisbursementError, PayoutRequest, PayoutStatus, PayoutTransactionResult, PoolUtxo,
+    DUST_LIMIT_SATS,
+};
+use bitcoin::{Network, ScriptBuf, TxOut};
+use serde::{Deserialize, Serialize};
+
+/// One participant's share of an epoch distribution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayoutAllocation {
+    pub participant_id: String,
+    pub recipient_address: String,
+    pub amount_sats: u64,
+}
+
+/// Who pays the mining fee of a batch transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchFeePolicy {
+    /// Fee is funded from pool inputs; recipients receive their full amount.
+    #[default]
+    PoolPays,
+    /// Fee is deducted from recipient outputs in proportion to their amount.
+    SplitProRata,
+}
+
+/// What happens to recipients whose merged allocation is below dust.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubDustPolicy {
+    /// Allocation is forfeited and stays in the pool.
+    #[default]
+    Drop,
+    /// Allocation is returned to the caller to add to the next epoch.
+    RollOver,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchPayoutRequest {
+    pub allocations: Vec<PayoutAllocation>,
+    #[serde(default)]
+    pub fee_policy: BatchFeePolicy,
+    #[serde(default)]
+    pub sub_dust_policy: SubDustPolicy,
+    /// Lowers `DisbursementConfig::max_batch_outputs` for this batch.
+    pub max_outputs_per_tx: Option<usize>,
+    pub change_address: Option<String>,
+    pub fee_rate_sats_per_vbyte: Option<u64>,
+    pub dry_run: Option<bool>,
+}
+
+/// A recipient output of a batch transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchOutput {
+    pub recipient_address: String,
+    /// Participants whose allocations were merged into this output.
+    pub participant_ids: Vec<String>,
+    pub allocated_sats: u64,
+    /// Share of the fee deducted under `BatchFeePolicy::SplitProRata`.
+    pub fee_share_sats: u64,
+    /// Output value: `allocated_sats - fee_share_sats`.
+    pub amount_sats: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchTransaction {
+    /// Payout record tracking this transaction through signing and
+    /// broadcast: `<batch_id>-<n>`, counting from 1.
+    pub payout_id: String,
+    pub txid: String,
+    pub psbt_base64: String,
+    pub raw_tx_hex: String,
+    pub outputs: Vec<BatchOutput>,
+    pub fee_sats: u64,
+    pub change_sats: Option<u64>,
+    /// Findings for every recipient of this transaction.
+    pub trust_audit: AileeTrustAudit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchPayoutResult {
+    pub batch_id: String,
+    pub status: PayoutStatus,
+    pub fee_policy: BatchFeePolicy,
+    pub transactions: Vec<BatchTransaction>,
+    /// Sub-dust allocations forfeited under `SubDustPolicy::Drop`.
+    pub dropped: Vec<PayoutAllocation>,
+    /// Sub-dust allocations to carry into the next epoch.
+    pub rolled_over: Vec<PayoutAllocation>,
+    pub total_paid_sats: u64,
+    pub total_fee_sats: u64,
+    pub timestamp: String,
+    pub is_dry_run: bool,
+}
+
+impl BatchPayoutResult {
+    /// One payout record per transaction, so each goes through the same
+    /// signing, broadcast and confirmation lifecycle as a single payout.
+    pub fn payout_records(&self) -> Vec<PayoutTransactionResult> {
+        self.transactions
+            .iter()
+            .map(|tx| PayoutTransactionResult {
+                payout_id: tx.payout_id.clone(),
+                recipient_address: tx
+                    .outputs
+                    .first()
+                    .map(|o| o.recipient_address.clone())
+                    .unwrap_or_default(),
+                amount_sats: tx.outputs.iter().map(|o| o.amount_sats).sum(),
+                fee_sats: tx.fee_sats,
+                status: self.status.clone(),
+                psbt_base64: tx.psbt_base64.clone(),
+                raw_tx_hex: tx.raw_tx_hex.clone(),
+                txid: tx.txid.clone(),
+                timestamp: self.timestamp.clone(),
+                trust_audit: tx.trust_audit.clone(),
+                is_dry_run: self.is_dry_run,
+                confirmations: 0,
+                signed_psbt_base64: None,
+                final_tx_hex: None,
+                fee_bump_of: None,
+                fee_bumped_by: None,
+                signing: decode_psbt(&tx.psbt_base64)
+                    .ok()
+                    .and_then(|psbt| signing_progress(&psbt)),
+                batch_id: Some(self.batch_id.clone()),
+            })
+            .collect()
+    }
+}
+
+/// Allocations merged by recipient script.
+struct MergedRecipient {
+    address: String,
+    script_pubkey: ScriptBuf,
+    allocations: Vec<PayoutAllocation>,
+    amount_sats: u64,
+    /// Set once the trust policy has evaluated the recipient.
+    trust_audit: Option<AileeTrustAudit>,
+}
+
+impl DisbursementEngine {
+    /// Builds unsigned batch transactions paying every allocation of an
+    /// epoch, one output per distinct recipient.
+    ///
+    /// Recipients are split into chunks of at most `max_outputs_per_tx`
+    /// outputs; each chunk is funded from pool UTXOs not used by earlier
+    /// chunks.
+    pub fn create_batch_payout(
+        &self,
+        batch_id: String,
+        req: &BatchPayoutRequest,
+        pool_utxos: &[PoolUtxo],
+    ) -> Result<BatchPayoutResult, DisbursementError> {
+        let max_outputs = req
+            .max_outputs_per_tx
+            .map_or(self.config.max_batch_outputs, |max| {
+                max.min(self.config.max_batch_outputs)
+            });
+        if max_outputs == 0 {
+            return Err(DisbursementError::InvalidRequest(
+                "max_outputs_per_tx must be > 0".to_string(),
+            ));
+        }
+
+        let mut dropped = Vec::new();
+        let mut rolled_over = Vec::new();
+        let mut recipients = Vec::new();
+        for recipient in merge_allocations(&req.allocations, self.config.network)? {
+            if recipient.amount_sats < DUST_LIMIT_SATS {
+                match req.sub_dust_policy {
+                    SubDustPolicy::Drop => dropped.extend(recipient.allocations),
+                    SubDustPolicy::RollOver => rolled_over.extend(recipient.allocations),
+                }
+            } else {
+                recipients.push(recipient);
+            }
+        }
+        if recipients.is_empty() {
+            return Err(DisbursementError::InvalidRequest(
+                "Batch has no allocations at or above the dust limit".to_string(),
+            ));
+        }
+
+        // Pool-wide limits see everything allocated so far, and each
+        // transaction of the batch as a payout, so the last recipient is
+        // checked against the batch total.
+        let mut batch_sats = 0u64;
+        for (position, recipient) in recipients.iter_mut().enumerate() {
+            let audit = self.evaluate_trust(
+                &PayoutRequest {
+                    recipient_address: recipient.address.clone(),
+                    amount_sats: recipient.amount_sats,
+                    funding_utxo_txid: None,
+                    funding_utxo_vout: None,
+                    funding_utxo_value_sats: None,
+                    change_address: None,
+                    fee_rate_sats_per_vbyte: None,
+                    dry_run: None,
+                },
+                batch_sats,
+                (position / max_outputs) as u64,
+            );
+            if self.blocks_payout(&audit) {
+                return Err(DisbursementError::PolicyRejected {
+                    recipient_address: recipient.address.clone(),
+                    findings: audit.findings,
+                });
+            }
+            recipient.trust_audit = Some(audit);
+            batch_sats = batch_sats.saturating_add(recipient.amount_sats);
+        }
+
+        let fee_rate = self.fee_rate(req.fee_rate_sats_per_vbyte)?;
+        let mut available = self.with_pool_scripts(pool_utxos);
+        ensure_supported_inputs(&available)?;
+
+        let mut transactions = Vec::new();
+        for (n, chunk) in (1..).zip(recipients.chunks(max_outputs)) {
+            let payout_id = format!("{batch_id}-{n}");
+            let (tx, spent) =
+                self.build_batch_chunk(payout_id, chunk, req, fee_rate, pool_utxos, &available)?;
+            available.retain(|u| !spent.iter().any(|s| s.outpoint == u.outpoint));
+            transactions.push(tx);
+        }
+
+        let is_dry_run = req.dry_run.unwrap_or(false);
+        Ok(BatchPayoutResult {
+            batch_id,
+            status: if is_dry_run {
+                PayoutStatus::Pending
+            } else {
+                PayoutStatus::UnsignedCreated
+            },
+            fee_policy: req.fee_policy,
+            total_paid_sats: transactions
+                .iter()
+                .flat_map(|t| &t.outputs)
+                .map(|o| o.amount_sats)
+                .sum(),
+            total_fee_sats: transactions.iter().map(|t| t.fee_sats).sum(),
+            transactions,
+            dropped,
+            rolled_over,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            is_dry_run,
+        })
+    }
+
+    /// Funds and encodes one chunk from `available`, what earlier chunks
+    /// left of `pool_utxos`, returning the transaction and spent UTXOs.
+    fn build_batch_chunk(
+        &self,
+        payout_id: String,
+        chunk: &[MergedRecipient],
+        req: &BatchPayoutRequest,
+        fee_rate: u64,
+        pool_utxos: &[PoolUtxo],
+        available: &[PoolUtxo],
+    ) -> Result<(BatchTransaction, Vec<PoolUtxo>), DisbursementError> {
+        let is_dry_run = req.dry_run.unwrap_or(false);
+        let change = self.resolve_change(req.change_address.as_deref(), pool_utxos, is_dry_run)?;
+        let change_script = change.script_pubkey.as_script();
+        let scripts: Vec<&bitcoin::Script> =
+            chunk.iter().map(|r| r.script_pubkey.as_script()).collect();
+        let target_sats: u64 = chunk.iter().map(|r| r.amount_sats).sum();
+
+        let (funding, fee_shares) = match req.fee_policy {
+            BatchFeePolicy::PoolPays => {
+                let funding =
+                    fund_outputs(available, &scripts, target_sats, change_script, fee_rate)?;
+                (funding, vec![0; chunk.len()])
+            }
+            BatchFeePolicy::SplitProRata => {
+                // Inputs only need to cover the allocations; the fee comes out
+                // of the recipient outputs.
+                let mut funding = fund_outputs(available, &scripts, target_sats, change_script, 0)?;
+                let mut outputs = scripts.clone();
+                if funding.change_sats.is_some() {
+                    outputs.push(change_script);
+                }
+                let fee_sats =
+                    fee_rate.saturating_mul(estimate_vsize(&funding.input_types, &outputs));
+                let excess =
+                    funding.input_value_sats - target_sats - funding.change_sats.unwrap_or(0);
+                let shares = split_pro_rata(
+                    fee_sats.saturating_sub(excess),
+                    &chunk.iter().map(|r| r.amount_sats).collect::<Vec<_>>(),
+                );
+                funding.fee_sats = excess + shares.iter().sum::<u64>();
+                (funding, shares)
+            }
+        };
+
+        let mut batch_outputs = Vec::with_capacity(chunk.len());
+        let mut tx_outputs = Vec::with_capacity(chunk.len() + 1);
+        for (recipient, fee_share_sats) in chunk.iter().zip(fee_shares) {
+            let amount_sats = recipient
+                .amount_sats
+                .checked_sub(fee_share_sats)
+                .ok_or_else(|| {
+                    DisbursementError::InvalidRequest(format!(
+                        "Fee share of {fee_share_sats} sats exceeds the {} sats allocated to {}",
+                        recipient.amount_sats, recipient.address
+                    ))
+                })?;
+            if amount_sats < DUST_LIMIT_SATS {
+                return Err(DisbursementError::InvalidRequest(format!(
+                    "Output to {} falls below the dust limit after its fee share",
+                    recipient.address
+                )));
+            }
+            tx_outputs.push(TxOut {
+                value: amount_sats,
+                script_pubkey: recipient.script_pubkey.clone(),
+            });
+            batch_outputs.push(BatchOutput {
+                recipient_address: recipient.address.clone(),
+                participant_ids: recipient
+                    .allocations
+                    .iter()
+                    .map(|a| a.participant_id.clone())
+                    .collect(),
+                allocated_sats: recipient.amount_sats,
+                fee_share_sats,
+                amount_sats,
+            });
+        }
+        if let Some(change_sats) = funding.change_sats {
+            tx_outputs.push(TxOut {
+                value: change_sats,
+                script_pubkey: change.script_pubkey.clone(),
+            });
+        }
+
+        let tx = unsigned_transaction(&funding.selected, tx_outputs);
+        let change_output = funding.change_sats.map(|_| &change);
+        let (psbt_base64, raw_tx_hex) =
+            self.encode_unsigned(&tx, &funding.selected, change_output)?;
+        Ok((
+            BatchTransaction {
+                payout_id,
+                txid: tx.txid().to_string(),
+                psbt_base64,
+                raw_tx_hex,
+                outputs: batch_outputs,
+                fee_sats: funding.fee_sats,
+                change_sats: funding.change_sats,
+                trust_audit: combined_audit(chunk.iter().filter_map(|r| r.trust_audit.as_ref()))
+                    .expect("batch recipients are evaluated before chunking"),
+            },
+            funding.selected,
+        ))
+    }
+}
+
+/// Merges allocations paying the same script, keeping first-seen order.
+fn merge_allocations(
+    allocations: &[PayoutAllocation],
+    network: Network,
+) -> Result<Vec<MergedRecipient>, DisbursementError> {
+    let mut merged: Vec<MergedRecipient> = Vec::new();
+    for allocation in allocations {
+        let script_pubkey = parse_address(
+            &allocation.recipient_address,
+            network,
+            AddressRole::Recipient,
+        )?
+        .script_pubkey();
+
+        match merged.iter_mut().find(|m| m.script_pubkey == script_pubkey) {
+            Some(existing) => {
+                existing.amount_sats = existing
+                    .amount_sats
+                    .checked_add(allocation.amount_sats)
+                    .ok_or_else(|| {
+                        DisbursementError::InvalidRequest("Allocation total overflows".into())
+                    })?;
+                existing.allocations.push(allocation.clone());
+            }
+            None => merged.push(MergedRecipient {
+                address: allocation.recipient_address.clone(),
+                script_pubkey,
+                allocations: vec![allocation.clone()],
+                amount_sats: allocation.amount_sats,
+                trust_audit: None,
+            }),
+        }
+    }
+    Ok(merged)
+}
+
+/// One audit for a transaction paying several recipients: every finding and
+/// warning, and the highest risk score. `None` without any audit.
+fn combined_audit<'a>(
+    audits: impl Iterator<Item = &'a AileeTrustAudit>,
+) -> Option<AileeTrustAudit> {
+    audits.cloned().reduce(|mut combined, audit| {
+        combined.passed &= audit.passed;
+        combined.risk_score = combined.risk_score.max(audit.risk_score);
+        combined.warnings.extend(audit.warnings);
+        combined.findings.extend(audit.findings);
+        combined
+    })
+}
+
+/// Splits `fee_sats` proportionally to `amounts`; rounding remainders go one
+/// sat at a time to the largest amounts.
+fn split_pro_rata(fee_sats: u64, amounts: &[u64]) -> Vec<u64> {
+    let total: u128 = amounts.iter().map(|&a| a as u128).sum();
+    if total == 0 {
+        return vec![0; amounts.len()];
+    }
+    let mut shares: Vec<u64> = amounts
+        .iter()
+        .map(|&a| (fee_sats as u128 * a as u128 / total) as u64)
+        .collect();
+
+    let mut remainder = fee_sats - shares.iter().sum::<u64>();
+    let mut order: Vec<usize> = (0..amounts.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(amounts[i]));
+    for i in order.into_iter().cycle() {
+        if remainder == 0 {
+            break;
+        }
+        shares[i] += 1;
+        remainder -= 1;
+    }
+    shares
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pro_rata_split_is_exact() {
+        let shares = split_pro_rata(1_001, &[300_000, 100_000, 600_000]);
+        assert_eq!(shares, vec![300, 100, 601]);
+        assert_eq!(shares.iter().sum::<u64>(), 1_001);
+    }
+}
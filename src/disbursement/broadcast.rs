@@ -0,0 +1,142 @@
+//! Broadcasting finalized payout transactions.
+//!
+//! Backends report rejections as free-form reasons (`min relay fee not met`,
+//! `bad-txns-inputs-missingorspent`, ...). [`classify_rejection`] maps them
+//! onto [`BroadcastError`] so the payout lifecycle can tell a transaction
+//! that will never be accepted from one worth retrying or bumping.
+
+use bitcoin::{Transaction, Txid};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BroadcastError {
+    /// Already in the mempool or the chain.
+    AlreadyKnown,
+    /// Below the relay or mempool minimum fee, or too low to replace.
+    InsufficientFee(String),
+    /// An input does not exist or is already spent in the chain.
+    MissingInputs,
+    /// An input is spent by another mempool transaction.
+    MempoolConflict,
+    /// Signature or script check failed.
+    ScriptVerification(String),
+    /// Rejected by standardness policy (dust, size, non-final, ...).
+    NonStandard(String),
+    /// Any other reject reason.
+    Rejected(String),
+    /// Backend unreachable or answered unexpectedly; nothing was decided.
+    Transport(String),
+}
+
+impl BroadcastError {
+    /// Whether resubmitting the same transaction can never succeed.
+    /// Unrecognised reasons are not treated as permanent. Missing inputs are
+    /// not either: the parent may simply not have reached the backend yet,
+    /// e.g. the stuck payout under a CPFP child.
+    pub fn is_permanent(&self) -> bool {
+        matches!(
+            self,
+            BroadcastError::ScriptVerification(_) | BroadcastError::NonStandard(_)
+        )
+    }
+}
+
+impl std::fmt::Display for BroadcastError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BroadcastError::AlreadyKnown => write!(f, "transaction already known"),
+            BroadcastError::InsufficientFee(reason) => write!(f, "insufficient fee: {reason}"),
+            BroadcastError::MissingInputs => write!(f, "inputs missing or already spent"),
+            BroadcastError::MempoolConflict => {
+                write!(f, "inputs spent by a conflicting mempool transaction")
+            }
+            BroadcastError::ScriptVerification(reason) => {
+                write!(f, "script verification failed: {reason}")
+            }
+            BroadcastError::NonStandard(reason) => write!(f, "non-standard transaction: {reason}"),
+            BroadcastError::Rejected(reason) => write!(f, "transaction rejected: {reason}"),
+            BroadcastError::Transport(reason) => write!(f, "broadcast backend error: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for BroadcastError {}
+
+/// Backend able to relay a fully signed transaction.
+pub trait Broadcaster: Send + Sync {
+    /// Submits `tx`, returning its txid once the backend accepted it.
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, BroadcastError>;
+}
+
+/// Maps a Bitcoin Core reject reason (as also relayed by Esplora) onto a
+/// typed error.
+pub fn classify_rejection(reason: &str) -> BroadcastError {
+    let lower = reason.to_lowercase();
+    let has = |needles: &[&str]| needles.iter().any(|n| lower.contains(n));
+
+    if has(&[
+        "txn-already-in-mempool",
+        "txn-already-known",
+        "already in block chain",
+        "already in utxo set",
+    ]) {
+        BroadcastError::AlreadyKnown
+    } else if has(&["txn-mempool-conflict"]) {
+        BroadcastError::MempoolConflict
+    } else if has(&["missing-inputs", "missing inputs", "inputs-missingorspent"]) {
+        BroadcastError::MissingInputs
+    } else if has(&[
+        "fee not met",
+        "insufficient fee",
+        "min-fee",
+        "min relay fee",
+    ]) {
+        BroadcastError::InsufficientFee(reason.to_string())
+    } else if has(&["script-verify-flag", "signature"]) {
+        BroadcastError::ScriptVerification(reason.to_string())
+    } else if has(&[
+        "dust",
+        "non-final",
+        "non-bip68-final",
+        "tx-size",
+        "scriptsig",
+        "scriptpubkey",
+        "version",
+        "multi-op-return",
+        "bare-multisig",
+        "non-standard",
+    ]) {
+        BroadcastError::NonStandard(reason.to_string())
+    } else {
+        BroadcastError::Rejected(reason.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_core_reject_reasons() {
+        assert_eq!(
+            classify_rejection("txn-already-in-mempool"),
+            BroadcastError::AlreadyKnown
+        );
+        assert_eq!(
+            classify_rejection("bad-txns-inputs-missingorspent"),
+            BroadcastError::MissingInputs
+        );
+        assert!(matches!(
+            classify_rejection("min relay fee not met, 100 < 141"),
+            BroadcastError::InsufficientFee(_)
+        ));
+        assert!(matches!(
+            classify_rejection(
+                "mandatory-script-verify-flag-failed (Signature must be zero for failed CHECK(MULTI)SIG operation)"
+            ),
+            BroadcastError::ScriptVerification(_)
+        ));
+        assert!(classify_rejection("dust").is_permanent());
+        assert!(!classify_rejection("txn-mempool-conflict").is_permanent());
+        assert!(!classify_rejection("bad-txns-inputs-missingorspent").is_permanent());
+    }
+}
@@ -0,0 +1,529 @@
+use super::descriptor::KeyChainIndex;
+use super::tx_size::InputScriptType;
+use bitcoin::{OutPoint, ScriptBuf, Transaction, Txid};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Maximum number of branch-and-bound nodes explored before falling back.
+const BNB_MAX_TRIES: u32 = 100_000;
+
+/// A confirmed output controlled by the pool wallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolUtxo {
+    pub outpoint: OutPoint,
+    pub value_sats: u64,
+    pub script_pubkey: ScriptBuf,
+    /// Witness script for P2WSH outputs (stake locks); `None` otherwise.
+    pub witness_script: Option<ScriptBuf>,
+    /// Position of the key in the pool descriptor, for PSBT key origins.
+    pub derivation: Option<KeyChainIndex>,
+    /// Funding transaction, attached as `non_witness_utxo`. Required by
+    /// signers for P2PKH inputs.
+    pub previous_tx: Option<Transaction>,
+}
+
+impl PoolUtxo {
+    pub fn new(outpoint: OutPoint, value_sats: u64, script_pubkey: ScriptBuf) -> Self {
+        Self {
+            outpoint,
+            value_sats,
+            script_pubkey,
+            witness_script: None,
+            derivation: None,
+            previous_tx: None,
+        }
+    }
+
+    pub fn with_witness_script(mut self, witness_script: ScriptBuf) -> Self {
+        self.witness_script = Some(witness_script);
+        self
+    }
+
+    pub fn with_derivation(mut self, derivation: KeyChainIndex) -> Self {
+        self.derivation = Some(derivation);
+        self
+    }
+
+    pub fn with_previous_tx(mut self, previous_tx: Transaction) -> Self {
+        self.previous_tx = Some(previous_tx);
+        self
+    }
+
+    /// Spend path used for size estimation; `None` for unsupported scripts.
+    pub fn input_type(&self) -> Option<InputScriptType> {
+        InputScriptType::classify(&self.script_pubkey, self.witness_script.as_deref())
+    }
+}
+
+/// A [`PoolUtxo`] with hex-encoded scripts and funding transaction, as the
+/// API accepts it and registry storage keeps it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolUtxoRecord {
+    pub txid: String,
+    pub vout: u32,
+    pub value_sats: u64,
+    pub script_pubkey_hex: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub witness_script_hex: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derivation: Option<KeyChainIndex>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_tx_hex: Option<String>,
+}
+
+impl From<&PoolUtxo> for PoolUtxoRecord {
+    fn from(utxo: &PoolUtxo) -> Self {
+        Self {
+            txid: utxo.outpoint.txid.to_string(),
+            vout: utxo.outpoint.vout,
+            value_sats: utxo.value_sats,
+            script_pubkey_hex: hex::encode(utxo.script_pubkey.as_bytes()),
+            witness_script_hex: utxo
+                .witness_script
+                .as_ref()
+                .map(|script| hex::encode(script.as_bytes())),
+            derivation: utxo.derivation,
+            previous_tx_hex: utxo
+                .previous_tx
+                .as_ref()
+                .map(|tx| hex::encode(bitcoin::consensus::serialize(tx))),
+        }
+    }
+}
+
+impl TryFrom<PoolUtxoRecord> for PoolUtxo {
+    type Error = String;
+
+    fn try_from(record: PoolUtxoRecord) -> Result<Self, Self::Error> {
+        let script = |field: &str, hex_str: &str| {
+            hex::decode(hex_str)
+                .map(ScriptBuf::from_bytes)
+                .map_err(|e| format!("invalid {field}: {e}"))
+        };
+        let outpoint = OutPoint {
+            txid: Txid::from_str(&record.txid).map_err(|e| format!("invalid txid: {e}"))?,
+            vout: record.vout,
+        };
+        let previous_tx = match record.previous_tx_hex {
+            Some(ref tx_hex) => Some(
+                hex::decode(tx_hex)
+                    .map_err(|e| e.to_string())
+                    .and_then(|bytes| {
+                        bitcoin::consensus::deserialize(&bytes).map_err(|e| e.to_string())
+                    })
+                    .map_err(|e| format!("invalid previous transaction: {e}"))?,
+            ),
+            None => None,
+        };
+        Ok(PoolUtxo {
+            outpoint,
+            value_sats: record.value_sats,
+            script_pubkey: script("script_pubkey", &record.script_pubkey_hex)?,
+            witness_script: record
+                .witness_script_hex
+                .as_deref()
+                .map(|hex_str| script("witness script", hex_str))
+                .transpose()?,
+            derivation: record.derivation,
+            previous_tx,
+        })
+    }
+}
+
+/// Sizes and amounts the selection has to cover.
+#[derive(Debug, Clone)]
+pub struct SelectionParams {
+    /// Sum of all recipient outputs.
+    pub target_sats: u64,
+
+    /// Fee rate in sat/vB.
+    pub fee_rate: u64,
+
+    /// vsize of the transaction without any inputs and without change.
+    pub base_vsize: u64,
+
+    /// vsize added by a change output.
+    pub change_output_vsize: u64,
+
+    /// vsize of the input that will later spend the change output.
+    pub change_spend_vsize: u64,
+
+    pub dust_limit_sats: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionAlgorithm {
+    /// Exact match within the cost of change; no change output.
+    BranchAndBound,
+    LargestFirst,
+}
+
+#[derive(Debug, Clone)]
+pub struct CoinSelection {
+    pub selected: Vec<PoolUtxo>,
+    pub algorithm: SelectionAlgorithm,
+    pub input_value_sats: u64,
+    pub fee_sats: u64,
+    /// `None` when the excess is absorbed into the fee instead of creating
+    /// a change output below the dust limit.
+    pub change_sats: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoinSelectionError {
+    InsufficientFunds {
+        available_sats: u64,
+        required_sats: u64,
+    },
+    /// The target plus fees, or the value of the inputs, does not fit in a
+    /// `u64`.
+    AmountOverflow,
+}
+
+impl std::fmt::Display for CoinSelectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoinSelectionError::InsufficientFunds {
+                available_sats,
+                required_sats,
+            } => write!(
+                f,
+                "insufficient pool funds: available {available_sats} sats, required {required_sats} sats"
+            ),
+            CoinSelectionError::AmountOverflow => {
+                write!(f, "amount plus fees or input value overflows")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CoinSelectionError {}
+
+struct Candidate {
+    utxo: PoolUtxo,
+    input_fee: u64,
+    effective_value: u64,
+}
+
+/// Selects pool UTXOs to fund `params.target_sats` plus fees.
+///
+/// Tries branch-and-bound for a changeless solution first and falls back to
+/// largest-first. UTXOs whose value does not cover their own input fee are
+/// never selected. `input_vsize` gives the spend size of each UTXO.
+pub fn select_coins(
+    utxos: &[PoolUtxo],
+    params: &SelectionParams,
+    input_vsize: &dyn Fn(&PoolUtxo) -> u64,
+) -> Result<CoinSelection, CoinSelectionError> {
+    let mut candidates: Vec<Candidate> = utxos
+        .iter()
+        .filter_map(|utxo| {
+            let input_fee = params.fee_rate.saturating_mul(input_vsize(utxo));
+            let effective_value = utxo.value_sats.checked_sub(input_fee)?;
+            (effective_value > 0).then(|| Candidate {
+                utxo: utxo.clone(),
+                input_fee,
+                effective_value,
+            })
+        })
+        .collect();
+
+    // Deterministic order: largest effective value first, then outpoint.
+    candidates.sort_by(|a, b| {
+        b.effective_value
+            .cmp(&a.effective_value)
+            .then_with(|| a.utxo.outpoint.txid.cmp(&b.utxo.outpoint.txid))
+            .then_with(|| a.utxo.outpoint.vout.cmp(&b.utxo.outpoint.vout))
+    });
+
+    let base_fee = params.fee_rate.saturating_mul(params.base_vsize);
+    let target = params
+        .target_sats
+        .checked_add(base_fee)
+        .ok_or(CoinSelectionError::AmountOverflow)?;
+    let cost_of_change = params.fee_rate.saturating_mul(
+        params
+            .change_output_vsize
+            .saturating_add(params.change_spend_vsize),
+    );
+
+    if let Some(indices) = branch_and_bound(&candidates, target, cost_of_change) {
+        let selected: Vec<&Candidate> = indices.iter().map(|&i| &candidates[i]).collect();
+        let input_value_sats: u64 = selected.iter().map(|c| c.utxo.value_sats).sum();
+        return Ok(CoinSelection {
+            selected: selected.iter().map(|c| c.utxo.clone()).collect(),
+            algorithm: SelectionAlgorithm::BranchAndBound,
+            input_value_sats,
+            fee_sats: input_value_sats - params.target_sats,
+            change_sats: None,
+        });
+    }
+
+    largest_first(&candidates, params, base_fee)
+}
+
+fn branch_and_bound(
+    candidates: &[Candidate],
+    target: u64,
+    cost_of_change: u64,
+) -> Option<Vec<usize>> {
+    let total = candidates
+        .iter()
+        .fold(0u64, |total, c| total.saturating_add(c.effective_value));
+    if total < target {
+        return None;
+    }
+
+    let mut search = BnbSearch {
+        candidates,
+        target,
+        upper: target.saturating_add(cost_of_change),
+        tries: BNB_MAX_TRIES,
+        selected: Vec::new(),
+        best: None,
+    };
+    search.explore(0, 0, total);
+    search.best.map(|(_, indices)| indices)
+}
+
+struct BnbSearch<'a> {
+    candidates: &'a [Candidate],
+    target: u64,
+    upper: u64,
+    tries: u32,
+    selected: Vec<usize>,
+    best: Option<(u64, Vec<usize>)>,
+}
+
+impl BnbSearch<'_> {
+    fn explore(&mut self, index: usize, current: u64, remaining: u64) {
+        if self.tries == 0 || current > self.upper {
+            return;
+        }
+        self.tries -= 1;
+
+        if current >= self.target {
+            // Adding inputs only grows the excess, so this branch is done.
+            let waste = current - self.target;
+            let better = match &self.best {
+                None => true,
+                Some((best_waste, best)) => {
+                    waste < *best_waste
+                        || (waste == *best_waste && self.selected.len() < best.len())
+                }
+            };
+            if better {
+                self.best = Some((waste, self.selected.clone()));
+            }
+            return;
+        }
+
+        if index == self.candidates.len() || current.saturating_add(remaining) < self.target {
+            return;
+        }
+
+        let value = self.candidates[index].effective_value;
+        // `remaining` saturates at u64::MAX, so it stays an upper bound.
+        let remaining = remaining.saturating_sub(value);
+        // Inputs worth more than a u64 are past any upper bound.
+        if let Some(with_value) = current.checked_add(value) {
+            self.selected.push(index);
+            self.explore(index + 1, with_value, remaining);
+            self.selected.pop();
+        }
+        self.explore(index + 1, current, remaining);
+    }
+}
+
+fn largest_first(
+    candidates: &[Candidate],
+    params: &SelectionParams,
+    base_fee: u64,
+) -> Result<CoinSelection, CoinSelectionError> {
+    let change_output_fee = params.fee_rate.saturating_mul(params.change_output_vsize);
+
+    let mut input_value_sats = 0u64;
+    let mut input_fees = 0u64;
+    let mut required_sats = params
+        .target_sats
+        .checked_add(base_fee)
+        .ok_or(CoinSelectionError::AmountOverflow)?;
+    for (count, candidate) in candidates.iter().enumerate() {
+        input_value_sats = input_value_sats
+            .checked_add(candidate.utxo.value_sats)
+            .ok_or(CoinSelectionError::AmountOverflow)?;
+        input_fees = input_fees
+            .checked_add(candidate.input_fee)
+            .ok_or(CoinSelectionError::AmountOverflow)?;
+
+        let fee_without_change = base_fee
+            .checked_add(input_fees)
+            .ok_or(CoinSelectionError::AmountOverflow)?;
+        required_sats = params
+            .target_sats
+            .checked_add(fee_without_change)
+            .ok_or(CoinSelectionError::AmountOverflow)?;
+        let Some(excess) = input_value_sats.checked_sub(required_sats) else {
+            continue;
+        };
+
+        let selected = candidates[..=count]
+            .iter()
+            .map(|c| c.utxo.clone())
+            .collect();
+        let change = excess.saturating_sub(change_output_fee);
+        return Ok(
+            if excess > change_output_fee && change >= params.dust_limit_sats {
+                CoinSelection {
+                    selected,
+                    algorithm: SelectionAlgorithm::LargestFirst,
+                    input_value_sats,
+                    fee_sats: fee_without_change + change_output_fee,
+                    change_sats: Some(change),
+                }
+            } else {
+                // Change would be dust; leave the excess to the miners.
+                CoinSelection {
+                    selected,
+                    algorithm: SelectionAlgorithm::LargestFirst,
+                    input_value_sats,
+                    fee_sats: input_value_sats - params.target_sats,
+                    change_sats: None,
+                }
+            },
+        );
+    }
+
+    Err(CoinSelectionError::InsufficientFunds {
+        available_sats: input_value_sats,
+        required_sats,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+    use bitcoin::Txid;
+
+    fn utxo(n: u8, value_sats: u64) -> PoolUtxo {
+        PoolUtxo::new(
+            OutPoint {
+                txid: Txid::from_slice(&[n; 32]).unwrap(),
+                vout: 0,
+            },
+            value_sats,
+            ScriptBuf::new(),
+        )
+    }
+
+    fn params(target_sats: u64) -> SelectionParams {
+        SelectionParams {
+            target_sats,
+            fee_rate: 1,
+            base_vsize: 42,
+            change_output_vsize: 31,
+            change_spend_vsize: 68,
+            dust_limit_sats: 546,
+        }
+    }
+
+    #[test]
+    fn bnb_finds_changeless_combination() {
+        let pool = vec![
+            utxo(1, 500_000),
+            utxo(2, 30_068),
+            utxo(3, 70_068),
+            utxo(4, 1_000),
+        ];
+        // 30_000 + 70_000 effective + 42 base fee covers 100_000 exactly.
+        let selection = select_coins(&pool, &params(99_958), &|_| 68).unwrap();
+
+        assert_eq!(selection.algorithm, SelectionAlgorithm::BranchAndBound);
+        assert_eq!(selection.change_sats, None);
+        assert_eq!(selection.selected.len(), 2);
+        assert_eq!(selection.fee_sats, 42 + 2 * 68);
+    }
+
+    #[test]
+    fn largest_first_creates_change_or_drops_dust() {
+        let pool = vec![utxo(1, 60_000), utxo(2, 50_000)];
+        let selection = select_coins(&pool, &params(100_000), &|_| 68).unwrap();
+        assert_eq!(selection.algorithm, SelectionAlgorithm::LargestFirst);
+        assert_eq!(selection.selected.len(), 2);
+        let fee = 42 + 2 * 68 + 31;
+        assert_eq!(selection.fee_sats, fee);
+        assert_eq!(selection.change_sats, Some(110_000 - 100_000 - fee));
+
+        // Excess of 400 sats after fees is below dust: no change output.
+        let pool = vec![utxo(1, 100_000 + 42 + 68 + 400)];
+        let selection = select_coins(&pool, &params(100_000), &|_| 68).unwrap();
+        assert_eq!(selection.change_sats, None);
+        assert_eq!(selection.fee_sats, 42 + 68 + 400);
+    }
+
+    #[test]
+    fn insufficient_funds_reports_amounts() {
+        let pool = vec![utxo(1, 10_000), utxo(2, 50)];
+        let err = select_coins(&pool, &params(20_000), &|_| 68).unwrap_err();
+        assert_eq!(
+            err,
+            CoinSelectionError::InsufficientFunds {
+                available_sats: 10_000,
+                required_sats: 20_000 + 42 + 68,
+            }
+        );
+    }
+
+    #[test]
+    fn overflowing_amounts_are_refused() {
+        let pool = vec![utxo(1, 10_000)];
+        let err = select_coins(&pool, &params(u64::MAX - 10), &|_| 68).unwrap_err();
+        assert_eq!(err, CoinSelectionError::AmountOverflow);
+
+        let pool = vec![utxo(1, u64::MAX - 1_000), utxo(2, u64::MAX - 1_000)];
+        let err = select_coins(&pool, &params(u64::MAX - 100), &|_| 68).unwrap_err();
+        assert_eq!(err, CoinSelectionError::AmountOverflow);
+    }
+
+    #[test]
+    fn pool_utxo_records_round_trip() {
+        use crate::disbursement::{KeyChain, KeyChainIndex};
+
+        let previous_tx = Transaction {
+            version: 2,
+            lock_time: bitcoin::absolute::LockTime::ZERO,
+            input: Vec::new(),
+            output: vec![bitcoin::TxOut {
+                value: 10_000,
+                script_pubkey: ScriptBuf::from_bytes(vec![0x51]),
+            }],
+        };
+        let full = PoolUtxo::new(
+            OutPoint {
+                txid: previous_tx.txid(),
+                vout: 0,
+            },
+            10_000,
+            ScriptBuf::from_bytes(vec![0x51]),
+        )
+        .with_witness_script(ScriptBuf::from_bytes(vec![0x52]))
+        .with_derivation(KeyChainIndex {
+            chain: KeyChain::Internal,
+            index: 7,
+        })
+        .with_previous_tx(previous_tx);
+
+        for original in [utxo(1, 5_000), full] {
+            let json = serde_json::to_string(&PoolUtxoRecord::from(&original)).unwrap();
+            let record: PoolUtxoRecord = serde_json::from_str(&json).unwrap();
+            assert_eq!(PoolUtxo::try_from(record).unwrap(), original);
+        }
+
+        let mut bad = PoolUtxoRecord::from(&utxo(1, 5_000));
+        bad.script_pubkey_hex = "zz".to_string();
+        assert!(PoolUtxo::try_from(bad)
+            .unwrap_err()
+            .starts_with("invalid script_pubkey"));
+    }
+}
@@ -0,0 +1,426 @@
+//! Pool wallet descriptors.
+//!
+//! Supports single-key `wpkh(KEY)` and `tr(KEY)`, and k-of-n
+//! `wsh(sortedmulti(k,KEY,...))`, where `KEY` is an extended public key
+//! with optional origin and a ranged suffix, e.g.
+//! `wpkh([d34db33f/84'/0'/0']xpub.../<0;1>/*)`. A `<a;b>` step selects the
+//! receive (`a`) and change (`b`) chains; a single `/n/*` uses one chain for
+//! both. A trailing `#checksum` is verified when present.
+
+use super::multisig::{sortedmulti_script, MAX_MULTISIG_KEYS};
+use crate::storage::RegistryError;
+use bitcoin::bip32::{ChildNumber, DerivationPath, ExtendedPubKey, Fingerprint, KeySource};
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::{self, Secp256k1};
+use bitcoin::{Network, PublicKey, ScriptBuf, WPubkeyHash};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+const INPUT_CHARSET: &str =
+    "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolDescriptorType {
+    Wpkh,
+    /// Key-path-only taproot.
+    Tr,
+    /// k-of-n P2WSH multisig with BIP67 key ordering.
+    WshSortedMulti,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyChain {
+    /// Receive addresses.
+    External,
+    /// Change addresses.
+    Internal,
+}
+
+/// Position of a pool key within the descriptor's range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyChainIndex {
+    pub chain: KeyChain,
+    pub index: u32,
+}
+
+/// Durable counter for internal (change) key indexes, so a restarted node
+/// does not hand out change keys it already used.
+pub trait ChangeIndexStore: Send + Sync {
+    /// Index the next reservation will return.
+    fn next_change_index(&self) -> Result<u32, RegistryError>;
+
+    /// Returns the next index and advances the counter past it.
+    fn reserve_change_index(&self) -> Result<u32, RegistryError>;
+}
+
+/// Keys and script derived from a pool descriptor.
+#[derive(Debug, Clone)]
+pub struct DerivedPoolKey {
+    pub script_pubkey: ScriptBuf,
+    /// Multisig script behind a P2WSH `script_pubkey`.
+    pub witness_script: Option<ScriptBuf>,
+    /// Each key with its master fingerprint and full path, as signers
+    /// expect in a PSBT. One entry for single-key descriptors.
+    pub keys: Vec<(secp256k1::PublicKey, KeySource)>,
+}
+
+/// One ranged extended key of a descriptor.
+#[derive(Debug, Clone)]
+struct DescriptorKey {
+    origin: KeySource,
+    xpub: ExtendedPubKey,
+    external_chain: ChildNumber,
+    internal_chain: ChildNumber,
+}
+
+#[derive(Debug, Clone)]
+pub struct PoolDescriptor {
+    kind: PoolDescriptorType,
+    threshold: usize,
+    keys: Vec<DescriptorKey>,
+}
+
+impl PoolDescriptor {
+    pub fn kind(&self) -> PoolDescriptorType {
+        self.kind
+    }
+
+    /// First extended key; the only one of a single-key descriptor.
+    pub fn xpub(&self) -> &ExtendedPubKey {
+        &self.keys[0].xpub
+    }
+
+    /// `Bitcoin` or `Testnet`: extended keys do not tell the test networks
+    /// apart.
+    pub fn network(&self) -> Network {
+        self.xpub().network
+    }
+
+    /// Signatures a spend needs: `k` of a multisig, 1 otherwise.
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Master key fingerprints of the cosigners, in descriptor order.
+    pub fn cosigners(&self) -> Vec<Fingerprint> {
+        self.keys.iter().map(|k| k.origin.0).collect()
+    }
+
+    pub fn derive(&self, at: KeyChainIndex) -> Result<DerivedPoolKey, String> {
+        let index = ChildNumber::from_normal_idx(at.index)
+            .map_err(|e| format!("Invalid derivation index {}: {e}", at.index))?;
+
+        let secp = Secp256k1::verification_only();
+        let mut keys = Vec::with_capacity(self.keys.len());
+        for key in &self.keys {
+            let chain = match at.chain {
+                KeyChain::External => key.external_chain,
+                KeyChain::Internal => key.internal_chain,
+            };
+            let public_key = key
+                .xpub
+                .derive_pub(&secp, &[chain, index])
+                .map_err(|e| format!("Key derivation failed: {e}"))?
+                .public_key;
+            keys.push((
+                public_key,
+                (key.origin.0, key.origin.1.extend([chain, index])),
+            ));
+        }
+
+        let (script_pubkey, witness_script) = match self.kind {
+            PoolDescriptorType::Wpkh => (
+                ScriptBuf::new_v0_p2wpkh(&WPubkeyHash::hash(&keys[0].0.serialize())),
+                None,
+            ),
+            PoolDescriptorType::Tr => (
+                ScriptBuf::new_v1_p2tr(&secp, keys[0].0.x_only_public_key().0, None),
+                None,
+            ),
+            PoolDescriptorType::WshSortedMulti => {
+                let pubkeys: Vec<PublicKey> =
+                    keys.iter().map(|(k, _)| PublicKey::new(*k)).collect();
+                let witness_script = sortedmulti_script(self.threshold, &pubkeys);
+                (
+                    ScriptBuf::new_v0_p2wsh(&witness_script.wscript_hash()),
+                    Some(witness_script),
+                )
+            }
+        };
+
+        Ok(DerivedPoolKey {
+            script_pubkey,
+            witness_script,
+            keys,
+        })
+    }
+}
+
+impl FromStr for PoolDescriptor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let body = match s.split_once('#') {
+            Some((body, checksum)) => {
+                let expected = descriptor_checksum(body)?;
+                if checksum != expected {
+                    return Err(format!(
+                        "Descriptor checksum mismatch: got {checksum}, expected {expected}"
+                    ));
+                }
+                body
+            }
+            None => s,
+        };
+
+        if let Some(inner) = strip_wrapper(body, "wsh") {
+            let multi = strip_wrapper(inner, "sortedmulti")
+                .ok_or("Pool descriptor must be wsh(sortedmulti(k,KEY,...))")?;
+            let mut args = multi.split(',');
+            let threshold: usize = args
+                .next()
+                .and_then(|k| k.parse().ok())
+                .ok_or("sortedmulti threshold must be a number")?;
+            let keys = args.map(parse_key).collect::<Result<Vec<_>, _>>()?;
+            if keys.len() > MAX_MULTISIG_KEYS {
+                return Err(format!(
+                    "sortedmulti supports at most {MAX_MULTISIG_KEYS} keys, got {}",
+                    keys.len()
+                ));
+            }
+            if !(1..=keys.len()).contains(&threshold) {
+                return Err(format!(
+                    "sortedmulti threshold {threshold} must be between 1 and {}",
+                    keys.len()
+                ));
+            }
+            if keys.iter().any(|k| k.xpub.network != keys[0].xpub.network) {
+                return Err("Pool descriptor keys must belong to the same network".to_string());
+            }
+            return Ok(Self {
+                kind: PoolDescriptorType::WshSortedMulti,
+                threshold,
+                keys,
+            });
+        }
+
+        let (kind, key) = if let Some(inner) = strip_wrapper(body, "wpkh") {
+            (PoolDescriptorType::Wpkh, inner)
+        } else if let Some(inner) = strip_wrapper(body, "tr") {
+            if inner.contains(',') {
+                return Err(
+                    "Taproot script-path pool descriptors are not supported; use wsh(sortedmulti(...)) for multisig"
+                        .to_string(),
+                );
+            }
+            (PoolDescriptorType::Tr, inner)
+        } else {
+            return Err(
+                "Pool descriptor must be wpkh(KEY), tr(KEY) or wsh(sortedmulti(k,KEY,...))"
+                    .to_string(),
+            );
+        };
+
+        Ok(Self {
+            kind,
+            threshold: 1,
+            keys: vec![parse_key(key)?],
+        })
+    }
+}
+
+fn strip_wrapper<'a>(s: &'a str, name: &str) -> Option<&'a str> {
+    s.strip_prefix(name)?.strip_prefix('(')?.strip_suffix(')')
+}
+
+/// `[origin]xpub/<a;b>/*` or `[origin]xpub/n/*`.
+fn parse_key(key: &str) -> Result<DescriptorKey, String> {
+    let (origin, key) = match key.strip_prefix('[') {
+        Some(rest) => {
+            let (origin, key) = rest
+                .split_once(']')
+                .ok_or("Unterminated key origin in descriptor")?;
+            (Some(parse_origin(origin)?), key)
+        }
+        None => (None, key),
+    };
+
+    let mut steps = key.split('/');
+    let xpub = ExtendedPubKey::from_str(steps.next().unwrap_or_default())
+        .map_err(|e| format!("Invalid extended public key: {e}"))?;
+    let steps: Vec<&str> = steps.collect();
+    let Some((&"*", chain)) = steps.split_last() else {
+        return Err("Pool descriptor key must end in /*".to_string());
+    };
+    let (external_chain, internal_chain) = match chain {
+        [multipath] if multipath.starts_with('<') => {
+            let pair = multipath
+                .strip_prefix('<')
+                .and_then(|m| m.strip_suffix('>'))
+                .and_then(|m| m.split_once(';'))
+                .ok_or("Multipath step must be <receive;change>")?;
+            (parse_unhardened(pair.0)?, parse_unhardened(pair.1)?)
+        }
+        [single] => {
+            let chain = parse_unhardened(single)?;
+            (chain, chain)
+        }
+        _ => return Err("Pool descriptor key must be xpub/<chain>/* or xpub/<a;b>/*".into()),
+    };
+
+    let origin = origin.unwrap_or_else(|| (xpub.fingerprint(), DerivationPath::master()));
+    Ok(DescriptorKey {
+        origin,
+        xpub,
+        external_chain,
+        internal_chain,
+    })
+}
+
+fn parse_origin(origin: &str) -> Result<KeySource, String> {
+    let (fingerprint, path) = origin.split_once('/').unwrap_or((origin, ""));
+    let fingerprint = Fingerprint::from_str(fingerprint)
+        .map_err(|e| format!("Invalid key origin fingerprint: {e}"))?;
+    let path = DerivationPath::from_str(&format!("m/{path}").replace('h', "'"))
+        .map_err(|e| format!("Invalid key origin path: {e}"))?;
+    Ok((fingerprint, path))
+}
+
+fn parse_unhardened(step: &str) -> Result<ChildNumber, String> {
+    step.parse::<u32>()
+        .ok()
+        .and_then(|n| ChildNumber::from_normal_idx(n).ok())
+        .ok_or_else(|| format!("Invalid unhardened derivation step '{step}'"))
+}
+
+/// BIP380 descriptor checksum.
+pub fn descriptor_checksum(desc: &str) -> Result<String, String> {
+    fn polymod(c: u64, val: u64) -> u64 {
+        let c0 = c >> 35;
+        let mut c = ((c & 0x7_ffff_ffff) << 5) ^ val;
+        for (bit, generator) in [
+            0xf5dee51989,
+            0xa9fdca3312,
+            0x1bab10e32d,
+            0x3706b1677a,
+            0x644d626ffd,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            if c0 & (1 << bit) != 0 {
+                c ^= generator;
+            }
+        }
+        c
+    }
+
+    let mut c = 1u64;
+    let mut cls = 0u64;
+    let mut cls_count = 0;
+    for ch in desc.chars() {
+        let pos = INPUT_CHARSET
+            .find(ch)
+            .ok_or_else(|| format!("Invalid descriptor character '{ch}'"))?
+            as u64;
+        c = polymod(c, pos & 31);
+        cls = cls * 3 + (pos >> 5);
+        cls_count += 1;
+        if cls_count == 3 {
+            c = polymod(c, cls);
+            cls = 0;
+            cls_count = 0;
+        }
+    }
+    if cls_count > 0 {
+        c = polymod(c, cls);
+    }
+    for _ in 0..8 {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+
+    Ok((0..8)
+        .map(|j| CHECKSUM_CHARSET[((c >> (5 * (7 - j))) & 31) as usize] as char)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::bip32::ExtendedPrivKey;
+    use bitcoin::Network;
+
+    #[test]
+    fn checksum_matches_bip380_vector() {
+        assert_eq!(descriptor_checksum("raw(deadbeef)").unwrap(), "89f8spxm");
+    }
+
+    #[test]
+    fn parses_multipath_wpkh_and_derives_change() {
+        let secp = Secp256k1::new();
+        let master = ExtendedPrivKey::new_master(Network::Bitcoin, &[1u8; 32]).unwrap();
+        let account_path = DerivationPath::from_str("m/84'/0'/0'").unwrap();
+        let account = master.derive_priv(&secp, &account_path).unwrap();
+        let xpub = ExtendedPubKey::from_priv(&secp, &account);
+        let body = format!(
+            "wpkh([{}/84h/0h/0h]{xpub}/<0;1>/*)",
+            master.fingerprint(&secp)
+        );
+        let desc = format!("{body}#{}", descriptor_checksum(&body).unwrap());
+
+        let descriptor = PoolDescriptor::from_str(&desc).unwrap();
+        let change = descriptor
+            .derive(KeyChainIndex {
+                chain: KeyChain::Internal,
+                index: 5,
+            })
+            .unwrap();
+
+        let path = DerivationPath::from_str("m/84'/0'/0'/1/5").unwrap();
+        let expected = master.derive_priv(&secp, &path).unwrap();
+        assert_eq!(
+            change.keys,
+            vec![(
+                expected.private_key.public_key(&secp),
+                (master.fingerprint(&secp), path)
+            )]
+        );
+
+        let tampered = desc.replace("<0;1>", "<0;2>");
+        assert!(PoolDescriptor::from_str(&tampered).is_err());
+    }
+
+    #[test]
+    fn parses_sortedmulti_and_rejects_bad_thresholds() {
+        let secp = Secp256k1::new();
+        let keys: Vec<String> = (1u8..=3)
+            .map(|seed| {
+                let master = ExtendedPrivKey::new_master(Network::Bitcoin, &[seed; 32]).unwrap();
+                format!("{}/0/*", ExtendedPubKey::from_priv(&secp, &master))
+            })
+            .collect();
+        let keys = keys.join(",");
+
+        let descriptor = PoolDescriptor::from_str(&format!("wsh(sortedmulti(2,{keys}))")).unwrap();
+        assert_eq!(descriptor.kind(), PoolDescriptorType::WshSortedMulti);
+        assert_eq!(descriptor.cosigners().len(), 3);
+        let derived = descriptor
+            .derive(KeyChainIndex {
+                chain: KeyChain::Internal,
+                index: 0,
+            })
+            .unwrap();
+        assert_eq!(derived.keys.len(), 3);
+        let witness_script = derived.witness_script.unwrap();
+        assert_eq!(
+            derived.script_pubkey,
+            ScriptBuf::new_v0_p2wsh(&witness_script.wscript_hash())
+        );
+
+        assert!(PoolDescriptor::from_str(&format!("wsh(sortedmulti(4,{keys}))")).is_err());
+        assert!(PoolDescriptor::from_str(&format!("wsh(sortedmulti(0,{keys}))")).is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use super::address::AddressError;
+use super::coin_selection::CoinSelectionError;
+use super::trust_policy::TrustFinding;
+
+/// Why a payout, batch or fee bump could not be built.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisbursementError {
+    /// The AILEE Trust Layer audit failed; `findings` lists every rule that
+    /// fired.
+    PolicyRejected {
+        recipient_address: String,
+        findings: Vec<TrustFinding>,
+    },
+    /// The spendable pool UTXOs cannot cover the outputs and fee.
+    InsufficientFunds {
+        available_sats: u64,
+        required_sats: u64,
+    },
+    InvalidAddress(AddressError),
+    /// A pinned or selected pool UTXO is unknown, malformed or inconsistent.
+    InvalidUtxo(String),
+    /// The request is well-formed but cannot be served (e.g. a fee bump of
+    /// a payout that is not stuck).
+    InvalidRequest(String),
+    /// The unsigned transaction or its PSBT could not be assembled.
+    PsbtBuild(String),
+    /// The payout was built but could not be stored.
+    Storage(String),
+}
+
+impl DisbursementError {
+    /// Stable machine-readable identifier, e.g. for API error bodies.
+    pub fn code(&self) -> &'static str {
+        match self {
+            DisbursementError::PolicyRejected { .. } => "policy_rejected",
+            DisbursementError::InsufficientFunds { .. } => "insufficient_funds",
+            DisbursementError::InvalidAddress(_) => "invalid_address",
+            DisbursementError::InvalidUtxo(_) => "invalid_utxo",
+            DisbursementError::InvalidRequest(_) => "invalid_request",
+            DisbursementError::PsbtBuild(_) => "psbt_build_failed",
+            DisbursementError::Storage(_) => "storage_failed",
+        }
+    }
+}
+
+impl std::fmt::Display for DisbursementError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisbursementError::PolicyRejected {
+                recipient_address,
+                findings,
+            } => {
+                let messages: Vec<&str> = findings.iter().map(|f| f.message.as_str()).collect();
+                write!(
+                    f,
+                    "AILEE Trust Layer policy check failed for {recipient_address}: {}",
+                    messages.join("; ")
+                )
+            }
+            DisbursementError::InsufficientFunds {
+                available_sats,
+                required_sats,
+            } => write!(
+                f,
+                "insufficient pool funds: available {available_sats} sats, required {required_sats} sats"
+            ),
+            DisbursementError::InvalidAddress(err) => write!(f, "{err}"),
+            DisbursementError::InvalidUtxo(reason)
+            | DisbursementError::InvalidRequest(reason)
+            | DisbursementError::PsbtBuild(reason)
+            | DisbursementError::Storage(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+impl std::error::Error for DisbursementError {}
+
+impl From<AddressError> for DisbursementError {
+    fn from(err: AddressError) -> Self {
+        DisbursementError::InvalidAddress(err)
+    }
+}
+
+impl From<CoinSelectionError> for DisbursementError {
+    fn from(err: CoinSelectionError) -> Self {
+        match err {
+            CoinSelectionError::InsufficientFunds {
+                available_sats,
+                required_sats,
+            } => DisbursementError::InsufficientFunds {
+                available_sats,
+                required_sats,
+            },
+            CoinSelectionError::AmountOverflow => DisbursementError::InvalidRequest(
+                "Payout amount plus fees, or the pool inputs, overflow".to_string(),
+            ),
+        }
+    }
+}
@@ -0,0 +1,433 @@
+//! Fee bumping for payouts stuck in the mempool.
+//!
+//! A replace-by-fee (BIP125) replacement spends the original inputs and pays
+//! the same recipient. The extra fee comes out of change, and pool inputs
+//! are added when change runs short. It has to pay at least the original
+//! absolute fee plus the incremental relay fee for its own size, at a higher
+//! feerate. When the original cannot be replaced, a child-pays-for-parent
+//! transaction spends the pool's change output instead, so that parent and
+//! child together reach the target feerate.
+
+use super::{
+    decode_psbt, encode_psbt, estimate_vsize, signing_progress, unsigned_transaction,
+    ChangeDestination, DisbursementEngine, DisbursementError, PayoutStatus,
+    PayoutTransactionResult, PoolUtxo, DUST_LIMIT_SATS,
+};
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::{Address, OutPoint, Script, Transaction, TxOut, Txid};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// BIP125 rule 4 increment in sat/vB (Bitcoin Core `-incrementalrelayfee`).
+const INCREMENTAL_RELAY_FEE_RATE: u64 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeeBumpKind {
+    Rbf,
+    Cpfp,
+}
+
+/// Link between a stuck payout and the payout bumping its fee.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeBumpLink {
+    pub payout_id: String,
+    pub kind: FeeBumpKind,
+}
+
+/// Broadcast payout decoded for fee bumping.
+struct StuckPayout {
+    psbt: PartiallySignedTransaction,
+    inputs: Vec<PoolUtxo>,
+    fee_sats: u64,
+    vsize: u64,
+    final_tx: Option<Transaction>,
+}
+
+impl DisbursementEngine {
+    /// Bumps a broadcast payout to `fee_rate` sat/vB, by RBF when possible
+    /// and by CPFP otherwise. The returned payout is new and unsigned.
+    /// Rates above `max_fee_rate` are refused.
+    pub fn bump_fee(
+        &self,
+        payout_id: String,
+        original: &PayoutTransactionResult,
+        fee_rate: u64,
+        pool_utxos: &[PoolUtxo],
+    ) -> Result<PayoutTransactionResult, DisbursementError> {
+        let fee_rate = self.fee_rate(Some(fee_rate))?;
+        self.replace_by_fee(payout_id.clone(), original, fee_rate, pool_utxos)
+            .or_else(|rbf_err| {
+                self.child_pays_for_parent(payout_id, original, fee_rate, pool_utxos)
+                    .map_err(|cpfp_err| match (rbf_err, cpfp_err) {
+                        (
+                            DisbursementError::InvalidRequest(rbf),
+                            DisbursementError::InvalidRequest(cpfp),
+                        ) => DisbursementError::InvalidRequest(format!("RBF: {rbf}; CPFP: {cpfp}")),
+                        // A typed failure (e.g. too few pool funds) says more
+                        // than the other method not applying
+                        (DisbursementError::InvalidRequest(_), typed) | (typed, _) => typed,
+                    })
+            })
+    }
+
+    /// Replacement of `original` paying the same recipient at `fee_rate`.
+    pub fn replace_by_fee(
+        &self,
+        payout_id: String,
+        original: &PayoutTransactionResult,
+        fee_rate: u64,
+        pool_utxos: &[PoolUtxo],
+    ) -> Result<PayoutTransactionResult, DisbursementError> {
+        let stuck = stuck_payout(original)?;
+        let parent = &stuck.psbt.unsigned_tx;
+        if !parent.is_explicitly_rbf() {
+            return Err(DisbursementError::InvalidRequest(
+                "Original transaction does not signal replaceability".to_string(),
+            ));
+        }
+        ensure_higher_fee_rate(&stuck, fee_rate)?;
+
+        let recipient = parent.output.first().cloned().ok_or_else(|| {
+            DisbursementError::InvalidRequest("Original transaction has no outputs".to_string())
+        })?;
+        let original_change = parent.output.get(1).map(|o| o.script_pubkey.clone());
+        let change = match original_change {
+            Some(ref script_pubkey) => ChangeDestination {
+                script_pubkey: script_pubkey.clone(),
+                derivation: None,
+            },
+            None => self.resolve_change(None, &[], false)?,
+        };
+
+        // Rules 3 and 4: pay for the replaced fee plus our own bandwidth.
+        let original_fee = stuck.fee_sats;
+        let min_fee = |vsize: u64| {
+            fee_rate
+                .saturating_mul(vsize)
+                .max(original_fee + INCREMENTAL_RELAY_FEE_RATE * vsize)
+        };
+        let mut selected = stuck.inputs.clone();
+        let (fee_sats, change_sats) = fund_bump(
+            &mut selected,
+            &additional_inputs(&self.with_pool_scripts(pool_utxos), &stuck.inputs),
+            &recipient,
+            &change.script_pubkey,
+            &min_fee,
+        )?;
+
+        let mut outputs = vec![recipient];
+        if let Some(value) = change_sats {
+            outputs.push(TxOut {
+                value,
+                script_pubkey: change.script_pubkey.clone(),
+            });
+        }
+        let tx = unsigned_transaction(&selected, outputs);
+        let mut psbt = self.build_psbt(&tx, &selected, change_sats.map(|_| &change))?;
+
+        // The original inputs and change keep their signing metadata.
+        for (input, original_input) in psbt.inputs.iter_mut().zip(&stuck.psbt.inputs) {
+            *input = original_input.clone();
+        }
+        if change_sats.is_some() && original_change.is_some() {
+            psbt.outputs[1] = stuck.psbt.outputs[1].clone();
+        }
+
+        let amount_sats = original.amount_sats;
+        Ok(bump_payout(
+            payout_id,
+            original,
+            FeeBumpKind::Rbf,
+            &psbt,
+            original.recipient_address.clone(),
+            amount_sats,
+            fee_sats,
+        ))
+    }
+
+    /// Child spending the pool change output of `original` so the package
+    /// pays `fee_rate`.
+    pub fn child_pays_for_parent(
+        &self,
+        payout_id: String,
+        original: &PayoutTransactionResult,
+        fee_rate: u64,
+        pool_utxos: &[PoolUtxo],
+    ) -> Result<PayoutTransactionResult, DisbursementError> {
+        let stuck = stuck_payout(original)?;
+        ensure_higher_fee_rate(&stuck, fee_rate)?;
+
+        let parent = &stuck.psbt.unsigned_tx;
+        let vout = 1;
+        let parent_change = parent.output.get(vout).ok_or_else(|| {
+            DisbursementError::InvalidRequest(
+                "Original transaction has no change output".to_string(),
+            )
+        })?;
+        let change_meta = &stuck.psbt.outputs[vout];
+        let pool_owned = !change_meta.bip32_derivation.is_empty()
+            || change_meta.tap_internal_key.is_some()
+            || stuck
+                .inputs
+                .iter()
+                .any(|u| u.script_pubkey == parent_change.script_pubkey);
+        if !pool_owned {
+            return Err(DisbursementError::InvalidRequest(
+                "Change output is not controlled by the pool".to_string(),
+            ));
+        }
+
+        let parent_txid = Txid::from_str(&original.txid)
+            .map_err(|e| DisbursementError::InvalidRequest(format!("Invalid payout txid: {e}")))?;
+        let mut anchor = PoolUtxo::new(
+            OutPoint {
+                txid: parent_txid,
+                vout: vout as u32,
+            },
+            parent_change.value,
+            parent_change.script_pubkey.clone(),
+        );
+        anchor.witness_script = change_meta.witness_script.clone();
+        if let Some(ref final_tx) = stuck.final_tx {
+            anchor = anchor.with_previous_tx(final_tx.clone());
+        }
+        if anchor.input_type().is_none() {
+            return Err(DisbursementError::InvalidUtxo(
+                "Change output has an unsupported script type".to_string(),
+            ));
+        }
+        let change = self.resolve_change(None, &[], false)?;
+
+        // Package feerate: the child also pays what the parent is missing.
+        let parent_shortfall = fee_rate
+            .saturating_mul(stuck.vsize)
+            .saturating_sub(stuck.fee_sats);
+        let min_fee = |vsize: u64| {
+            (fee_rate.saturating_mul(vsize) + parent_shortfall)
+                .max(INCREMENTAL_RELAY_FEE_RATE * vsize)
+        };
+        let mut selected = vec![anchor];
+        let sweep = TxOut {
+            value: 0,
+            script_pubkey: change.script_pubkey.clone(),
+        };
+        let (fee_sats, change_sats) = fund_bump(
+            &mut selected,
+            &additional_inputs(&self.with_pool_scripts(pool_utxos), &stuck.inputs),
+            &sweep,
+            &change.script_pubkey,
+            &min_fee,
+        )?;
+        let Some(value) = change_sats else {
+            return Err(DisbursementError::InvalidRequest(
+                "Change output is too small to pay for a child transaction".to_string(),
+            ));
+        };
+
+        let tx = unsigned_transaction(
+            &selected,
+            vec![TxOut {
+                value,
+                script_pubkey: change.script_pubkey.clone(),
+            }],
+        );
+        let mut psbt = self.build_psbt(&tx, &selected, Some(&change))?;
+        let input = &mut psbt.inputs[0];
+        input.bip32_derivation = change_meta.bip32_derivation.clone();
+        input.tap_internal_key = change_meta.tap_internal_key;
+        input.tap_key_origins = change_meta.tap_key_origins.clone();
+
+        let recipient_address = Address::from_script(&change.script_pubkey, self.config.network)
+            .map(|a| a.to_string())
+            .unwrap_or_default();
+        Ok(bump_payout(
+            payout_id,
+            original,
+            FeeBumpKind::Cpfp,
+            &psbt,
+            recipient_address,
+            value,
+            fee_sats,
+        ))
+    }
+}
+
+fn bump_payout(
+    payout_id: String,
+    original: &PayoutTransactionResult,
+    kind: FeeBumpKind,
+    psbt: &PartiallySignedTransaction,
+    recipient_address: String,
+    amount_sats: u64,
+    fee_sats: u64,
+) -> PayoutTransactionResult {
+    let (psbt_base64, raw_tx_hex) = encode_psbt(psbt);
+    PayoutTransactionResult {
+        payout_id,
+        recipient_address,
+        amount_sats,
+        fee_sats,
+        status: PayoutStatus::UnsignedCreated,
+        psbt_base64,
+        raw_tx_hex,
+        txid: psbt.unsigned_tx.txid().to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        trust_audit: original.trust_audit.clone(),
+        is_dry_run: false,
+        confirmations: 0,
+        signed_psbt_base64: None,
+        final_tx_hex: None,
+        fee_bump_of: Some(FeeBumpLink {
+            payout_id: original.payout_id.clone(),
+            kind,
+        }),
+        fee_bumped_by: None,
+        signing: signing_progress(psbt),
+        batch_id: None,
+    }
+}
+
+fn stuck_payout(original: &PayoutTransactionResult) -> Result<StuckPayout, DisbursementError> {
+    if original.status != PayoutStatus::Broadcast {
+        return Err(DisbursementError::InvalidRequest(format!(
+            "Payout {} is {}; only broadcast payouts can be fee-bumped",
+            original.payout_id, original.status
+        )));
+    }
+    let psbt = decode_psbt(&original.psbt_base64).map_err(DisbursementError::PsbtBuild)?;
+
+    let mut inputs = Vec::with_capacity(psbt.inputs.len());
+    for (txin, input) in psbt.unsigned_tx.input.iter().zip(&psbt.inputs) {
+        let outpoint = txin.previous_output;
+        let spent = input
+            .witness_utxo
+            .clone()
+            .or_else(|| {
+                input
+                    .non_witness_utxo
+                    .as_ref()
+                    .and_then(|tx| tx.output.get(outpoint.vout as usize).cloned())
+            })
+            .ok_or_else(|| {
+                DisbursementError::InvalidUtxo(format!(
+                    "Payout PSBT lacks UTXO data for input {outpoint}"
+                ))
+            })?;
+        let mut utxo = PoolUtxo::new(outpoint, spent.value, spent.script_pubkey);
+        utxo.witness_script = input.witness_script.clone();
+        utxo.previous_tx = input.non_witness_utxo.clone();
+        if utxo.input_type().is_none() {
+            return Err(DisbursementError::InvalidUtxo(format!(
+                "Payout input {outpoint} has an unsupported script type"
+            )));
+        }
+        inputs.push(utxo);
+    }
+
+    let input_value: u64 = inputs.iter().map(|u| u.value_sats).sum();
+    let output_value: u64 = psbt.unsigned_tx.output.iter().map(|o| o.value).sum();
+    let fee_sats = input_value.checked_sub(output_value).ok_or_else(|| {
+        DisbursementError::PsbtBuild("Payout outputs exceed its inputs".to_string())
+    })?;
+
+    let final_tx: Option<Transaction> = original
+        .final_tx_hex
+        .as_deref()
+        .and_then(|raw| hex::decode(raw).ok())
+        .and_then(|bytes| bitcoin::consensus::deserialize(&bytes).ok());
+    let vsize = match final_tx {
+        Some(ref tx) => tx.vsize() as u64,
+        None => {
+            let types: Vec<_> = inputs.iter().filter_map(PoolUtxo::input_type).collect();
+            let scripts: Vec<&Script> = psbt
+                .unsigned_tx
+                .output
+                .iter()
+                .map(|o| o.script_pubkey.as_script())
+                .collect();
+            estimate_vsize(&types, &scripts)
+        }
+    };
+
+    Ok(StuckPayout {
+        psbt,
+        inputs,
+        fee_sats,
+        vsize,
+        final_tx,
+    })
+}
+
+fn ensure_higher_fee_rate(stuck: &StuckPayout, fee_rate: u64) -> Result<(), DisbursementError> {
+    if fee_rate.saturating_mul(stuck.vsize) <= stuck.fee_sats {
+        return Err(DisbursementError::InvalidRequest(format!(
+            "Fee rate {fee_rate} sat/vB does not exceed the original {:.2} sat/vB",
+            stuck.fee_sats as f64 / stuck.vsize as f64
+        )));
+    }
+    Ok(())
+}
+
+/// Spendable pool UTXOs not already spent by the stuck payout, largest first.
+fn additional_inputs(pool_utxos: &[PoolUtxo], spent: &[PoolUtxo]) -> Vec<PoolUtxo> {
+    let mut extra: Vec<PoolUtxo> = pool_utxos
+        .iter()
+        .filter(|u| u.input_type().is_some())
+        .filter(|u| spent.iter().all(|s| s.outpoint != u.outpoint))
+        .cloned()
+        .collect();
+    extra.sort_by_key(|u| std::cmp::Reverse(u.value_sats));
+    extra
+}
+
+/// Adds `extra` inputs to `selected` until they pay `output` plus
+/// `min_fee(vsize)`, returning the fee and change. A zero-value `output` is
+/// a sweep: everything after the fee goes to change, which must clear dust.
+fn fund_bump(
+    selected: &mut Vec<PoolUtxo>,
+    extra: &[PoolUtxo],
+    output: &TxOut,
+    change_script: &Script,
+    min_fee: &dyn Fn(u64) -> u64,
+) -> Result<(u64, Option<u64>), DisbursementError> {
+    let sweep = output.value == 0;
+    let mut extra = extra.iter();
+    loop {
+        let types: Vec<_> = selected.iter().filter_map(PoolUtxo::input_type).collect();
+        let input_value: u64 = selected.iter().map(|u| u.value_sats).sum();
+
+        let scripts: Vec<&Script> = if sweep {
+            vec![change_script]
+        } else {
+            vec![&output.script_pubkey, change_script]
+        };
+        let fee_with_change = min_fee(estimate_vsize(&types, &scripts));
+        let change = input_value
+            .saturating_sub(output.value)
+            .saturating_sub(fee_with_change);
+        if change >= DUST_LIMIT_SATS {
+            return Ok((fee_with_change, Some(change)));
+        }
+
+        let fee_without_change = min_fee(estimate_vsize(&types, &[&output.script_pubkey]));
+        let required = output.value + fee_without_change;
+        if !sweep && input_value >= required {
+            return Ok((input_value - output.value, None));
+        }
+
+        match extra.next() {
+            Some(utxo) => selected.push(utxo.clone()),
+            None => {
+                return Err(DisbursementError::InsufficientFunds {
+                    available_sats: input_value,
+                    required_sats: if sweep {
+                        fee_with_change + DUST_LIMIT_SATS
+                    } else {
+                        required
+                    },
+                })
+            }
+        }
+    }
+}
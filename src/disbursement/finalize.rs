@@ -0,0 +1,297 @@
+//! Finalization of signed payout PSBTs.
+//!
+//! Pool spends are single-key or bare multisig, so finalizing does not need
+//! a miniscript satisfier: a P2WPKH input with one partial signature becomes
+//! `[sig, pubkey]`, a P2TR key-path input becomes `[sig]`, and a P2WSH
+//! multisig input with `k` signatures becomes `[<>, sig..., script]` with
+//! the signatures in script key order. Each signature is checked against
+//! the input's sighash before it is moved into the witness. Inputs the
+//! signer already finalized are left as they are, and multisig inputs below
+//! their threshold stay partially signed.
+//!
+//! Final scripts from an outside signer are never trusted as they are:
+//! [`unfinalize_inputs`] turns them back into partial signatures first, so
+//! they go through the same checks.
+
+use super::multisig::parse_multisig;
+use bitcoin::hashes::Hash;
+use bitcoin::psbt::{Input, PartiallySignedTransaction};
+use bitcoin::secp256k1::{Message, Secp256k1, XOnlyPublicKey};
+use bitcoin::sighash::{Prevouts, SighashCache};
+use bitcoin::taproot::TapLeafHash;
+use bitcoin::{ecdsa, taproot, PublicKey, ScriptBuf, Transaction, TxOut, WPubkeyHash, Witness};
+
+/// Finalizes every input it can and reports whether the PSBT is complete.
+pub fn finalize_psbt(psbt: &mut PartiallySignedTransaction) -> Result<bool, String> {
+    let prevouts = spent_outputs(psbt);
+    let secp = Secp256k1::verification_only();
+    let mut cache = SighashCache::new(&psbt.unsigned_tx);
+
+    for (index, input) in psbt.inputs.iter_mut().enumerate() {
+        if is_final(input) {
+            continue;
+        }
+        let Some(prevout) = &prevouts[index] else {
+            continue;
+        };
+        let script = &prevout.script_pubkey;
+
+        let witness = if script.is_v0_p2wpkh() {
+            let mut sigs = input.partial_sigs.iter();
+            let (Some((pubkey, sig)), None) = (sigs.next(), sigs.next()) else {
+                continue;
+            };
+            let expected = ScriptBuf::new_v0_p2wpkh(&WPubkeyHash::hash(&pubkey.to_bytes()));
+            if !pubkey.compressed || expected != *script {
+                return Err(format!(
+                    "input {index}: signing key does not match the spent P2WPKH output"
+                ));
+            }
+            let script_code = script
+                .p2wpkh_script_code()
+                .ok_or_else(|| format!("input {index}: malformed P2WPKH script"))?;
+            let sighash = cache
+                .segwit_signature_hash(index, &script_code, prevout.value, sig.hash_ty)
+                .map_err(|e| format!("input {index}: {e}"))?;
+            secp.verify_ecdsa(&Message::from(sighash), &sig.sig, &pubkey.inner)
+                .map_err(|_| format!("input {index}: invalid ECDSA signature"))?;
+            Witness::from_slice(&[sig.to_vec(), pubkey.to_bytes()])
+        } else if script.is_v1_p2tr() {
+            let Some(sig) = input.tap_key_sig else {
+                continue;
+            };
+            let all_prevouts = prevouts
+                .iter()
+                .cloned()
+                .collect::<Option<Vec<TxOut>>>()
+                .ok_or_else(|| {
+                    format!("input {index}: taproot signing requires every spent output")
+                })?;
+            let sighash = cache
+                .taproot_key_spend_signature_hash(index, &Prevouts::All(&all_prevouts), sig.hash_ty)
+                .map_err(|e| format!("input {index}: {e}"))?;
+            let output_key = XOnlyPublicKey::from_slice(&script.as_bytes()[2..])
+                .map_err(|e| format!("input {index}: invalid taproot output key: {e}"))?;
+            secp.verify_schnorr(&sig.sig, &Message::from(sighash), &output_key)
+                .map_err(|_| format!("input {index}: invalid Schnorr signature"))?;
+            Witness::from_slice(&[sig.to_vec()])
+        } else if script.is_v0_p2wsh() {
+            let Some(witness_script) = input.witness_script.clone() else {
+                continue;
+            };
+            // Stake locks and other non-multisig scripts are not ours to
+            // satisfy.
+            let Some((threshold, keys)) = parse_multisig(&witness_script) else {
+                continue;
+            };
+            if ScriptBuf::new_v0_p2wsh(&witness_script.wscript_hash()) != *script {
+                return Err(format!(
+                    "input {index}: witness script does not match the spent P2WSH output"
+                ));
+            }
+            let mut items = vec![Vec::new()];
+            for key in &keys {
+                if items.len() > threshold {
+                    break;
+                }
+                let Some(sig) = input.partial_sigs.get(key) else {
+                    continue;
+                };
+                let sighash = cache
+                    .segwit_signature_hash(index, &witness_script, prevout.value, sig.hash_ty)
+                    .map_err(|e| format!("input {index}: {e}"))?;
+                secp.verify_ecdsa(&Message::from(sighash), &sig.sig, &key.inner)
+                    .map_err(|_| format!("input {index}: invalid ECDSA signature"))?;
+                items.push(sig.to_vec());
+            }
+            if items.len() <= threshold {
+                continue;
+            }
+            items.push(witness_script.into_bytes());
+            Witness::from_slice(&items)
+        } else {
+            continue;
+        };
+
+        // BIP174: a finalized input keeps only the UTXO and final scripts.
+        *input = Input {
+            non_witness_utxo: input.non_witness_utxo.take(),
+            witness_utxo: input.witness_utxo.take(),
+            final_script_witness: Some(witness),
+            proprietary: std::mem::take(&mut input.proprietary),
+            unknown: std::mem::take(&mut input.unknown),
+            ..Default::default()
+        };
+    }
+
+    Ok(psbt.inputs.iter().all(is_final))
+}
+
+/// Replaces the final scripts of `psbt` by the signatures they carry, read
+/// against the outputs `reference` (the PSBT we built) says each input
+/// spends. P2WSH multisig signatures are matched to their key by verifying
+/// them; one that verifies against no key is an error.
+pub(crate) fn unfinalize_inputs(
+    psbt: &mut PartiallySignedTransaction,
+    reference: &PartiallySignedTransaction,
+) -> Result<(), String> {
+    let prevouts = spent_outputs(reference);
+    let secp = Secp256k1::verification_only();
+    let mut cache = SighashCache::new(&reference.unsigned_tx);
+
+    for (index, input) in psbt.inputs.iter_mut().enumerate() {
+        if input.final_script_sig.take().is_some() {
+            return Err(format!(
+                "input {index}: final scriptSigs are not accepted; submit partial signatures"
+            ));
+        }
+        let Some(witness) = input.final_script_witness.take() else {
+            continue;
+        };
+        let prevout = prevouts
+            .get(index)
+            .cloned()
+            .flatten()
+            .ok_or_else(|| format!("input {index}: spent output unknown"))?;
+        let script = &prevout.script_pubkey;
+        let items: Vec<&[u8]> = witness.iter().collect();
+        let malformed = || format!("input {index}: unexpected final witness");
+
+        if script.is_v0_p2wpkh() {
+            let [sig, pubkey] = items[..] else {
+                return Err(malformed());
+            };
+            let sig = ecdsa::Signature::from_slice(sig).map_err(|_| malformed())?;
+            let pubkey = PublicKey::from_slice(pubkey).map_err(|_| malformed())?;
+            input.partial_sigs.insert(pubkey, sig);
+        } else if script.is_v1_p2tr() {
+            let [sig] = items[..] else {
+                return Err(malformed());
+            };
+            input.tap_key_sig = Some(taproot::Signature::from_slice(sig).map_err(|_| malformed())?);
+        } else if script.is_v0_p2wsh() {
+            let Some((&[], rest)) = items.split_first() else {
+                return Err(malformed());
+            };
+            let Some((witness_script, sigs)) = rest.split_last() else {
+                return Err(malformed());
+            };
+            let witness_script = ScriptBuf::from_bytes(witness_script.to_vec());
+            let (_, keys) = parse_multisig(&witness_script).ok_or_else(malformed)?;
+            for sig in sigs {
+                let sig = ecdsa::Signature::from_slice(sig).map_err(|_| malformed())?;
+                let sighash = cache
+                    .segwit_signature_hash(index, &witness_script, prevout.value, sig.hash_ty)
+                    .map_err(|e| format!("input {index}: {e}"))?;
+                let key = keys
+                    .iter()
+                    .find(|key| {
+                        secp.verify_ecdsa(&Message::from(sighash), &sig.sig, &key.inner)
+                            .is_ok()
+                    })
+                    .ok_or_else(|| format!("input {index}: invalid ECDSA signature"))?;
+                input.partial_sigs.insert(*key, sig);
+            }
+            input.witness_script.get_or_insert(witness_script);
+        } else {
+            return Err(malformed());
+        }
+    }
+    Ok(())
+}
+
+/// Whether `sig` is `key`'s valid signature for input `index`, with the
+/// script code of a P2WSH, P2WPKH or P2PKH spend. Other spends cannot be
+/// checked here and never verify.
+pub(crate) fn verify_ecdsa_sig(
+    cache: &mut SighashCache<&Transaction>,
+    index: usize,
+    input: &Input,
+    prevout: &TxOut,
+    key: &PublicKey,
+    sig: &ecdsa::Signature,
+) -> bool {
+    let script = &prevout.script_pubkey;
+    let sighash = if script.is_v0_p2wsh() || script.is_v0_p2wpkh() {
+        let script_code = match &input.witness_script {
+            Some(witness_script) if script.is_v0_p2wsh() => witness_script.clone(),
+            _ => match script.p2wpkh_script_code() {
+                Some(script_code) => script_code,
+                None => return false,
+            },
+        };
+        cache
+            .segwit_signature_hash(index, &script_code, prevout.value, sig.hash_ty)
+            .map(Message::from)
+    } else if script.is_p2pkh() {
+        cache
+            .legacy_signature_hash(index, script, sig.hash_ty.to_u32())
+            .map(Message::from)
+    } else {
+        return false;
+    };
+    sighash.is_ok_and(|sighash| {
+        Secp256k1::verification_only()
+            .verify_ecdsa(&sighash, &sig.sig, &key.inner)
+            .is_ok()
+    })
+}
+
+/// Whether `sig` is a valid taproot signature for input `index`: a key-path
+/// signature by the output key when `leaf` is `None`, otherwise `key`'s
+/// signature for that script leaf. Needs every spent output.
+pub(crate) fn verify_schnorr_sig(
+    cache: &mut SighashCache<&Transaction>,
+    index: usize,
+    prevouts: &[Option<TxOut>],
+    key: &XOnlyPublicKey,
+    leaf: Option<TapLeafHash>,
+    sig: &taproot::Signature,
+) -> bool {
+    let Some(all_prevouts) = prevouts.iter().cloned().collect::<Option<Vec<TxOut>>>() else {
+        return false;
+    };
+    let prevouts = Prevouts::All(&all_prevouts);
+    let (sighash, key) = match leaf {
+        Some(leaf) => (
+            cache.taproot_script_spend_signature_hash(index, &prevouts, leaf, sig.hash_ty),
+            *key,
+        ),
+        None => {
+            let script = &all_prevouts[index].script_pubkey;
+            let Ok(output_key) = XOnlyPublicKey::from_slice(&script.as_bytes()[2..]) else {
+                return false;
+            };
+            (
+                cache.taproot_key_spend_signature_hash(index, &prevouts, sig.hash_ty),
+                output_key,
+            )
+        }
+    };
+    sighash.is_ok_and(|sighash| {
+        Secp256k1::verification_only()
+            .verify_schnorr(&sig.sig, &Message::from(sighash), &key)
+            .is_ok()
+    })
+}
+
+pub(crate) fn is_final(input: &Input) -> bool {
+    input.final_script_witness.is_some() || input.final_script_sig.is_some()
+}
+
+/// Output spent by each input, from `witness_utxo` or `non_witness_utxo`.
+pub(crate) fn spent_outputs(psbt: &PartiallySignedTransaction) -> Vec<Option<TxOut>> {
+    psbt.unsigned_tx
+        .input
+        .iter()
+        .zip(&psbt.inputs)
+        .map(|(txin, input)| {
+            input.witness_utxo.clone().or_else(|| {
+                input
+                    .non_witness_utxo
+                    .as_ref()
+                    .and_then(|tx| tx.output.get(txin.previous_output.vout as usize).cloned())
+            })
+        })
+        .collect()
+}
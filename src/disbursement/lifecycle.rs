@@ -0,0 +1,402 @@
+//! Payout lifecycle: allowed status transitions and the transition log.
+//!
+//! ```text
+//! UnsignedCreated -> PartiallySigned -> Finalized -> Broadcast -> Confirmed(n) -> Completed
+//!                                                       |              |
+//!                                                       +-> Replaced   +-> Broadcast (reorg)
+//! ```
+//!
+//! Any non-terminal stage after creation may also move to `Failed`.
+
+use super::broadcast::{BroadcastError, Broadcaster};
+use super::finalize::{self, finalize_psbt};
+use super::multisig::signing_progress;
+use super::{decode_psbt, PayoutStatus, PayoutTransactionResult};
+use crate::velocity_analyzer::VelocityError;
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::Txid;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+impl PayoutStatus {
+    pub fn can_transition_to(&self, next: &PayoutStatus) -> bool {
+        use PayoutStatus::*;
+        matches!(
+            (self, next),
+            (Pending, Rejected)
+                | (UnsignedCreated, PartiallySigned | Finalized | Failed)
+                | (PartiallySigned, PartiallySigned | Finalized | Failed)
+                | (Finalized, Broadcast | Failed)
+                | (Broadcast, Confirmed | Replaced | Failed)
+                | (Confirmed, Confirmed | Broadcast | Completed | Failed)
+        )
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            PayoutStatus::Completed
+                | PayoutStatus::Rejected
+                | PayoutStatus::Replaced
+                | PayoutStatus::Failed
+        )
+    }
+
+    /// Ended without being paid: its inputs may fund later payouts.
+    pub fn is_abandoned(&self) -> bool {
+        matches!(self, PayoutStatus::Rejected | PayoutStatus::Failed)
+    }
+}
+
+impl FromStr for PayoutStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "pending" => PayoutStatus::Pending,
+            "unsigned_created" => PayoutStatus::UnsignedCreated,
+            "partially_signed" => PayoutStatus::PartiallySigned,
+            "finalized" => PayoutStatus::Finalized,
+            "broadcast" => PayoutStatus::Broadcast,
+            "confirmed" => PayoutStatus::Confirmed,
+            "completed" => PayoutStatus::Completed,
+            "rejected" => PayoutStatus::Rejected,
+            "replaced" => PayoutStatus::Replaced,
+            "failed" => PayoutStatus::Failed,
+            other => return Err(format!("unknown payout status: {other}")),
+        })
+    }
+}
+
+/// Timestamped entry of the payout transition log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayoutTransition {
+    pub payout_id: String,
+    pub from: PayoutStatus,
+    pub to: PayoutStatus,
+    /// Confirmations at the time of the transition.
+    pub confirmations: u32,
+    pub detail: Option<String>,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LifecycleError {
+    InvalidTransition {
+        from: PayoutStatus,
+        to: PayoutStatus,
+    },
+    InvalidPsbt(String),
+    /// Broadcast failed in a way worth retrying; the payout is unchanged.
+    Broadcast(BroadcastError),
+}
+
+impl std::fmt::Display for LifecycleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LifecycleError::InvalidTransition { from, to } => {
+                write!(f, "invalid payout transition: {from} -> {to}")
+            }
+            LifecycleError::InvalidPsbt(msg) => write!(f, "invalid signed PSBT: {msg}"),
+            LifecycleError::Broadcast(err) => write!(f, "broadcast failed: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for LifecycleError {}
+
+/// Where a payout transaction stands according to the chain backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxChainStatus {
+    /// Not seen; may still be propagating.
+    Unknown,
+    InMempool,
+    Confirmed {
+        confirmations: u32,
+    },
+    /// A conflicting transaction spent one of its inputs.
+    Conflicted,
+}
+
+/// Chain backend able to report on payout transactions.
+pub trait ConfirmationSource: Send + Sync {
+    fn transaction_status(&self, txid: &Txid) -> Result<TxChainStatus, VelocityError>;
+}
+
+impl PayoutTransactionResult {
+    /// Moves the payout to `to`, returning the log entry to persist.
+    pub fn transition(
+        &mut self,
+        to: PayoutStatus,
+        detail: Option<String>,
+    ) -> Result<PayoutTransition, LifecycleError> {
+        if !self.status.can_transition_to(&to) {
+            return Err(LifecycleError::InvalidTransition {
+                from: self.status.clone(),
+                to,
+            });
+        }
+        let from = std::mem::replace(&mut self.status, to.clone());
+        Ok(PayoutTransition {
+            payout_id: self.payout_id.clone(),
+            from,
+            to,
+            confirmations: self.confirmations,
+            detail,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Records a signed PSBT for this payout.
+    ///
+    /// The PSBT's unsigned transaction must match the stored one byte for
+    /// byte. Its signatures are combined with those already collected and
+    /// inputs with enough signatures are finalized: the payout becomes
+    /// `Finalized` when every input carries a final script,
+    /// `PartiallySigned` otherwise (e.g. a multisig pool below its
+    /// threshold). Cosigner progress is recorded in `signing`.
+    pub fn apply_signed_psbt(
+        &mut self,
+        psbt: &PartiallySignedTransaction,
+    ) -> Result<PayoutTransition, LifecycleError> {
+        let unsigned = bitcoin::consensus::serialize(&psbt.unsigned_tx);
+        if hex::decode(&self.raw_tx_hex).ok().as_deref() != Some(unsigned.as_slice()) {
+            return Err(LifecycleError::InvalidPsbt(format!(
+                "PSBT transaction {} does not match the unsigned transaction of payout {}",
+                psbt.unsigned_tx.txid(),
+                self.payout_id
+            )));
+        }
+
+        let signed = psbt.inputs.iter().any(|i| {
+            !i.partial_sigs.is_empty()
+                || i.tap_key_sig.is_some()
+                || !i.tap_script_sigs.is_empty()
+                || finalize::is_final(i)
+        });
+        if !signed {
+            return Err(LifecycleError::InvalidPsbt(
+                "PSBT carries no signatures".into(),
+            ));
+        }
+
+        // Start from what we already hold: earlier signatures, or the
+        // unsigned PSBT with the UTXO and key-origin data signers may strip.
+        let stored = self
+            .signed_psbt_base64
+            .as_deref()
+            .unwrap_or(&self.psbt_base64);
+        let mut combined = decode_psbt(stored)
+            .map_err(|e| LifecycleError::InvalidPsbt(format!("stored PSBT: {e}")))?;
+        // Final scripts are only ever built here, from verified signatures.
+        let mut incoming = psbt.clone();
+        finalize::unfinalize_inputs(&mut incoming, &combined)
+            .map_err(LifecycleError::InvalidPsbt)?;
+        combined
+            .combine(incoming)
+            .map_err(|e| LifecycleError::InvalidPsbt(e.to_string()))?;
+        // Finalizing strips key origins and partial signatures.
+        let signing = signing_progress(&combined).or_else(|| self.signing.clone());
+        let finalized = finalize_psbt(&mut combined).map_err(LifecycleError::InvalidPsbt)?;
+
+        let next = if finalized {
+            PayoutStatus::Finalized
+        } else {
+            PayoutStatus::PartiallySigned
+        };
+        let detail = signing.as_ref().map(|s| {
+            let signed: Vec<&str> = s
+                .cosigners
+                .iter()
+                .filter(|c| c.signed)
+                .map(|c| c.fingerprint.as_str())
+                .collect();
+            format!(
+                "{} of {} required signatures: {}",
+                s.signed_count(),
+                s.threshold,
+                signed.join(", ")
+            )
+        });
+        let transition = self.transition(next, detail)?;
+        self.signing = signing;
+
+        use base64::Engine;
+        self.signed_psbt_base64 =
+            Some(base64::engine::general_purpose::STANDARD.encode(combined.serialize()));
+        if finalized {
+            // Legacy inputs change the txid once their scriptSigs are set.
+            let tx = combined.extract_tx();
+            self.txid = tx.txid().to_string();
+            self.final_tx_hex = Some(hex::encode(bitcoin::consensus::serialize(&tx)));
+        }
+        Ok(transition)
+    }
+
+    /// Pushes the finalized transaction through `broadcaster`.
+    ///
+    /// Acceptance, including a backend that already knows the transaction,
+    /// moves the payout to `Broadcast`. A rejection that resubmitting cannot
+    /// fix moves it to `Failed` with the reason as detail; anything else is
+    /// returned as [`LifecycleError::Broadcast`] and leaves it `Finalized`.
+    pub fn broadcast_with(
+        &mut self,
+        broadcaster: &dyn Broadcaster,
+    ) -> Result<PayoutTransition, LifecycleError> {
+        if !self.status.can_transition_to(&PayoutStatus::Broadcast) {
+            return Err(LifecycleError::InvalidTransition {
+                from: self.status.clone(),
+                to: PayoutStatus::Broadcast,
+            });
+        }
+        let tx: bitcoin::Transaction = self
+            .final_tx_hex
+            .as_deref()
+            .and_then(|raw| hex::decode(raw).ok())
+            .and_then(|bytes| bitcoin::consensus::deserialize(&bytes).ok())
+            .ok_or_else(|| {
+                LifecycleError::InvalidPsbt("payout has no extracted final transaction".into())
+            })?;
+
+        match broadcaster.broadcast(&tx) {
+            Ok(txid) => {
+                self.transition(PayoutStatus::Broadcast, Some(format!("accepted as {txid}")))
+            }
+            Err(BroadcastError::AlreadyKnown) => self.transition(
+                PayoutStatus::Broadcast,
+                Some("already known to the network".into()),
+            ),
+            Err(err) if err.is_permanent() => {
+                self.transition(PayoutStatus::Failed, Some(err.to_string()))
+            }
+            Err(err) => Err(LifecycleError::Broadcast(err)),
+        }
+    }
+
+    /// Applies a chain observation to a broadcast or confirmed payout and
+    /// returns the transitions taken, oldest first.
+    ///
+    /// Reaching `final_confirmations` completes the payout; dropping back to
+    /// the mempool (reorg) returns it to `Broadcast`.
+    pub fn observe_chain(
+        &mut self,
+        status: TxChainStatus,
+        final_confirmations: u32,
+    ) -> Result<Vec<PayoutTransition>, LifecycleError> {
+        if !matches!(
+            self.status,
+            PayoutStatus::Broadcast | PayoutStatus::Confirmed
+        ) {
+            return Ok(Vec::new());
+        }
+
+        let mut transitions = Vec::new();
+        match status {
+            TxChainStatus::Unknown => {}
+            TxChainStatus::InMempool => {
+                if self.status == PayoutStatus::Confirmed {
+                    self.confirmations = 0;
+                    transitions.push(self.transition(
+                        PayoutStatus::Broadcast,
+                        Some("transaction returned to mempool".into()),
+                    )?);
+                }
+            }
+            TxChainStatus::Confirmed { confirmations } => {
+                if confirmations > 0
+                    && (self.status == PayoutStatus::Broadcast
+                        || confirmations != self.confirmations)
+                {
+                    self.confirmations = confirmations;
+                    transitions.push(self.transition(PayoutStatus::Confirmed, None)?);
+                }
+                if confirmations >= final_confirmations.max(1) {
+                    transitions.push(self.transition(PayoutStatus::Completed, None)?);
+                }
+            }
+            TxChainStatus::Conflicted => {
+                transitions.push(self.transition(
+                    PayoutStatus::Failed,
+                    Some("conflicting transaction spent a payout input".into()),
+                )?);
+            }
+        }
+        Ok(transitions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disbursement::{AileeTrustAudit, TrustPolicy};
+
+    fn payout(status: PayoutStatus) -> PayoutTransactionResult {
+        PayoutTransactionResult {
+            payout_id: "payout-1".into(),
+            recipient_address: String::new(),
+            amount_sats: 10_000,
+            fee_sats: 200,
+            status,
+            psbt_base64: String::new(),
+            raw_tx_hex: String::new(),
+            txid: String::new(),
+            timestamp: String::new(),
+            trust_audit: AileeTrustAudit {
+                passed: true,
+                risk_score: 0.0,
+                policy_version: TrustPolicy::standard().version().to_string(),
+                warnings: Vec::new(),
+                max_payout_sats_limit: 0,
+                findings: Vec::new(),
+            },
+            is_dry_run: false,
+            confirmations: 0,
+            signed_psbt_base64: None,
+            final_tx_hex: None,
+            fee_bump_of: None,
+            fee_bumped_by: None,
+            signing: None,
+            batch_id: None,
+        }
+    }
+
+    #[test]
+    fn rejects_skipping_stages() {
+        let mut p = payout(PayoutStatus::UnsignedCreated);
+        assert_eq!(
+            p.transition(PayoutStatus::Broadcast, None),
+            Err(LifecycleError::InvalidTransition {
+                from: PayoutStatus::UnsignedCreated,
+                to: PayoutStatus::Broadcast,
+            })
+        );
+        assert!(p.transition(PayoutStatus::Finalized, None).is_ok());
+        assert!(p.transition(PayoutStatus::Broadcast, None).is_ok());
+    }
+
+    #[test]
+    fn chain_observations_confirm_complete_and_reorg() {
+        let mut p = payout(PayoutStatus::Broadcast);
+        let steps = p
+            .observe_chain(TxChainStatus::Confirmed { confirmations: 2 }, 6)
+            .unwrap();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(
+            (p.status.clone(), p.confirmations),
+            (PayoutStatus::Confirmed, 2)
+        );
+
+        p.observe_chain(TxChainStatus::InMempool, 6).unwrap();
+        assert_eq!(
+            (p.status.clone(), p.confirmations),
+            (PayoutStatus::Broadcast, 0)
+        );
+
+        let steps = p
+            .observe_chain(TxChainStatus::Confirmed { confirmations: 6 }, 6)
+            .unwrap();
+        let path: Vec<_> = steps.iter().map(|t| t.to.clone()).collect();
+        assert_eq!(path, vec![PayoutStatus::Confirmed, PayoutStatus::Completed]);
+        assert!(p.status.is_terminal());
+    }
+}
@@ -0,0 +1,840 @@
+pub mod address;
+pub mod batch;
+pub mod broadcast;
+pub mod coin_selection;
+pub mod descriptor;
+pub mod error;
+pub mod fee_bump;
+pub mod finalize;
+pub mod lifecycle;
+pub mod multisig;
+#[cfg(feature = "test-signer")]
+pub mod test_signer;
+pub mod trust_policy;
+pub mod tx_size;
+pub mod velocity_limits;
+
+pub use address::{parse_address, AddressError, AddressRole};
+pub use batch::{
+    BatchFeePolicy, BatchOutput, BatchPayoutRequest, BatchPayoutResult, BatchTransaction,
+    PayoutAllocation, SubDustPolicy,
+};
+pub use broadcast::{classify_rejection, BroadcastError, Broadcaster};
+pub use coin_selection::{
+    select_coins, CoinSelection, CoinSelectionError, PoolUtxo, PoolUtxoRecord, SelectionAlgorithm,
+    SelectionParams,
+};
+pub use descriptor::{
+    ChangeIndexStore, DerivedPoolKey, KeyChain, KeyChainIndex, PoolDescriptor, PoolDescriptorType,
+};
+pub use error::DisbursementError;
+pub use fee_bump::{FeeBumpKind, FeeBumpLink};
+pub use finalize::finalize_psbt;
+pub use lifecycle::{ConfirmationSource, LifecycleError, PayoutTransition, TxChainStatus};
+pub use multisig::{signing_progress, CosignerSignature, SigningProgress};
+pub use trust_policy::{
+    PayoutHistory, RecipientScriptType, TrustFinding, TrustPolicy, TrustPolicyConfig, TrustRule,
+};
+pub use tx_size::{estimate_vsize, estimate_weight, InputScriptType};
+pub use velocity_limits::{counts_toward_velocity, recipient_outputs, PayoutVolume, VelocityScope};
+
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::transaction::Transaction;
+use bitcoin::{Network, OutPoint, Script, ScriptBuf, Sequence, TxIn, TxOut, Txid, Witness};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Outputs below this value are non-standard.
+pub const DUST_LIMIT_SATS: u64 = 546;
+
+/// Status of a payout request; see [`lifecycle`] for allowed transitions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayoutStatus {
+    /// Dry run; never signed or broadcast.
+    Pending,
+    UnsignedCreated,
+    PartiallySigned,
+    Finalized,
+    Broadcast,
+    /// Mined with fewer than the final number of confirmations.
+    Confirmed,
+    /// Final: confirmed deeply enough.
+    Completed,
+    Rejected,
+    /// Superseded by a fee-bumped replacement.
+    Replaced,
+    Failed,
+}
+
+impl std::fmt::Display for PayoutStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PayoutStatus::Pending => write!(f, "pending"),
+            PayoutStatus::UnsignedCreated => write!(f, "unsigned_created"),
+            PayoutStatus::PartiallySigned => write!(f, "partially_signed"),
+            PayoutStatus::Finalized => write!(f, "finalized"),
+            PayoutStatus::Broadcast => write!(f, "broadcast"),
+            PayoutStatus::Confirmed => write!(f, "confirmed"),
+            PayoutStatus::Completed => write!(f, "completed"),
+            PayoutStatus::Rejected => write!(f, "rejected"),
+            PayoutStatus::Replaced => write!(f, "replaced"),
+            PayoutStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// Request payload for creating/executing a payout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayoutRequest {
+    pub recipient_address: String,
+    pub amount_sats: u64,
+    /// Pins coin selection to this pool outpoint (coin control).
+    pub funding_utxo_txid: Option<String>,
+    pub funding_utxo_vout: Option<u32>,
+    /// When set, must match the pinned pool UTXO's value.
+    pub funding_utxo_value_sats: Option<u64>,
+    pub change_address: Option<String>,
+    pub fee_rate_sats_per_vbyte: Option<u64>,
+    pub dry_run: Option<bool>,
+}
+
+/// AILEE Trust Layer evaluation result and safety audit parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AileeTrustAudit {
+    pub passed: bool,
+    pub risk_score: f64,
+    pub policy_version: String,
+    pub warnings: Vec<String>,
+    pub max_payout_sats_limit: u64,
+    /// Rules that fired, in policy order.
+    #[serde(default)]
+    pub findings: Vec<TrustFinding>,
+}
+
+impl AileeTrustAudit {
+    /// Policy version of [`Self::unrecorded`] audits.
+    pub const UNRECORDED_VERSION: &'static str = "unrecorded";
+
+    /// Stand-in for a stored payout without a readable audit, e.g. one
+    /// written by an early version. Not evidence the payout passed.
+    pub fn unrecorded() -> Self {
+        Self {
+            passed: false,
+            risk_score: 1.0,
+            policy_version: Self::UNRECORDED_VERSION.to_string(),
+            warnings: Vec::new(),
+            max_payout_sats_limit: 0,
+            findings: Vec::new(),
+        }
+    }
+}
+
+/// Result of creating an unsigned PSBT / raw payout transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayoutTransactionResult {
+    pub payout_id: String,
+    pub recipient_address: String,
+    pub amount_sats: u64,
+    pub fee_sats: u64,
+    pub status: PayoutStatus,
+    pub psbt_base64: String,
+    pub raw_tx_hex: String,
+    pub txid: String,
+    pub timestamp: String,
+    pub trust_audit: AileeTrustAudit,
+    pub is_dry_run: bool,
+    #[serde(default)]
+    pub confirmations: u32,
+    /// Latest signed PSBT submitted for this payout.
+    #[serde(default)]
+    pub signed_psbt_base64: Option<String>,
+    /// Fully signed transaction, once finalized.
+    #[serde(default)]
+    pub final_tx_hex: Option<String>,
+    /// Stuck payout whose fee this payout bumps.
+    #[serde(default)]
+    pub fee_bump_of: Option<FeeBumpLink>,
+    /// Latest payout created to bump this payout's fee.
+    #[serde(default)]
+    pub fee_bumped_by: Option<FeeBumpLink>,
+    /// Cosigners and their signatures so far; `None` when the PSBT names
+    /// no pool keys.
+    #[serde(default)]
+    pub signing: Option<SigningProgress>,
+    /// Epoch batch this transaction belongs to. `recipient_address` is then
+    /// its first recipient and `amount_sats` the total paid by it.
+    #[serde(default)]
+    pub batch_id: Option<String>,
+}
+
+/// Configuration for disbursement safeguards
+#[derive(Debug, Clone)]
+pub struct DisbursementConfig {
+    pub network: Network,
+    pub max_single_payout_sats: u64,
+    pub require_ailee_trust_pass: bool,
+    pub default_fee_rate: u64,
+    /// Highest fee rate in sat/vB accepted for a payout, batch or fee
+    /// bump. Fees do not count toward the amount and velocity limits: they
+    /// are only known after coin selection, which runs once the trust rules
+    /// passed, so this cap bounds them instead.
+    pub max_fee_rate: u64,
+    /// Maximum recipient outputs per batch payout transaction.
+    pub max_batch_outputs: usize,
+    /// Pool wallet descriptor used for PSBT key origins and change
+    /// addresses. Without one, PSBTs carry UTXO data only.
+    pub pool_descriptor: Option<PoolDescriptor>,
+    /// Pool address receiving change when there is no pool descriptor and
+    /// the request names none.
+    pub change_address: Option<String>,
+    /// Confirmations after which a payout is `Completed`.
+    pub final_confirmations: u32,
+    /// AILEE Trust Layer rule set evaluated for every payout.
+    pub trust_policy: Arc<TrustPolicy>,
+}
+
+impl Default for DisbursementConfig {
+    fn default() -> Self {
+        Self {
+            network: Network::Bitcoin,
+            max_single_payout_sats: 500_000_000, // 5 BTC
+            require_ailee_trust_pass: true,
+            default_fee_rate: 10,
+            max_fee_rate: 1_000,
+            max_batch_outputs: 250,
+            pool_descriptor: None,
+            change_address: None,
+            final_confirmations: 6,
+            trust_policy: Arc::new(TrustPolicy::standard()),
+        }
+    }
+}
+
+pub struct DisbursementEngine {
+    pub config: DisbursementConfig,
+    next_change_index: AtomicU32,
+    change_index_store: Option<Arc<dyn ChangeIndexStore>>,
+    payout_history: Option<Arc<dyn PayoutHistory>>,
+    pool_balance: Option<Arc<RwLock<u64>>>,
+}
+
+impl DisbursementEngine {
+    pub fn new(config: DisbursementConfig) -> Self {
+        Self {
+            config,
+            next_change_index: AtomicU32::new(0),
+            change_index_store: None,
+            payout_history: None,
+            pool_balance: None,
+        }
+    }
+
+    /// Resumes descriptor change derivation at `index`, e.g. after restart.
+    /// Ignored once a [`ChangeIndexStore`] is attached.
+    pub fn with_next_change_index(self, index: u32) -> Self {
+        self.next_change_index.store(index, Ordering::SeqCst);
+        self
+    }
+
+    /// Reserves change indexes from `store` instead of an in-process counter.
+    pub fn with_change_index_store(mut self, store: Arc<dyn ChangeIndexStore>) -> Self {
+        self.change_index_store = Some(store);
+        self
+    }
+
+    /// Past payouts for cooling-period and velocity rules.
+    pub fn with_payout_history(mut self, history: Arc<dyn PayoutHistory>) -> Self {
+        self.payout_history = Some(history);
+        self
+    }
+
+    /// Live pool balance P̂ for pool-share limits.
+    pub fn with_pool_balance(mut self, pool_balance: Arc<RwLock<u64>>) -> Self {
+        self.pool_balance = Some(pool_balance);
+        self
+    }
+
+    /// Index the next descriptor change key will be derived at.
+    pub fn next_change_index(&self) -> Result<u32, DisbursementError> {
+        match self.change_index_store {
+            Some(ref store) => store
+                .next_change_index()
+                .map_err(|e| DisbursementError::Storage(format!("change index: {e}"))),
+            None => Ok(self.next_change_index.load(Ordering::SeqCst)),
+        }
+    }
+
+    /// Evaluates the configured AILEE Trust Layer rule set on a requested payout
+    pub fn evaluate_ailee_trust_policy(&self, req: &PayoutRequest) -> AileeTrustAudit {
+        self.evaluate_trust(req, 0, 0)
+    }
+
+    /// [`Self::evaluate_ailee_trust_policy`] for a batch recipient, after
+    /// `batch_sats` already allocated to earlier ones and `batch_count`
+    /// earlier transactions of the batch.
+    fn evaluate_trust(
+        &self,
+        req: &PayoutRequest,
+        batch_sats: u64,
+        batch_count: u64,
+    ) -> AileeTrustAudit {
+        self.config
+            .trust_policy
+            .evaluate(&trust_policy::TrustContext {
+                request: req,
+                config: &self.config,
+                payout_history: self.payout_history.as_deref(),
+                pool_balance_sats: self
+                    .pool_balance
+                    .as_ref()
+                    .and_then(|balance| balance.read().ok().map(|b| *b)),
+                batch_sats,
+                batch_count,
+                now: chrono::Utc::now(),
+            })
+    }
+
+    /// Whether `audit` stops the payout under this engine's configuration.
+    pub fn blocks_payout(&self, audit: &AileeTrustAudit) -> bool {
+        self.config.require_ailee_trust_pass && !audit.passed
+    }
+
+    /// Record of a payout refused by the trust layer, with the blocking
+    /// findings as the reason for its `Pending -> Rejected` transition.
+    pub fn rejected_payout(
+        &self,
+        payout_id: String,
+        req: &PayoutRequest,
+        trust_audit: AileeTrustAudit,
+    ) -> (PayoutTransactionResult, PayoutTransition) {
+        let reasons = trust_audit
+            .findings
+            .iter()
+            .filter(|f| f.blocking)
+            .map(|f| format!("{}: {}", f.rule_id, f.message))
+            .collect::<Vec<_>>()
+            .join("; ");
+        let timestamp = chrono::Utc::now().to_rfc3339();
+        let transition = PayoutTransition {
+            payout_id: payout_id.clone(),
+            from: PayoutStatus::Pending,
+            to: PayoutStatus::Rejected,
+            confirmations: 0,
+            detail: Some(reasons),
+            timestamp: timestamp.clone(),
+        };
+        let payout = PayoutTransactionResult {
+            payout_id,
+            recipient_address: req.recipient_address.clone(),
+            amount_sats: req.amount_sats,
+            fee_sats: 0,
+            status: PayoutStatus::Rejected,
+            psbt_base64: String::new(),
+            raw_tx_hex: String::new(),
+            txid: String::new(),
+            timestamp,
+            trust_audit,
+            is_dry_run: req.dry_run.unwrap_or(false),
+            confirmations: 0,
+            signed_psbt_base64: None,
+            final_tx_hex: None,
+            fee_bump_of: None,
+            fee_bumped_by: None,
+            signing: None,
+            batch_id: None,
+        };
+        (payout, transition)
+    }
+
+    /// Creates an Unsigned PSBT and Raw Unsigned Transaction for a payout,
+    /// funded by coin selection over the spendable pool UTXOs.
+    pub fn create_unsigned_payout(
+        &self,
+        payout_id: String,
+        req: &PayoutRequest,
+        pool_utxos: &[PoolUtxo],
+    ) -> Result<PayoutTransactionResult, DisbursementError> {
+        let trust_audit = self.evaluate_ailee_trust_policy(req);
+        self.create_audited_payout(payout_id, req, pool_utxos, trust_audit)
+    }
+
+    /// [`Self::create_unsigned_payout`] with an already evaluated audit.
+    pub fn create_audited_payout(
+        &self,
+        payout_id: String,
+        req: &PayoutRequest,
+        pool_utxos: &[PoolUtxo],
+        trust_audit: AileeTrustAudit,
+    ) -> Result<PayoutTransactionResult, DisbursementError> {
+        if self.blocks_payout(&trust_audit) {
+            return Err(DisbursementError::PolicyRejected {
+                recipient_address: req.recipient_address.clone(),
+                findings: trust_audit.findings,
+            });
+        }
+
+        let recipient_addr = parse_address(
+            &req.recipient_address,
+            self.config.network,
+            AddressRole::Recipient,
+        )?;
+
+        let recipient_script = recipient_addr.script_pubkey();
+
+        let is_dry_run = req.dry_run.unwrap_or(false);
+        let candidates = self.funding_candidates(req, pool_utxos)?;
+        ensure_supported_inputs(&candidates)?;
+        let change = self.resolve_change(req.change_address.as_deref(), pool_utxos, is_dry_run)?;
+
+        let fee_rate = self.fee_rate(req.fee_rate_sats_per_vbyte)?;
+        let funding = fund_outputs(
+            &candidates,
+            &[&recipient_script],
+            req.amount_sats,
+            &change.script_pubkey,
+            fee_rate,
+        )?;
+        let fee_sats = funding.fee_sats;
+
+        let mut outputs = vec![TxOut {
+            value: req.amount_sats,
+            script_pubkey: recipient_script,
+        }];
+
+        if let Some(change_sats) = funding.change_sats {
+            outputs.push(TxOut {
+                value: change_sats,
+                script_pubkey: change.script_pubkey.clone(),
+            });
+        }
+
+        let tx = unsigned_transaction(&funding.selected, outputs);
+        let change_output = funding.change_sats.map(|_| &change);
+        let psbt = self.build_psbt(&tx, &funding.selected, change_output)?;
+        let (psbt_base64, raw_tx_hex) = encode_psbt(&psbt);
+        let calculated_txid = tx.txid().to_string();
+
+        let status = if is_dry_run {
+            PayoutStatus::Pending
+        } else {
+            PayoutStatus::UnsignedCreated
+        };
+
+        Ok(PayoutTransactionResult {
+            payout_id,
+            recipient_address: req.recipient_address.clone(),
+            amount_sats: req.amount_sats,
+            fee_sats,
+            status,
+            psbt_base64,
+            raw_tx_hex,
+            txid: calculated_txid,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            trust_audit,
+            is_dry_run,
+            confirmations: 0,
+            signed_psbt_base64: None,
+            final_tx_hex: None,
+            fee_bump_of: None,
+            fee_bumped_by: None,
+            signing: signing_progress(&psbt),
+            batch_id: None,
+        })
+    }
+
+    /// The pool descriptor, provided its keys belong to the configured
+    /// network.
+    fn pool_descriptor(&self, role: AddressRole) -> Result<Option<&PoolDescriptor>, AddressError> {
+        let Some(ref descriptor) = self.config.pool_descriptor else {
+            return Ok(None);
+        };
+        if (descriptor.network() == Network::Bitcoin) != (self.config.network == Network::Bitcoin) {
+            return Err(AddressError::PoolKeyNetworkMismatch {
+                role,
+                xpub: descriptor.xpub().to_string(),
+                expected: self.config.network,
+            });
+        }
+        Ok(Some(descriptor))
+    }
+
+    /// Change returns to the pool: to the next internal descriptor key when
+    /// a pool descriptor is configured, otherwise to the configured change
+    /// address. Without either there is nowhere safe to send change.
+    ///
+    /// Trust rules never see the change output, so a change address named
+    /// by the caller must be the configured one or pay a script of
+    /// `pool_utxos`.
+    fn resolve_change(
+        &self,
+        change_address: Option<&str>,
+        pool_utxos: &[PoolUtxo],
+        is_dry_run: bool,
+    ) -> Result<ChangeDestination, DisbursementError> {
+        if let Some(change_addr_str) = change_address {
+            let script_pubkey =
+                parse_address(change_addr_str, self.config.network, AddressRole::Change)?
+                    .script_pubkey();
+            if self.configured_change()? != Some(script_pubkey.clone())
+                && !pool_utxos.iter().any(|u| u.script_pubkey == script_pubkey)
+            {
+                return Err(DisbursementError::InvalidRequest(format!(
+                    "Change address {change_addr_str} does not belong to the pool"
+                )));
+            }
+            return Ok(ChangeDestination {
+                script_pubkey,
+                derivation: None,
+            });
+        }
+
+        if let Some(descriptor) = self.pool_descriptor(AddressRole::Change)? {
+            // Dry runs preview the next key without consuming it.
+            let index = match (&self.change_index_store, is_dry_run) {
+                (Some(store), true) => store.next_change_index(),
+                (Some(store), false) => store.reserve_change_index(),
+                (None, true) => Ok(self.next_change_index.load(Ordering::SeqCst)),
+                (None, false) => Ok(self.next_change_index.fetch_add(1, Ordering::SeqCst)),
+            }
+            .map_err(|e| DisbursementError::Storage(format!("change index: {e}")))?;
+            let derivation = KeyChainIndex {
+                chain: KeyChain::Internal,
+                index,
+            };
+            return Ok(ChangeDestination {
+                script_pubkey: descriptor
+                    .derive(derivation)
+                    .map_err(DisbursementError::PsbtBuild)?
+                    .script_pubkey,
+                derivation: Some(derivation),
+            });
+        }
+
+        match self.configured_change()? {
+            Some(script_pubkey) => Ok(ChangeDestination {
+                script_pubkey,
+                derivation: None,
+            }),
+            None => Err(DisbursementError::InvalidRequest(
+                "No change destination: name a change address or configure a pool descriptor or change address"
+                    .to_string(),
+            )),
+        }
+    }
+
+    /// The requested fee rate, or the default, within `max_fee_rate`.
+    fn fee_rate(&self, requested: Option<u64>) -> Result<u64, DisbursementError> {
+        let fee_rate = requested.unwrap_or(self.config.default_fee_rate);
+        if fee_rate > self.config.max_fee_rate {
+            return Err(DisbursementError::InvalidRequest(format!(
+                "Fee rate {fee_rate} sat/vB exceeds the maximum of {} sat/vB",
+                self.config.max_fee_rate
+            )));
+        }
+        Ok(fee_rate)
+    }
+
+    /// Script of the configured change address, if any.
+    fn configured_change(&self) -> Result<Option<ScriptBuf>, DisbursementError> {
+        let Some(ref configured) = self.config.change_address else {
+            return Ok(None);
+        };
+        Ok(Some(
+            parse_address(configured, self.config.network, AddressRole::Change)?.script_pubkey(),
+        ))
+    }
+
+    /// Base64 PSBT and raw hex of an unsigned transaction.
+    fn encode_unsigned(
+        &self,
+        tx: &Transaction,
+        selected: &[PoolUtxo],
+        change: Option<&ChangeDestination>,
+    ) -> Result<(String, String), DisbursementError> {
+        let psbt = self.build_psbt(tx, selected, change)?;
+        Ok(encode_psbt(&psbt))
+    }
+
+    /// PSBT for an unsigned transaction spending `selected` in order.
+    ///
+    /// Inputs carry `witness_utxo` (segwit), `non_witness_utxo` when the
+    /// funding transaction is known, and the P2WSH witness script. With a
+    /// pool descriptor, inputs and the change output (always last) also
+    /// carry BIP32 key origins (every cosigner's for multisig) so standard
+    /// signers can sign.
+    fn build_psbt(
+        &self,
+        tx: &Transaction,
+        selected: &[PoolUtxo],
+        change: Option<&ChangeDestination>,
+    ) -> Result<PartiallySignedTransaction, DisbursementError> {
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx.clone())
+            .map_err(|e| DisbursementError::PsbtBuild(format!("Failed to create PSBT: {e}")))?;
+        let funding_keys = self.pool_descriptor(AddressRole::Funding)?;
+
+        for (input, utxo) in psbt.inputs.iter_mut().zip(selected) {
+            if utxo.input_type().is_some_and(|t| t.is_segwit()) {
+                input.witness_utxo = Some(TxOut {
+                    value: utxo.value_sats,
+                    script_pubkey: utxo.script_pubkey.clone(),
+                });
+            }
+            if let Some(ref previous_tx) = utxo.previous_tx {
+                let spent = previous_tx.output.get(utxo.outpoint.vout as usize);
+                if previous_tx.txid() != utxo.outpoint.txid
+                    || spent.map(|o| (o.value, &o.script_pubkey))
+                        != Some((utxo.value_sats, &utxo.script_pubkey))
+                {
+                    return Err(DisbursementError::InvalidUtxo(format!(
+                        "Previous transaction does not match pool UTXO {}",
+                        utxo.outpoint
+                    )));
+                }
+                input.non_witness_utxo = Some(previous_tx.clone());
+            }
+            input.witness_script = utxo.witness_script.clone();
+
+            if let (Some(descriptor), Some(at)) = (funding_keys, utxo.derivation) {
+                let key = descriptor
+                    .derive(at)
+                    .map_err(DisbursementError::InvalidUtxo)?;
+                if key.script_pubkey != utxo.script_pubkey
+                    || (utxo.witness_script.is_some() && utxo.witness_script != key.witness_script)
+                {
+                    return Err(DisbursementError::InvalidUtxo(format!(
+                        "Pool UTXO {} does not match its descriptor derivation",
+                        utxo.outpoint
+                    )));
+                }
+                input.witness_script = key.witness_script;
+                match descriptor.kind() {
+                    PoolDescriptorType::Wpkh | PoolDescriptorType::WshSortedMulti => {
+                        input.bip32_derivation.extend(key.keys);
+                    }
+                    PoolDescriptorType::Tr => {
+                        let (public_key, key_source) = key.keys[0].clone();
+                        let (x_only, _) = public_key.x_only_public_key();
+                        input.tap_internal_key = Some(x_only);
+                        input
+                            .tap_key_origins
+                            .insert(x_only, (Vec::new(), key_source));
+                    }
+                }
+            }
+        }
+
+        if let (Some(descriptor), Some(at)) = (funding_keys, change.and_then(|c| c.derivation)) {
+            let key = descriptor
+                .derive(at)
+                .map_err(DisbursementError::PsbtBuild)?;
+            let output = psbt.outputs.last_mut().ok_or_else(|| {
+                DisbursementError::PsbtBuild("Change output missing from payout transaction".into())
+            })?;
+            output.witness_script = key.witness_script;
+            match descriptor.kind() {
+                PoolDescriptorType::Wpkh | PoolDescriptorType::WshSortedMulti => {
+                    output.bip32_derivation.extend(key.keys);
+                }
+                PoolDescriptorType::Tr => {
+                    let (public_key, key_source) = key.keys[0].clone();
+                    let (x_only, _) = public_key.x_only_public_key();
+                    output.tap_internal_key = Some(x_only);
+                    output
+                        .tap_key_origins
+                        .insert(x_only, (Vec::new(), key_source));
+                }
+            }
+        }
+
+        Ok(psbt)
+    }
+
+    /// `utxos` with the multisig witness script of each descriptor-derived
+    /// UTXO filled in, so their spend size can be estimated.
+    fn with_pool_scripts(&self, utxos: &[PoolUtxo]) -> Vec<PoolUtxo> {
+        let descriptor = match self.config.pool_descriptor {
+            Some(ref d) if d.kind() == PoolDescriptorType::WshSortedMulti => d,
+            _ => return utxos.to_vec(),
+        };
+        utxos
+            .iter()
+            .cloned()
+            .map(|mut utxo| {
+                if let (None, Some(at)) = (&utxo.witness_script, utxo.derivation) {
+                    utxo.witness_script = descriptor.derive(at).ok().and_then(|k| k.witness_script);
+                }
+                utxo
+            })
+            .collect()
+    }
+
+    /// Pool UTXOs eligible for this payout, honouring a pinned funding
+    /// outpoint when the request names one.
+    fn funding_candidates(
+        &self,
+        req: &PayoutRequest,
+        pool_utxos: &[PoolUtxo],
+    ) -> Result<Vec<PoolUtxo>, DisbursementError> {
+        let pool_utxos = self.with_pool_scripts(pool_utxos);
+        let Some(ref txid_str) = req.funding_utxo_txid else {
+            return Ok(pool_utxos);
+        };
+
+        let outpoint = OutPoint {
+            txid: Txid::from_str(txid_str).map_err(|e| {
+                DisbursementError::InvalidUtxo(format!("Invalid funding UTXO txid: {e}"))
+            })?,
+            vout: req.funding_utxo_vout.unwrap_or(0),
+        };
+        let utxo = pool_utxos
+            .iter()
+            .find(|u| u.outpoint == outpoint)
+            .ok_or_else(|| {
+                DisbursementError::InvalidUtxo(format!(
+                    "Funding UTXO {outpoint} is not a spendable pool UTXO"
+                ))
+            })?;
+        if let Some(expected) = req.funding_utxo_value_sats {
+            if expected != utxo.value_sats {
+                return Err(DisbursementError::InvalidUtxo(format!(
+                    "Funding UTXO {outpoint} value mismatch: request says {expected} sats, pool has {} sats",
+                    utxo.value_sats
+                )));
+            }
+        }
+        Ok(vec![utxo.clone()])
+    }
+}
+
+/// Base64 PSBT and raw hex of its unsigned transaction.
+fn encode_psbt(psbt: &PartiallySignedTransaction) -> (String, String) {
+    use base64::Engine;
+    let psbt_base64 = base64::engine::general_purpose::STANDARD.encode(psbt.serialize());
+    let raw_tx_hex = hex::encode(bitcoin::consensus::serialize(&psbt.unsigned_tx));
+    (psbt_base64, raw_tx_hex)
+}
+
+pub(crate) fn decode_psbt(psbt_base64: &str) -> Result<PartiallySignedTransaction, String> {
+    use base64::Engine;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(psbt_base64.trim())
+        .map_err(|e| e.to_string())?;
+    PartiallySignedTransaction::deserialize(&bytes).map_err(|e| e.to_string())
+}
+
+/// Rejects pool UTXOs whose spend size cannot be estimated.
+fn ensure_supported_inputs(candidates: &[PoolUtxo]) -> Result<(), DisbursementError> {
+    match candidates.iter().find(|u| u.input_type().is_none()) {
+        Some(utxo) => Err(DisbursementError::InvalidUtxo(format!(
+            "Pool UTXO {} has an unsupported script type",
+            utxo.outpoint
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Where change is paid, with its descriptor position when pool-owned.
+struct ChangeDestination {
+    script_pubkey: ScriptBuf,
+    derivation: Option<KeyChainIndex>,
+}
+
+/// Inputs, fee and optional change funding a set of recipient outputs.
+struct Funding {
+    selected: Vec<PoolUtxo>,
+    input_types: Vec<InputScriptType>,
+    input_value_sats: u64,
+    fee_sats: u64,
+    change_sats: Option<u64>,
+}
+
+/// Selects inputs for `target_sats` across `recipient_scripts` and settles
+/// the exact fee at `fee_rate` sat/vB.
+///
+/// Selection works on per-input vsizes rounded up, so its fee never
+/// undershoots; the exact fee is then computed from the final input set and
+/// a change output is added only if it still clears the dust limit after
+/// paying for its own size. Otherwise the excess goes to the fee.
+fn fund_outputs(
+    candidates: &[PoolUtxo],
+    recipient_scripts: &[&Script],
+    target_sats: u64,
+    change_script: &Script,
+    fee_rate: u64,
+) -> Result<Funding, DisbursementError> {
+    let segwit_marker_weight = 2;
+    let change_spend_type =
+        InputScriptType::classify(change_script, None).unwrap_or(InputScriptType::P2wpkh);
+    let selection = select_coins(
+        candidates,
+        &SelectionParams {
+            target_sats,
+            fee_rate,
+            base_vsize: (estimate_weight(&[], recipient_scripts) + segwit_marker_weight)
+                .div_ceil(4),
+            change_output_vsize: tx_size::output_weight(change_script) / 4,
+            change_spend_vsize: change_spend_type.vsize(),
+            dust_limit_sats: DUST_LIMIT_SATS,
+        },
+        &|utxo| utxo.input_type().map_or(0, |t| t.vsize()),
+    )?;
+
+    let input_types: Vec<InputScriptType> = selection
+        .selected
+        .iter()
+        .filter_map(PoolUtxo::input_type)
+        .collect();
+    let input_value_sats = selection.input_value_sats;
+
+    let mut with_change = recipient_scripts.to_vec();
+    with_change.push(change_script);
+    let fee_with_change = fee_rate.saturating_mul(estimate_vsize(&input_types, &with_change));
+    let change = input_value_sats
+        .saturating_sub(target_sats)
+        .saturating_sub(fee_with_change);
+    let (fee_sats, change_sats) = if change >= DUST_LIMIT_SATS {
+        (fee_with_change, Some(change))
+    } else {
+        let fee_without_change =
+            fee_rate.saturating_mul(estimate_vsize(&input_types, recipient_scripts));
+        let required = target_sats.saturating_add(fee_without_change);
+        if input_value_sats < required {
+            return Err(DisbursementError::InsufficientFunds {
+                available_sats: input_value_sats,
+                required_sats: required,
+            });
+        }
+        (input_value_sats - target_sats, None)
+    };
+
+    Ok(Funding {
+        selected: selection.selected,
+        input_types,
+        input_value_sats,
+        fee_sats,
+        change_sats,
+    })
+}
+
+/// Version 2, RBF-signalling transaction spending `selected` in order.
+fn unsigned_transaction(selected: &[PoolUtxo], outputs: Vec<TxOut>) -> Transaction {
+    let inputs = selected
+        .iter()
+        .map(|utxo| TxIn {
+            previous_output: utxo.outpoint,
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::default(),
+        })
+        .collect();
+
+    Transaction {
+        version: 2,
+        lock_time: bitcoin::absolute::LockTime::ZERO,
+        input: inputs,
+        output: outputs,
+    }
+}
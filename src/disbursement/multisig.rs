@@ -0,0 +1,204 @@
+//! k-of-n pool scripts and cosigner signing progress.
+//!
+//! A `wsh(sortedmulti(k,...))` pool locks each output to
+//! `OP_k <pubkey>... OP_n OP_CHECKMULTISIG` with the keys sorted
+//! lexicographically (BIP67). Cosigners are identified by the master key
+//! fingerprint in the PSBT key origins; a payout stays `PartiallySigned`
+//! until `k` of them have signed every input.
+
+use super::finalize::{is_final, spent_outputs, verify_ecdsa_sig, verify_schnorr_sig};
+use bitcoin::blockdata::opcodes::all::OP_CHECKMULTISIG;
+use bitcoin::blockdata::script::{Builder, Instruction};
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::sighash::SighashCache;
+use bitcoin::{PublicKey, Script, ScriptBuf};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Keys `OP_CHECKMULTISIG` accepts in a standard P2WSH script.
+pub const MAX_MULTISIG_KEYS: usize = 20;
+
+/// `OP_k <keys sorted> OP_n OP_CHECKMULTISIG`.
+pub fn sortedmulti_script(threshold: usize, keys: &[PublicKey]) -> ScriptBuf {
+    let mut keys = keys.to_vec();
+    keys.sort_by_key(|k| k.to_bytes());
+    let mut builder = Builder::new().push_int(threshold as i64);
+    for key in &keys {
+        builder = builder.push_key(key);
+    }
+    builder
+        .push_int(keys.len() as i64)
+        .push_opcode(OP_CHECKMULTISIG)
+        .into_script()
+}
+
+/// Threshold and keys of a bare `OP_CHECKMULTISIG` script, in script order.
+pub fn parse_multisig(script: &Script) -> Option<(usize, Vec<PublicKey>)> {
+    let mut instructions = script.instructions();
+    let threshold = small_int(instructions.next()?.ok()?)?;
+    let mut keys = Vec::new();
+    let count = loop {
+        match instructions.next()?.ok()? {
+            Instruction::PushBytes(bytes) if bytes.len() == 33 => {
+                keys.push(PublicKey::from_slice(bytes.as_bytes()).ok()?);
+            }
+            other => break small_int(other)?,
+        }
+    };
+    let checkmultisig = matches!(
+        instructions.next()?.ok()?,
+        Instruction::Op(op) if op == OP_CHECKMULTISIG
+    );
+    (checkmultisig
+        && instructions.next().is_none()
+        && count == keys.len()
+        && (1..=count).contains(&threshold))
+    .then_some((threshold, keys))
+}
+
+fn small_int(instruction: Instruction<'_>) -> Option<usize> {
+    match instruction {
+        Instruction::Op(op) => {
+            let code = op.to_u8();
+            // OP_1 ..= OP_16
+            (0x51..=0x60)
+                .contains(&code)
+                .then(|| (code - 0x50) as usize)
+        }
+        // Script numbers above 16 are pushed as one byte.
+        Instruction::PushBytes(bytes) if bytes.len() == 1 && bytes[0] > 16 && bytes[0] < 0x80 => {
+            Some(bytes[0] as usize)
+        }
+        _ => None,
+    }
+}
+
+/// Whether one pool cosigner has signed a payout.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CosignerSignature {
+    /// Master key fingerprint, hex.
+    pub fingerprint: String,
+    /// Signed every input its key can spend.
+    pub signed: bool,
+}
+
+/// Signatures collected on a payout PSBT.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningProgress {
+    /// Signatures each input needs.
+    pub threshold: usize,
+    pub cosigners: Vec<CosignerSignature>,
+}
+
+impl SigningProgress {
+    pub fn signed_count(&self) -> usize {
+        self.cosigners.iter().filter(|c| c.signed).count()
+    }
+
+    /// Cosigners that have not signed yet.
+    pub fn awaiting(&self) -> impl Iterator<Item = &str> {
+        self.cosigners
+            .iter()
+            .filter(|c| !c.signed)
+            .map(|c| c.fingerprint.as_str())
+    }
+}
+
+/// Cosigners named by the key origins of a PSBT's unfinalized inputs and
+/// whether each has signed all of them. Only signatures that verify against
+/// the input's sighash count. `None` when no input carries key origins, e.g.
+/// without a pool descriptor.
+pub fn signing_progress(psbt: &PartiallySignedTransaction) -> Option<SigningProgress> {
+    let prevouts = spent_outputs(psbt);
+    let mut cache = SighashCache::new(&psbt.unsigned_tx);
+    let mut threshold = 0;
+    let mut cosigners: BTreeMap<String, bool> = BTreeMap::new();
+    for (index, input) in psbt.inputs.iter().enumerate() {
+        if is_final(input) {
+            continue;
+        }
+        let mut signers = Vec::new();
+        for (key, (fingerprint, _)) in &input.bip32_derivation {
+            let key = PublicKey::new(*key);
+            let signed = match (input.partial_sigs.get(&key), &prevouts[index]) {
+                (Some(sig), Some(prevout)) => {
+                    verify_ecdsa_sig(&mut cache, index, input, prevout, &key, sig)
+                }
+                _ => false,
+            };
+            signers.push((fingerprint, signed));
+        }
+        for (key, (leaves, (fingerprint, _))) in &input.tap_key_origins {
+            let signed = if leaves.is_empty() {
+                input.tap_key_sig.is_some_and(|sig| {
+                    verify_schnorr_sig(&mut cache, index, &prevouts, key, None, &sig)
+                })
+            } else {
+                leaves.iter().any(|leaf| {
+                    input
+                        .tap_script_sigs
+                        .get(&(*key, *leaf))
+                        .is_some_and(|sig| {
+                            verify_schnorr_sig(&mut cache, index, &prevouts, key, Some(*leaf), sig)
+                        })
+                })
+            };
+            signers.push((fingerprint, signed));
+        }
+        if signers.is_empty() {
+            continue;
+        }
+
+        let input_threshold = input
+            .witness_script
+            .as_deref()
+            .and_then(parse_multisig)
+            .map_or(1, |(k, _)| k);
+        threshold = threshold.max(input_threshold);
+        for (fingerprint, signed) in signers {
+            let all_signed = cosigners.entry(fingerprint.to_string()).or_insert(true);
+            *all_signed &= signed;
+        }
+    }
+
+    (!cosigners.is_empty()).then(|| SigningProgress {
+        threshold,
+        cosigners: cosigners
+            .into_iter()
+            .map(|(fingerprint, signed)| CosignerSignature {
+                fingerprint,
+                signed,
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::secp256k1::{Secp256k1, SecretKey};
+
+    #[test]
+    fn sortedmulti_round_trips_through_parse() {
+        let secp = Secp256k1::new();
+        let keys: Vec<PublicKey> = (1u8..=3)
+            .map(|i| PublicKey::new(SecretKey::from_slice(&[i; 32]).unwrap().public_key(&secp)))
+            .collect();
+        let reversed: Vec<PublicKey> = keys.iter().rev().copied().collect();
+
+        let script = sortedmulti_script(2, &keys);
+        assert_eq!(script, sortedmulti_script(2, &reversed));
+        let (threshold, parsed) = parse_multisig(&script).unwrap();
+        assert_eq!(threshold, 2);
+        let mut sorted = keys.clone();
+        sorted.sort_by_key(|k| k.to_bytes());
+        assert_eq!(parsed, sorted);
+
+        // Stake locks and other P2WSH scripts are not multisig.
+        let single = Builder::new()
+            .push_key(&keys[0])
+            .push_opcode(bitcoin::blockdata::opcodes::all::OP_CHECKSIG)
+            .into_script();
+        assert_eq!(parse_multisig(&single), None);
+    }
+}
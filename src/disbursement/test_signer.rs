@@ -0,0 +1,281 @@
+//! Deterministic software signer and spend verifier for end-to-end tests.
+//!
+//! Enabled by the `test-signer` feature. [`TestSigner`] derives its master
+//! key from a seed and signs the P2WPKH, P2TR key-path and P2WSH multisig
+//! inputs of payout PSBTs whose key origins carry its fingerprint. ECDSA
+//! signatures are ground to low R and Schnorr signatures use no auxiliary
+//! randomness, so signed transactions are reproducible and never exceed the
+//! sizes the fee estimator assumes. [`verify_spends`] then checks each
+//! input's witness against the output it spends the way script execution
+//! would for those script types, without a node or libbitcoinconsensus.
+//!
+//! Never use these keys for real funds.
+
+use super::multisig::parse_multisig;
+use bitcoin::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey, Fingerprint};
+use bitcoin::ecdsa;
+use bitcoin::hashes::Hash;
+use bitcoin::key::TapTweak;
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::secp256k1::{All, KeyPair, Message, Secp256k1, XOnlyPublicKey};
+use bitcoin::sighash::{EcdsaSighashType, Prevouts, SighashCache, TapSighashType};
+use bitcoin::taproot;
+use bitcoin::{Network, PublicKey, Script, ScriptBuf, Transaction, TxOut, WPubkeyHash};
+
+/// Seed-derived BIP32 signer.
+pub struct TestSigner {
+    secp: Secp256k1<All>,
+    master: ExtendedPrivKey,
+}
+
+impl TestSigner {
+    pub fn from_seed(seed: &[u8], network: Network) -> Self {
+        Self {
+            secp: Secp256k1::new(),
+            master: ExtendedPrivKey::new_master(network, seed)
+                .expect("seed must be 16 to 64 bytes"),
+        }
+    }
+
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.master.fingerprint(&self.secp)
+    }
+
+    pub fn xpub(&self, path: &DerivationPath) -> ExtendedPubKey {
+        let xpriv = self
+            .master
+            .derive_priv(&self.secp, path)
+            .expect("derivation from a master key cannot fail");
+        ExtendedPubKey::from_priv(&self.secp, &xpriv)
+    }
+
+    /// `[fingerprint/path]xpub/<0;1>/*` for the account at `path`, ready to
+    /// wrap in `wpkh(..)`, `tr(..)` or `wsh(sortedmulti(k,..))`.
+    pub fn descriptor_key(&self, path: &DerivationPath) -> String {
+        let origin = path.to_string();
+        let origin = origin.trim_start_matches('m');
+        format!(
+            "[{}{origin}]{}/<0;1>/*",
+            self.fingerprint(),
+            self.xpub(path)
+        )
+    }
+
+    /// Signs every unfinalized input whose key origins name this signer and
+    /// returns the number of signatures added.
+    pub fn sign_psbt(&self, psbt: &mut PartiallySignedTransaction) -> Result<usize, String> {
+        let prevouts = psbt
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(index, input)| {
+                input
+                    .witness_utxo
+                    .clone()
+                    .ok_or_else(|| format!("input {index}: missing witness_utxo"))
+            })
+            .collect::<Result<Vec<TxOut>, String>>()?;
+        let mut cache = SighashCache::new(&psbt.unsigned_tx);
+        let fingerprint = self.fingerprint();
+        let mut signed = 0;
+
+        for (index, input) in psbt.inputs.iter_mut().enumerate() {
+            if input.final_script_witness.is_some() {
+                continue;
+            }
+            let spent = &prevouts[index];
+
+            for (public_key, (origin, path)) in &input.bip32_derivation {
+                if *origin != fingerprint {
+                    continue;
+                }
+                let private_key = self.private_key(path)?;
+                if private_key.public_key(&self.secp) != *public_key {
+                    return Err(format!("input {index}: key origin {path} is not ours"));
+                }
+                let script_code = if spent.script_pubkey.is_v0_p2wpkh() {
+                    spent
+                        .script_pubkey
+                        .p2wpkh_script_code()
+                        .ok_or_else(|| format!("input {index}: malformed P2WPKH script"))?
+                } else if spent.script_pubkey.is_v0_p2wsh() {
+                    input
+                        .witness_script
+                        .clone()
+                        .ok_or_else(|| format!("input {index}: missing witness script"))?
+                } else {
+                    return Err(format!("input {index}: unsupported ECDSA spend"));
+                };
+                let sighash = cache
+                    .segwit_signature_hash(index, &script_code, spent.value, EcdsaSighashType::All)
+                    .map_err(|e| format!("input {index}: {e}"))?;
+                let sig = self
+                    .secp
+                    .sign_ecdsa_low_r(&Message::from(sighash), &private_key);
+                input.partial_sigs.insert(
+                    PublicKey::new(*public_key),
+                    ecdsa::Signature::sighash_all(sig),
+                );
+                signed += 1;
+            }
+
+            for (x_only, (leaves, (origin, path))) in &input.tap_key_origins {
+                if *origin != fingerprint || !leaves.is_empty() {
+                    continue;
+                }
+                let keypair = KeyPair::from_secret_key(&self.secp, &self.private_key(path)?);
+                if keypair.x_only_public_key().0 != *x_only {
+                    return Err(format!("input {index}: key origin {path} is not ours"));
+                }
+                let sighash = cache
+                    .taproot_key_spend_signature_hash(
+                        index,
+                        &Prevouts::All(&prevouts),
+                        TapSighashType::Default,
+                    )
+                    .map_err(|e| format!("input {index}: {e}"))?;
+                let tweaked = keypair
+                    .tap_tweak(&self.secp, input.tap_merkle_root)
+                    .to_inner();
+                input.tap_key_sig = Some(taproot::Signature {
+                    sig: self
+                        .secp
+                        .sign_schnorr_no_aux_rand(&Message::from(sighash), &tweaked),
+                    hash_ty: TapSighashType::Default,
+                });
+                signed += 1;
+            }
+        }
+        Ok(signed)
+    }
+
+    fn private_key(&self, path: &DerivationPath) -> Result<bitcoin::secp256k1::SecretKey, String> {
+        self.master
+            .derive_priv(&self.secp, path)
+            .map(|xpriv| xpriv.private_key)
+            .map_err(|e| format!("key derivation failed: {e}"))
+    }
+}
+
+/// Checks that each input of `tx` validly spends `spent[i]`: the witness
+/// program matches, and the signatures verify against the BIP143/BIP341
+/// sighash. Supports the script types pool payouts spend: P2WPKH, P2TR key
+/// path and P2WSH `OP_CHECKMULTISIG`.
+pub fn verify_spends(tx: &Transaction, spent: &[TxOut]) -> Result<(), String> {
+    if tx.input.len() != spent.len() {
+        return Err(format!(
+            "{} inputs but {} spent outputs",
+            tx.input.len(),
+            spent.len()
+        ));
+    }
+    let input_value: u64 = spent.iter().map(|o| o.value).sum();
+    let output_value: u64 = tx.output.iter().map(|o| o.value).sum();
+    if output_value > input_value {
+        return Err("outputs exceed inputs".to_string());
+    }
+
+    let secp = Secp256k1::verification_only();
+    let mut cache = SighashCache::new(tx);
+    for (index, (txin, prevout)) in tx.input.iter().zip(spent).enumerate() {
+        let witness = txin.witness.to_vec();
+        let script = &prevout.script_pubkey;
+        let fail = |reason: &str| format!("input {index}: {reason}");
+        if !txin.script_sig.is_empty() {
+            return Err(fail("segwit spends must have an empty scriptSig"));
+        }
+
+        if script.is_v0_p2wpkh() {
+            let [sig, pubkey] = witness.as_slice() else {
+                return Err(fail("P2WPKH witness must be [sig, pubkey]"));
+            };
+            let pubkey = PublicKey::from_slice(pubkey).map_err(|e| fail(&e.to_string()))?;
+            if !pubkey.compressed
+                || ScriptBuf::new_v0_p2wpkh(&WPubkeyHash::hash(&pubkey.to_bytes())) != *script
+            {
+                return Err(fail("public key does not match the witness program"));
+            }
+            let script_code = script
+                .p2wpkh_script_code()
+                .ok_or_else(|| fail("malformed P2WPKH script"))?;
+            verify_ecdsa(
+                &secp,
+                &mut cache,
+                index,
+                &script_code,
+                prevout.value,
+                sig,
+                &pubkey,
+            )
+            .map_err(|e| fail(&e))?;
+        } else if script.is_v1_p2tr() {
+            let [sig] = witness.as_slice() else {
+                return Err(fail("only single-signature key-path spends are supported"));
+            };
+            let sig = taproot::Signature::from_slice(sig).map_err(|e| fail(&e.to_string()))?;
+            let sighash = cache
+                .taproot_key_spend_signature_hash(index, &Prevouts::All(spent), sig.hash_ty)
+                .map_err(|e| fail(&e.to_string()))?;
+            let output_key = XOnlyPublicKey::from_slice(&script.as_bytes()[2..])
+                .map_err(|e| fail(&e.to_string()))?;
+            secp.verify_schnorr(&sig.sig, &Message::from(sighash), &output_key)
+                .map_err(|_| fail("invalid Schnorr signature"))?;
+        } else if script.is_v0_p2wsh() {
+            let Some((witness_script, stack)) = witness.split_last() else {
+                return Err(fail("empty P2WSH witness"));
+            };
+            let witness_script = ScriptBuf::from_bytes(witness_script.clone());
+            if ScriptBuf::new_v0_p2wsh(&witness_script.wscript_hash()) != *script {
+                return Err(fail("witness script does not match the witness program"));
+            }
+            let (threshold, keys) = parse_multisig(&witness_script)
+                .ok_or_else(|| fail("unsupported witness script"))?;
+            // CHECKMULTISIG pops one extra element, which must be empty.
+            let Some((dummy, sigs)) = stack.split_first() else {
+                return Err(fail("missing CHECKMULTISIG dummy element"));
+            };
+            if !dummy.is_empty() || sigs.len() != threshold {
+                return Err(fail("witness must be [<>, sig x threshold, script]"));
+            }
+            // Signatures must match keys in script order.
+            let mut keys = keys.iter();
+            for sig in sigs {
+                let matched = keys.by_ref().any(|key| {
+                    verify_ecdsa(
+                        &secp,
+                        &mut cache,
+                        index,
+                        &witness_script,
+                        prevout.value,
+                        sig,
+                        key,
+                    )
+                    .is_ok()
+                });
+                if !matched {
+                    return Err(fail("CHECKMULTISIG failed"));
+                }
+            }
+        } else {
+            return Err(fail("unsupported script type"));
+        }
+    }
+    Ok(())
+}
+
+fn verify_ecdsa(
+    secp: &Secp256k1<bitcoin::secp256k1::VerifyOnly>,
+    cache: &mut SighashCache<&Transaction>,
+    index: usize,
+    script_code: &Script,
+    value: u64,
+    sig: &[u8],
+    pubkey: &PublicKey,
+) -> Result<(), String> {
+    let sig = ecdsa::Signature::from_slice(sig).map_err(|e| e.to_string())?;
+    let sighash = cache
+        .segwit_signature_hash(index, script_code, value, sig.hash_ty)
+        .map_err(|e| e.to_string())?;
+    secp.verify_ecdsa(&Message::from(sighash), &sig.sig, &pubkey.inner)
+        .map_err(|_| "invalid ECDSA signature".to_string())
+}
@@ -0,0 +1,685 @@
+//! AILEE Trust Layer rule engine.
+//!
+//! A [`TrustPolicy`] is an ordered set of [`TrustRule`]s. Every rule that
+//! fires contributes a [`TrustFinding`] with a risk score. The audit's risk
+//! is the combined probability that any finding is real,
+//! `1 - (1 - base) * prod(1 - score)`, so adding findings never lowers it.
+//! A payout fails the audit only when a blocking finding fires.
+//!
+//! Policies are built in code or loaded from JSON:
+//!
+//! ```json
+//! {
+//!   "version": "pool-policy-2025-01",
+//!   "rules": [
+//!     { "rule": "amount_limit" },
+//!     { "rule": "address_network" },
+//!     { "rule": "dust" },
+//!     { "rule": "deny_list", "addresses": ["bc1q..."] },
+//!     { "rule": "script_types", "allowed": ["p2wpkh", "p2tr"] },
+//!     { "rule": "new_recipient_cooling", "period_secs": 86400, "blocking": false },
+//!     { "rule": "velocity_limit", "scope": "recipient", "window_secs": 86400, "max_sats": 100000000 },
+//!     { "rule": "velocity_limit", "scope": "global", "window_secs": 3600, "max_count": 50 },
+//!     { "rule": "pool_share", "window_secs": 86400, "max_percent": 20.0 }
+//!   ]
+//! }
+//! ```
+
+use super::address::{parse_address, AddressError, AddressRole};
+use super::velocity_limits::{PayoutVolume, PoolShareRule, VelocityLimitRule, VelocityScope};
+use super::{AileeTrustAudit, DisbursementConfig, PayoutRequest, DUST_LIMIT_SATS};
+use crate::velocity_analyzer::VelocityError;
+use bitcoin::{Address, ScriptBuf};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+pub const STANDARD_POLICY_VERSION: &str = "ailee-trust-standard-v4";
+
+/// A rule that fired during evaluation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrustFinding {
+    pub rule_id: String,
+    /// Risk contribution in `[0, 1]`.
+    pub score: f64,
+    /// Whether this finding fails the audit on its own.
+    pub blocking: bool,
+    pub message: String,
+}
+
+/// Past payouts as seen by the stateful rules (cooling periods, velocity
+/// limits).
+pub trait PayoutHistory: Send + Sync {
+    /// Time of the earliest payout to `address`'s script that reached the
+    /// network. Batch transactions, which pay several recipients, are not
+    /// counted.
+    fn first_seen(&self, address: &str) -> Result<Option<DateTime<Utc>>, VelocityError>;
+
+    /// Payouts created at or after `since`, to `recipient`'s script or to
+    /// anyone. See [`counts_toward_velocity`] for which payouts count. For a
+    /// recipient, batch transactions paying it count once each, with the
+    /// sats of their [`recipient_outputs`](super::recipient_outputs) to it.
+    fn volume_since(
+        &self,
+        recipient: Option<&str>,
+        since: DateTime<Utc>,
+    ) -> Result<PayoutVolume, VelocityError>;
+}
+
+/// What a rule sees of the payout under evaluation.
+pub struct TrustContext<'a> {
+    pub request: &'a PayoutRequest,
+    pub config: &'a DisbursementConfig,
+    pub payout_history: Option<&'a dyn PayoutHistory>,
+    /// Current pool balance P̂, when known.
+    pub pool_balance_sats: Option<u64>,
+    /// Sats paid to earlier recipients of the same batch, not yet in the
+    /// payout history; pool-wide limits count them with this payout.
+    pub batch_sats: u64,
+    /// Transactions of the same batch before the one paying this recipient,
+    /// counted as payouts by pool-wide limits.
+    pub batch_count: u64,
+    pub now: DateTime<Utc>,
+}
+
+impl TrustContext<'_> {
+    fn recipient_script(&self) -> Option<ScriptBuf> {
+        Address::from_str(&self.request.recipient_address)
+            .ok()
+            .map(|a| a.assume_checked().script_pubkey())
+    }
+}
+
+pub trait TrustRule: Send + Sync {
+    fn id(&self) -> &str;
+    fn evaluate(&self, ctx: &TrustContext<'_>) -> Option<TrustFinding>;
+}
+
+/// Score and blocking behaviour shared by the built-in rules.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RuleSeverity {
+    pub score: f64,
+    pub blocking: bool,
+}
+
+impl RuleSeverity {
+    pub fn blocking(score: f64) -> Self {
+        Self {
+            score,
+            blocking: true,
+        }
+    }
+
+    pub fn warning(score: f64) -> Self {
+        Self {
+            score,
+            blocking: false,
+        }
+    }
+
+    pub fn finding(&self, rule_id: &str, message: String) -> TrustFinding {
+        TrustFinding {
+            rule_id: rule_id.to_string(),
+            score: self.score.clamp(0.0, 1.0),
+            blocking: self.blocking,
+            message,
+        }
+    }
+}
+
+/// Rejects amounts above `max_sats`, or the engine's
+/// `max_single_payout_sats` when unset.
+pub struct AmountLimitRule {
+    pub max_sats: Option<u64>,
+    pub severity: RuleSeverity,
+}
+
+impl TrustRule for AmountLimitRule {
+    fn id(&self) -> &str {
+        "amount_limit"
+    }
+
+    fn evaluate(&self, ctx: &TrustContext<'_>) -> Option<TrustFinding> {
+        let limit = self.max_sats.unwrap_or(ctx.config.max_single_payout_sats);
+        (ctx.request.amount_sats > limit).then(|| {
+            self.severity.finding(
+                self.id(),
+                format!(
+                    "Requested amount {} sats exceeds AILEE Trust Layer max limit of {} sats",
+                    ctx.request.amount_sats, limit
+                ),
+            )
+        })
+    }
+}
+
+/// Flags unparseable recipients (with `invalid`) and recipients for another
+/// network (with `mismatch`). Both block in the standard policy; the
+/// engine refuses such recipients regardless.
+pub struct AddressNetworkRule {
+    pub invalid: RuleSeverity,
+    pub mismatch: RuleSeverity,
+}
+
+impl TrustRule for AddressNetworkRule {
+    fn id(&self) -> &str {
+        "address_network"
+    }
+
+    fn evaluate(&self, ctx: &TrustContext<'_>) -> Option<TrustFinding> {
+        let err = parse_address(
+            &ctx.request.recipient_address,
+            ctx.config.network,
+            AddressRole::Recipient,
+        )
+        .err()?;
+        let severity = match err {
+            AddressError::Malformed { .. } => &self.invalid,
+            AddressError::NetworkMismatch { .. } | AddressError::PoolKeyNetworkMismatch { .. } => {
+                &self.mismatch
+            }
+        };
+        Some(severity.finding(self.id(), err.to_string()))
+    }
+}
+
+pub struct DustRule {
+    pub min_sats: u64,
+    pub severity: RuleSeverity,
+}
+
+impl TrustRule for DustRule {
+    fn id(&self) -> &str {
+        "dust"
+    }
+
+    fn evaluate(&self, ctx: &TrustContext<'_>) -> Option<TrustFinding> {
+        (ctx.request.amount_sats < self.min_sats).then(|| {
+            self.severity.finding(
+                self.id(),
+                format!("Payout amount below dust limit ({} sats)", self.min_sats),
+            )
+        })
+    }
+}
+
+/// Recipient list membership, compared by output script so address
+/// encodings do not matter.
+pub struct RecipientListRule {
+    id: &'static str,
+    scripts: Vec<ScriptBuf>,
+    /// Deny lists fire on membership, allow lists on its absence.
+    deny: bool,
+    pub severity: RuleSeverity,
+}
+
+impl RecipientListRule {
+    pub fn allow(addresses: &[String], severity: RuleSeverity) -> Result<Self, String> {
+        Ok(Self {
+            id: "allow_list",
+            scripts: parse_scripts(addresses)?,
+            deny: false,
+            severity,
+        })
+    }
+
+    pub fn deny(addresses: &[String], severity: RuleSeverity) -> Result<Self, String> {
+        Ok(Self {
+            id: "deny_list",
+            scripts: parse_scripts(addresses)?,
+            deny: true,
+            severity,
+        })
+    }
+}
+
+impl TrustRule for RecipientListRule {
+    fn id(&self) -> &str {
+        self.id
+    }
+
+    fn evaluate(&self, ctx: &TrustContext<'_>) -> Option<TrustFinding> {
+        let script = ctx.recipient_script()?;
+        let listed = self.scripts.contains(&script);
+        let message = if self.deny && listed {
+            "Recipient is on the deny list"
+        } else if !self.deny && !listed {
+            "Recipient is not on the allow list"
+        } else {
+            return None;
+        };
+        Some(self.severity.finding(self.id(), message.to_string()))
+    }
+}
+
+fn parse_scripts(addresses: &[String]) -> Result<Vec<ScriptBuf>, String> {
+    addresses
+        .iter()
+        .map(|a| {
+            Address::from_str(a)
+                .map(|a| a.assume_checked().script_pubkey())
+                .map_err(|e| format!("Invalid address {a} in recipient list: {e}"))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecipientScriptType {
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+}
+
+impl RecipientScriptType {
+    pub fn of(script: &ScriptBuf) -> Option<Self> {
+        if script.is_p2pkh() {
+            Some(Self::P2pkh)
+        } else if script.is_p2sh() {
+            Some(Self::P2sh)
+        } else if script.is_v0_p2wpkh() {
+            Some(Self::P2wpkh)
+        } else if script.is_v0_p2wsh() {
+            Some(Self::P2wsh)
+        } else if script.is_v1_p2tr() {
+            Some(Self::P2tr)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct ScriptTypeRule {
+    pub allowed: Vec<RecipientScriptType>,
+    pub severity: RuleSeverity,
+}
+
+impl TrustRule for ScriptTypeRule {
+    fn id(&self) -> &str {
+        "script_types"
+    }
+
+    fn evaluate(&self, ctx: &TrustContext<'_>) -> Option<TrustFinding> {
+        let script = ctx.recipient_script()?;
+        match RecipientScriptType::of(&script) {
+            Some(kind) if self.allowed.contains(&kind) => None,
+            kind => Some(self.severity.finding(
+                self.id(),
+                format!("Recipient script type {kind:?} is not permitted"),
+            )),
+        }
+    }
+}
+
+/// Flags recipients first seen less than `period` ago. Without a recipient
+/// history every recipient counts as new.
+pub struct NewRecipientCoolingRule {
+    pub period: Duration,
+    pub severity: RuleSeverity,
+}
+
+impl TrustRule for NewRecipientCoolingRule {
+    fn id(&self) -> &str {
+        "new_recipient_cooling"
+    }
+
+    fn evaluate(&self, ctx: &TrustContext<'_>) -> Option<TrustFinding> {
+        let first_seen = ctx
+            .payout_history
+            .and_then(|h| h.first_seen(&ctx.request.recipient_address).ok())
+            .flatten();
+        let message = match first_seen {
+            None => "Recipient has no history with the pool".to_string(),
+            Some(seen) if ctx.now - seen < self.period => format!(
+                "Recipient first seen at {} is within the {}h cooling period",
+                seen.to_rfc3339(),
+                self.period.num_hours()
+            ),
+            Some(_) => return None,
+        };
+        Some(self.severity.finding(self.id(), message))
+    }
+}
+
+/// Ordered rule set with a version recorded in every audit.
+pub struct TrustPolicy {
+    version: String,
+    base_risk: f64,
+    rules: Vec<Box<dyn TrustRule>>,
+}
+
+impl std::fmt::Debug for TrustPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrustPolicy")
+            .field("version", &self.version)
+            .field("base_risk", &self.base_risk)
+            .field("rules", &self.rule_ids())
+            .finish()
+    }
+}
+
+impl Default for TrustPolicy {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl TrustPolicy {
+    pub fn new(version: impl Into<String>) -> Self {
+        Self {
+            version: version.into(),
+            base_risk: 0.05,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Amount limit, address network and dust checks, plus hourly and daily
+    /// velocity limits per recipient and overall, and at most 25% of P̂ per
+    /// day.
+    pub fn standard() -> Self {
+        Self::new(STANDARD_POLICY_VERSION)
+            .with_rule(AmountLimitRule {
+                max_sats: None,
+                severity: RuleSeverity::blocking(0.95),
+            })
+            .with_rule(AddressNetworkRule {
+                invalid: RuleSeverity::blocking(1.0),
+                mismatch: RuleSeverity::blocking(1.0),
+            })
+            .with_rule(DustRule {
+                min_sats: DUST_LIMIT_SATS,
+                severity: RuleSeverity::blocking(0.85),
+            })
+            .with_rule(
+                VelocityLimitRule::new(
+                    VelocityScope::Recipient,
+                    Duration::hours(1),
+                    RuleSeverity::blocking(0.9),
+                )
+                .with_max_sats(500_000_000)
+                .with_max_count(10),
+            )
+            .with_rule(
+                VelocityLimitRule::new(
+                    VelocityScope::Recipient,
+                    Duration::days(1),
+                    RuleSeverity::blocking(0.9),
+                )
+                .with_max_sats(1_000_000_000),
+            )
+            .with_rule(
+                VelocityLimitRule::new(
+                    VelocityScope::Global,
+                    Duration::hours(1),
+                    RuleSeverity::blocking(0.95),
+                )
+                .with_max_sats(2_000_000_000)
+                .with_max_count(100),
+            )
+            .with_rule(
+                VelocityLimitRule::new(
+                    VelocityScope::Global,
+                    Duration::days(1),
+                    RuleSeverity::blocking(0.95),
+                )
+                .with_max_sats(5_000_000_000),
+            )
+            .with_rule(PoolShareRule::new(
+                Duration::days(1),
+                25.0,
+                RuleSeverity::blocking(0.95),
+            ))
+    }
+
+    pub fn with_base_risk(mut self, base_risk: f64) -> Self {
+        self.base_risk = base_risk.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_rule(mut self, rule: impl TrustRule + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn rule_ids(&self) -> Vec<&str> {
+        self.rules.iter().map(|r| r.id()).collect()
+    }
+
+    /// Runs every rule in order.
+    pub fn evaluate(&self, ctx: &TrustContext<'_>) -> AileeTrustAudit {
+        let findings: Vec<TrustFinding> = self
+            .rules
+            .iter()
+            .filter_map(|rule| rule.evaluate(ctx))
+            .collect();
+        let clear = findings
+            .iter()
+            .fold(1.0 - self.base_risk, |acc, f| acc * (1.0 - f.score));
+
+        AileeTrustAudit {
+            passed: !findings.iter().any(|f| f.blocking),
+            risk_score: 1.0 - clear,
+            policy_version: self.version.clone(),
+            warnings: findings.iter().map(|f| f.message.clone()).collect(),
+            max_payout_sats_limit: ctx.config.max_single_payout_sats,
+            findings,
+        }
+    }
+
+    pub fn from_config(config: &TrustPolicyConfig) -> Result<Self, String> {
+        let mut policy = Self::new(&config.version).with_base_risk(config.base_risk);
+        for rule in &config.rules {
+            let severity = |score: f64, blocking: bool| RuleSeverity {
+                score: rule.score.unwrap_or(score),
+                blocking: rule.blocking.unwrap_or(blocking),
+            };
+            policy = match &rule.kind {
+                TrustRuleKind::AmountLimit { max_sats } => policy.with_rule(AmountLimitRule {
+                    max_sats: *max_sats,
+                    severity: severity(0.95, true),
+                }),
+                TrustRuleKind::AddressNetwork => policy.with_rule(AddressNetworkRule {
+                    invalid: RuleSeverity::blocking(1.0),
+                    mismatch: severity(1.0, true),
+                }),
+                TrustRuleKind::Dust { min_sats } => policy.with_rule(DustRule {
+                    min_sats: min_sats.unwrap_or(DUST_LIMIT_SATS),
+                    severity: severity(0.85, true),
+                }),
+                TrustRuleKind::AllowList { addresses } => {
+                    policy.with_rule(RecipientListRule::allow(addresses, severity(0.9, true))?)
+                }
+                TrustRuleKind::DenyList { addresses } => {
+                    policy.with_rule(RecipientListRule::deny(addresses, severity(1.0, true))?)
+                }
+                TrustRuleKind::ScriptTypes { allowed } => policy.with_rule(ScriptTypeRule {
+                    allowed: allowed.clone(),
+                    severity: severity(0.7, true),
+                }),
+                TrustRuleKind::NewRecipientCooling { period_secs } => {
+                    policy.with_rule(NewRecipientCoolingRule {
+                        period: Duration::seconds(*period_secs as i64),
+                        severity: severity(0.5, false),
+                    })
+                }
+                TrustRuleKind::VelocityLimit {
+                    scope,
+                    window_secs,
+                    max_sats,
+                    max_count,
+                } => {
+                    let mut rule = VelocityLimitRule::new(
+                        *scope,
+                        Duration::seconds(*window_secs as i64),
+                        severity(0.9, true),
+                    );
+                    rule.max_sats = *max_sats;
+                    rule.max_count = *max_count;
+                    policy.with_rule(rule)
+                }
+                TrustRuleKind::PoolShare {
+                    window_secs,
+                    max_percent,
+                } => policy.with_rule(PoolShareRule::new(
+                    Duration::seconds(*window_secs as i64),
+                    *max_percent,
+                    severity(0.95, true),
+                )),
+            };
+        }
+        Ok(policy)
+    }
+}
+
+/// Serialized form of a [`TrustPolicy`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustPolicyConfig {
+    pub version: String,
+    #[serde(default = "default_base_risk")]
+    pub base_risk: f64,
+    pub rules: Vec<TrustRuleConfig>,
+}
+
+fn default_base_risk() -> f64 {
+    0.05
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustRuleConfig {
+    #[serde(flatten)]
+    pub kind: TrustRuleKind,
+    /// Overrides the rule's default score.
+    #[serde(default)]
+    pub score: Option<f64>,
+    /// Overrides whether the rule blocks the payout.
+    #[serde(default)]
+    pub blocking: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum TrustRuleKind {
+    AmountLimit {
+        #[serde(default)]
+        max_sats: Option<u64>,
+    },
+    AddressNetwork,
+    Dust {
+        #[serde(default)]
+        min_sats: Option<u64>,
+    },
+    AllowList {
+        addresses: Vec<String>,
+    },
+    DenyList {
+        addresses: Vec<String>,
+    },
+    ScriptTypes {
+        allowed: Vec<RecipientScriptType>,
+    },
+    NewRecipientCooling {
+        period_secs: u64,
+    },
+    VelocityLimit {
+        scope: VelocityScope,
+        window_secs: u64,
+        #[serde(default)]
+        max_sats: Option<u64>,
+        #[serde(default)]
+        max_count: Option<u64>,
+    },
+    PoolShare {
+        window_secs: u64,
+        max_percent: f64,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(recipient_address: &str, amount_sats: u64) -> PayoutRequest {
+        PayoutRequest {
+            recipient_address: recipient_address.to_string(),
+            amount_sats,
+            funding_utxo_txid: None,
+            funding_utxo_vout: None,
+            funding_utxo_value_sats: None,
+            change_address: None,
+            fee_rate_sats_per_vbyte: None,
+            dry_run: None,
+        }
+    }
+
+    #[test]
+    fn findings_accumulate_in_rule_order() {
+        let config = DisbursementConfig::default();
+        let req = request("bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh", 100);
+        let audit = TrustPolicy::standard().evaluate(&TrustContext {
+            request: &req,
+            config: &config,
+            payout_history: None,
+            pool_balance_sats: None,
+            batch_sats: 0,
+            batch_count: 0,
+            now: Utc::now(),
+        });
+        assert!(!audit.passed);
+        assert_eq!(audit.policy_version, STANDARD_POLICY_VERSION);
+        assert_eq!(audit.findings.len(), 1);
+        assert!((audit.risk_score - (1.0 - 0.95 * 0.15)).abs() < 1e-9);
+
+        let req = request("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", 700_000_000);
+        let audit = TrustPolicy::standard().evaluate(&TrustContext {
+            request: &req,
+            config: &config,
+            payout_history: None,
+            pool_balance_sats: None,
+            batch_sats: 0,
+            batch_count: 0,
+            now: Utc::now(),
+        });
+        let fired: Vec<&str> = audit.findings.iter().map(|f| f.rule_id.as_str()).collect();
+        assert_eq!(fired, vec!["amount_limit", "address_network"]);
+        assert!(audit.findings[1].blocking);
+        assert_eq!(audit.risk_score, 1.0);
+    }
+
+    #[test]
+    fn loads_rule_set_from_json() {
+        let config: TrustPolicyConfig = serde_json::from_str(
+            r#"{
+                "version": "pool-policy-7",
+                "rules": [
+                    { "rule": "dust", "min_sats": 1000 },
+                    { "rule": "script_types", "allowed": ["p2tr"], "blocking": false, "score": 0.2 }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let policy = TrustPolicy::from_config(&config).unwrap();
+        assert_eq!(policy.version(), "pool-policy-7");
+        assert_eq!(policy.rule_ids(), vec!["dust", "script_types"]);
+
+        let engine_config = DisbursementConfig::default();
+        let req = request("bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh", 5_000);
+        let audit = policy.evaluate(&TrustContext {
+            request: &req,
+            config: &engine_config,
+            payout_history: None,
+            pool_balance_sats: None,
+            batch_sats: 0,
+            batch_count: 0,
+            now: Utc::now(),
+        });
+        assert!(audit.passed);
+        assert_eq!(audit.findings[0].rule_id, "script_types");
+        assert_eq!(audit.findings[0].score, 0.2);
+    }
+}
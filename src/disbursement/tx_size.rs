@@ -0,0 +1,169 @@
+//! Transaction weight estimation from input spend paths and output scripts.
+//!
+//! Signatures are sized at their maximum standard encoding (72 bytes for
+//! DER ECDSA plus sighash byte, 64 bytes for BIP340 with `SIGHASH_DEFAULT`),
+//! so estimates never undershoot the signed transaction.
+
+use super::multisig::parse_multisig;
+use bitcoin::{Script, VarInt};
+
+const ECDSA_SIG_LEN: u64 = 72;
+const SCHNORR_SIG_LEN: u64 = 64;
+const COMPRESSED_PUBKEY_LEN: u64 = 33;
+
+/// outpoint (36) + sequence (4)
+const INPUT_BASE_LEN: u64 = 40;
+
+/// Spend path of an input, which determines its size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputScriptType {
+    P2wpkh,
+    /// Taproot key-path spend.
+    P2tr,
+    /// P2WSH stake lock (`<height> OP_CHECKLOCKTIMEVERIFY OP_DROP <pubkey>
+    /// OP_CHECKSIG`) spent with a single signature.
+    P2wshCltvStake {
+        witness_script_len: usize,
+    },
+    /// P2WSH k-of-n `OP_CHECKMULTISIG` pool output.
+    P2wshMultisig {
+        threshold: usize,
+        witness_script_len: usize,
+    },
+    P2pkh,
+}
+
+impl InputScriptType {
+    /// Classifies a spendable output. P2WSH requires the witness script.
+    pub fn classify(script_pubkey: &Script, witness_script: Option<&Script>) -> Option<Self> {
+        if script_pubkey.is_v0_p2wpkh() {
+            Some(InputScriptType::P2wpkh)
+        } else if script_pubkey.is_v1_p2tr() {
+            Some(InputScriptType::P2tr)
+        } else if script_pubkey.is_p2pkh() {
+            Some(InputScriptType::P2pkh)
+        } else if script_pubkey.is_v0_p2wsh() {
+            witness_script.map(|ws| match parse_multisig(ws) {
+                Some((threshold, _)) => InputScriptType::P2wshMultisig {
+                    threshold,
+                    witness_script_len: ws.len(),
+                },
+                None => InputScriptType::P2wshCltvStake {
+                    witness_script_len: ws.len(),
+                },
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn is_segwit(&self) -> bool {
+        !matches!(self, InputScriptType::P2pkh)
+    }
+
+    /// Weight of the input excluding the per-input witness count byte that
+    /// non-witness inputs carry inside a segwit transaction.
+    pub fn weight(&self) -> u64 {
+        let (script_sig_len, witness_len) = match *self {
+            InputScriptType::P2wpkh => (0, witness_len(&[ECDSA_SIG_LEN, COMPRESSED_PUBKEY_LEN])),
+            InputScriptType::P2tr => (0, witness_len(&[SCHNORR_SIG_LEN])),
+            InputScriptType::P2wshCltvStake { witness_script_len } => {
+                (0, witness_len(&[ECDSA_SIG_LEN, witness_script_len as u64]))
+            }
+            // Empty dummy element for the CHECKMULTISIG off-by-one, then
+            // `threshold` signatures and the script.
+            InputScriptType::P2wshMultisig {
+                threshold,
+                witness_script_len,
+            } => {
+                let mut items = vec![0];
+                items.extend(std::iter::repeat_n(ECDSA_SIG_LEN, threshold));
+                items.push(witness_script_len as u64);
+                (0, witness_len(&items))
+            }
+            // <sig> <pubkey>, each with a one-byte push opcode.
+            InputScriptType::P2pkh => (1 + ECDSA_SIG_LEN + 1 + COMPRESSED_PUBKEY_LEN, 0),
+        };
+        let non_witness = INPUT_BASE_LEN + varint_len(script_sig_len) + script_sig_len;
+        non_witness * 4 + witness_len
+    }
+
+    /// Standalone vsize, rounded up; used for per-input effective values.
+    pub fn vsize(&self) -> u64 {
+        self.weight().div_ceil(4)
+    }
+}
+
+/// Weight of a serialized output with the given script.
+pub fn output_weight(script_pubkey: &Script) -> u64 {
+    let len = script_pubkey.len() as u64;
+    (8 + varint_len(len) + len) * 4
+}
+
+/// Weight of a transaction with the given inputs and output scripts.
+pub fn estimate_weight(inputs: &[InputScriptType], outputs: &[&Script]) -> u64 {
+    let segwit = inputs.iter().any(InputScriptType::is_segwit);
+    // version + locktime + input/output counts
+    let mut weight =
+        (4 + 4 + varint_len(inputs.len() as u64) + varint_len(outputs.len() as u64)) * 4;
+    if segwit {
+        // marker + flag
+        weight += 2;
+    }
+    for input in inputs {
+        weight += input.weight();
+        if segwit && !input.is_segwit() {
+            // empty witness stack
+            weight += 1;
+        }
+    }
+    weight + outputs.iter().map(|s| output_weight(s)).sum::<u64>()
+}
+
+pub fn estimate_vsize(inputs: &[InputScriptType], outputs: &[&Script]) -> u64 {
+    estimate_weight(inputs, outputs).div_ceil(4)
+}
+
+/// Witness serialization: item count followed by length-prefixed items.
+fn witness_len(items: &[u64]) -> u64 {
+    varint_len(items.len() as u64) + items.iter().map(|len| varint_len(*len) + len).sum::<u64>()
+}
+
+fn varint_len(n: u64) -> u64 {
+    VarInt(n).len() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::ScriptBuf;
+
+    #[test]
+    fn input_sizes_match_reference_values() {
+        assert_eq!(InputScriptType::P2wpkh.weight(), 272);
+        assert_eq!(InputScriptType::P2tr.weight(), 230);
+        assert_eq!(InputScriptType::P2pkh.weight(), 592);
+        // 1 + (1 + 72) + (1 + 42) witness bytes for a 42-byte stake script.
+        assert_eq!(
+            InputScriptType::P2wshCltvStake {
+                witness_script_len: 42
+            }
+            .weight(),
+            164 + 117
+        );
+    }
+
+    #[test]
+    fn one_in_two_out_p2wpkh_is_141_vbytes() {
+        let p2wpkh = ScriptBuf::from_bytes(vec![0u8; 22]);
+        let p2tr = ScriptBuf::from_bytes(vec![0u8; 34]);
+        assert_eq!(
+            estimate_vsize(&[InputScriptType::P2wpkh], &[&p2wpkh, &p2wpkh]),
+            141
+        );
+        assert_eq!(
+            estimate_weight(&[InputScriptType::P2wpkh, InputScriptType::P2pkh], &[&p2tr]),
+            (4 + 4 + 1 + 1) * 4 + 2 + 272 + 592 + 1 + 43 * 4
+        );
+    }
+}
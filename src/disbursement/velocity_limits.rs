@@ -0,0 +1,311 @@
+//! Rolling-window payout velocity limits.
+//!
+//! `max_single_payout_sats` bounds one payout; these rules bound what leaves
+//! the pool over time. Each sums the payouts in the [`PayoutHistory`] created
+//! within its window, adds the requested payout and compares the total
+//! against sats, count or pool-share (P̂) caps.
+
+use super::trust_policy::{PayoutHistory, RuleSeverity, TrustContext, TrustFinding, TrustRule};
+use super::{PayoutStatus, PayoutTransactionResult};
+use crate::velocity_analyzer::VelocityError;
+use bitcoin::{Address, ScriptBuf, TxOut};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Payouts within a window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PayoutVolume {
+    pub count: u64,
+    pub sats: u64,
+}
+
+/// Whether a payout counts toward velocity limits. Dry runs and payouts
+/// that were rejected or failed moved no funds, and fee bumps re-spend a
+/// payout that is already counted.
+pub fn counts_toward_velocity(payout: &PayoutTransactionResult) -> bool {
+    !payout.is_dry_run
+        && payout.fee_bump_of.is_none()
+        && !matches!(
+            payout.status,
+            PayoutStatus::Pending | PayoutStatus::Rejected | PayoutStatus::Failed
+        )
+}
+
+/// Outputs a payout pays its recipients: the leading outputs of its
+/// transaction adding up to `amount_sats`, since change always comes last.
+/// Batch transactions have one per recipient. Without a decodable
+/// transaction, e.g. for a rejected payout, the requested recipient and
+/// amount.
+pub fn recipient_outputs(payout: &PayoutTransactionResult) -> Vec<TxOut> {
+    let tx = hex::decode(&payout.raw_tx_hex)
+        .ok()
+        .and_then(|bytes| bitcoin::consensus::deserialize::<bitcoin::Transaction>(&bytes).ok());
+    if let Some(tx) = tx {
+        let mut paid = 0u64;
+        let outputs: Vec<TxOut> = tx
+            .output
+            .into_iter()
+            .take_while(|o| {
+                let take = paid < payout.amount_sats;
+                paid = paid.saturating_add(o.value);
+                take
+            })
+            .collect();
+        if paid == payout.amount_sats {
+            return outputs;
+        }
+    }
+    recipient_script(&payout.recipient_address)
+        .map(|script_pubkey| {
+            vec![TxOut {
+                value: payout.amount_sats,
+                script_pubkey,
+            }]
+        })
+        .unwrap_or_default()
+}
+
+/// Script an address pays, whatever its case or network.
+fn recipient_script(address: &str) -> Option<ScriptBuf> {
+    address
+        .trim()
+        .parse::<Address<_>>()
+        .ok()
+        .map(|a| a.assume_checked().script_pubkey())
+}
+
+/// In-memory payout store, e.g. the API node's cache.
+impl PayoutHistory for RwLock<HashMap<String, PayoutTransactionResult>> {
+    fn first_seen(&self, address: &str) -> Result<Option<DateTime<Utc>>, VelocityError> {
+        let payouts = self
+            .read()
+            .map_err(|_| VelocityError::DataSource("payout store lock poisoned".into()))?;
+        let Some(script) = recipient_script(address) else {
+            return Ok(None);
+        };
+        Ok(payouts
+            .values()
+            .filter(|p| !p.is_dry_run && p.batch_id.is_none())
+            .filter(|p| {
+                recipient_outputs(p)
+                    .iter()
+                    .any(|o| o.script_pubkey == script)
+            })
+            .filter(|p| {
+                matches!(
+                    p.status,
+                    PayoutStatus::Broadcast
+                        | PayoutStatus::Confirmed
+                        | PayoutStatus::Completed
+                        | PayoutStatus::Replaced
+                )
+            })
+            .filter_map(created_at)
+            .min())
+    }
+
+    fn volume_since(
+        &self,
+        recipient: Option<&str>,
+        since: DateTime<Utc>,
+    ) -> Result<PayoutVolume, VelocityError> {
+        let payouts = self
+            .read()
+            .map_err(|_| VelocityError::DataSource("payout store lock poisoned".into()))?;
+        let script = recipient.map(recipient_script);
+        Ok(payouts
+            .values()
+            .filter(|p| counts_toward_velocity(p))
+            // Unreadable timestamps count as recent.
+            .filter(|p| created_at(p).is_none_or(|t| t >= since))
+            .filter_map(|p| match script {
+                None => Some(p.amount_sats),
+                // Every output to the recipient, batch outputs included
+                Some(ref script) => {
+                    let paid = recipient_outputs(p)
+                        .iter()
+                        .filter(|o| script.as_ref() == Some(&o.script_pubkey))
+                        .map(|o| o.value)
+                        .collect::<Vec<_>>();
+                    (!paid.is_empty()).then(|| paid.iter().sum())
+                }
+            })
+            .fold(PayoutVolume::default(), |v, sats| PayoutVolume {
+                count: v.count + 1,
+                sats: v.sats + sats,
+            }))
+    }
+}
+
+fn created_at(payout: &PayoutTransactionResult) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(&payout.timestamp)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// `24h`, `7d`, ... for rule ids and messages.
+fn window_label(window: Duration) -> String {
+    let secs = window.num_seconds();
+    match secs {
+        s if s > 0 && s % 86_400 == 0 => format!("{}d", s / 86_400),
+        s if s > 0 && s % 3_600 == 0 => format!("{}h", s / 3_600),
+        s if s > 0 && s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{s}s"),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VelocityScope {
+    /// Payouts to the requested recipient.
+    Recipient,
+    /// All payouts from the pool.
+    Global,
+}
+
+/// Caps the sats and/or number of payouts within a rolling window.
+/// Inactive without a payout history.
+pub struct VelocityLimitRule {
+    id: String,
+    scope: VelocityScope,
+    window: Duration,
+    pub max_sats: Option<u64>,
+    pub max_count: Option<u64>,
+    pub severity: RuleSeverity,
+}
+
+impl VelocityLimitRule {
+    pub fn new(scope: VelocityScope, window: Duration, severity: RuleSeverity) -> Self {
+        let scope_name = match scope {
+            VelocityScope::Recipient => "recipient",
+            VelocityScope::Global => "global",
+        };
+        Self {
+            id: format!("{scope_name}_velocity_{}", window_label(window)),
+            scope,
+            window,
+            max_sats: None,
+            max_count: None,
+            severity,
+        }
+    }
+
+    pub fn with_max_sats(mut self, max_sats: u64) -> Self {
+        self.max_sats = Some(max_sats);
+        self
+    }
+
+    pub fn with_max_count(mut self, max_count: u64) -> Self {
+        self.max_count = Some(max_count);
+        self
+    }
+}
+
+impl TrustRule for VelocityLimitRule {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn evaluate(&self, ctx: &TrustContext<'_>) -> Option<TrustFinding> {
+        let history = ctx.payout_history?;
+        let (recipient, who) = match self.scope {
+            VelocityScope::Recipient => (
+                Some(ctx.request.recipient_address.as_str()),
+                "this recipient",
+            ),
+            VelocityScope::Global => (None, "all recipients"),
+        };
+        let label = window_label(self.window);
+        let volume = match history.volume_since(recipient, ctx.now - self.window) {
+            Ok(volume) => volume,
+            Err(e) => {
+                return Some(
+                    self.severity
+                        .finding(&self.id, format!("Payout history unavailable: {e}")),
+                )
+            }
+        };
+
+        let (batch_sats, batch_count) = match self.scope {
+            VelocityScope::Recipient => (0, 0),
+            VelocityScope::Global => (ctx.batch_sats, ctx.batch_count),
+        };
+        let mut breaches = Vec::new();
+        let sats = volume
+            .sats
+            .saturating_add(batch_sats)
+            .saturating_add(ctx.request.amount_sats);
+        if let Some(max) = self.max_sats.filter(|max| sats > *max) {
+            breaches.push(format!(
+                "{sats} sats to {who} within {label} exceeds the {max} sats limit"
+            ));
+        }
+        let count = volume.count.saturating_add(batch_count) + 1;
+        if let Some(max) = self.max_count.filter(|max| count > *max) {
+            breaches.push(format!(
+                "{count} payouts to {who} within {label} exceeds the limit of {max}"
+            ));
+        }
+        (!breaches.is_empty()).then(|| self.severity.finding(&self.id, breaches.join("; ")))
+    }
+}
+
+/// Caps the sats paid within a rolling window to a percentage of the
+/// current pool balance P̂. Inactive while P̂ is unknown.
+pub struct PoolShareRule {
+    id: String,
+    window: Duration,
+    pub max_percent: f64,
+    pub severity: RuleSeverity,
+}
+
+impl PoolShareRule {
+    pub fn new(window: Duration, max_percent: f64, severity: RuleSeverity) -> Self {
+        Self {
+            id: format!("pool_share_{}", window_label(window)),
+            window,
+            max_percent,
+            severity,
+        }
+    }
+}
+
+impl TrustRule for PoolShareRule {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn evaluate(&self, ctx: &TrustContext<'_>) -> Option<TrustFinding> {
+        let pool = ctx.pool_balance_sats?;
+        let paid = match ctx
+            .payout_history
+            .map(|h| h.volume_since(None, ctx.now - self.window))
+            .transpose()
+        {
+            Ok(volume) => volume.unwrap_or_default().sats,
+            Err(e) => {
+                return Some(
+                    self.severity
+                        .finding(&self.id, format!("Payout history unavailable: {e}")),
+                )
+            }
+        };
+
+        let total = paid
+            .saturating_add(ctx.batch_sats)
+            .saturating_add(ctx.request.amount_sats);
+        let cap = (pool as f64 * self.max_percent / 100.0) as u64;
+        (total > cap).then(|| {
+            self.severity.finding(
+                &self.id,
+                format!(
+                    "{total} sats paid within {} exceeds {}% of the pool balance ({cap} of {pool} sats)",
+                    window_label(self.window),
+                    self.max_percent
+                ),
+            )
+        })
+    }
+}
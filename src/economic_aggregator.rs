@@ -0,0 +1,323 @@
+use crate::economic_oracle::{EconomicDataProvider, EconomicError, EconomicObservation};
+use serde::Serialize;
+
+/// How accepted source readings are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregationMethod {
+    Median,
+    /// Drops `trim_fraction` of the readings from each end before averaging.
+    TrimmedMean {
+        trim_fraction: f64,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct AggregationConfig {
+    pub method: AggregationMethod,
+
+    /// Minimum number of sources that must respond and survive outlier
+    /// rejection.
+    pub quorum: usize,
+
+    /// Maximum absolute distance of a source's D_s from the cross-source median.
+    pub max_demand_shock_deviation: f64,
+
+    /// Maximum absolute distance of a source's A from the cross-source median.
+    pub max_productivity_deviation: f64,
+}
+
+impl Default for AggregationConfig {
+    fn default() -> Self {
+        Self {
+            method: AggregationMethod::Median,
+            quorum: 2,
+            max_demand_shock_deviation: 0.01,
+            max_productivity_deviation: 0.05,
+        }
+    }
+}
+
+impl AggregationConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.quorum == 0 {
+            return Err("quorum must be > 0".into());
+        }
+        if let AggregationMethod::TrimmedMean { trim_fraction } = self.method {
+            if !(0.0..0.5).contains(&trim_fraction) {
+                return Err("trim_fraction must be in [0, 0.5)".into());
+            }
+        }
+        if !self.max_demand_shock_deviation.is_finite() || self.max_demand_shock_deviation < 0.0 {
+            return Err("max_demand_shock_deviation must be finite and >= 0".into());
+        }
+        if !self.max_productivity_deviation.is_finite() || self.max_productivity_deviation < 0.0 {
+            return Err("max_productivity_deviation must be finite and >= 0".into());
+        }
+        Ok(())
+    }
+}
+
+/// Why a source did not contribute to the aggregate.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum SourceRejection {
+    Error {
+        message: String,
+    },
+    InvalidValue {
+        message: String,
+    },
+    Outlier {
+        demand_shock_deviation: f64,
+        productivity_deviation: f64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RejectedSource {
+    pub name: String,
+    pub reason: SourceRejection,
+}
+
+/// Provenance of an aggregated reading; attached to `RBISnapshot`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OracleAggregationReport {
+    pub method: AggregationMethod,
+    pub quorum: usize,
+    pub used_sources: Vec<String>,
+    pub rejected_sources: Vec<RejectedSource>,
+    pub demand_shock: f64,
+    pub productivity: f64,
+}
+
+/// Provider that queries several named sources and combines their readings.
+pub struct AggregatingEconomicProvider {
+    config: AggregationConfig,
+    sources: Vec<(String, Box<dyn EconomicDataProvider>)>,
+}
+
+impl AggregatingEconomicProvider {
+    pub fn new(config: AggregationConfig) -> Result<Self, EconomicError> {
+        config.validate().map_err(EconomicError::InvalidData)?;
+        Ok(Self {
+            config,
+            sources: Vec::new(),
+        })
+    }
+
+    pub fn with_source<P: EconomicDataProvider + 'static>(
+        mut self,
+        name: impl Into<String>,
+        provider: P,
+    ) -> Self {
+        self.sources.push((name.into(), Box::new(provider)));
+        self
+    }
+
+    pub fn config(&self) -> &AggregationConfig {
+        &self.config
+    }
+
+    fn aggregate(&self) -> Result<(f64, f64, OracleAggregationReport), EconomicError> {
+        let mut rejected = Vec::new();
+        let mut readings = Vec::new();
+
+        for (name, source) in &self.sources {
+            match source.observe() {
+                Ok(obs) => {
+                    if !obs.demand_shock.is_finite() || obs.demand_shock < 0.0 {
+                        rejected.push(RejectedSource {
+                            name: name.clone(),
+                            reason: SourceRejection::InvalidValue {
+                                message: "demand_shock_rate must be finite and >= 0".into(),
+                            },
+                        });
+                    } else if !obs.productivity.is_finite() {
+                        rejected.push(RejectedSource {
+                            name: name.clone(),
+                            reason: SourceRejection::InvalidValue {
+                                message: "productivity_expansion must be finite".into(),
+                            },
+                        });
+                    } else {
+                        readings.push((name.clone(), obs.demand_shock, obs.productivity));
+                    }
+                }
+                Err(err) => rejected.push(RejectedSource {
+                    name: name.clone(),
+                    reason: SourceRejection::Error {
+                        message: err.to_string(),
+                    },
+                }),
+            }
+        }
+
+        if readings.len() < self.config.quorum {
+            return Err(EconomicError::Provider(format!(
+                "oracle quorum not met: {} of {} sources responded, {} required",
+                readings.len(),
+                self.sources.len(),
+                self.config.quorum
+            )));
+        }
+
+        let median_ds = median(readings.iter().map(|r| r.1).collect());
+        let median_a = median(readings.iter().map(|r| r.2).collect());
+
+        let mut accepted = Vec::new();
+        for (name, ds, a) in readings {
+            let ds_dev = (ds - median_ds).abs();
+            let a_dev = (a - median_a).abs();
+            if ds_dev > self.config.max_demand_shock_deviation
+                || a_dev > self.config.max_productivity_deviation
+            {
+                rejected.push(RejectedSource {
+                    name,
+                    reason: SourceRejection::Outlier {
+                        demand_shock_deviation: ds_dev,
+                        productivity_deviation: a_dev,
+                    },
+                });
+            } else {
+                accepted.push((name, ds, a));
+            }
+        }
+
+        if accepted.len() < self.config.quorum {
+            return Err(EconomicError::Provider(format!(
+                "oracle quorum not met after outlier rejection: {} accepted, {} required",
+                accepted.len(),
+                self.config.quorum
+            )));
+        }
+
+        let ds_values: Vec<f64> = accepted.iter().map(|r| r.1).collect();
+        let a_values: Vec<f64> = accepted.iter().map(|r| r.2).collect();
+        let (demand_shock, productivity) = match self.config.method {
+            AggregationMethod::Median => (median(ds_values), median(a_values)),
+            AggregationMethod::TrimmedMean { trim_fraction } => (
+                trimmed_mean(ds_values, trim_fraction),
+                trimmed_mean(a_values, trim_fraction),
+            ),
+        };
+
+        let report = OracleAggregationReport {
+            method: self.config.method,
+            quorum: self.config.quorum,
+            used_sources: accepted.into_iter().map(|r| r.0).collect(),
+            rejected_sources: rejected,
+            demand_shock,
+            productivity,
+        };
+        Ok((demand_shock, productivity, report))
+    }
+}
+
+impl EconomicDataProvider for AggregatingEconomicProvider {
+    fn demand_shock_rate(&self) -> Result<f64, EconomicError> {
+        Ok(self.aggregate()?.0)
+    }
+
+    fn productivity_expansion(&self) -> Result<f64, EconomicError> {
+        Ok(self.aggregate()?.1)
+    }
+
+    fn observe(&self) -> Result<EconomicObservation, EconomicError> {
+        let (demand_shock, productivity, report) = self.aggregate()?;
+        Ok(EconomicObservation {
+            demand_shock,
+            productivity,
+            report: Some(report),
+        })
+    }
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+fn trimmed_mean(mut values: Vec<f64>, trim_fraction: f64) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let trim = (values.len() as f64 * trim_fraction).floor() as usize;
+    let kept = &values[trim..values.len() - trim];
+    kept.iter().sum::<f64>() / kept.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economic_oracle::MockEconomicDataProvider;
+    use crate::rbi_engine::{DistributionPoolState, ParticipantSnapshot, RBIEngine};
+
+    struct FailingProvider;
+
+    impl EconomicDataProvider for FailingProvider {
+        fn demand_shock_rate(&self) -> Result<f64, EconomicError> {
+            Err(EconomicError::Provider("feed offline".into()))
+        }
+
+        fn productivity_expansion(&self) -> Result<f64, EconomicError> {
+            Err(EconomicError::Provider("feed offline".into()))
+        }
+    }
+
+    fn mock(demand_shock: f64, productivity: f64) -> MockEconomicDataProvider {
+        MockEconomicDataProvider {
+            demand_shock,
+            productivity,
+        }
+    }
+
+    #[test]
+    fn median_rejects_outliers_and_reports_sources() {
+        let provider = AggregatingEconomicProvider::new(AggregationConfig::default())
+            .unwrap()
+            .with_source("fred", mock(0.020, 0.05))
+            .with_source("ecb", mock(0.022, 0.06))
+            .with_source("rogue", mock(0.500, 0.05))
+            .with_source("offline", FailingProvider);
+
+        let mut engine = RBIEngine::new(provider);
+        let state = DistributionPoolState {
+            total_distributed_sats: 1_000_000_000,
+            average_participant_velocity: 1.2,
+            epoch_duration_days: 1,
+            participants: vec![ParticipantSnapshot {
+                participant_id: "alice".into(),
+                stake_amount_sats: 100_000_000,
+                trust_coefficient: 1.3,
+            }],
+        };
+        let snapshot = engine.calculate_rbi(&state, 800_000).unwrap();
+        let report = snapshot.oracle_report.expect("aggregation report");
+
+        assert_eq!(report.used_sources, vec!["fred", "ecb"]);
+        assert!((snapshot.d_s - 0.021).abs() < 1e-12);
+        let rejected: Vec<&str> = report
+            .rejected_sources
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect();
+        assert_eq!(rejected, vec!["offline", "rogue"]);
+    }
+
+    #[test]
+    fn quorum_failure_is_an_error() {
+        let provider = AggregatingEconomicProvider::new(AggregationConfig {
+            quorum: 2,
+            ..Default::default()
+        })
+        .unwrap()
+        .with_source("fred", mock(0.02, 0.05))
+        .with_source("offline", FailingProvider);
+
+        assert!(provider.observe().is_err());
+    }
+}
@@ -1,10 +1,17 @@
-use std::sync::Mutex;
+use crate::economic_aggregator::OracleAggregationReport;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 #[derive(Debug)]
 pub enum EconomicError {
     Provider(String),
     InvalidData(String),
+    /// Requested point falls outside a recorded series.
+    OutOfRange {
+        requested: u64,
+        first: u64,
+        last: u64,
+    },
 }
 
 impl std::fmt::Display for EconomicError {
@@ -12,6 +19,14 @@ impl std::fmt::Display for EconomicError {
         match self {
             EconomicError::Provider(e) => write!(f, "provider error: {e}"),
             EconomicError::InvalidData(e) => write!(f, "invalid data: {e}"),
+            EconomicError::OutOfRange {
+                requested,
+                first,
+                last,
+            } => write!(
+                f,
+                "requested point {requested} outside series range [{first}, {last}]"
+            ),
         }
     }
 }
@@ -26,15 +41,94 @@ pub trait EconomicDataProvider: Send + Sync {
 
     /// AI productivity expansion factor A (unitless). Example: 0.05 = 5%.
     fn productivity_expansion(&self) -> Result<f64, EconomicError>;
+
+    /// Reads both inputs together, with provenance when the provider has any.
+    /// Providers whose two values must come from the same fetch override this.
+    fn observe(&self) -> Result<EconomicObservation, EconomicError> {
+        Ok(EconomicObservation {
+            demand_shock: self.demand_shock_rate()?,
+            productivity: self.productivity_expansion()?,
+            report: None,
+        })
+    }
+}
+
+/// A single reading of D_s and A as consumed by `RBIEngine`.
+#[derive(Debug, Clone)]
+pub struct EconomicObservation {
+    pub demand_shock: f64,
+    pub productivity: f64,
+    pub report: Option<OracleAggregationReport>,
+}
+
+/// Monotonic time source used by [`CachedProvider`]; injectable so expiry
+/// is deterministic in tests.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// Wall-clock backed [`Clock`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Clock that only moves when [`ManualClock::advance`] is called.
+#[derive(Debug)]
+pub struct ManualClock {
+    base: Instant,
+    offset: Mutex<Duration>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            base: Instant::now(),
+            offset: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        if let Ok(mut offset) = self.offset.lock() {
+            *offset += by;
+        }
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        let offset = self.offset.lock().map(|o| *o).unwrap_or_default();
+        self.base + offset
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CachedObservation {
+    observation: EconomicObservation,
+    fetched_at: Instant,
 }
 
 /// Cached wrapper around any provider, to avoid over-querying.
+///
+/// Refreshes transparently through `&self` once the TTL expires. With
+/// [`CachedProvider::with_stale_if_error`], a failed refresh falls back to the
+/// last good observation as long as it is younger than the configured age.
 pub struct CachedProvider<P: EconomicDataProvider> {
     inner: P,
     ttl: Duration,
-    cached_at: Option<Instant>,
-    cached_demand_shock: Option<f64>,
-    cached_productivity: Option<f64>,
+    stale_if_error: Option<Duration>,
+    clock: Arc<dyn Clock>,
+    cached: Mutex<Option<CachedObservation>>,
 }
 
 impl<P: EconomicDataProvider> CachedProvider<P> {
@@ -42,50 +136,84 @@ impl<P: EconomicDataProvider> CachedProvider<P> {
         Self {
             inner,
             ttl,
-            cached_at: None,
-            cached_demand_shock: None,
-            cached_productivity: None,
+            stale_if_error: None,
+            clock: Arc::new(SystemClock),
+            cached: Mutex::new(None),
         }
     }
 
-    pub fn refresh_if_needed(&mut self) -> Result<(), EconomicError> {
-        let stale = match self.cached_at {
-            None => true,
-            Some(t) => t.elapsed() >= self.ttl,
-        };
-        if stale {
-            let ds = self.inner.demand_shock_rate()?;
-            let a = self.inner.productivity_expansion()?;
-            if !ds.is_finite() || ds < 0.0 {
-                return Err(EconomicError::InvalidData(
-                    "demand_shock_rate must be finite and >= 0".into(),
-                ));
+    /// Serve the last good value, up to `max_age` old, when the inner
+    /// provider fails.
+    pub fn with_stale_if_error(mut self, max_age: Duration) -> Self {
+        self.stale_if_error = Some(max_age);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn refresh_if_needed(&self) -> Result<EconomicObservation, EconomicError> {
+        // Held across the fetch so concurrent callers share one refresh.
+        let mut cached = self
+            .cached
+            .lock()
+            .map_err(|_| EconomicError::Provider("cache lock poisoned".into()))?;
+        let now = self.clock.now();
+
+        if let Some(entry) = cached.as_ref() {
+            if now.saturating_duration_since(entry.fetched_at) < self.ttl {
+                return Ok(entry.observation.clone());
             }
-            if !a.is_finite() {
-                return Err(EconomicError::InvalidData(
-                    "productivity_expansion must be finite".into(),
-                ));
+        }
+
+        match self.fetch() {
+            Ok(observation) => {
+                *cached = Some(CachedObservation {
+                    observation: observation.clone(),
+                    fetched_at: now,
+                });
+                Ok(observation)
             }
-            self.cached_demand_shock = Some(ds);
-            self.cached_productivity = Some(a);
-            self.cached_at = Some(Instant::now());
+            Err(err) => match (cached.as_ref(), self.stale_if_error) {
+                (Some(entry), Some(max_age))
+                    if now.saturating_duration_since(entry.fetched_at) <= max_age =>
+                {
+                    Ok(entry.observation.clone())
+                }
+                _ => Err(err),
+            },
         }
-        Ok(())
+    }
+
+    fn fetch(&self) -> Result<EconomicObservation, EconomicError> {
+        let observation = self.inner.observe()?;
+        if !observation.demand_shock.is_finite() || observation.demand_shock < 0.0 {
+            return Err(EconomicError::InvalidData(
+                "demand_shock_rate must be finite and >= 0".into(),
+            ));
+        }
+        if !observation.productivity.is_finite() {
+            return Err(EconomicError::InvalidData(
+                "productivity_expansion must be finite".into(),
+            ));
+        }
+        Ok(observation)
     }
 }
 
 impl<P: EconomicDataProvider> EconomicDataProvider for CachedProvider<P> {
     fn demand_shock_rate(&self) -> Result<f64, EconomicError> {
-        // NOTE: immutable self; callers should use &mut CachedProvider in practice.
-        self.cached_demand_shock.ok_or_else(|| {
-            EconomicError::Provider("cache not initialized; call via &mut and refresh".into())
-        })
+        Ok(self.refresh_if_needed()?.demand_shock)
     }
 
     fn productivity_expansion(&self) -> Result<f64, EconomicError> {
-        self.cached_productivity.ok_or_else(|| {
-            EconomicError::Provider("cache not initialized; call via &mut and refresh".into())
-        })
+        Ok(self.refresh_if_needed()?.productivity)
+    }
+
+    fn observe(&self) -> Result<EconomicObservation, EconomicError> {
+        self.refresh_if_needed()
     }
 }
 
@@ -149,3 +277,64 @@ impl EconomicDataProvider for RecordedEconomicProvider {
         Ok(self.get_snapshot()?.productivity)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    #[derive(Clone, Default)]
+    struct FlakyProvider {
+        calls: Arc<AtomicUsize>,
+        failing: Arc<AtomicBool>,
+    }
+
+    impl EconomicDataProvider for FlakyProvider {
+        fn demand_shock_rate(&self) -> Result<f64, EconomicError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(EconomicError::Provider("feed offline".into()));
+            }
+            Ok(0.02)
+        }
+
+        fn productivity_expansion(&self) -> Result<f64, EconomicError> {
+            Ok(0.05)
+        }
+    }
+
+    #[test]
+    fn cached_provider_refreshes_through_trait_object() {
+        let inner = FlakyProvider::default();
+        let clock = Arc::new(ManualClock::new());
+        let cached =
+            CachedProvider::new(inner.clone(), Duration::from_secs(60)).with_clock(clock.clone());
+        let provider: &dyn EconomicDataProvider = &cached;
+
+        assert_eq!(provider.demand_shock_rate().unwrap(), 0.02);
+        assert_eq!(provider.productivity_expansion().unwrap(), 0.05);
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+
+        clock.advance(Duration::from_secs(60));
+        provider.demand_shock_rate().unwrap();
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn stale_value_served_until_max_age_on_error() {
+        let inner = FlakyProvider::default();
+        let clock = Arc::new(ManualClock::new());
+        let cached = CachedProvider::new(inner.clone(), Duration::from_secs(60))
+            .with_stale_if_error(Duration::from_secs(300))
+            .with_clock(clock.clone());
+
+        cached.refresh_if_needed().unwrap();
+        inner.failing.store(true, Ordering::SeqCst);
+
+        clock.advance(Duration::from_secs(120));
+        assert_eq!(cached.demand_shock_rate().unwrap(), 0.02);
+
+        clock.advance(Duration::from_secs(181));
+        assert!(cached.demand_shock_rate().is_err());
+    }
+}
@@ -0,0 +1,304 @@
+use crate::economic_oracle::{
+    EconomicDataProvider, EconomicError, EconomicObservation, RecordedEconomicSnapshot,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// One dated observation of the economic inputs.
+///
+/// `at` is either a block height or a unix timestamp; a series must use one
+/// consistently. JSON and CSV inputs may name the column `at`, `height` or
+/// `timestamp`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EconomicSeriesPoint {
+    #[serde(alias = "height", alias = "timestamp")]
+    pub at: u64,
+    pub demand_shock: f64,
+    pub productivity: f64,
+}
+
+/// How values between two recorded points are derived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Hold the most recent point until the next one.
+    Step,
+    /// Interpolate linearly between the surrounding points.
+    Linear,
+}
+
+/// Provider that replays a recorded series of economic observations.
+///
+/// The trait methods have no notion of height, so callers position the
+/// provider with [`TimeSeriesEconomicProvider::set_cursor`] before each RBI
+/// calculation, or query [`TimeSeriesEconomicProvider::snapshot_at`] directly.
+#[derive(Debug)]
+pub struct TimeSeriesEconomicProvider {
+    points: Vec<EconomicSeriesPoint>,
+    interpolation: Interpolation,
+    cursor: Mutex<Option<u64>>,
+}
+
+impl TimeSeriesEconomicProvider {
+    pub fn new(
+        mut points: Vec<EconomicSeriesPoint>,
+        interpolation: Interpolation,
+    ) -> Result<Self, EconomicError> {
+        if points.is_empty() {
+            return Err(EconomicError::InvalidData(
+                "economic series must contain at least one point".into(),
+            ));
+        }
+
+        points.sort_by_key(|p| p.at);
+        for pair in points.windows(2) {
+            if pair[0].at == pair[1].at {
+                return Err(EconomicError::InvalidData(format!(
+                    "duplicate economic series point at {}",
+                    pair[0].at
+                )));
+            }
+        }
+        for p in &points {
+            if !p.demand_shock.is_finite() || p.demand_shock < 0.0 {
+                return Err(EconomicError::InvalidData(format!(
+                    "demand_shock at {} must be finite and >= 0",
+                    p.at
+                )));
+            }
+            if !p.productivity.is_finite() {
+                return Err(EconomicError::InvalidData(format!(
+                    "productivity at {} must be finite",
+                    p.at
+                )));
+            }
+        }
+
+        Ok(Self {
+            points,
+            interpolation,
+            cursor: Mutex::new(None),
+        })
+    }
+
+    /// Parses a JSON array of points.
+    pub fn from_json_str(json: &str, interpolation: Interpolation) -> Result<Self, EconomicError> {
+        let points: Vec<EconomicSeriesPoint> = serde_json::from_str(json)
+            .map_err(|e| EconomicError::InvalidData(format!("invalid series json: {e}")))?;
+        Self::new(points, interpolation)
+    }
+
+    /// Parses comma-separated values with a header row naming the
+    /// `at`/`height`/`timestamp`, `demand_shock` and `productivity` columns.
+    /// Blank lines and lines starting with `#` are ignored; quoting is not
+    /// supported since every field is numeric.
+    pub fn from_csv_str(csv: &str, interpolation: Interpolation) -> Result<Self, EconomicError> {
+        let mut lines = csv
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let (_, header) = lines
+            .next()
+            .ok_or_else(|| EconomicError::InvalidData("series csv is empty".into()))?;
+        let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+        let column = |names: &[&str]| {
+            columns
+                .iter()
+                .position(|c| names.contains(c))
+                .ok_or_else(|| {
+                    EconomicError::InvalidData(format!(
+                        "series csv header missing column {}",
+                        names.join("/")
+                    ))
+                })
+        };
+        let at_col = column(&["at", "height", "timestamp"])?;
+        let ds_col = column(&["demand_shock"])?;
+        let a_col = column(&["productivity"])?;
+
+        let mut points = Vec::new();
+        for (line_no, line) in lines {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if fields.len() != columns.len() {
+                return Err(EconomicError::InvalidData(format!(
+                    "series csv line {line_no}: expected {} fields, found {}",
+                    columns.len(),
+                    fields.len()
+                )));
+            }
+            let parse_err = |name: &str, e: &dyn std::fmt::Display| {
+                EconomicError::InvalidData(format!("series csv line {line_no}: {name}: {e}"))
+            };
+            points.push(EconomicSeriesPoint {
+                at: fields[at_col]
+                    .parse()
+                    .map_err(|e| parse_err(columns[at_col], &e))?,
+                demand_shock: fields[ds_col]
+                    .parse()
+                    .map_err(|e| parse_err("demand_shock", &e))?,
+                productivity: fields[a_col]
+                    .parse()
+                    .map_err(|e| parse_err("productivity", &e))?,
+            });
+        }
+
+        Self::new(points, interpolation)
+    }
+
+    /// Loads a series from disk, choosing the format from the file
+    /// extension (`.json` or `.csv`).
+    pub fn from_path<P: AsRef<Path>>(
+        path: P,
+        interpolation: Interpolation,
+    ) -> Result<Self, EconomicError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            EconomicError::Provider(format!("failed to read {}: {e}", path.display()))
+        })?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => {
+                Self::from_json_str(&contents, interpolation)
+            }
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => {
+                Self::from_csv_str(&contents, interpolation)
+            }
+            _ => Err(EconomicError::InvalidData(format!(
+                "unsupported series file extension: {}",
+                path.display()
+            ))),
+        }
+    }
+
+    pub fn points(&self) -> &[EconomicSeriesPoint] {
+        &self.points
+    }
+
+    /// First and last `at` values covered by the series.
+    pub fn range(&self) -> (u64, u64) {
+        (self.points[0].at, self.points[self.points.len() - 1].at)
+    }
+
+    /// Positions the provider for subsequent trait calls.
+    pub fn set_cursor(&self, at: u64) {
+        if let Ok(mut guard) = self.cursor.lock() {
+            *guard = Some(at);
+        }
+    }
+
+    pub fn snapshot_at(&self, at: u64) -> Result<RecordedEconomicSnapshot, EconomicError> {
+        let (first, last) = self.range();
+        if at < first || at > last {
+            return Err(EconomicError::OutOfRange {
+                requested: at,
+                first,
+                last,
+            });
+        }
+
+        // Index of the first point strictly after `at`; it is >= 1 because
+        // `at >= first`.
+        let next = self.points.partition_point(|p| p.at <= at);
+        let prev = self.points[next - 1];
+        if prev.at == at || self.interpolation == Interpolation::Step {
+            return Ok(RecordedEconomicSnapshot {
+                demand_shock: prev.demand_shock,
+                productivity: prev.productivity,
+            });
+        }
+
+        let upper = self.points[next];
+        let t = (at - prev.at) as f64 / (upper.at - prev.at) as f64;
+        Ok(RecordedEconomicSnapshot {
+            demand_shock: prev.demand_shock + t * (upper.demand_shock - prev.demand_shock),
+            productivity: prev.productivity + t * (upper.productivity - prev.productivity),
+        })
+    }
+
+    /// Snapshot at the cursor, read once so both values share a position.
+    fn current_snapshot(&self) -> Result<RecordedEconomicSnapshot, EconomicError> {
+        let cursor = self
+            .cursor
+            .lock()
+            .map_err(|_| EconomicError::Provider("series cursor lock poisoned".into()))?;
+        let at = cursor.ok_or_else(|| {
+            EconomicError::Provider("series cursor not set; call set_cursor first".into())
+        })?;
+        self.snapshot_at(at)
+    }
+}
+
+impl EconomicDataProvider for TimeSeriesEconomicProvider {
+    fn demand_shock_rate(&self) -> Result<f64, EconomicError> {
+        Ok(self.current_snapshot()?.demand_shock)
+    }
+
+    fn productivity_expansion(&self) -> Result<f64, EconomicError> {
+        Ok(self.current_snapshot()?.productivity)
+    }
+
+    /// Both values from one cursor position, even if another thread moves
+    /// the cursor between the two reads.
+    fn observe(&self) -> Result<EconomicObservation, EconomicError> {
+        let snapshot = self.current_snapshot()?;
+        Ok(EconomicObservation {
+            demand_shock: snapshot.demand_shock,
+            productivity: snapshot.productivity,
+            report: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERIES_CSV: &str = "\
+# replayed macro history
+height,demand_shock,productivity
+800000,0.02,0.05
+800100,0.04,0.15
+";
+
+    #[test]
+    fn csv_series_interpolates() {
+        let step =
+            TimeSeriesEconomicProvider::from_csv_str(SERIES_CSV, Interpolation::Step).unwrap();
+        let linear =
+            TimeSeriesEconomicProvider::from_csv_str(SERIES_CSV, Interpolation::Linear).unwrap();
+
+        let s = step.snapshot_at(800_050).unwrap();
+        assert_eq!(s.demand_shock, 0.02);
+        let l = linear.snapshot_at(800_050).unwrap();
+        assert!((l.demand_shock - 0.03).abs() < 1e-12);
+        assert!((l.productivity - 0.10).abs() < 1e-12);
+
+        linear.set_cursor(800_100);
+        assert_eq!(linear.productivity_expansion().unwrap(), 0.15);
+        let observation = linear.observe().unwrap();
+        assert_eq!(
+            (observation.demand_shock, observation.productivity),
+            (0.04, 0.15)
+        );
+    }
+
+    #[test]
+    fn out_of_range_height_is_rejected() {
+        let provider = TimeSeriesEconomicProvider::from_json_str(
+            r#"[{"timestamp": 10, "demand_shock": 0.02, "productivity": 0.05}]"#,
+            Interpolation::Linear,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            provider.snapshot_at(11),
+            Err(EconomicError::OutOfRange {
+                requested: 11,
+                first: 10,
+                last: 10
+            })
+        ));
+        assert!(provider.demand_shock_rate().is_err());
+    }
+}
@@ -0,0 +1,193 @@
+//! Esplora (`POST /tx`) transaction broadcaster and confirmation source.
+//!
+//! Esplora relays Bitcoin Core's reject reason in the error body, e.g.
+//! `sendrawtransaction RPC error: {"code":-26,"message":"min relay fee not met"}`,
+//! so rejections are classified the same way as with a local node. Esplora
+//! offers no acceptance pre-check.
+//!
+//! Confirmations are read from `GET /tx/:txid/status` and the tip height.
+//! Esplora does not report conflicts: a double-spent payout stays `Unknown`.
+//!
+//! Co-spends for Sybil clustering come from each address's confirmed
+//! history, `GET /address/:address/txs/chain`, paged newest first until the
+//! start of the window.
+
+use crate::disbursement::{
+    classify_rejection, BroadcastError, Broadcaster, ConfirmationSource, TxChainStatus,
+};
+use crate::sybil_clustering::{CoSpend, CoSpendSource};
+use crate::velocity_analyzer::VelocityError;
+use bitcoin::{Transaction, Txid};
+use serde::Deserialize;
+use std::str::FromStr;
+use std::time::Duration;
+
+pub struct EsploraBroadcaster {
+    /// API root, e.g. `https://blockstream.info/api`.
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+impl EsploraBroadcaster {
+    pub fn new(base_url: impl Into<String>, timeout: Duration) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            agent: ureq::AgentBuilder::new().timeout(timeout).build(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn get(&self, path: &str) -> Result<Option<String>, VelocityError> {
+        match self.agent.get(&format!("{}{path}", self.base_url)).call() {
+            Ok(response) => response
+                .into_string()
+                .map(Some)
+                .map_err(|e| VelocityError::DataSource(format!("unreadable response: {e}"))),
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(err) => Err(VelocityError::DataSource(err.to_string())),
+        }
+    }
+}
+
+/// Transactions per page of confirmed address history.
+const CHAIN_PAGE_SIZE: usize = 25;
+
+#[derive(Deserialize)]
+struct TxStatus {
+    confirmed: bool,
+    block_height: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct AddressTx {
+    txid: String,
+    vin: Vec<TxInput>,
+    status: TxStatus,
+}
+
+#[derive(Deserialize)]
+struct TxInput {
+    /// `None` for coinbase inputs.
+    prevout: Option<PrevOut>,
+}
+
+#[derive(Deserialize)]
+struct PrevOut {
+    /// `None` for scripts without an address form.
+    scriptpubkey_address: Option<String>,
+}
+
+impl EsploraBroadcaster {
+    /// Confirmed transactions of `address`, newest first, down to the first
+    /// page reaching below `start_height`.
+    fn confirmed_history(
+        &self,
+        address: &str,
+        start_height: u64,
+    ) -> Result<Vec<AddressTx>, VelocityError> {
+        let mut history = Vec::new();
+        let mut path = format!("/address/{address}/txs/chain");
+        while let Some(body) = self.get(&path)? {
+            let page: Vec<AddressTx> = serde_json::from_str(&body)
+                .map_err(|e| VelocityError::DataSource(format!("unexpected history: {e}")))?;
+            let is_full = page.len() >= CHAIN_PAGE_SIZE;
+            let next = page
+                .last()
+                .filter(|tx| is_full && tx.status.block_height >= Some(start_height))
+                .map(|tx| format!("/address/{address}/txs/chain/{}", tx.txid));
+            history.extend(page);
+            match next {
+                Some(next) => path = next,
+                None => break,
+            }
+        }
+        Ok(history)
+    }
+}
+
+impl ConfirmationSource for EsploraBroadcaster {
+    fn transaction_status(&self, txid: &Txid) -> Result<TxChainStatus, VelocityError> {
+        let Some(body) = self.get(&format!("/tx/{txid}/status"))? else {
+            return Ok(TxChainStatus::Unknown);
+        };
+        let status: TxStatus = serde_json::from_str(&body)
+            .map_err(|e| VelocityError::DataSource(format!("unexpected tx status: {e}")))?;
+        let Some(block_height) = status.block_height.filter(|_| status.confirmed) else {
+            return Ok(TxChainStatus::InMempool);
+        };
+        let tip = self
+            .get("/blocks/tip/height")?
+            .ok_or_else(|| VelocityError::DataSource("no tip height".into()))?;
+        let tip: u64 = tip
+            .trim()
+            .parse()
+            .map_err(|e| VelocityError::DataSource(format!("unexpected tip height: {e}")))?;
+        let confirmations = tip.saturating_sub(block_height).saturating_add(1);
+        Ok(TxChainStatus::Confirmed {
+            confirmations: u32::try_from(confirmations).unwrap_or(u32::MAX),
+        })
+    }
+}
+
+impl CoSpendSource for EsploraBroadcaster {
+    fn co_spends_for_addresses(
+        &self,
+        addresses: &[String],
+        start_height: u64,
+        end_height: u64,
+    ) -> Result<Vec<CoSpend>, VelocityError> {
+        let mut co_spends: Vec<CoSpend> = Vec::new();
+        for address in addresses {
+            for tx in self.confirmed_history(address, start_height)? {
+                let in_window = tx.status.block_height.is_some_and(|h| {
+                    tx.status.confirmed && (start_height..=end_height).contains(&h)
+                });
+                if !in_window || co_spends.iter().any(|c| c.txid == tx.txid) {
+                    continue;
+                }
+                let mut input_addresses: Vec<String> = Vec::new();
+                for input in tx.vin {
+                    if let Some(input_address) = input.prevout.and_then(|p| p.scriptpubkey_address)
+                    {
+                        if !input_addresses.contains(&input_address) {
+                            input_addresses.push(input_address);
+                        }
+                    }
+                }
+                // Receipts, and spends of one address alone, link nothing
+                if input_addresses.len() > 1 && input_addresses.contains(address) {
+                    co_spends.push(CoSpend {
+                        txid: tx.txid,
+                        input_addresses,
+                    });
+                }
+            }
+        }
+        Ok(co_spends)
+    }
+}
+
+impl Broadcaster for EsploraBroadcaster {
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, BroadcastError> {
+        let raw_hex = hex::encode(bitcoin::consensus::serialize(tx));
+        let url = format!("{}/tx", self.base_url);
+        match self.agent.post(&url).send_string(&raw_hex) {
+            Ok(response) => {
+                let body = response
+                    .into_string()
+                    .map_err(|e| BroadcastError::Transport(format!("unreadable response: {e}")))?;
+                Txid::from_str(body.trim()).map_err(|e| {
+                    BroadcastError::Transport(format!("unexpected response '{body}': {e}"))
+                })
+            }
+            Err(ureq::Error::Status(code, response)) if (400..500).contains(&code) => {
+                let body = response.into_string().unwrap_or_default();
+                Err(classify_rejection(&body))
+            }
+            Err(err) => Err(BroadcastError::Transport(err.to_string())),
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! HTTP JSON economic feed with publisher signature verification.
+//!
+//! The feed serves a document of the form
+//!
+//! ```json
+//! {
+//!   "payload": { "timestamp": 1760000000, "demand_shock": 0.02, "productivity": 0.05 },
+//!   "signature": "<hex>"
+//! }
+//! ```
+//!
+//! The signature commits to [`feed_message_digest`] of the payload rather than
+//! to the JSON text, so whitespace or key order changes in transit cannot
+//! alter what was signed. Schnorr (BIP340, 64-byte) and ECDSA (DER or 64-byte
+//! compact) signatures are supported, matching the configured publisher key.
+
+use crate::economic_oracle::{EconomicDataProvider, EconomicError, EconomicObservation};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::{self, ecdsa, schnorr, Message, Secp256k1, VerifyOnly};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const FEED_DIGEST_TAG: &[u8] = b"BDLD-ECONOMIC-FEED-V1";
+
+/// Key the feed publisher signs with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedPublisherKey {
+    Schnorr(secp256k1::XOnlyPublicKey),
+    Ecdsa(secp256k1::PublicKey),
+}
+
+/// Signed economic values as published by the feed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SignedFeedPayload {
+    /// Unix timestamp (seconds) at which the publisher produced the values.
+    pub timestamp: u64,
+    pub demand_shock: f64,
+    pub productivity: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedFeedDocument {
+    pub payload: SignedFeedPayload,
+    pub signature: String,
+}
+
+/// Digest the publisher signs: SHA-256 over a fixed tag followed by the
+/// big-endian timestamp and the big-endian IEEE-754 bits of D_s and A.
+pub fn feed_message_digest(payload: &SignedFeedPayload) -> [u8; 32] {
+    let mut engine = sha256::Hash::engine();
+    engine.input(FEED_DIGEST_TAG);
+    engine.input(&payload.timestamp.to_be_bytes());
+    engine.input(&payload.demand_shock.to_bits().to_be_bytes());
+    engine.input(&payload.productivity.to_bits().to_be_bytes());
+    sha256::Hash::from_engine(engine).to_byte_array()
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpEconomicProviderConfig {
+    pub url: String,
+    pub publisher_key: FeedPublisherKey,
+
+    /// Oldest payload timestamp accepted, relative to local time.
+    pub max_age: Duration,
+
+    /// How far a payload timestamp may run ahead of local time.
+    pub max_future_skew: Duration,
+
+    pub timeout: Duration,
+}
+
+impl HttpEconomicProviderConfig {
+    pub fn new(url: impl Into<String>, publisher_key: FeedPublisherKey) -> Self {
+        Self {
+            url: url.into(),
+            publisher_key,
+            max_age: Duration::from_secs(3_600),
+            max_future_skew: Duration::from_secs(300),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Provider fetching D_s and A from a signed HTTP JSON feed.
+///
+/// Every call performs a fetch; wrap it in `CachedProvider` to bound request
+/// volume.
+pub struct HttpEconomicProvider {
+    config: HttpEconomicProviderConfig,
+    agent: ureq::Agent,
+    secp: Secp256k1<VerifyOnly>,
+}
+
+impl HttpEconomicProvider {
+    pub fn new(config: HttpEconomicProviderConfig) -> Self {
+        let agent = ureq::AgentBuilder::new().timeout(config.timeout).build();
+        Self {
+            config,
+            agent,
+            secp: Secp256k1::verification_only(),
+        }
+    }
+
+    pub fn config(&self) -> &HttpEconomicProviderConfig {
+        &self.config
+    }
+
+    pub fn fetch(&self) -> Result<SignedFeedPayload, EconomicError> {
+        let response =
+            self.agent.get(&self.config.url).call().map_err(|e| {
+                EconomicError::Provider(format!("economic feed request failed: {e}"))
+            })?;
+        let body = response
+            .into_string()
+            .map_err(|e| EconomicError::Provider(format!("economic feed body unreadable: {e}")))?;
+        let document: SignedFeedDocument = serde_json::from_str(&body)
+            .map_err(|e| EconomicError::InvalidData(format!("malformed economic feed: {e}")))?;
+
+        self.verify(&document)?;
+        Ok(document.payload)
+    }
+
+    /// Checks the signature and timestamp freshness of a feed document.
+    pub fn verify(&self, document: &SignedFeedDocument) -> Result<(), EconomicError> {
+        let payload = &document.payload;
+        let digest = feed_message_digest(payload);
+        let message = Message::from_slice(&digest)
+            .map_err(|e| EconomicError::InvalidData(format!("feed digest invalid: {e}")))?;
+        let sig_bytes = hex::decode(document.signature.trim())
+            .map_err(|e| EconomicError::InvalidData(format!("feed signature is not hex: {e}")))?;
+
+        let verified = match self.config.publisher_key {
+            FeedPublisherKey::Schnorr(key) => schnorr::Signature::from_slice(&sig_bytes)
+                .and_then(|sig| self.secp.verify_schnorr(&sig, &message, &key)),
+            FeedPublisherKey::Ecdsa(key) => ecdsa::Signature::from_der(&sig_bytes)
+                .or_else(|_| ecdsa::Signature::from_compact(&sig_bytes))
+                .and_then(|sig| self.secp.verify_ecdsa(&message, &sig, &key)),
+        };
+        verified.map_err(|e| {
+            EconomicError::InvalidData(format!("feed signature verification failed: {e}"))
+        })?;
+
+        let now = chrono::Utc::now().timestamp().max(0) as u64;
+        if payload.timestamp > now.saturating_add(self.config.max_future_skew.as_secs()) {
+            return Err(EconomicError::InvalidData(format!(
+                "feed timestamp {} is in the future (now {now})",
+                payload.timestamp
+            )));
+        }
+        if now.saturating_sub(payload.timestamp) > self.config.max_age.as_secs() {
+            return Err(EconomicError::InvalidData(format!(
+                "feed timestamp {} is stale (now {now}, max age {}s)",
+                payload.timestamp,
+                self.config.max_age.as_secs()
+            )));
+        }
+
+        if !payload.demand_shock.is_finite() || payload.demand_shock < 0.0 {
+            return Err(EconomicError::InvalidData(
+                "demand_shock_rate must be finite and >= 0".into(),
+            ));
+        }
+        if !payload.productivity.is_finite() {
+            return Err(EconomicError::InvalidData(
+                "productivity_expansion must be finite".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl EconomicDataProvider for HttpEconomicProvider {
+    fn demand_shock_rate(&self) -> Result<f64, EconomicError> {
+        Ok(self.fetch()?.demand_shock)
+    }
+
+    fn productivity_expansion(&self) -> Result<f64, EconomicError> {
+        Ok(self.fetch()?.productivity)
+    }
+
+    fn observe(&self) -> Result<EconomicObservation, EconomicError> {
+        let payload = self.fetch()?;
+        Ok(EconomicObservation {
+            demand_shock: payload.demand_shock,
+            productivity: payload.productivity,
+            report: None,
+        })
+    }
+}
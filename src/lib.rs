@@ -1,9 +1,17 @@
 pub mod alerts;
+pub mod audit_log;
 pub mod disbursement;
+pub mod economic_aggregator;
 pub mod economic_oracle;
+pub mod economic_series;
+#[cfg(feature = "postgres")]
+pub mod postgres_registry;
 pub mod rbi_engine;
 pub mod simulation;
 pub mod sqlite_participant_registry;
+pub mod stake;
+pub mod storage;
+pub mod sybil_clustering;
 pub mod utxo_scoring;
 pub mod velocity_analyzer;
 pub mod velocity_config;
@@ -11,6 +19,12 @@ pub mod velocity_config;
 #[cfg(feature = "rpc")]
 pub mod bitcoin_core_chain;
 
+#[cfg(feature = "http-oracle")]
+pub mod http_economic_provider;
+
+#[cfg(feature = "esplora")]
+pub mod esplora_broadcaster;
+
 #[cfg(feature = "api")]
 pub mod api;
 
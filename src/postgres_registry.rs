@@ -0,0 +1,1495 @@
+//! PostgreSQL registry storage, for several API nodes sharing one database.
+//!
+//! Holds the same data as
+//! [`SqliteParticipantRegistry`](crate::sqlite_participant_registry::SqliteParticipantRegistry).
+//! Write transactions take an exclusive lock on `audit_log` first, so writes
+//! from every node are serialized and the audit chain stays linear.
+//!
+//! The blocking client runs its own runtime, so every call is made on a
+//! short-lived thread; the registry can be used from async handlers.
+
+use crate::audit_log::{entry_hash, AuditEntry, AuditEvent, GENESIS_HASH};
+use crate::disbursement::{
+    AileeTrustAudit, ChangeIndexStore, PayoutHistory, PayoutStatus, PayoutTransactionResult,
+    PayoutTransition, PayoutVolume, PoolUtxo,
+};
+use crate::rbi_engine::ParticipantSnapshot;
+use crate::stake::{StakePosition, StakeVerification};
+use crate::storage::{
+    check_address, check_registration, check_spend, check_stake, check_trust, payout_events,
+    payout_outputs, pool_utxo_from_json, pool_utxo_json, stake_updated_event, stored_address,
+    ParticipantAddress, ParticipantPage, ParticipantRecord, PayoutLock, RegistryError,
+    RegistryStorage,
+};
+use crate::velocity_analyzer::{ParticipantRegistry, VelocityError};
+use bitcoin::{Network, OutPoint, Script, ScriptBuf, Txid};
+use chrono::{DateTime, Utc};
+use postgres::{Client, Config, GenericClient, NoTls, Row, Transaction};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+const AUDIT_SELECT_COLUMNS: &str = "seq, recorded_at, event_type, payload_json, prev_hash, hash";
+
+const STAKE_SELECT_COLUMNS: &str = "txid, vout, participant_id, amount_sats, lock_height, \
+     witness_script_hex, verification, confirmed_height, spent_height";
+
+const PAYOUT_SELECT_COLUMNS: &str = "payout_id, recipient_address, amount_sats, fee_sats, status, \
+     psbt_base64, raw_tx_hex, txid, timestamp, trust_audit_json, is_dry_run, \
+     confirmations, signed_psbt_base64, final_tx_hex, fee_bump_of_json, fee_bumped_by_json, \
+     signing_json, batch_id";
+
+/// Registry backed by a PostgreSQL database. Connections are plain TCP
+/// (no TLS); reach remote servers through a private network or tunnel.
+pub struct PostgresParticipantRegistry {
+    config: Config,
+    /// `None` until reconnected after a failed connection attempt.
+    client: Mutex<Option<Client>>,
+    /// Session holding the payout advisory lock, apart from `client` so
+    /// registry reads and writes go on while it is held.
+    lock_client: Mutex<Option<Client>>,
+    /// Network that registered payout addresses must belong to.
+    network: Network,
+}
+
+impl std::fmt::Debug for PostgresParticipantRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PostgresParticipantRegistry")
+            .field("hosts", &self.config.get_hosts())
+            .field("dbname", &self.config.get_dbname())
+            .finish_non_exhaustive()
+    }
+}
+
+impl From<postgres::Error> for RegistryError {
+    fn from(err: postgres::Error) -> Self {
+        RegistryError::Storage(describe(&err))
+    }
+}
+
+/// The server's message where there is one; the error's own `Display` is
+/// only "db error".
+fn describe(err: &postgres::Error) -> String {
+    match err.as_db_error() {
+        Some(db) => db.to_string(),
+        None => err.to_string(),
+    }
+}
+
+impl PostgresParticipantRegistry {
+    /// Connects with a `postgres://` URL or `key=value` connection string
+    /// and migrates the database to [`SCHEMA_VERSION`].
+    pub fn connect(url: &str) -> Result<Self, VelocityError> {
+        let config = Config::from_str(url).map_err(|e| VelocityError::DataSource(e.to_string()))?;
+        Self::from_config(config)
+    }
+
+    /// Connects and migrates. Nodes starting together migrate one at a time.
+    /// Fails without writing if the database is newer than this binary.
+    pub fn from_config(config: Config) -> Result<Self, VelocityError> {
+        let client = on_worker(|| {
+            let mut client = config
+                .connect(NoTls)
+                .map_err(|e| VelocityError::DataSource(describe(&e)))?;
+            migrate(&mut client)?;
+            Ok::<_, VelocityError>(client)
+        })?;
+        Ok(Self {
+            config,
+            client: Mutex::new(Some(client)),
+            lock_client: Mutex::new(None),
+            network: Network::Bitcoin,
+        })
+    }
+
+    /// Validates registered addresses against `network` instead of mainnet.
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Runs `f` on the connection, reconnecting first if it was lost.
+    fn with_client<T: Send>(
+        &self,
+        f: impl FnOnce(&mut Client) -> Result<T, RegistryError> + Send,
+    ) -> Result<T, RegistryError> {
+        let mut guard = self
+            .client
+            .lock()
+            .map_err(|_| RegistryError::Storage("registry lock poisoned".into()))?;
+        let slot: &mut Option<Client> = &mut guard;
+        on_worker(|| f(self.connected(slot)?))
+    }
+
+    /// The client in `slot`, connecting first if there is none or it closed.
+    fn connected<'c>(&self, slot: &'c mut Option<Client>) -> Result<&'c mut Client, RegistryError> {
+        if slot.as_ref().is_none_or(Client::is_closed) {
+            *slot = None;
+            *slot = Some(self.config.connect(NoTls)?);
+        }
+        Ok(slot.as_mut().expect("connected above"))
+    }
+
+    fn read<T: Send>(
+        &self,
+        f: impl FnOnce(&mut Client) -> Result<T, RegistryError> + Send,
+    ) -> Result<T, RegistryError> {
+        self.with_client(f)
+    }
+
+    /// Runs `f` in a transaction holding the audit log lock, committed only
+    /// if it succeeds.
+    fn write<T: Send>(
+        &self,
+        f: impl FnOnce(&mut Transaction<'_>) -> Result<T, RegistryError> + Send,
+    ) -> Result<T, RegistryError> {
+        self.with_client(|client| {
+            let mut tx = client.transaction()?;
+            tx.batch_execute("LOCK TABLE audit_log IN EXCLUSIVE MODE")?;
+            let value = f(&mut tx)?;
+            tx.commit()?;
+            Ok(value)
+        })
+    }
+
+    fn update_stake(
+        &self,
+        outpoint: &OutPoint,
+        verification: StakeVerification,
+        confirmed_height: Option<u64>,
+    ) -> Result<StakePosition, RegistryError> {
+        self.write(|tx| {
+            require_stake(tx, outpoint)?;
+            tx.execute(
+                "UPDATE stakes SET verification = $3, confirmed_height = $4 \
+                 WHERE txid = $1 AND vout = $2",
+                &[
+                    &outpoint.txid.to_string(),
+                    &i64::from(outpoint.vout),
+                    &verification.to_string(),
+                    &confirmed_height.map(to_sql_int),
+                ],
+            )?;
+            audit_stake_update(tx, outpoint)
+        })
+    }
+}
+
+impl Drop for PostgresParticipantRegistry {
+    /// Closing the connection blocks too.
+    fn drop(&mut self) {
+        let clients = [&mut self.client, &mut self.lock_client]
+            .into_iter()
+            .filter_map(|slot| slot.get_mut().ok().and_then(Option::take))
+            .collect::<Vec<_>>();
+        if !clients.is_empty() {
+            on_worker(move || drop(clients));
+        }
+    }
+}
+
+/// Runs `f` on a scoped thread, outside any async runtime of the caller.
+fn on_worker<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    std::thread::scope(|scope| {
+        scope
+            .spawn(f)
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+fn data_source(err: RegistryError) -> VelocityError {
+    match err {
+        RegistryError::Storage(msg) => VelocityError::DataSource(msg),
+        other => VelocityError::DataSource(other.to_string()),
+    }
+}
+
+/// Heights and amounts are stored as BIGINT.
+fn to_sql_int(value: u64) -> i64 {
+    value as i64
+}
+
+impl RegistryStorage for PostgresParticipantRegistry {
+    fn save_payout_transitions(
+        &self,
+        payout: &PayoutTransactionResult,
+        transitions: &[PayoutTransition],
+    ) -> Result<(), VelocityError> {
+        let trust_audit_json = serde_json::to_string(&payout.trust_audit)
+            .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+        let fee_bump_of_json = payout
+            .fee_bump_of
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+        let fee_bumped_by_json = payout
+            .fee_bumped_by
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+        let signing_json = payout
+            .signing
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+
+        self.write(|tx| {
+            let is_new = tx
+                .query_opt(
+                    "SELECT 1 FROM payouts WHERE payout_id = $1",
+                    &[&payout.payout_id],
+                )?
+                .is_none();
+            tx.execute(
+                "INSERT INTO payouts (
+                    payout_id, recipient_address, amount_sats, fee_sats, status,
+                    psbt_base64, raw_tx_hex, txid, timestamp, trust_audit_json, is_dry_run,
+                    confirmations, signed_psbt_base64, final_tx_hex,
+                    fee_bump_of_json, fee_bumped_by_json, signing_json, batch_id
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+                    $18)
+                ON CONFLICT (payout_id) DO UPDATE SET
+                    recipient_address = EXCLUDED.recipient_address,
+                    amount_sats = EXCLUDED.amount_sats,
+                    fee_sats = EXCLUDED.fee_sats,
+                    status = EXCLUDED.status,
+                    psbt_base64 = EXCLUDED.psbt_base64,
+                    raw_tx_hex = EXCLUDED.raw_tx_hex,
+                    txid = EXCLUDED.txid,
+                    timestamp = EXCLUDED.timestamp,
+                    trust_audit_json = EXCLUDED.trust_audit_json,
+                    is_dry_run = EXCLUDED.is_dry_run,
+                    confirmations = EXCLUDED.confirmations,
+                    signed_psbt_base64 = EXCLUDED.signed_psbt_base64,
+                    final_tx_hex = EXCLUDED.final_tx_hex,
+                    fee_bump_of_json = EXCLUDED.fee_bump_of_json,
+                    fee_bumped_by_json = EXCLUDED.fee_bumped_by_json,
+                    signing_json = EXCLUDED.signing_json,
+                    batch_id = EXCLUDED.batch_id",
+                &[
+                    &payout.payout_id,
+                    &payout.recipient_address,
+                    &to_sql_int(payout.amount_sats),
+                    &to_sql_int(payout.fee_sats),
+                    &payout.status.to_string(),
+                    &payout.psbt_base64,
+                    &payout.raw_tx_hex,
+                    &payout.txid,
+                    &payout.timestamp,
+                    &trust_audit_json,
+                    &payout.is_dry_run,
+                    &i64::from(payout.confirmations),
+                    &payout.signed_psbt_base64,
+                    &payout.final_tx_hex,
+                    &fee_bump_of_json,
+                    &fee_bumped_by_json,
+                    &signing_json,
+                    &payout.batch_id,
+                ],
+            )?;
+
+            tx.execute(
+                "DELETE FROM payout_outputs WHERE payout_id = $1",
+                &[&payout.payout_id],
+            )?;
+            for (position, (address, amount_sats)) in
+                payout_outputs(payout, self.network).iter().enumerate()
+            {
+                tx.execute(
+                    "INSERT INTO payout_outputs (payout_id, position, address, amount_sats) \
+                     VALUES ($1, $2, $3, $4)",
+                    &[
+                        &payout.payout_id,
+                        &(position as i64),
+                        address,
+                        &to_sql_int(*amount_sats),
+                    ],
+                )?;
+            }
+
+            for transition in transitions {
+                tx.execute(
+                    "INSERT INTO payout_transitions (
+                        payout_id, from_status, to_status, confirmations, detail, timestamp
+                    ) VALUES ($1, $2, $3, $4, $5, $6)",
+                    &[
+                        &transition.payout_id,
+                        &transition.from.to_string(),
+                        &transition.to.to_string(),
+                        &i64::from(transition.confirmations),
+                        &transition.detail,
+                        &transition.timestamp,
+                    ],
+                )?;
+            }
+
+            for event in payout_events(payout, is_new, transitions) {
+                append_audit(tx, &event)?;
+            }
+            Ok(())
+        })
+        .map_err(data_source)
+    }
+
+    fn get_payout_by_id(
+        &self,
+        payout_id: &str,
+    ) -> Result<Option<PayoutTransactionResult>, VelocityError> {
+        self.read(|client| {
+            client
+                .query_opt(
+                    &format!("SELECT {PAYOUT_SELECT_COLUMNS} FROM payouts WHERE payout_id = $1"),
+                    &[&payout_id],
+                )?
+                .map(|row| payout_from_row(&row))
+                .transpose()
+        })
+        .map_err(data_source)
+    }
+
+    fn get_all_payouts(&self) -> Result<Vec<PayoutTransactionResult>, VelocityError> {
+        self.read(|client| {
+            client
+                .query(
+                    &format!("SELECT {PAYOUT_SELECT_COLUMNS} FROM payouts ORDER BY timestamp DESC"),
+                    &[],
+                )?
+                .iter()
+                .map(payout_from_row)
+                .collect()
+        })
+        .map_err(data_source)
+    }
+
+    fn get_payout_transitions(
+        &self,
+        payout_id: &str,
+    ) -> Result<Vec<PayoutTransition>, VelocityError> {
+        self.read(|client| {
+            client
+                .query(
+                    "SELECT payout_id, from_status, to_status, confirmations, detail, timestamp \
+                     FROM payout_transitions WHERE payout_id = $1 ORDER BY id ASC",
+                    &[&payout_id],
+                )?
+                .iter()
+                .map(|row| {
+                    Ok(PayoutTransition {
+                        payout_id: row.try_get(0)?,
+                        from: parse_status(row, 1)?,
+                        to: parse_status(row, 2)?,
+                        confirmations: get_u32(row, 3)?,
+                        detail: row.try_get(4)?,
+                        timestamp: row.try_get(5)?,
+                    })
+                })
+                .collect()
+        })
+        .map_err(data_source)
+    }
+
+    fn replace_pool_utxos(&self, utxos: &[PoolUtxo]) -> Result<(), RegistryError> {
+        self.write(|tx| {
+            let mut reserved = HashMap::new();
+            for row in tx.query(
+                "SELECT txid, vout, reserved_by FROM pool_utxos WHERE reserved_by IS NOT NULL",
+                &[],
+            )? {
+                let key: (String, i64) = (row.try_get(0)?, row.try_get(1)?);
+                reserved.insert(key, row.try_get::<_, String>(2)?);
+            }
+            tx.execute("DELETE FROM pool_utxos", &[])?;
+            for utxo in utxos {
+                let key = (
+                    utxo.outpoint.txid.to_string(),
+                    i64::from(utxo.outpoint.vout),
+                );
+                tx.execute(
+                    "INSERT INTO pool_utxos (txid, vout, utxo_json, reserved_by) \
+                     VALUES ($1, $2, $3, $4)",
+                    &[&key.0, &key.1, &pool_utxo_json(utxo)?, &reserved.get(&key)],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    fn pool_utxos(&self) -> Result<Vec<PoolUtxo>, RegistryError> {
+        self.read(|client| {
+            client
+                .query(
+                    "SELECT utxo_json FROM pool_utxos WHERE reserved_by IS NULL ORDER BY id ASC",
+                    &[],
+                )?
+                .iter()
+                .map(|row| pool_utxo_from_json(&row.try_get::<_, String>(0)?))
+                .collect()
+        })
+    }
+
+    fn reserve_pool_utxos(
+        &self,
+        payout_id: &str,
+        outpoints: &[OutPoint],
+    ) -> Result<(), RegistryError> {
+        self.write(|tx| {
+            for outpoint in outpoints {
+                let key = (outpoint.txid.to_string(), i64::from(outpoint.vout));
+                let Some(row) = tx.query_opt(
+                    "SELECT reserved_by FROM pool_utxos WHERE txid = $1 AND vout = $2 FOR UPDATE",
+                    &[&key.0, &key.1],
+                )?
+                else {
+                    continue;
+                };
+                if let Some(holder) = row.try_get::<_, Option<String>>(0)? {
+                    if holder != payout_id {
+                        return Err(RegistryError::UtxoReserved {
+                            outpoint: *outpoint,
+                            payout_id: holder,
+                        });
+                    }
+                }
+                tx.execute(
+                    "UPDATE pool_utxos SET reserved_by = $3 WHERE txid = $1 AND vout = $2",
+                    &[&key.0, &key.1, &payout_id],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    fn release_pool_utxos(&self, payout_id: &str) -> Result<usize, RegistryError> {
+        self.write(|tx| {
+            let released = tx.execute(
+                "UPDATE pool_utxos SET reserved_by = NULL WHERE reserved_by = $1",
+                &[&payout_id],
+            )?;
+            Ok(released as usize)
+        })
+    }
+
+    /// A session-level advisory lock on its own connection, held until the
+    /// guard drops. If that session is lost, its lock goes with it and the
+    /// next call reconnects.
+    fn lock_payouts(&self) -> Result<PayoutLock<'_>, RegistryError> {
+        let mut guard = self
+            .lock_client
+            .lock()
+            .map_err(|_| RegistryError::Storage("payout lock poisoned".into()))?;
+        let slot: &mut Option<Client> = &mut guard;
+        on_worker(|| {
+            self.connected(slot)?
+                .batch_execute("SELECT pg_advisory_lock(hashtext('bdld_payouts'))")
+                .map_err(RegistryError::from)
+        })?;
+        Ok(PayoutLock::new(move || {
+            let slot: &mut Option<Client> = &mut guard;
+            on_worker(|| {
+                let unlocked = slot.as_mut().map(|client| {
+                    client.batch_execute("SELECT pg_advisory_unlock(hashtext('bdld_payouts'))")
+                });
+                if !matches!(unlocked, Some(Ok(()))) {
+                    *slot = None;
+                }
+            });
+        }))
+    }
+
+    fn register_participant(
+        &self,
+        participant_id: &str,
+        addresses: &[String],
+    ) -> Result<ParticipantRecord, RegistryError> {
+        let addresses = check_registration(participant_id, addresses, self.network)?;
+        self.write(|tx| {
+            if participant_active(tx, participant_id)?.is_some() {
+                return Err(RegistryError::ParticipantExists(participant_id.to_string()));
+            }
+            tx.execute(
+                "INSERT INTO participants (participant_id, active, registered_at) \
+                 VALUES ($1, TRUE, $2)",
+                &[&participant_id, &Utc::now().to_rfc3339()],
+            )?;
+            for (position, address) in (0u32..).zip(&addresses) {
+                insert_address(tx, participant_id, address, position)?;
+            }
+            append_audit(
+                tx,
+                &AuditEvent::ParticipantRegistered {
+                    participant_id: participant_id.to_string(),
+                    addresses: addresses.clone(),
+                },
+            )?;
+            load_participant(tx, participant_id)
+        })
+    }
+
+    fn add_participant_address(
+        &self,
+        participant_id: &str,
+        address: &str,
+        position: Option<u32>,
+    ) -> Result<ParticipantRecord, RegistryError> {
+        let address = check_address(address, self.network)?;
+        self.write(|tx| {
+            ensure_active(tx, participant_id)?;
+            let position = match position {
+                Some(position) => position,
+                None => {
+                    let next: i64 = tx
+                        .query_one(
+                            "SELECT COALESCE(MAX(position) + 1, 0) FROM participant_addresses \
+                             WHERE participant_id = $1",
+                            &[&participant_id],
+                        )?
+                        .try_get(0)?;
+                    u32::try_from(next).map_err(|e| RegistryError::Storage(e.to_string()))?
+                }
+            };
+            insert_address(tx, participant_id, &address, position)?;
+            append_audit(
+                tx,
+                &AuditEvent::ParticipantAddressAdded {
+                    participant_id: participant_id.to_string(),
+                    address: address.clone(),
+                    position,
+                },
+            )?;
+            load_participant(tx, participant_id)
+        })
+    }
+
+    fn remove_participant_address(
+        &self,
+        participant_id: &str,
+        address: &str,
+    ) -> Result<ParticipantRecord, RegistryError> {
+        let address = stored_address(address, self.network);
+        self.write(|tx| {
+            ensure_active(tx, participant_id)?;
+            let removed = tx.execute(
+                "DELETE FROM participant_addresses WHERE participant_id = $1 AND address = $2",
+                &[&participant_id, &address],
+            )?;
+            if removed == 0 {
+                return Err(RegistryError::AddressNotFound {
+                    participant_id: participant_id.to_string(),
+                    address: address.clone(),
+                });
+            }
+            append_audit(
+                tx,
+                &AuditEvent::ParticipantAddressRemoved {
+                    participant_id: participant_id.to_string(),
+                    address: address.clone(),
+                },
+            )?;
+            load_participant(tx, participant_id)
+        })
+    }
+
+    fn deactivate_participant(
+        &self,
+        participant_id: &str,
+        height: u64,
+    ) -> Result<ParticipantRecord, RegistryError> {
+        self.write(|tx| {
+            ensure_active(tx, participant_id)?;
+            tx.execute(
+                "UPDATE participants SET active = FALSE, deactivated_height = $2 \
+                 WHERE participant_id = $1",
+                &[&participant_id, &to_sql_int(height)],
+            )?;
+            append_audit(
+                tx,
+                &AuditEvent::ParticipantDeactivated {
+                    participant_id: participant_id.to_string(),
+                    effective_height: Some(height),
+                },
+            )?;
+            load_participant(tx, participant_id)
+        })
+    }
+
+    fn get_participant(
+        &self,
+        participant_id: &str,
+    ) -> Result<Option<ParticipantRecord>, RegistryError> {
+        self.read(|client| match load_participant(client, participant_id) {
+            Ok(record) => Ok(Some(record)),
+            Err(RegistryError::ParticipantNotFound(_)) => Ok(None),
+            Err(err) => Err(err),
+        })
+    }
+
+    fn list_participants(
+        &self,
+        page: u32,
+        page_size: u32,
+        include_inactive: bool,
+    ) -> Result<ParticipantPage, RegistryError> {
+        self.read(|client| {
+            let mut tx = client.build_transaction().read_only(true).start()?;
+            let filter = if include_inactive { "" } else { "WHERE active" };
+            let total: i64 = tx
+                .query_one(&format!("SELECT COUNT(*) FROM participants {filter}"), &[])?
+                .try_get(0)?;
+            let ids = tx
+                .query(
+                    &format!(
+                        "SELECT participant_id FROM participants {filter} \
+                         ORDER BY participant_id ASC LIMIT $1 OFFSET $2"
+                    ),
+                    &[
+                        &i64::from(page_size),
+                        &(i64::from(page) * i64::from(page_size)),
+                    ],
+                )?
+                .iter()
+                .map(|row| row.try_get::<_, String>(0))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let participants = ids
+                .iter()
+                .map(|id| load_participant(&mut tx, id))
+                .collect::<Result<Vec<_>, _>>()?;
+            tx.commit()?;
+            Ok(ParticipantPage {
+                participants,
+                total_count: total as usize,
+            })
+        })
+    }
+
+    fn record_stake(
+        &self,
+        participant_id: &str,
+        outpoint: OutPoint,
+        amount_sats: u64,
+        witness_script: &Script,
+    ) -> Result<StakePosition, RegistryError> {
+        let lock_height = check_stake(amount_sats, witness_script)?;
+
+        self.write(|tx| {
+            ensure_active(tx, participant_id)?;
+            if load_stake(tx, &outpoint)?.is_some() {
+                return Err(RegistryError::StakeExists(outpoint));
+            }
+            tx.execute(
+                "INSERT INTO stakes (
+                    txid, vout, participant_id, amount_sats, lock_height, witness_script_hex,
+                    verification, recorded_at
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                &[
+                    &outpoint.txid.to_string(),
+                    &i64::from(outpoint.vout),
+                    &participant_id,
+                    &to_sql_int(amount_sats),
+                    &i64::from(lock_height),
+                    &hex::encode(witness_script.as_bytes()),
+                    &StakeVerification::Pending.to_string(),
+                    &Utc::now().to_rfc3339(),
+                ],
+            )?;
+            append_audit(
+                tx,
+                &AuditEvent::StakeRecorded {
+                    outpoint: outpoint.to_string(),
+                    participant_id: participant_id.to_string(),
+                    amount_sats,
+                    lock_height,
+                },
+            )?;
+            require_stake(tx, &outpoint)
+        })
+    }
+
+    fn verify_stake(
+        &self,
+        outpoint: &OutPoint,
+        confirmed_height: u64,
+    ) -> Result<StakePosition, RegistryError> {
+        self.update_stake(
+            outpoint,
+            StakeVerification::Verified,
+            Some(confirmed_height),
+        )
+    }
+
+    fn reject_stake(&self, outpoint: &OutPoint) -> Result<StakePosition, RegistryError> {
+        self.update_stake(outpoint, StakeVerification::Rejected, None)
+    }
+
+    fn mark_stake_spent(
+        &self,
+        outpoint: &OutPoint,
+        spent_height: u64,
+    ) -> Result<StakePosition, RegistryError> {
+        self.write(|tx| {
+            check_spend(&require_stake(tx, outpoint)?, spent_height)?;
+            tx.execute(
+                "UPDATE stakes SET spent_height = $3 WHERE txid = $1 AND vout = $2",
+                &[
+                    &outpoint.txid.to_string(),
+                    &i64::from(outpoint.vout),
+                    &to_sql_int(spent_height),
+                ],
+            )?;
+            audit_stake_update(tx, outpoint)
+        })
+    }
+
+    fn stakes_for(&self, participant_id: &str) -> Result<Vec<StakePosition>, RegistryError> {
+        self.read(|client| {
+            client
+                .query(
+                    &format!(
+                        "SELECT {STAKE_SELECT_COLUMNS} FROM stakes \
+                         WHERE participant_id = $1 ORDER BY id ASC"
+                    ),
+                    &[&participant_id],
+                )?
+                .iter()
+                .map(stake_from_row)
+                .collect()
+        })
+    }
+
+    fn record_trust(
+        &self,
+        participant_id: &str,
+        effective_height: u64,
+        trust_coefficient: f64,
+    ) -> Result<(), RegistryError> {
+        check_trust(trust_coefficient)?;
+        self.write(|tx| {
+            ensure_active(tx, participant_id)?;
+            tx.execute(
+                "INSERT INTO participant_trust (
+                    participant_id, effective_height, trust_coefficient, recorded_at
+                ) VALUES ($1, $2, $3, $4)
+                ON CONFLICT (participant_id, effective_height) DO UPDATE SET
+                    trust_coefficient = EXCLUDED.trust_coefficient,
+                    recorded_at = EXCLUDED.recorded_at",
+                &[
+                    &participant_id,
+                    &to_sql_int(effective_height),
+                    &trust_coefficient,
+                    &Utc::now().to_rfc3339(),
+                ],
+            )?;
+            append_audit(
+                tx,
+                &AuditEvent::ParameterChanged {
+                    parameter: "trust_coefficient".into(),
+                    value: trust_coefficient.to_string(),
+                    participant_id: Some(participant_id.to_string()),
+                    effective_height: Some(effective_height),
+                },
+            )?;
+            Ok(())
+        })
+    }
+
+    fn participant_snapshots(
+        &self,
+        height: u64,
+    ) -> Result<Vec<ParticipantSnapshot>, RegistryError> {
+        self.read(|client| {
+            client
+                .query(
+                    "SELECT p.participant_id, SUM(s.amount_sats)::BIGINT,
+                        COALESCE((
+                            SELECT t.trust_coefficient FROM participant_trust t
+                            WHERE t.participant_id = p.participant_id
+                                AND t.effective_height <= $1
+                            ORDER BY t.effective_height DESC LIMIT 1
+                        ), 1.0)
+                     FROM participants p
+                     JOIN stakes s ON s.participant_id = p.participant_id
+                     WHERE (p.active OR p.deactivated_height > $1)
+                        AND s.verification = 'verified'
+                        AND s.confirmed_height <= $1
+                        AND (s.spent_height IS NULL OR s.spent_height > $1)
+                     GROUP BY p.participant_id
+                     ORDER BY p.participant_id ASC",
+                    &[&to_sql_int(height)],
+                )?
+                .iter()
+                .map(|row| {
+                    Ok(ParticipantSnapshot {
+                        participant_id: row.try_get(0)?,
+                        stake_amount_sats: get_u64(row, 1)?,
+                        trust_coefficient: row.try_get(2)?,
+                    })
+                })
+                .collect()
+        })
+    }
+
+    fn record_audit_event(&self, event: &AuditEvent) -> Result<AuditEntry, RegistryError> {
+        self.write(|tx| append_audit(tx, event))
+    }
+
+    fn record_parameter(&self, parameter: &str, value: &str) -> Result<bool, RegistryError> {
+        self.write(|tx| {
+            let last: Option<String> = tx
+                .query_opt(
+                    "SELECT payload_json::jsonb ->> 'value' FROM audit_log \
+                     WHERE event_type = 'parameter_changed' \
+                       AND payload_json::jsonb ->> 'parameter' = $1 \
+                       AND payload_json::jsonb ->> 'participant_id' IS NULL \
+                     ORDER BY seq DESC LIMIT 1",
+                    &[&parameter],
+                )?
+                .map(|row| row.try_get(0))
+                .transpose()?;
+            if last.as_deref() == Some(value) {
+                return Ok(false);
+            }
+            append_audit(
+                tx,
+                &AuditEvent::ParameterChanged {
+                    parameter: parameter.to_string(),
+                    value: value.to_string(),
+                    participant_id: None,
+                    effective_height: None,
+                },
+            )?;
+            Ok(true)
+        })
+    }
+
+    fn audit_entries(&self, after_seq: u64, limit: u32) -> Result<Vec<AuditEntry>, RegistryError> {
+        self.read(|client| {
+            client
+                .query(
+                    &format!(
+                        "SELECT {AUDIT_SELECT_COLUMNS} FROM audit_log \
+                         WHERE seq > $1 ORDER BY seq ASC LIMIT $2"
+                    ),
+                    &[&to_sql_int(after_seq), &i64::from(limit)],
+                )?
+                .iter()
+                .map(audit_entry_from_row)
+                .collect()
+        })
+    }
+}
+
+impl ParticipantRegistry for PostgresParticipantRegistry {
+    fn addresses_for(&self, participant_id: &str) -> Result<Vec<String>, VelocityError> {
+        let addresses = self
+            .read(|client| {
+                client
+                    .query(
+                        "SELECT a.address FROM participant_addresses a \
+                         JOIN participants p ON p.participant_id = a.participant_id \
+                         WHERE a.participant_id = $1 AND p.active \
+                         ORDER BY a.position ASC, a.address ASC",
+                        &[&participant_id],
+                    )?
+                    .iter()
+                    .map(|row| Ok(row.try_get(0)?))
+                    .collect::<Result<Vec<String>, RegistryError>>()
+            })
+            .map_err(data_source)?;
+
+        if addresses.is_empty() {
+            return Err(VelocityError::ParticipantNotFound);
+        }
+
+        Ok(addresses)
+    }
+}
+
+impl PayoutHistory for PostgresParticipantRegistry {
+    /// Time of the earliest single payout to `address` that reached the
+    /// network; batch transactions are skipped.
+    fn first_seen(&self, address: &str) -> Result<Option<DateTime<Utc>>, VelocityError> {
+        let address = stored_address(address, self.network);
+        let earliest: Option<String> = self
+            .read(|client| {
+                Ok(client
+                    .query_one(
+                        "SELECT MIN(p.timestamp) FROM payouts p \
+                         JOIN payout_outputs o ON o.payout_id = p.payout_id \
+                         WHERE o.address = $1 AND NOT p.is_dry_run AND p.batch_id IS NULL \
+                         AND p.status IN ('broadcast', 'confirmed', 'completed', 'replaced')",
+                        &[&address],
+                    )?
+                    .try_get(0)?)
+            })
+            .map_err(data_source)?;
+
+        earliest
+            .map(|ts| {
+                DateTime::parse_from_rfc3339(&ts)
+                    .map(|t| t.with_timezone(&Utc))
+                    .map_err(|e| VelocityError::DataSource(format!("bad payout timestamp: {e}")))
+            })
+            .transpose()
+    }
+
+    /// Mirrors [`counts_toward_velocity`](crate::disbursement::counts_toward_velocity). Timestamps are compared as the
+    /// RFC 3339 UTC strings the payouts are stored with. A recipient's
+    /// volume sums its rows in `payout_outputs`, batch outputs included.
+    fn volume_since(
+        &self,
+        recipient: Option<&str>,
+        since: DateTime<Utc>,
+    ) -> Result<PayoutVolume, VelocityError> {
+        const COUNTED: &str = "NOT p.is_dry_run AND p.fee_bump_of_json IS NULL \
+             AND p.status NOT IN ('pending', 'rejected', 'failed') AND p.timestamp >= $1";
+        let recipient = recipient.map(|r| stored_address(r, self.network));
+        let (count, sats): (i64, i64) = self
+            .read(|client| {
+                let row = match &recipient {
+                    None => client.query_one(
+                        &format!(
+                            "SELECT COUNT(*), COALESCE(SUM(p.amount_sats), 0)::BIGINT \
+                             FROM payouts p WHERE {COUNTED}"
+                        ),
+                        &[&since.to_rfc3339()],
+                    )?,
+                    Some(recipient) => client.query_one(
+                        &format!(
+                            "SELECT COUNT(DISTINCT p.payout_id), \
+                             COALESCE(SUM(o.amount_sats), 0)::BIGINT \
+                             FROM payouts p JOIN payout_outputs o ON o.payout_id = p.payout_id \
+                             WHERE {COUNTED} AND o.address = $2"
+                        ),
+                        &[&since.to_rfc3339(), recipient],
+                    )?,
+                };
+                Ok((row.try_get(0)?, row.try_get(1)?))
+            })
+            .map_err(data_source)?;
+
+        Ok(PayoutVolume {
+            count: count as u64,
+            sats: sats as u64,
+        })
+    }
+}
+
+impl ChangeIndexStore for PostgresParticipantRegistry {
+    fn next_change_index(&self) -> Result<u32, RegistryError> {
+        self.read(|client| {
+            client
+                .query_opt(
+                    "SELECT value FROM pool_counters WHERE name = 'change_index'",
+                    &[],
+                )?
+                .map_or(Ok(0), |row| get_u32(&row, 0))
+        })
+    }
+
+    /// One upsert, so replicas sharing the database never reserve the same
+    /// index.
+    fn reserve_change_index(&self) -> Result<u32, RegistryError> {
+        self.read(|client| {
+            let row = client.query_one(
+                "INSERT INTO pool_counters (name, value) VALUES ('change_index', 1) \
+                 ON CONFLICT (name) DO UPDATE SET value = pool_counters.value + 1 \
+                 RETURNING value - 1",
+                &[],
+            )?;
+            get_u32(&row, 0)
+        })
+    }
+}
+
+/// `Some(active)` for a registered participant.
+fn participant_active(
+    client: &mut impl GenericClient,
+    participant_id: &str,
+) -> Result<Option<bool>, RegistryError> {
+    Ok(client
+        .query_opt(
+            "SELECT active FROM participants WHERE participant_id = $1",
+            &[&participant_id],
+        )?
+        .map(|row| row.try_get(0))
+        .transpose()?)
+}
+
+fn ensure_active(
+    client: &mut impl GenericClient,
+    participant_id: &str,
+) -> Result<(), RegistryError> {
+    match participant_active(client, participant_id)? {
+        Some(true) => Ok(()),
+        Some(false) => Err(RegistryError::ParticipantInactive(
+            participant_id.to_string(),
+        )),
+        None => Err(RegistryError::ParticipantNotFound(
+            participant_id.to_string(),
+        )),
+    }
+}
+
+/// Inserts an address after checking it is not registered to anyone,
+/// including `participant_id` itself, and that its position is free.
+fn insert_address(
+    client: &mut impl GenericClient,
+    participant_id: &str,
+    address: &str,
+    position: u32,
+) -> Result<(), RegistryError> {
+    let owner: Option<String> = client
+        .query_opt(
+            "SELECT participant_id FROM participant_addresses WHERE address = $1",
+            &[&address],
+        )?
+        .map(|row| row.try_get(0))
+        .transpose()?;
+    if let Some(owner) = owner {
+        return Err(RegistryError::AddressInUse {
+            address: address.to_string(),
+            participant_id: owner,
+        });
+    }
+    let occupied: bool = client
+        .query_one(
+            "SELECT EXISTS (SELECT 1 FROM participant_addresses \
+             WHERE participant_id = $1 AND position = $2)",
+            &[&participant_id, &i64::from(position)],
+        )?
+        .try_get(0)?;
+    if occupied {
+        return Err(RegistryError::PositionInUse {
+            participant_id: participant_id.to_string(),
+            position,
+        });
+    }
+    client.execute(
+        "INSERT INTO participant_addresses (participant_id, address, position) \
+         VALUES ($1, $2, $3)",
+        &[&participant_id, &address, &i64::from(position)],
+    )?;
+    Ok(())
+}
+
+fn load_participant(
+    client: &mut impl GenericClient,
+    participant_id: &str,
+) -> Result<ParticipantRecord, RegistryError> {
+    let row = client
+        .query_opt(
+            "SELECT active, registered_at, deactivated_height FROM participants \
+             WHERE participant_id = $1",
+            &[&participant_id],
+        )?
+        .ok_or_else(|| RegistryError::ParticipantNotFound(participant_id.to_string()))?;
+    let addresses = client
+        .query(
+            "SELECT address, position FROM participant_addresses WHERE participant_id = $1 \
+             ORDER BY position ASC, address ASC",
+            &[&participant_id],
+        )?
+        .iter()
+        .map(|row| {
+            Ok(ParticipantAddress {
+                address: row.try_get(0)?,
+                position: get_u32(row, 1)?,
+            })
+        })
+        .collect::<Result<Vec<_>, RegistryError>>()?;
+    Ok(ParticipantRecord {
+        participant_id: participant_id.to_string(),
+        active: row.try_get(0)?,
+        registered_at: row.try_get(1)?,
+        deactivated_height: get_opt_u64(&row, 2)?,
+        addresses,
+    })
+}
+
+/// Chains `event` onto the last audit entry inside the caller's transaction,
+/// which holds the audit log lock.
+fn append_audit(tx: &mut Transaction<'_>, event: &AuditEvent) -> Result<AuditEntry, RegistryError> {
+    let payload_json =
+        serde_json::to_string(event).map_err(|e| RegistryError::Storage(e.to_string()))?;
+    let (last_seq, prev_hash) = match tx.query_opt(
+        "SELECT seq, hash FROM audit_log ORDER BY seq DESC LIMIT 1",
+        &[],
+    )? {
+        Some(row) => (get_u64(&row, 0)?, row.try_get(1)?),
+        None => (0, GENESIS_HASH.to_string()),
+    };
+    let seq = last_seq + 1;
+    let recorded_at = Utc::now().to_rfc3339();
+    let hash = entry_hash(seq, &recorded_at, event.kind(), &payload_json, &prev_hash);
+    tx.execute(
+        &format!("INSERT INTO audit_log ({AUDIT_SELECT_COLUMNS}) VALUES ($1, $2, $3, $4, $5, $6)"),
+        &[
+            &to_sql_int(seq),
+            &recorded_at,
+            &event.kind(),
+            &payload_json,
+            &prev_hash,
+            &hash,
+        ],
+    )?;
+    Ok(AuditEntry {
+        seq,
+        recorded_at,
+        event_type: event.kind().to_string(),
+        payload_json,
+        prev_hash,
+        hash,
+    })
+}
+
+fn audit_stake_update(
+    tx: &mut Transaction<'_>,
+    outpoint: &OutPoint,
+) -> Result<StakePosition, RegistryError> {
+    let stake = require_stake(tx, outpoint)?;
+    append_audit(tx, &stake_updated_event(&stake))?;
+    Ok(stake)
+}
+
+fn audit_entry_from_row(row: &Row) -> Result<AuditEntry, RegistryError> {
+    Ok(AuditEntry {
+        seq: get_u64(row, 0)?,
+        recorded_at: row.try_get(1)?,
+        event_type: row.try_get(2)?,
+        payload_json: row.try_get(3)?,
+        prev_hash: row.try_get(4)?,
+        hash: row.try_get(5)?,
+    })
+}
+
+fn stake_from_row(row: &Row) -> Result<StakePosition, RegistryError> {
+    let invalid =
+        |column: &str, e: String| RegistryError::Storage(format!("invalid stake {column}: {e}"));
+    let txid: String = row.try_get(0)?;
+    let script_hex: String = row.try_get(5)?;
+    let verification: String = row.try_get(6)?;
+    Ok(StakePosition {
+        outpoint: OutPoint {
+            txid: Txid::from_str(&txid).map_err(|e| invalid("txid", e.to_string()))?,
+            vout: get_u32(row, 1)?,
+        },
+        participant_id: row.try_get(2)?,
+        amount_sats: get_u64(row, 3)?,
+        lock_height: get_u32(row, 4)?,
+        witness_script: ScriptBuf::from_bytes(
+            hex::decode(script_hex).map_err(|e| invalid("witness script", e.to_string()))?,
+        ),
+        verification: verification
+            .parse()
+            .map_err(|e| invalid("verification", e))?,
+        confirmed_height: get_opt_u64(row, 7)?,
+        spent_height: get_opt_u64(row, 8)?,
+    })
+}
+
+fn load_stake(
+    client: &mut impl GenericClient,
+    outpoint: &OutPoint,
+) -> Result<Option<StakePosition>, RegistryError> {
+    client
+        .query_opt(
+            &format!("SELECT {STAKE_SELECT_COLUMNS} FROM stakes WHERE txid = $1 AND vout = $2"),
+            &[&outpoint.txid.to_string(), &i64::from(outpoint.vout)],
+        )?
+        .map(|row| stake_from_row(&row))
+        .transpose()
+}
+
+fn require_stake(
+    client: &mut impl GenericClient,
+    outpoint: &OutPoint,
+) -> Result<StakePosition, RegistryError> {
+    load_stake(client, outpoint)?.ok_or(RegistryError::StakeNotFound(*outpoint))
+}
+
+fn payout_from_row(row: &Row) -> Result<PayoutTransactionResult, RegistryError> {
+    let trust_audit_json: String = row.try_get(9)?;
+    let trust_audit =
+        serde_json::from_str(&trust_audit_json).unwrap_or_else(|_| AileeTrustAudit::unrecorded());
+
+    Ok(PayoutTransactionResult {
+        payout_id: row.try_get(0)?,
+        recipient_address: row.try_get(1)?,
+        amount_sats: get_u64(row, 2)?,
+        fee_sats: get_u64(row, 3)?,
+        status: parse_status(row, 4)?,
+        psbt_base64: row.try_get(5)?,
+        raw_tx_hex: row.try_get(6)?,
+        txid: row.try_get(7)?,
+        timestamp: row.try_get(8)?,
+        trust_audit,
+        is_dry_run: row.try_get(10)?,
+        confirmations: get_u32(row, 11)?,
+        signed_psbt_base64: row.try_get(12)?,
+        final_tx_hex: row.try_get(13)?,
+        fee_bump_of: json_column(row, 14)?,
+        fee_bumped_by: json_column(row, 15)?,
+        signing: json_column(row, 16)?,
+        batch_id: row.try_get(17)?,
+    })
+}
+
+fn json_column<T: serde::de::DeserializeOwned>(
+    row: &Row,
+    idx: usize,
+) -> Result<Option<T>, RegistryError> {
+    let json: Option<String> = row.try_get(idx)?;
+    json.map(|json| serde_json::from_str(&json))
+        .transpose()
+        .map_err(|e| RegistryError::Storage(format!("invalid JSON in column {idx}: {e}")))
+}
+
+fn parse_status(row: &Row, idx: usize) -> Result<PayoutStatus, RegistryError> {
+    let status: String = row.try_get(idx)?;
+    status.parse().map_err(RegistryError::Storage)
+}
+
+fn get_opt_u64(row: &Row, idx: usize) -> Result<Option<u64>, RegistryError> {
+    let value: Option<i64> = row.try_get(idx)?;
+    value
+        .map(|v| u64::try_from(v).map_err(|e| RegistryError::Storage(e.to_string())))
+        .transpose()
+}
+
+fn get_u64(row: &Row, idx: usize) -> Result<u64, RegistryError> {
+    let value: i64 = row.try_get(idx)?;
+    u64::try_from(value).map_err(|e| RegistryError::Storage(e.to_string()))
+}
+
+fn get_u32(row: &Row, idx: usize) -> Result<u32, RegistryError> {
+    let value: i64 = row.try_get(idx)?;
+    u32::try_from(value).map_err(|e| RegistryError::Storage(e.to_string()))
+}
+
+/// Schema version this binary writes, stored in `schema_version`.
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// One up-migration; its version is its position in [`MIGRATIONS`], 1-based.
+struct Migration {
+    description: &'static str,
+    sql: &'static str,
+}
+
+/// Ordered up-migrations. Append only: never edit or reorder a shipped entry.
+///
+/// Text columns that are compared or sorted use the "C" collation, so
+/// ordering matches the SQLite registry byte for byte.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        description: "registry, payouts and audit log",
+        sql: r#"
+        CREATE TABLE participants (
+            participant_id TEXT COLLATE "C" PRIMARY KEY,
+            active BOOLEAN NOT NULL DEFAULT TRUE,
+            registered_at TEXT
+        );
+        CREATE TABLE participant_addresses (
+            participant_id TEXT COLLATE "C" NOT NULL REFERENCES participants(participant_id),
+            address TEXT COLLATE "C" NOT NULL UNIQUE,
+            position BIGINT NOT NULL,
+            PRIMARY KEY (participant_id, address)
+        );
+        CREATE TABLE payouts (
+            payout_id TEXT PRIMARY KEY,
+            recipient_address TEXT NOT NULL,
+            amount_sats BIGINT NOT NULL,
+            fee_sats BIGINT NOT NULL,
+            status TEXT NOT NULL,
+            psbt_base64 TEXT NOT NULL,
+            raw_tx_hex TEXT NOT NULL,
+            txid TEXT NOT NULL,
+            timestamp TEXT COLLATE "C" NOT NULL,
+            trust_audit_json TEXT NOT NULL,
+            is_dry_run BOOLEAN NOT NULL,
+            confirmations BIGINT NOT NULL DEFAULT 0,
+            signed_psbt_base64 TEXT,
+            final_tx_hex TEXT,
+            fee_bump_of_json TEXT,
+            fee_bumped_by_json TEXT,
+            signing_json TEXT
+        );
+        CREATE INDEX payouts_recipient ON payouts (recipient_address);
+        CREATE TABLE payout_transitions (
+            id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+            payout_id TEXT NOT NULL,
+            from_status TEXT NOT NULL,
+            to_status TEXT NOT NULL,
+            confirmations BIGINT NOT NULL,
+            detail TEXT,
+            timestamp TEXT NOT NULL
+        );
+        CREATE INDEX payout_transitions_payout ON payout_transitions (payout_id);
+        CREATE TABLE stakes (
+            id BIGINT GENERATED ALWAYS AS IDENTITY UNIQUE,
+            txid TEXT NOT NULL,
+            vout BIGINT NOT NULL,
+            participant_id TEXT COLLATE "C" NOT NULL REFERENCES participants(participant_id),
+            amount_sats BIGINT NOT NULL,
+            lock_height BIGINT NOT NULL,
+            witness_script_hex TEXT NOT NULL,
+            verification TEXT NOT NULL,
+            confirmed_height BIGINT,
+            spent_height BIGINT,
+            recorded_at TEXT NOT NULL,
+            PRIMARY KEY (txid, vout)
+        );
+        CREATE INDEX stakes_participant ON stakes (participant_id);
+        CREATE TABLE participant_trust (
+            participant_id TEXT COLLATE "C" NOT NULL REFERENCES participants(participant_id),
+            effective_height BIGINT NOT NULL,
+            trust_coefficient DOUBLE PRECISION NOT NULL,
+            recorded_at TEXT NOT NULL,
+            PRIMARY KEY (participant_id, effective_height)
+        );
+        CREATE TABLE audit_log (
+            seq BIGINT PRIMARY KEY,
+            recorded_at TEXT NOT NULL,
+            event_type TEXT NOT NULL,
+            payload_json TEXT NOT NULL,
+            prev_hash TEXT NOT NULL,
+            hash TEXT NOT NULL
+        );
+        CREATE FUNCTION audit_log_append_only() RETURNS trigger AS $$
+        BEGIN
+            RAISE EXCEPTION 'audit_log is append-only';
+        END;
+        $$ LANGUAGE plpgsql;
+        CREATE TRIGGER audit_log_no_update BEFORE UPDATE OR DELETE ON audit_log
+            FOR EACH ROW EXECUTE FUNCTION audit_log_append_only();
+        CREATE TRIGGER audit_log_no_truncate BEFORE TRUNCATE ON audit_log
+            FOR EACH STATEMENT EXECUTE FUNCTION audit_log_append_only();
+    "#,
+    },
+    Migration {
+        description: "pool UTXO set",
+        sql: r#"
+        CREATE TABLE pool_utxos (
+            id BIGINT GENERATED ALWAYS AS IDENTITY UNIQUE,
+            txid TEXT NOT NULL,
+            vout BIGINT NOT NULL,
+            utxo_json TEXT NOT NULL,
+            PRIMARY KEY (txid, vout)
+        );
+    "#,
+    },
+    Migration {
+        description: "batch payout records",
+        sql: "ALTER TABLE payouts ADD COLUMN batch_id TEXT;",
+    },
+    Migration {
+        description: "pool counters",
+        sql: r#"
+        CREATE TABLE pool_counters (
+            name TEXT PRIMARY KEY,
+            value BIGINT NOT NULL
+        );
+    "#,
+    },
+    // Bech32 addresses are registered lower-case; participants with shared
+    // positions are renumbered in their existing order
+    Migration {
+        description: "canonical addresses and unique positions",
+        sql: r#"
+        UPDATE participant_addresses SET address = lower(address)
+            WHERE lower(address) ~ '^(bc|tb|bcrt)1';
+        UPDATE participant_addresses p SET position = r.position
+            FROM (
+                SELECT participant_id, address,
+                    MIN(position) OVER (PARTITION BY participant_id)
+                        + ROW_NUMBER() OVER (
+                            PARTITION BY participant_id ORDER BY position, address
+                        ) - 1 AS position
+                FROM participant_addresses
+            ) r
+            WHERE p.participant_id = r.participant_id AND p.address = r.address
+              AND p.participant_id IN (
+                SELECT participant_id FROM participant_addresses
+                GROUP BY participant_id, position HAVING COUNT(*) > 1
+              );
+        CREATE UNIQUE INDEX participant_addresses_position
+            ON participant_addresses (participant_id, position);
+    "#,
+    },
+    Migration {
+        description: "participant deactivation height",
+        sql: "ALTER TABLE participants ADD COLUMN deactivated_height BIGINT;",
+    },
+    Migration {
+        description: "pool UTXO reservations",
+        sql: "ALTER TABLE pool_utxos ADD COLUMN reserved_by TEXT;",
+    },
+    // Earlier batch records keep only their first recipient, so only
+    // single payouts are backfilled.
+    Migration {
+        description: "payout outputs by recipient",
+        sql: r#"
+        CREATE TABLE payout_outputs (
+            payout_id TEXT NOT NULL,
+            position BIGINT NOT NULL,
+            address TEXT NOT NULL,
+            amount_sats BIGINT NOT NULL,
+            PRIMARY KEY (payout_id, position)
+        );
+        CREATE INDEX payout_outputs_address ON payout_outputs (address);
+        INSERT INTO payout_outputs (payout_id, position, address, amount_sats)
+            SELECT payout_id, 0,
+                   CASE WHEN lower(recipient_address) ~ '^(bc|tb|bcrt)1'
+                        THEN lower(recipient_address) ELSE recipient_address END,
+                   amount_sats
+            FROM payouts WHERE batch_id IS NULL;
+    "#,
+    },
+];
+
+/// Applies every migration above the stored version in one transaction,
+/// under an advisory lock so concurrently starting nodes wait their turn.
+fn migrate(client: &mut Client) -> Result<(), VelocityError> {
+    let failed = |e: postgres::Error| {
+        VelocityError::DataSource(format!("migration failed: {}", describe(&e)))
+    };
+    let mut tx = client.transaction().map_err(failed)?;
+    tx.batch_execute(
+        "SELECT pg_advisory_xact_lock(hashtext('bdld_registry_schema'));
+         CREATE TABLE IF NOT EXISTS schema_version (version BIGINT NOT NULL);",
+    )
+    .map_err(failed)?;
+    let current: i64 = match tx
+        .query_opt("SELECT version FROM schema_version", &[])
+        .map_err(failed)?
+    {
+        Some(row) => row.try_get(0).map_err(failed)?,
+        None => {
+            tx.execute("INSERT INTO schema_version (version) VALUES (0)", &[])
+                .map_err(failed)?;
+            0
+        }
+    };
+    if current > i64::from(SCHEMA_VERSION) {
+        return Err(VelocityError::InvalidData(format!(
+            "database schema version {current} is newer than supported version {SCHEMA_VERSION}"
+        )));
+    }
+
+    for (version, migration) in (1i64..).zip(MIGRATIONS).skip(current as usize) {
+        let failed = |e: postgres::Error| {
+            VelocityError::DataSource(format!(
+                "migration {version} ({}) failed: {}",
+                migration.description,
+                describe(&e)
+            ))
+        };
+        tx.batch_execute(migration.sql).map_err(failed)?;
+        tx.execute("UPDATE schema_version SET version = $1", &[&version])
+            .map_err(failed)?;
+    }
+    tx.commit().map_err(failed)
+}
@@ -1,9 +1,15 @@
 pub use crate::alerts::{evaluate_alert, AlertThresholds, RBIAlert};
+pub use crate::economic_aggregator::{
+    AggregatingEconomicProvider, AggregationConfig, AggregationMethod, OracleAggregationReport,
+};
 pub use crate::economic_oracle::{
-    EconomicDataProvider, EconomicError, MockEconomicDataProvider, RecordedEconomicProvider,
-    RecordedEconomicSnapshot,
+    CachedProvider, Clock, EconomicDataProvider, EconomicError, EconomicObservation, ManualClock,
+    MockEconomicDataProvider, RecordedEconomicProvider, RecordedEconomicSnapshot, SystemClock,
 };
+pub use crate::economic_series::{EconomicSeriesPoint, Interpolation, TimeSeriesEconomicProvider};
 pub use crate::rbi_engine::{
-    DistributionPoolState, ParticipantSnapshot, RBIEngine, RBIError, RBISnapshot, RbiStatus,
+    DistributionPoolState, EvaluationOverrides, ParticipantSnapshot, RBIEngine, RBIError,
+    RBISnapshot, RBISurface, RBISurfaceCell, RbiStatus, SweepAxis, SweepGrid, SweepInput,
 };
 pub use crate::sqlite_participant_registry::SqliteParticipantRegistry;
+pub use crate::storage::RegistryStorage;
@@ -1,5 +1,6 @@
 use crate::alerts::{evaluate_alert, AlertThresholds, RBIAlert};
-use crate::economic_oracle::{EconomicDataProvider, EconomicError};
+use crate::economic_aggregator::OracleAggregationReport;
+use crate::economic_oracle::{EconomicDataProvider, EconomicError, RecordedEconomicSnapshot};
 use crate::velocity_config::VelocityConfig;
 use chrono::{DateTime, Utc};
 use rust_decimal::prelude::ToPrimitive;
@@ -46,6 +47,9 @@ pub struct RBISnapshot {
     pub status: RbiStatus,
     pub is_healthy: bool,
     pub alert: Option<RBIAlert>,
+
+    /// Sources behind `d_s`/`productivity_a` when the provider aggregates.
+    pub oracle_report: Option<OracleAggregationReport>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
@@ -138,173 +142,415 @@ impl<P: EconomicDataProvider> RBIEngine<P> {
             ));
         }
 
-        let d_s = self.provider.demand_shock_rate()?;
-        let a = self.provider.productivity_expansion()?;
+        let observation = self.provider.observe()?;
+        let economics = RecordedEconomicSnapshot {
+            demand_shock: observation.demand_shock,
+            productivity: observation.productivity,
+        };
+
+        let mut snapshot = compute_snapshot(
+            pool_state,
+            economics,
+            &self.thresholds,
+            &self.velocity_config,
+            current_height,
+            timestamp,
+        )?;
+        snapshot.oracle_report = observation.report;
+
+        self.history.push(snapshot.clone());
+        Ok(snapshot)
+    }
+
+    /// Counterfactual evaluation: computes an RBI snapshot for the given pool
+    /// state and economic inputs without querying the provider or touching
+    /// `history`. Overrides fall back to the engine's configured values.
+    pub fn evaluate(
+        &self,
+        pool_state: &DistributionPoolState,
+        economics: RecordedEconomicSnapshot,
+        overrides: &EvaluationOverrides,
+        current_height: u64,
+        timestamp: DateTime<Utc>,
+    ) -> Result<RBISnapshot, RBIError> {
+        let thresholds = overrides.thresholds.as_ref().unwrap_or(&self.thresholds);
+        let velocity_config = overrides
+            .velocity_config
+            .as_ref()
+            .unwrap_or(&self.velocity_config);
+
+        compute_snapshot(
+            pool_state,
+            economics,
+            thresholds,
+            velocity_config,
+            current_height,
+            timestamp,
+        )
+    }
 
-        if !d_s.is_finite() || d_s < 0.0 {
-            return Err(RBIError::InvalidState("d_s must be finite and >= 0".into()));
+    /// Evaluates RBI over a one- or two-dimensional grid of inputs.
+    ///
+    /// Each cell starts from `base_state`/`economics` and replaces the swept
+    /// inputs. Cells that fail to evaluate carry the error instead of
+    /// aborting the sweep, so a single invalid combination does not hide the
+    /// rest of the surface.
+    pub fn sweep(
+        &self,
+        base_state: &DistributionPoolState,
+        economics: RecordedEconomicSnapshot,
+        overrides: &EvaluationOverrides,
+        grid: &SweepGrid,
+        current_height: u64,
+        timestamp: DateTime<Utc>,
+    ) -> Result<RBISurface, RBIError> {
+        let x_axis = &grid.x;
+        let y_axis = grid.y.as_ref();
+        if x_axis.values.is_empty() {
+            return Err(RBIError::InvalidState("sweep x axis has no values".into()));
         }
-        if !a.is_finite() {
-            return Err(RBIError::InvalidState("A must be finite".into()));
+        if let Some(y) = y_axis {
+            if y.values.is_empty() {
+                return Err(RBIError::InvalidState("sweep y axis has no values".into()));
+            }
+            if y.input == x_axis.input {
+                return Err(RBIError::InvalidState(
+                    "sweep axes must vary different inputs".into(),
+                ));
+            }
         }
 
-        let total_stake: u64 = pool_state
-            .participants
-            .iter()
-            .map(|p| p.stake_amount_sats)
-            .sum();
-        if pool_state.participants.is_empty() || total_stake == 0 {
-            let snapshot = RBISnapshot {
-                timestamp,
-                block_height: current_height,
-                v_dld: 0.0,
-                t_c: 1.0,
-                d_s,
-                productivity_a: a,
-                rbi_value: 0.0,
-                status: RbiStatus::Indeterminate,
-                is_healthy: false,
-                alert: None,
-            };
-            self.history.push(snapshot.clone());
-            return Ok(snapshot);
+        let y_values: Vec<Option<f64>> = match y_axis {
+            Some(y) => y.values.iter().copied().map(Some).collect(),
+            None => vec![None],
+        };
+
+        let mut cells = Vec::with_capacity(x_axis.values.len() * y_values.len());
+        for y in &y_values {
+            for &x in &x_axis.values {
+                let mut state = base_state.clone();
+                let mut econ = economics;
+                let applied =
+                    x_axis
+                        .input
+                        .apply(x, &mut state, &mut econ)
+                        .and_then(|()| match (y_axis, y) {
+                            (Some(y_axis), Some(y)) => {
+                                y_axis.input.apply(*y, &mut state, &mut econ)
+                            }
+                            _ => Ok(()),
+                        });
+
+                let evaluated = applied.and_then(|()| {
+                    self.evaluate(&state, econ, overrides, current_height, timestamp)
+                });
+                let cell = match evaluated {
+                    Ok(snapshot) => RBISurfaceCell {
+                        x,
+                        y: *y,
+                        rbi_value: Some(snapshot.rbi_value),
+                        status: Some(snapshot.status),
+                        is_healthy: snapshot.is_healthy,
+                        error: None,
+                    },
+                    Err(err) => RBISurfaceCell {
+                        x,
+                        y: *y,
+                        rbi_value: None,
+                        status: None,
+                        is_healthy: false,
+                        error: Some(err.to_string()),
+                    },
+                };
+                cells.push(cell);
+            }
         }
 
-        let v_dld = self.calculate_dld_velocity(pool_state)?;
-        let t_c = self.calculate_system_trust(pool_state)?;
+        Ok(RBISurface {
+            x_input: x_axis.input,
+            y_input: y_axis.map(|y| y.input),
+            width: x_axis.values.len(),
+            height: y_values.len(),
+            cells,
+        })
+    }
+}
 
-        if !v_dld.is_finite() || v_dld < 0.0 {
-            return Err(RBIError::InvalidState(
-                "v_dld must be finite and >= 0".into(),
-            ));
+/// Optional per-evaluation replacements for the engine configuration.
+#[derive(Debug, Clone, Default)]
+pub struct EvaluationOverrides {
+    pub thresholds: Option<AlertThresholds>,
+    pub velocity_config: Option<VelocityConfig>,
+}
+
+/// Input that a sweep axis varies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum SweepInput {
+    /// `total_distributed_sats`; the lever for contribution-rate planning.
+    TotalDistributedSats,
+    AverageParticipantVelocity,
+    EpochDurationDays,
+    DemandShock,
+    Productivity,
+}
+
+impl SweepInput {
+    fn apply(
+        self,
+        value: f64,
+        state: &mut DistributionPoolState,
+        economics: &mut RecordedEconomicSnapshot,
+    ) -> Result<(), RBIError> {
+        if !value.is_finite() {
+            return Err(RBIError::InvalidState(format!(
+                "sweep value for {self:?} must be finite"
+            )));
         }
-        if !t_c.is_finite() || t_c <= 0.0 {
-            return Err(RBIError::InvalidState("t_c must be finite and > 0".into()));
+        match self {
+            SweepInput::TotalDistributedSats => {
+                if value < 0.0 || value > u64::MAX as f64 {
+                    return Err(RBIError::InvalidState(
+                        "total_distributed_sats sweep value out of range".into(),
+                    ));
+                }
+                state.total_distributed_sats = value.round() as u64;
+            }
+            SweepInput::AverageParticipantVelocity => {
+                state.average_participant_velocity = value;
+            }
+            SweepInput::EpochDurationDays => {
+                if value < 0.0 || value > u32::MAX as f64 {
+                    return Err(RBIError::InvalidState(
+                        "epoch_duration_days sweep value out of range".into(),
+                    ));
+                }
+                state.epoch_duration_days = value.round() as u32;
+            }
+            SweepInput::DemandShock => economics.demand_shock = value,
+            SweepInput::Productivity => economics.productivity = value,
         }
+        Ok(())
+    }
+}
 
-        if d_s.abs() < MIN_DEMAND_THRESHOLD {
-            let snapshot = RBISnapshot {
-                timestamp,
-                block_height: current_height,
-                v_dld,
-                t_c,
-                d_s,
-                productivity_a: a,
-                rbi_value: 0.0,
-                status: RbiStatus::Indeterminate,
-                is_healthy: false,
-                alert: None,
-            };
-            self.history.push(snapshot.clone());
-            return Ok(snapshot);
-        }
+/// One dimension of an RBI sweep.
+#[derive(Debug, Clone)]
+pub struct SweepAxis {
+    pub input: SweepInput,
+    pub values: Vec<f64>,
+}
 
-        // RBI = (V_DLD × T_c) / (D_s / e^A)
-        let numerator = v_dld * t_c;
+#[derive(Debug, Clone, Serialize)]
+pub struct RBISurfaceCell {
+    pub x: f64,
+    pub y: Option<f64>,
+    pub rbi_value: Option<f64>,
+    pub status: Option<RbiStatus>,
+    pub is_healthy: bool,
+    pub error: Option<String>,
+}
 
-        let exp_a = a.exp(); // e^A
-        if !exp_a.is_finite() || exp_a <= 0.0 {
-            return Err(RBIError::Calculation("e^A invalid".into()));
-        }
+/// Axes of a sweep; `y` is optional for one-dimensional curves.
+#[derive(Debug, Clone)]
+pub struct SweepGrid {
+    pub x: SweepAxis,
+    pub y: Option<SweepAxis>,
+}
 
-        let denominator = d_s / exp_a;
-        let mut rbi_value = numerator / denominator;
-
-        let mut status = if !rbi_value.is_finite() {
-            RbiStatus::Invalid
-        } else if rbi_value < self.thresholds.critical_low {
-            RbiStatus::Critical
-        } else if rbi_value < self.thresholds.warning_low
-            || rbi_value > self.thresholds.overheating_high
-        {
-            RbiStatus::Warning
-        } else {
-            RbiStatus::Healthy
-        };
+/// Result of a sweep; `cells` are row-major (`y` outer, `x` inner).
+#[derive(Debug, Clone, Serialize)]
+pub struct RBISurface {
+    pub x_input: SweepInput,
+    pub y_input: Option<SweepInput>,
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<RBISurfaceCell>,
+}
 
-        if !rbi_value.is_finite() {
-            status = RbiStatus::Invalid;
-            rbi_value = 0.0;
+impl RBISurface {
+    pub fn cell(&self, x_index: usize, y_index: usize) -> Option<&RBISurfaceCell> {
+        if x_index >= self.width || y_index >= self.height {
+            return None;
         }
+        self.cells.get(y_index * self.width + x_index)
+    }
+}
 
-        let is_healthy = rbi_value >= self.thresholds.warning_low
-            && !matches!(status, RbiStatus::Invalid | RbiStatus::Indeterminate);
-        let alert = if matches!(status, RbiStatus::Invalid | RbiStatus::Indeterminate) {
-            None
-        } else {
-            evaluate_alert(rbi_value, &self.thresholds)
-        };
+fn compute_snapshot(
+    pool_state: &DistributionPoolState,
+    economics: RecordedEconomicSnapshot,
+    thresholds: &AlertThresholds,
+    velocity_config: &VelocityConfig,
+    current_height: u64,
+    timestamp: DateTime<Utc>,
+) -> Result<RBISnapshot, RBIError> {
+    if pool_state.epoch_duration_days == 0 {
+        return Err(RBIError::InvalidState(
+            "epoch_duration_days must be > 0".into(),
+        ));
+    }
+
+    let d_s = economics.demand_shock;
+    let a = economics.productivity;
+
+    if !d_s.is_finite() || d_s < 0.0 {
+        return Err(RBIError::InvalidState("d_s must be finite and >= 0".into()));
+    }
+    if !a.is_finite() {
+        return Err(RBIError::InvalidState("A must be finite".into()));
+    }
+
+    let total_stake: u64 = pool_state
+        .participants
+        .iter()
+        .map(|p| p.stake_amount_sats)
+        .sum();
+    if pool_state.participants.is_empty() || total_stake == 0 {
+        return Ok(RBISnapshot {
+            timestamp,
+            block_height: current_height,
+            v_dld: 0.0,
+            t_c: 1.0,
+            d_s,
+            productivity_a: a,
+            rbi_value: 0.0,
+            status: RbiStatus::Indeterminate,
+            is_healthy: false,
+            alert: None,
+            oracle_report: None,
+        });
+    }
+
+    let v_dld = calculate_dld_velocity(pool_state, velocity_config)?;
+    let t_c = calculate_system_trust(pool_state)?;
+
+    if !v_dld.is_finite() || v_dld < 0.0 {
+        return Err(RBIError::InvalidState(
+            "v_dld must be finite and >= 0".into(),
+        ));
+    }
+    if !t_c.is_finite() || t_c <= 0.0 {
+        return Err(RBIError::InvalidState("t_c must be finite and > 0".into()));
+    }
 
-        let snapshot = RBISnapshot {
+    if d_s.abs() < MIN_DEMAND_THRESHOLD {
+        return Ok(RBISnapshot {
             timestamp,
             block_height: current_height,
             v_dld,
             t_c,
             d_s,
             productivity_a: a,
-            rbi_value,
-            status,
-            is_healthy,
-            alert,
-        };
+            rbi_value: 0.0,
+            status: RbiStatus::Indeterminate,
+            is_healthy: false,
+            alert: None,
+            oracle_report: None,
+        });
+    }
 
-        self.history.push(snapshot.clone());
-        Ok(snapshot)
+    // RBI = (V_DLD × T_c) / (D_s / e^A)
+    let numerator = v_dld * t_c;
+
+    let exp_a = a.exp(); // e^A
+    if !exp_a.is_finite() || exp_a <= 0.0 {
+        return Err(RBIError::Calculation("e^A invalid".into()));
     }
 
-    fn calculate_dld_velocity(&self, pool_state: &DistributionPoolState) -> Result<f64, RBIError> {
-        let total_distributed = pool_state.total_distributed_sats as f64;
-        let avg_velocity = pool_state.average_participant_velocity;
-        let epoch_days = pool_state.epoch_duration_days as f64;
+    let denominator = d_s / exp_a;
+    let mut rbi_value = numerator / denominator;
+
+    let mut status = if !rbi_value.is_finite() {
+        RbiStatus::Invalid
+    } else if rbi_value < thresholds.critical_low {
+        RbiStatus::Critical
+    } else if rbi_value < thresholds.warning_low || rbi_value > thresholds.overheating_high {
+        RbiStatus::Warning
+    } else {
+        RbiStatus::Healthy
+    };
+
+    if !rbi_value.is_finite() {
+        status = RbiStatus::Invalid;
+        rbi_value = 0.0;
+    }
 
-        if epoch_days <= 0.0 {
-            return Err(RBIError::InvalidState("epoch_days must be > 0".into()));
-        }
-        if !avg_velocity.is_finite() || avg_velocity <= 0.0 {
-            return Err(RBIError::InvalidState(
-                "average_participant_velocity must be finite and > 0".into(),
-            ));
-        }
+    let is_healthy = rbi_value >= thresholds.warning_low
+        && !matches!(status, RbiStatus::Invalid | RbiStatus::Indeterminate);
+    let alert = if matches!(status, RbiStatus::Invalid | RbiStatus::Indeterminate) {
+        None
+    } else {
+        evaluate_alert(rbi_value, thresholds)
+    };
+
+    Ok(RBISnapshot {
+        timestamp,
+        block_height: current_height,
+        v_dld,
+        t_c,
+        d_s,
+        productivity_a: a,
+        rbi_value,
+        status,
+        is_healthy,
+        alert,
+        oracle_report: None,
+    })
+}
 
-        let min_velocity = self
-            .velocity_config
-            .min_velocity_multiplier
-            .to_f64()
-            .ok_or_else(|| RBIError::InvalidState("min velocity conversion failed".into()))?;
-        let max_velocity = self
-            .velocity_config
-            .max_velocity_multiplier
-            .to_f64()
-            .ok_or_else(|| RBIError::InvalidState("max velocity conversion failed".into()))?;
-        let clamped_velocity = avg_velocity.clamp(min_velocity, max_velocity);
+fn calculate_dld_velocity(
+    pool_state: &DistributionPoolState,
+    velocity_config: &VelocityConfig,
+) -> Result<f64, RBIError> {
+    let total_distributed = pool_state.total_distributed_sats as f64;
+    let avg_velocity = pool_state.average_participant_velocity;
+    let epoch_days = pool_state.epoch_duration_days as f64;
 
-        Ok((total_distributed * clamped_velocity) / epoch_days) // sats/day adjusted
+    if epoch_days <= 0.0 {
+        return Err(RBIError::InvalidState("epoch_days must be > 0".into()));
+    }
+    if !avg_velocity.is_finite() || avg_velocity <= 0.0 {
+        return Err(RBIError::InvalidState(
+            "average_participant_velocity must be finite and > 0".into(),
+        ));
     }
 
-    fn calculate_system_trust(&self, pool_state: &DistributionPoolState) -> Result<f64, RBIError> {
-        if pool_state.participants.is_empty() {
-            return Ok(1.0); // neutral baseline (indeterminate handled upstream)
-        }
+    let min_velocity = velocity_config
+        .min_velocity_multiplier
+        .to_f64()
+        .ok_or_else(|| RBIError::InvalidState("min velocity conversion failed".into()))?;
+    let max_velocity = velocity_config
+        .max_velocity_multiplier
+        .to_f64()
+        .ok_or_else(|| RBIError::InvalidState("max velocity conversion failed".into()))?;
+    let clamped_velocity = avg_velocity.clamp(min_velocity, max_velocity);
+
+    Ok((total_distributed * clamped_velocity) / epoch_days) // sats/day adjusted
+}
 
-        let mut weighted_sum = 0.0;
-        let mut total_weight = 0.0;
+fn calculate_system_trust(pool_state: &DistributionPoolState) -> Result<f64, RBIError> {
+    if pool_state.participants.is_empty() {
+        return Ok(1.0); // neutral baseline (indeterminate handled upstream)
+    }
 
-        for p in &pool_state.participants {
-            let w = p.stake_amount_sats as f64;
-            if !p.trust_coefficient.is_finite() || p.trust_coefficient <= 0.0 {
-                return Err(RBIError::InvalidState(
-                    "trust_coefficient must be finite and > 0".into(),
-                ));
-            }
-            weighted_sum += w * p.trust_coefficient;
-            total_weight += w;
-        }
+    let mut weighted_sum = 0.0;
+    let mut total_weight = 0.0;
 
-        if total_weight.abs() < f64::EPSILON {
-            Ok(1.0)
-        } else {
-            Ok(weighted_sum / total_weight)
+    for p in &pool_state.participants {
+        let w = p.stake_amount_sats as f64;
+        if !p.trust_coefficient.is_finite() || p.trust_coefficient <= 0.0 {
+            return Err(RBIError::InvalidState(
+                "trust_coefficient must be finite and > 0".into(),
+            ));
         }
+        weighted_sum += w * p.trust_coefficient;
+        total_weight += w;
+    }
+
+    if total_weight.abs() < f64::EPSILON {
+        Ok(1.0)
+    } else {
+        Ok(weighted_sum / total_weight)
     }
 }
 
@@ -337,4 +583,129 @@ mod tests {
         assert!(snap.rbi_value.is_finite());
         assert!(snap.is_healthy);
     }
+
+    fn sample_state() -> DistributionPoolState {
+        DistributionPoolState {
+            total_distributed_sats: 1_000_000_000,
+            average_participant_velocity: 1.2,
+            epoch_duration_days: 1,
+            participants: vec![ParticipantSnapshot {
+                participant_id: "alice".into(),
+                stake_amount_sats: 100_000_000,
+                trust_coefficient: 1.3,
+            }],
+        }
+    }
+
+    #[test]
+    fn evaluate_leaves_history_untouched() {
+        let provider = MockEconomicDataProvider {
+            demand_shock: 0.02,
+            productivity: 0.05,
+        };
+        let mut engine = RBIEngine::new(provider);
+        let state = sample_state();
+        let timestamp = Utc::now();
+
+        let recorded = engine.calculate_rbi_at(&state, 800_000, timestamp).unwrap();
+        let what_if = engine
+            .evaluate(
+                &state,
+                RecordedEconomicSnapshot {
+                    demand_shock: 0.02,
+                    productivity: 0.05,
+                },
+                &EvaluationOverrides::default(),
+                800_000,
+                timestamp,
+            )
+            .unwrap();
+
+        assert_eq!(engine.history().len(), 1);
+        assert_eq!(recorded.rbi_value, what_if.rbi_value);
+
+        let strict = EvaluationOverrides {
+            thresholds: Some(AlertThresholds {
+                critical_low: f64::MAX,
+                warning_low: f64::MAX,
+                overheating_high: f64::MAX,
+            }),
+            velocity_config: None,
+        };
+        let critical = engine
+            .evaluate(
+                &state,
+                RecordedEconomicSnapshot {
+                    demand_shock: 0.02,
+                    productivity: 0.05,
+                },
+                &strict,
+                800_000,
+                timestamp,
+            )
+            .unwrap();
+        assert_eq!(critical.status, RbiStatus::Critical);
+        assert_eq!(engine.history().len(), 1);
+    }
+
+    #[test]
+    fn sweep_builds_two_dimensional_surface() {
+        let engine = RBIEngine::new(MockEconomicDataProvider {
+            demand_shock: 0.02,
+            productivity: 0.05,
+        });
+        let surface = engine
+            .sweep(
+                &sample_state(),
+                RecordedEconomicSnapshot {
+                    demand_shock: 0.02,
+                    productivity: 0.05,
+                },
+                &EvaluationOverrides::default(),
+                &SweepGrid {
+                    x: SweepAxis {
+                        input: SweepInput::TotalDistributedSats,
+                        values: vec![1_000.0, 1_000_000.0, 1_000_000_000.0],
+                    },
+                    y: Some(SweepAxis {
+                        input: SweepInput::EpochDurationDays,
+                        values: vec![0.0, 14.0],
+                    }),
+                },
+                800_000,
+                Utc::now(),
+            )
+            .unwrap();
+
+        assert_eq!(surface.cells.len(), 6);
+        // epoch_duration_days = 0 is invalid; the row reports errors.
+        assert!(surface.cell(0, 0).unwrap().error.is_some());
+        let low = surface.cell(0, 1).unwrap().rbi_value.unwrap();
+        let high = surface.cell(2, 1).unwrap().rbi_value.unwrap();
+        assert!(high > low);
+        assert!(engine.history().is_empty());
+
+        // A non-finite value fails only its own cell
+        let surface = engine
+            .sweep(
+                &sample_state(),
+                RecordedEconomicSnapshot {
+                    demand_shock: 0.02,
+                    productivity: 0.05,
+                },
+                &EvaluationOverrides::default(),
+                &SweepGrid {
+                    x: SweepAxis {
+                        input: SweepInput::DemandShock,
+                        values: vec![f64::NAN, 0.02],
+                    },
+                    y: None,
+                },
+                800_000,
+                Utc::now(),
+            )
+            .unwrap();
+        assert!(surface.cell(0, 0).unwrap().error.is_some());
+        assert!(surface.cell(1, 0).unwrap().rbi_value.is_some());
+    }
 }
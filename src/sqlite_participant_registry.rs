@@ -1,22 +1,63 @@
-use crate::disbursement::{AileeTrustAudit, PayoutStatus, PayoutTransactionResult};
+use crate::audit_log::{entry_hash, AuditEntry, AuditEvent, GENESIS_HASH};
+use crate::disbursement::{
+    AileeTrustAudit, ChangeIndexStore, PayoutHistory, PayoutStatus, PayoutTransactionResult,
+    PayoutTransition, PayoutVolume, PoolUtxo,
+};
+use crate::rbi_engine::ParticipantSnapshot;
+use crate::stake::{StakePosition, StakeVerification};
+use crate::storage::{
+    check_address, check_registration, check_spend, check_stake, check_trust, payout_events,
+    payout_outputs, pool_utxo_from_json, pool_utxo_json, stake_updated_event, stored_address,
+    RegistryStorage,
+};
 use crate::velocity_analyzer::{ParticipantRegistry, VelocityError};
-use rusqlite::{params, Connection, OpenFlags};
-use std::collections::HashSet;
+use bitcoin::{Network, OutPoint, Script, ScriptBuf, Txid};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row, Transaction};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::str::FromStr;
 use std::sync::Mutex;
 
+pub use crate::storage::{ParticipantAddress, ParticipantPage, ParticipantRecord, RegistryError};
+
 const REQUIRED_TABLES: &[&str] = &["participants", "participant_addresses"];
 const PARTICIPANTS_COLUMNS: &[&str] = &["participant_id"];
+const PARTICIPANT_STATUS_COLUMNS: &[&str] = &["active", "registered_at", "deactivated_height"];
+const TRUST_COLUMNS: &[&str] = &["participant_id", "effective_height", "trust_coefficient"];
 const ADDRESS_COLUMNS: &[&str] = &["participant_id", "address", "position"];
+const TRANSITION_COLUMNS: &[&str] = &[
+    "payout_id",
+    "from_status",
+    "to_status",
+    "confirmations",
+    "detail",
+    "timestamp",
+];
+const AUDIT_SELECT_COLUMNS: &str = "seq, recorded_at, event_type, payload_json, prev_hash, hash";
 
 /// Read-write or read-only registry backed by a stable SQLite schema.
 #[derive(Debug)]
 pub struct SqliteParticipantRegistry {
     conn: Mutex<Connection>,
     is_read_only: bool,
+    /// Migrated to [`SCHEMA_VERSION`]. Unversioned read-only databases lack
+    /// participant status, stakes and trust metadata: every participant
+    /// counts as active and none has a stake.
+    is_versioned: bool,
+    /// Network that registered payout addresses must belong to.
+    network: Network,
+}
+
+impl From<rusqlite::Error> for RegistryError {
+    fn from(err: rusqlite::Error) -> Self {
+        RegistryError::Storage(err.to_string())
+    }
 }
 
 impl SqliteParticipantRegistry {
+    /// Opens an existing database read-only. It must be unversioned or at
+    /// exactly [`SCHEMA_VERSION`], since a read-only handle cannot migrate.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, VelocityError> {
         let conn = Connection::open_with_flags(
             path,
@@ -24,14 +65,19 @@ impl SqliteParticipantRegistry {
         )
         .map_err(|err| VelocityError::DataSource(err.to_string()))?;
         validate_schema(&conn)?;
+        let is_versioned = schema_version(&conn)? == SCHEMA_VERSION;
         Ok(Self {
             conn: Mutex::new(conn),
             is_read_only: true,
+            is_versioned,
+            network: Network::Bitcoin,
         })
     }
 
+    /// Opens or creates the database and migrates it to [`SCHEMA_VERSION`].
+    /// Fails without writing if the database is newer than this binary.
     pub fn open_read_write<P: AsRef<Path>>(path: P) -> Result<Self, VelocityError> {
-        let conn = Connection::open_with_flags(
+        let mut conn = Connection::open_with_flags(
             path,
             OpenFlags::SQLITE_OPEN_READ_WRITE
                 | OpenFlags::SQLITE_OPEN_CREATE
@@ -39,15 +85,109 @@ impl SqliteParticipantRegistry {
         )
         .map_err(|err| VelocityError::DataSource(err.to_string()))?;
 
-        init_schema(&conn)?;
+        migrate(&mut conn)?;
         Ok(Self {
             conn: Mutex::new(conn),
             is_read_only: false,
+            is_versioned: true,
+            network: Network::Bitcoin,
+        })
+    }
+
+    /// Validates registered addresses against `network` instead of mainnet.
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.network = network;
+        self
+    }
+
+    fn update_stake(
+        &self,
+        outpoint: &OutPoint,
+        verification: StakeVerification,
+        confirmed_height: Option<u64>,
+    ) -> Result<StakePosition, RegistryError> {
+        self.write(|tx| {
+            require_stake(tx, outpoint)?;
+            tx.execute(
+                "UPDATE stakes SET verification = ?3, confirmed_height = ?4 \
+                 WHERE txid = ?1 AND vout = ?2",
+                params![
+                    outpoint.txid.to_string(),
+                    outpoint.vout,
+                    verification.to_string(),
+                    confirmed_height,
+                ],
+            )?;
+            audit_stake_update(tx, outpoint)
         })
     }
 
-    pub fn save_payout(&self, payout: &PayoutTransactionResult) -> Result<(), VelocityError> {
+    fn load_record(
+        &self,
+        conn: &Connection,
+        participant_id: &str,
+    ) -> Result<ParticipantRecord, RegistryError> {
+        if self.is_versioned {
+            return load_participant(conn, participant_id);
+        }
+        let exists: Option<i64> = conn
+            .query_row(
+                "SELECT 1 FROM participants WHERE participant_id = ?1",
+                params![participant_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match exists {
+            None => Err(RegistryError::ParticipantNotFound(
+                participant_id.to_string(),
+            )),
+            Some(_) => Ok(ParticipantRecord {
+                participant_id: participant_id.to_string(),
+                active: true,
+                registered_at: None,
+                deactivated_height: None,
+                addresses: load_addresses(conn, participant_id)?,
+            }),
+        }
+    }
+
+    fn read<T>(
+        &self,
+        f: impl FnOnce(&Connection) -> Result<T, RegistryError>,
+    ) -> Result<T, RegistryError> {
         let conn = self
+            .conn
+            .lock()
+            .map_err(|_| RegistryError::Storage("registry lock poisoned".into()))?;
+        f(&conn)
+    }
+
+    /// Runs `f` in a transaction that is committed only if it succeeds.
+    fn write<T>(
+        &self,
+        f: impl FnOnce(&Transaction<'_>) -> Result<T, RegistryError>,
+    ) -> Result<T, RegistryError> {
+        if self.is_read_only {
+            return Err(RegistryError::ReadOnly);
+        }
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| RegistryError::Storage("registry lock poisoned".into()))?;
+        let tx = conn.transaction()?;
+        let value = f(&tx)?;
+        tx.commit()?;
+        Ok(value)
+    }
+}
+
+impl RegistryStorage for SqliteParticipantRegistry {
+    fn save_payout_transitions(
+        &self,
+        payout: &PayoutTransactionResult,
+        transitions: &[PayoutTransition],
+    ) -> Result<(), VelocityError> {
+        let mut conn = self
             .conn
             .lock()
             .map_err(|_| VelocityError::DataSource("registry lock poisoned".into()))?;
@@ -59,12 +199,45 @@ impl SqliteParticipantRegistry {
 
         let trust_audit_json = serde_json::to_string(&payout.trust_audit)
             .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+        let fee_bump_of_json = payout
+            .fee_bump_of
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+        let fee_bumped_by_json = payout
+            .fee_bumped_by
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+        let signing_json = payout
+            .signing
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| VelocityError::DataSource(e.to_string()))?;
 
-        conn.execute(
+        let tx = conn
+            .transaction()
+            .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+        let is_new = tx
+            .query_row(
+                "SELECT 1 FROM payouts WHERE payout_id = ?1",
+                params![payout.payout_id],
+                |_| Ok(()),
+            )
+            .optional()
+            .map_err(|e| VelocityError::DataSource(e.to_string()))?
+            .is_none();
+        tx.execute(
             "INSERT OR REPLACE INTO payouts (
                 payout_id, recipient_address, amount_sats, fee_sats, status,
-                psbt_base64, raw_tx_hex, txid, timestamp, trust_audit_json, is_dry_run
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                psbt_base64, raw_tx_hex, txid, timestamp, trust_audit_json, is_dry_run,
+                confirmations, signed_psbt_base64, final_tx_hex,
+                fee_bump_of_json, fee_bumped_by_json, signing_json, batch_id
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                ?18)",
             params![
                 payout.payout_id,
                 payout.recipient_address,
@@ -77,14 +250,64 @@ impl SqliteParticipantRegistry {
                 payout.timestamp,
                 trust_audit_json,
                 payout.is_dry_run as i32,
+                payout.confirmations,
+                payout.signed_psbt_base64,
+                payout.final_tx_hex,
+                fee_bump_of_json,
+                fee_bumped_by_json,
+                signing_json,
+                payout.batch_id,
             ],
         )
         .map_err(|e| VelocityError::DataSource(e.to_string()))?;
 
-        Ok(())
+        tx.execute(
+            "DELETE FROM payout_outputs WHERE payout_id = ?1",
+            params![payout.payout_id],
+        )
+        .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+        for (position, (address, amount_sats)) in
+            payout_outputs(payout, self.network).iter().enumerate()
+        {
+            tx.execute(
+                "INSERT INTO payout_outputs (payout_id, position, address, amount_sats) \
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    payout.payout_id,
+                    position as i64,
+                    address,
+                    *amount_sats as i64
+                ],
+            )
+            .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+        }
+
+        for transition in transitions {
+            tx.execute(
+                "INSERT INTO payout_transitions (
+                    payout_id, from_status, to_status, confirmations, detail, timestamp
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    transition.payout_id,
+                    transition.from.to_string(),
+                    transition.to.to_string(),
+                    transition.confirmations,
+                    transition.detail,
+                    transition.timestamp,
+                ],
+            )
+            .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+        }
+
+        for event in payout_events(payout, is_new, transitions) {
+            append_audit(&tx, &event).map_err(|e| VelocityError::DataSource(e.to_string()))?;
+        }
+
+        tx.commit()
+            .map_err(|e| VelocityError::DataSource(e.to_string()))
     }
 
-    pub fn get_payout_by_id(
+    fn get_payout_by_id(
         &self,
         payout_id: &str,
     ) -> Result<Option<PayoutTransactionResult>, VelocityError> {
@@ -94,71 +317,48 @@ impl SqliteParticipantRegistry {
             .map_err(|_| VelocityError::DataSource("registry lock poisoned".into()))?;
 
         let mut stmt = conn
-            .prepare(
-                "SELECT payout_id, recipient_address, amount_sats, fee_sats, status, \
-                        psbt_base64, raw_tx_hex, txid, timestamp, trust_audit_json, is_dry_run \
-                 FROM payouts WHERE payout_id = ?1",
-            )
+            .prepare(&format!(
+                "SELECT {PAYOUT_SELECT_COLUMNS} FROM payouts WHERE payout_id = ?1"
+            ))
             .map_err(|e| VelocityError::DataSource(e.to_string()))?;
 
         let mut rows = stmt
-            .query(params![payout_id])
+            .query_map(params![payout_id], payout_from_row)
             .map_err(|e| VelocityError::DataSource(e.to_string()))?;
 
-        if let Some(row) = rows
-            .next()
-            .map_err(|e| VelocityError::DataSource(e.to_string()))?
-        {
-            let status_str: String = row.get(4).unwrap_or_default();
-            let status = match status_str.as_str() {
-                "pending" => PayoutStatus::Pending,
-                "unsigned_created" => PayoutStatus::UnsignedCreated,
-                "completed" => PayoutStatus::Completed,
-                "rejected" => PayoutStatus::Rejected,
-                _ => PayoutStatus::Pending,
-            };
+        rows.next()
+            .transpose()
+            .map_err(|e| VelocityError::DataSource(e.to_string()))
+    }
 
-            let trust_audit_json: String = row.get(9).unwrap_or_default();
-            let trust_audit: AileeTrustAudit =
-                serde_json::from_str(&trust_audit_json).unwrap_or_default();
-
-            let is_dry_run_i32: i32 = row.get(10).unwrap_or(0);
-
-            Ok(Some(PayoutTransactionResult {
-                payout_id: row
-                    .get(0)
-                    .map_err(|e| VelocityError::DataSource(e.to_string()))?,
-                recipient_address: row
-                    .get(1)
-                    .map_err(|e| VelocityError::DataSource(e.to_string()))?,
-                amount_sats: row
-                    .get(2)
-                    .map_err(|e| VelocityError::DataSource(e.to_string()))?,
-                fee_sats: row
-                    .get(3)
-                    .map_err(|e| VelocityError::DataSource(e.to_string()))?,
-                status,
-                psbt_base64: row
-                    .get(5)
-                    .map_err(|e| VelocityError::DataSource(e.to_string()))?,
-                raw_tx_hex: row
-                    .get(6)
-                    .map_err(|e| VelocityError::DataSource(e.to_string()))?,
-                txid: row
-                    .get(7)
-                    .map_err(|e| VelocityError::DataSource(e.to_string()))?,
-                timestamp: row
-                    .get(8)
-                    .map_err(|e| VelocityError::DataSource(e.to_string()))?,
-                trust_audit,
-                is_dry_run: is_dry_run_i32 != 0,
-            }))
-        } else {
-            Ok(None)
+    fn get_all_payouts(&self) -> Result<Vec<PayoutTransactionResult>, VelocityError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| VelocityError::DataSource("registry lock poisoned".into()))?;
+
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {PAYOUT_SELECT_COLUMNS} FROM payouts ORDER BY timestamp DESC"
+            ))
+            .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+
+        let rows = stmt
+            .query_map([], payout_from_row)
+            .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+
+        let mut payouts = Vec::new();
+        for row in rows {
+            payouts.push(row.map_err(|e| VelocityError::DataSource(e.to_string()))?);
         }
+
+        Ok(payouts)
     }
 
-    pub fn get_all_payouts(&self) -> Result<Vec<PayoutTransactionResult>, VelocityError> {
+    fn get_payout_transitions(
+        &self,
+        payout_id: &str,
+    ) -> Result<Vec<PayoutTransition>, VelocityError> {
         let conn = self
             .conn
             .lock()
@@ -166,54 +366,691 @@ impl SqliteParticipantRegistry {
 
         let mut stmt = conn
             .prepare(
-                "SELECT payout_id, recipient_address, amount_sats, fee_sats, status, \
-                        psbt_base64, raw_tx_hex, txid, timestamp, trust_audit_json, is_dry_run \
-                 FROM payouts ORDER BY timestamp DESC",
+                "SELECT payout_id, from_status, to_status, confirmations, detail, timestamp \
+                 FROM payout_transitions WHERE payout_id = ?1 ORDER BY id ASC",
             )
             .map_err(|e| VelocityError::DataSource(e.to_string()))?;
 
         let rows = stmt
-            .query_map([], |row| {
-                let status_str: String = row.get(4)?;
-                let status = match status_str.as_str() {
-                    "pending" => PayoutStatus::Pending,
-                    "unsigned_created" => PayoutStatus::UnsignedCreated,
-                    "completed" => PayoutStatus::Completed,
-                    "rejected" => PayoutStatus::Rejected,
-                    _ => PayoutStatus::Pending,
-                };
-
-                let trust_audit_json: String = row.get(9).unwrap_or_default();
-                let trust_audit: AileeTrustAudit =
-                    serde_json::from_str(&trust_audit_json).unwrap_or_default();
-
-                let is_dry_run_i32: i32 = row.get(10).unwrap_or(0);
-
-                Ok(PayoutTransactionResult {
+            .query_map(params![payout_id], |row| {
+                Ok(PayoutTransition {
                     payout_id: row.get(0)?,
-                    recipient_address: row.get(1)?,
-                    amount_sats: row.get(2)?,
-                    fee_sats: row.get(3)?,
-                    status,
-                    psbt_base64: row.get(5)?,
-                    raw_tx_hex: row.get(6)?,
-                    txid: row.get(7)?,
-                    timestamp: row.get(8)?,
-                    trust_audit,
-                    is_dry_run: is_dry_run_i32 != 0,
+                    from: parse_status(row, 1)?,
+                    to: parse_status(row, 2)?,
+                    confirmations: row.get(3)?,
+                    detail: row.get(4)?,
+                    timestamp: row.get(5)?,
                 })
             })
             .map_err(|e| VelocityError::DataSource(e.to_string()))?;
 
-        let mut payouts = Vec::new();
+        let mut transitions = Vec::new();
         for row in rows {
-            payouts.push(row.map_err(|e| VelocityError::DataSource(e.to_string()))?);
+            transitions.push(row.map_err(|e| VelocityError::DataSource(e.to_string()))?);
         }
 
-        Ok(payouts)
+        Ok(transitions)
+    }
+
+    fn replace_pool_utxos(&self, utxos: &[PoolUtxo]) -> Result<(), RegistryError> {
+        self.write(|tx| {
+            let reserved: HashMap<(String, u32), String> = tx
+                .prepare(
+                    "SELECT txid, vout, reserved_by FROM pool_utxos WHERE reserved_by IS NOT NULL",
+                )?
+                .query_map([], |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)))?
+                .collect::<Result<_, _>>()?;
+            tx.execute("DELETE FROM pool_utxos", [])?;
+            for utxo in utxos {
+                let key = (utxo.outpoint.txid.to_string(), utxo.outpoint.vout);
+                tx.execute(
+                    "INSERT INTO pool_utxos (txid, vout, utxo_json, reserved_by) \
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![key.0, key.1, pool_utxo_json(utxo)?, reserved.get(&key)],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    fn pool_utxos(&self) -> Result<Vec<PoolUtxo>, RegistryError> {
+        if !self.is_versioned {
+            return Ok(Vec::new());
+        }
+        self.read(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT utxo_json FROM pool_utxos WHERE reserved_by IS NULL ORDER BY rowid ASC",
+            )?;
+            let rows = stmt
+                .query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows.iter().map(|json| pool_utxo_from_json(json)).collect()
+        })
+    }
+
+    fn reserve_pool_utxos(
+        &self,
+        payout_id: &str,
+        outpoints: &[OutPoint],
+    ) -> Result<(), RegistryError> {
+        self.write(|tx| {
+            for outpoint in outpoints {
+                let txid = outpoint.txid.to_string();
+                let holder: Option<Option<String>> = tx
+                    .query_row(
+                        "SELECT reserved_by FROM pool_utxos WHERE txid = ?1 AND vout = ?2",
+                        params![txid, outpoint.vout],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                match holder {
+                    None => continue,
+                    Some(Some(holder)) if holder != payout_id => {
+                        return Err(RegistryError::UtxoReserved {
+                            outpoint: *outpoint,
+                            payout_id: holder,
+                        });
+                    }
+                    Some(_) => {}
+                }
+                tx.execute(
+                    "UPDATE pool_utxos SET reserved_by = ?3 WHERE txid = ?1 AND vout = ?2",
+                    params![txid, outpoint.vout, payout_id],
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    fn release_pool_utxos(&self, payout_id: &str) -> Result<usize, RegistryError> {
+        self.write(|tx| {
+            Ok(tx.execute(
+                "UPDATE pool_utxos SET reserved_by = NULL WHERE reserved_by = ?1",
+                params![payout_id],
+            )?)
+        })
+    }
+
+    fn register_participant(
+        &self,
+        participant_id: &str,
+        addresses: &[String],
+    ) -> Result<ParticipantRecord, RegistryError> {
+        let addresses = check_registration(participant_id, addresses, self.network)?;
+        self.write(|tx| {
+            if participant_active(tx, participant_id)?.is_some() {
+                return Err(RegistryError::ParticipantExists(participant_id.to_string()));
+            }
+            tx.execute(
+                "INSERT INTO participants (participant_id, active, registered_at) \
+                 VALUES (?1, 1, ?2)",
+                params![participant_id, Utc::now().to_rfc3339()],
+            )?;
+            for (position, address) in (0u32..).zip(&addresses) {
+                insert_address(tx, participant_id, address, position)?;
+            }
+            append_audit(
+                tx,
+                &AuditEvent::ParticipantRegistered {
+                    participant_id: participant_id.to_string(),
+                    addresses: addresses.clone(),
+                },
+            )?;
+            load_participant(tx, participant_id)
+        })
+    }
+
+    fn add_participant_address(
+        &self,
+        participant_id: &str,
+        address: &str,
+        position: Option<u32>,
+    ) -> Result<ParticipantRecord, RegistryError> {
+        let address = check_address(address, self.network)?;
+        self.write(|tx| {
+            ensure_active(tx, participant_id)?;
+            let position = match position {
+                Some(position) => position,
+                None => tx.query_row(
+                    "SELECT COALESCE(MAX(position) + 1, 0) FROM participant_addresses \
+                     WHERE participant_id = ?1",
+                    params![participant_id],
+                    |row| row.get(0),
+                )?,
+            };
+            insert_address(tx, participant_id, &address, position)?;
+            append_audit(
+                tx,
+                &AuditEvent::ParticipantAddressAdded {
+                    participant_id: participant_id.to_string(),
+                    address: address.clone(),
+                    position,
+                },
+            )?;
+            load_participant(tx, participant_id)
+        })
+    }
+
+    fn remove_participant_address(
+        &self,
+        participant_id: &str,
+        address: &str,
+    ) -> Result<ParticipantRecord, RegistryError> {
+        let address = stored_address(address, self.network);
+        self.write(|tx| {
+            ensure_active(tx, participant_id)?;
+            let removed = tx.execute(
+                "DELETE FROM participant_addresses WHERE participant_id = ?1 AND address = ?2",
+                params![participant_id, address],
+            )?;
+            if removed == 0 {
+                return Err(RegistryError::AddressNotFound {
+                    participant_id: participant_id.to_string(),
+                    address: address.clone(),
+                });
+            }
+            append_audit(
+                tx,
+                &AuditEvent::ParticipantAddressRemoved {
+                    participant_id: participant_id.to_string(),
+                    address: address.clone(),
+                },
+            )?;
+            load_participant(tx, participant_id)
+        })
+    }
+
+    fn deactivate_participant(
+        &self,
+        participant_id: &str,
+        height: u64,
+    ) -> Result<ParticipantRecord, RegistryError> {
+        self.write(|tx| {
+            ensure_active(tx, participant_id)?;
+            tx.execute(
+                "UPDATE participants SET active = 0, deactivated_height = ?2 \
+                 WHERE participant_id = ?1",
+                params![participant_id, height],
+            )?;
+            append_audit(
+                tx,
+                &AuditEvent::ParticipantDeactivated {
+                    participant_id: participant_id.to_string(),
+                    effective_height: Some(height),
+                },
+            )?;
+            load_participant(tx, participant_id)
+        })
+    }
+
+    fn get_participant(
+        &self,
+        participant_id: &str,
+    ) -> Result<Option<ParticipantRecord>, RegistryError> {
+        self.read(|conn| match self.load_record(conn, participant_id) {
+            Ok(record) => Ok(Some(record)),
+            Err(RegistryError::ParticipantNotFound(_)) => Ok(None),
+            Err(err) => Err(err),
+        })
+    }
+
+    fn list_participants(
+        &self,
+        page: u32,
+        page_size: u32,
+        include_inactive: bool,
+    ) -> Result<ParticipantPage, RegistryError> {
+        self.read(|conn| {
+            let filter = if include_inactive || !self.is_versioned {
+                ""
+            } else {
+                "WHERE active = 1"
+            };
+            let total: i64 = conn.query_row(
+                &format!("SELECT COUNT(*) FROM participants {filter}"),
+                [],
+                |row| row.get(0),
+            )?;
+            let mut stmt = conn.prepare(&format!(
+                "SELECT participant_id FROM participants {filter} \
+                 ORDER BY participant_id ASC LIMIT ?1 OFFSET ?2"
+            ))?;
+            let ids = stmt
+                .query_map(
+                    params![page_size, u64::from(page) * u64::from(page_size)],
+                    |row| row.get::<_, String>(0),
+                )?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let participants = ids
+                .iter()
+                .map(|id| self.load_record(conn, id))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(ParticipantPage {
+                participants,
+                total_count: total as usize,
+            })
+        })
+    }
+
+    fn record_stake(
+        &self,
+        participant_id: &str,
+        outpoint: OutPoint,
+        amount_sats: u64,
+        witness_script: &Script,
+    ) -> Result<StakePosition, RegistryError> {
+        let lock_height = check_stake(amount_sats, witness_script)?;
+
+        self.write(|tx| {
+            ensure_active(tx, participant_id)?;
+            if load_stake(tx, &outpoint)?.is_some() {
+                return Err(RegistryError::StakeExists(outpoint));
+            }
+            tx.execute(
+                "INSERT INTO stakes (
+                    txid, vout, participant_id, amount_sats, lock_height, witness_script_hex,
+                    verification, recorded_at
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    outpoint.txid.to_string(),
+                    outpoint.vout,
+                    participant_id,
+                    amount_sats,
+                    lock_height,
+                    hex::encode(witness_script.as_bytes()),
+                    StakeVerification::Pending.to_string(),
+                    Utc::now().to_rfc3339(),
+                ],
+            )?;
+            append_audit(
+                tx,
+                &AuditEvent::StakeRecorded {
+                    outpoint: outpoint.to_string(),
+                    participant_id: participant_id.to_string(),
+                    amount_sats,
+                    lock_height,
+                },
+            )?;
+            require_stake(tx, &outpoint)
+        })
+    }
+
+    fn verify_stake(
+        &self,
+        outpoint: &OutPoint,
+        confirmed_height: u64,
+    ) -> Result<StakePosition, RegistryError> {
+        self.update_stake(
+            outpoint,
+            StakeVerification::Verified,
+            Some(confirmed_height),
+        )
+    }
+
+    fn reject_stake(&self, outpoint: &OutPoint) -> Result<StakePosition, RegistryError> {
+        self.update_stake(outpoint, StakeVerification::Rejected, None)
+    }
+
+    fn mark_stake_spent(
+        &self,
+        outpoint: &OutPoint,
+        spent_height: u64,
+    ) -> Result<StakePosition, RegistryError> {
+        self.write(|tx| {
+            check_spend(&require_stake(tx, outpoint)?, spent_height)?;
+            tx.execute(
+                "UPDATE stakes SET spent_height = ?3 WHERE txid = ?1 AND vout = ?2",
+                params![outpoint.txid.to_string(), outpoint.vout, spent_height],
+            )?;
+            audit_stake_update(tx, outpoint)
+        })
+    }
+
+    fn stakes_for(&self, participant_id: &str) -> Result<Vec<StakePosition>, RegistryError> {
+        if !self.is_versioned {
+            return Ok(Vec::new());
+        }
+        self.read(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {STAKE_SELECT_COLUMNS} FROM stakes \
+                 WHERE participant_id = ?1 ORDER BY rowid ASC"
+            ))?;
+            let stakes = stmt
+                .query_map(params![participant_id], stake_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(stakes)
+        })
+    }
+
+    fn record_trust(
+        &self,
+        participant_id: &str,
+        effective_height: u64,
+        trust_coefficient: f64,
+    ) -> Result<(), RegistryError> {
+        check_trust(trust_coefficient)?;
+        self.write(|tx| {
+            ensure_active(tx, participant_id)?;
+            tx.execute(
+                "INSERT OR REPLACE INTO participant_trust (
+                    participant_id, effective_height, trust_coefficient, recorded_at
+                ) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    participant_id,
+                    effective_height,
+                    trust_coefficient,
+                    Utc::now().to_rfc3339()
+                ],
+            )?;
+            append_audit(
+                tx,
+                &AuditEvent::ParameterChanged {
+                    parameter: "trust_coefficient".into(),
+                    value: trust_coefficient.to_string(),
+                    participant_id: Some(participant_id.to_string()),
+                    effective_height: Some(effective_height),
+                },
+            )?;
+            Ok(())
+        })
+    }
+
+    fn record_audit_event(&self, event: &AuditEvent) -> Result<AuditEntry, RegistryError> {
+        self.write(|tx| append_audit(tx, event))
+    }
+
+    fn record_parameter(&self, parameter: &str, value: &str) -> Result<bool, RegistryError> {
+        self.write(|tx| {
+            let last: Option<String> = tx
+                .query_row(
+                    "SELECT json_extract(payload_json, '$.value') FROM audit_log \
+                     WHERE event_type = 'parameter_changed' \
+                       AND json_extract(payload_json, '$.parameter') = ?1 \
+                       AND json_extract(payload_json, '$.participant_id') IS NULL \
+                     ORDER BY seq DESC LIMIT 1",
+                    params![parameter],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if last.as_deref() == Some(value) {
+                return Ok(false);
+            }
+            append_audit(
+                tx,
+                &AuditEvent::ParameterChanged {
+                    parameter: parameter.to_string(),
+                    value: value.to_string(),
+                    participant_id: None,
+                    effective_height: None,
+                },
+            )?;
+            Ok(true)
+        })
+    }
+
+    fn audit_entries(&self, after_seq: u64, limit: u32) -> Result<Vec<AuditEntry>, RegistryError> {
+        if !self.is_versioned {
+            return Ok(Vec::new());
+        }
+        self.read(|conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {AUDIT_SELECT_COLUMNS} FROM audit_log \
+                 WHERE seq > ?1 ORDER BY seq ASC LIMIT ?2"
+            ))?;
+            let entries = stmt
+                .query_map(params![after_seq, limit], audit_entry_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(entries)
+        })
+    }
+
+    fn participant_snapshots(
+        &self,
+        height: u64,
+    ) -> Result<Vec<ParticipantSnapshot>, RegistryError> {
+        if !self.is_versioned {
+            return Ok(Vec::new());
+        }
+        self.read(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT p.participant_id, SUM(s.amount_sats),
+                    COALESCE((
+                        SELECT t.trust_coefficient FROM participant_trust t
+                        WHERE t.participant_id = p.participant_id AND t.effective_height <= ?1
+                        ORDER BY t.effective_height DESC LIMIT 1
+                    ), 1.0)
+                 FROM participants p
+                 JOIN stakes s ON s.participant_id = p.participant_id
+                 WHERE (p.active = 1 OR p.deactivated_height > ?1)
+                    AND s.verification = 'verified'
+                    AND s.confirmed_height <= ?1
+                    AND (s.spent_height IS NULL OR s.spent_height > ?1)
+                 GROUP BY p.participant_id
+                 ORDER BY p.participant_id ASC",
+            )?;
+            let snapshots = stmt
+                .query_map(params![height], |row| {
+                    Ok(ParticipantSnapshot {
+                        participant_id: row.get(0)?,
+                        stake_amount_sats: row.get(1)?,
+                        trust_coefficient: row.get(2)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(snapshots)
+        })
+    }
+}
+
+/// `Some(active)` for a registered participant.
+fn participant_active(
+    conn: &Connection,
+    participant_id: &str,
+) -> Result<Option<bool>, RegistryError> {
+    Ok(conn
+        .query_row(
+            "SELECT active FROM participants WHERE participant_id = ?1",
+            params![participant_id],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+fn ensure_active(conn: &Connection, participant_id: &str) -> Result<(), RegistryError> {
+    match participant_active(conn, participant_id)? {
+        Some(true) => Ok(()),
+        Some(false) => Err(RegistryError::ParticipantInactive(
+            participant_id.to_string(),
+        )),
+        None => Err(RegistryError::ParticipantNotFound(
+            participant_id.to_string(),
+        )),
     }
 }
 
+/// Inserts an address after checking it is not registered to anyone,
+/// including `participant_id` itself, and that its position is free.
+fn insert_address(
+    conn: &Connection,
+    participant_id: &str,
+    address: &str,
+    position: u32,
+) -> Result<(), RegistryError> {
+    let owner: Option<String> = conn
+        .query_row(
+            "SELECT participant_id FROM participant_addresses WHERE address = ?1",
+            params![address],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(owner) = owner {
+        return Err(RegistryError::AddressInUse {
+            address: address.to_string(),
+            participant_id: owner,
+        });
+    }
+    let occupied: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM participant_addresses \
+         WHERE participant_id = ?1 AND position = ?2)",
+        params![participant_id, position],
+        |row| row.get(0),
+    )?;
+    if occupied {
+        return Err(RegistryError::PositionInUse {
+            participant_id: participant_id.to_string(),
+            position,
+        });
+    }
+    conn.execute(
+        "INSERT INTO participant_addresses (participant_id, address, position) \
+         VALUES (?1, ?2, ?3)",
+        params![participant_id, address, position],
+    )?;
+    Ok(())
+}
+
+fn load_participant(
+    conn: &Connection,
+    participant_id: &str,
+) -> Result<ParticipantRecord, RegistryError> {
+    let (active, registered_at, deactivated_height): (bool, Option<String>, Option<u64>) = conn
+        .query_row(
+            "SELECT active, registered_at, deactivated_height FROM participants \
+             WHERE participant_id = ?1",
+            params![participant_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?
+        .ok_or_else(|| RegistryError::ParticipantNotFound(participant_id.to_string()))?;
+    Ok(ParticipantRecord {
+        participant_id: participant_id.to_string(),
+        active,
+        registered_at,
+        deactivated_height,
+        addresses: load_addresses(conn, participant_id)?,
+    })
+}
+
+fn load_addresses(
+    conn: &Connection,
+    participant_id: &str,
+) -> Result<Vec<ParticipantAddress>, RegistryError> {
+    let mut stmt = conn.prepare(
+        "SELECT address, position FROM participant_addresses WHERE participant_id = ?1 \
+         ORDER BY position ASC, address ASC",
+    )?;
+    let addresses = stmt
+        .query_map(params![participant_id], |row| {
+            Ok(ParticipantAddress {
+                address: row.get(0)?,
+                position: row.get(1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(addresses)
+}
+
+/// Chains `event` onto the last audit entry inside the caller's transaction.
+fn append_audit(conn: &Connection, event: &AuditEvent) -> Result<AuditEntry, RegistryError> {
+    let payload_json =
+        serde_json::to_string(event).map_err(|e| RegistryError::Storage(e.to_string()))?;
+    let (last_seq, prev_hash) = conn
+        .query_row(
+            "SELECT seq, hash FROM audit_log ORDER BY seq DESC LIMIT 1",
+            [],
+            |row| Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?)),
+        )
+        .optional()?
+        .unwrap_or((0, GENESIS_HASH.to_string()));
+    let seq = last_seq + 1;
+    let recorded_at = Utc::now().to_rfc3339();
+    let hash = entry_hash(seq, &recorded_at, event.kind(), &payload_json, &prev_hash);
+    conn.execute(
+        &format!("INSERT INTO audit_log ({AUDIT_SELECT_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6)"),
+        params![
+            seq,
+            recorded_at,
+            event.kind(),
+            payload_json,
+            prev_hash,
+            hash
+        ],
+    )?;
+    Ok(AuditEntry {
+        seq,
+        recorded_at,
+        event_type: event.kind().to_string(),
+        payload_json,
+        prev_hash,
+        hash,
+    })
+}
+
+fn audit_stake_update(
+    conn: &Connection,
+    outpoint: &OutPoint,
+) -> Result<StakePosition, RegistryError> {
+    let stake = require_stake(conn, outpoint)?;
+    append_audit(conn, &stake_updated_event(&stake))?;
+    Ok(stake)
+}
+
+fn audit_entry_from_row(row: &Row<'_>) -> rusqlite::Result<AuditEntry> {
+    Ok(AuditEntry {
+        seq: row.get(0)?,
+        recorded_at: row.get(1)?,
+        event_type: row.get(2)?,
+        payload_json: row.get(3)?,
+        prev_hash: row.get(4)?,
+        hash: row.get(5)?,
+    })
+}
+
+const STAKE_SELECT_COLUMNS: &str = "txid, vout, participant_id, amount_sats, lock_height, \
+     witness_script_hex, verification, confirmed_height, spent_height";
+
+fn stake_from_row(row: &Row<'_>) -> rusqlite::Result<StakePosition> {
+    let conversion = |idx: usize, e: String| {
+        rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, e.into())
+    };
+    let txid: String = row.get(0)?;
+    let script_hex: String = row.get(5)?;
+    let verification: String = row.get(6)?;
+    Ok(StakePosition {
+        outpoint: OutPoint {
+            txid: Txid::from_str(&txid).map_err(|e| conversion(0, e.to_string()))?,
+            vout: row.get(1)?,
+        },
+        participant_id: row.get(2)?,
+        amount_sats: row.get(3)?,
+        lock_height: row.get(4)?,
+        witness_script: ScriptBuf::from_bytes(
+            hex::decode(script_hex).map_err(|e| conversion(5, e.to_string()))?,
+        ),
+        verification: verification.parse().map_err(|e| conversion(6, e))?,
+        confirmed_height: row.get(7)?,
+        spent_height: row.get(8)?,
+    })
+}
+
+fn load_stake(
+    conn: &Connection,
+    outpoint: &OutPoint,
+) -> Result<Option<StakePosition>, RegistryError> {
+    Ok(conn
+        .query_row(
+            &format!("SELECT {STAKE_SELECT_COLUMNS} FROM stakes WHERE txid = ?1 AND vout = ?2"),
+            params![outpoint.txid.to_string(), outpoint.vout],
+            stake_from_row,
+        )
+        .optional()?)
+}
+
+fn require_stake(conn: &Connection, outpoint: &OutPoint) -> Result<StakePosition, RegistryError> {
+    load_stake(conn, outpoint)?.ok_or(RegistryError::StakeNotFound(*outpoint))
+}
+
 impl ParticipantRegistry for SqliteParticipantRegistry {
     fn addresses_for(&self, participant_id: &str) -> Result<Vec<String>, VelocityError> {
         let conn = self
@@ -221,12 +1058,18 @@ impl ParticipantRegistry for SqliteParticipantRegistry {
             .lock()
             .map_err(|_| VelocityError::DataSource("registry lock poisoned".into()))?;
 
+        let sql = if self.is_versioned {
+            "SELECT a.address FROM participant_addresses a \
+             LEFT JOIN participants p ON p.participant_id = a.participant_id \
+             WHERE a.participant_id = ?1 AND COALESCE(p.active, 1) = 1 \
+             ORDER BY a.position ASC, a.address ASC"
+        } else {
+            "SELECT address FROM participant_addresses \
+             WHERE participant_id = ?1 \
+             ORDER BY position ASC, address ASC"
+        };
         let mut stmt = conn
-            .prepare(
-                "SELECT address FROM participant_addresses \
-                 WHERE participant_id = ?1 \
-                 ORDER BY position ASC, address ASC",
-            )
+            .prepare(sql)
             .map_err(|err| VelocityError::DataSource(err.to_string()))?;
 
         let rows = stmt
@@ -246,29 +1089,281 @@ impl ParticipantRegistry for SqliteParticipantRegistry {
     }
 }
 
-fn init_schema(conn: &Connection) -> Result<(), VelocityError> {
-    conn.execute(
+impl PayoutHistory for SqliteParticipantRegistry {
+    /// Time of the earliest single payout to `address` that reached the
+    /// network; batch transactions are skipped.
+    fn first_seen(&self, address: &str) -> Result<Option<DateTime<Utc>>, VelocityError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| VelocityError::DataSource("registry lock poisoned".into()))?;
+
+        let earliest: Option<String> = conn
+            .query_row(
+                "SELECT MIN(p.timestamp) FROM payouts p \
+                 JOIN payout_outputs o ON o.payout_id = p.payout_id \
+                 WHERE o.address = ?1 AND p.is_dry_run = 0 AND p.batch_id IS NULL \
+                 AND p.status IN ('broadcast', 'confirmed', 'completed', 'replaced')",
+                params![stored_address(address, self.network)],
+                |row| row.get(0),
+            )
+            .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+
+        earliest
+            .map(|ts| {
+                DateTime::parse_from_rfc3339(&ts)
+                    .map(|t| t.with_timezone(&Utc))
+                    .map_err(|e| VelocityError::DataSource(format!("bad payout timestamp: {e}")))
+            })
+            .transpose()
+    }
+
+    /// Mirrors [`counts_toward_velocity`](crate::disbursement::counts_toward_velocity). Timestamps are compared as the
+    /// RFC 3339 UTC strings the payouts are stored with. A recipient's
+    /// volume sums its rows in `payout_outputs`, batch outputs included.
+    fn volume_since(
+        &self,
+        recipient: Option<&str>,
+        since: DateTime<Utc>,
+    ) -> Result<PayoutVolume, VelocityError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| VelocityError::DataSource("registry lock poisoned".into()))?;
+
+        const COUNTED: &str = "p.is_dry_run = 0 AND p.fee_bump_of_json IS NULL \
+             AND p.status NOT IN ('pending', 'rejected', 'failed') AND p.timestamp >= ?1";
+        let (count, sats): (i64, i64) = match recipient {
+            None => conn.query_row(
+                &format!(
+                    "SELECT COUNT(*), COALESCE(SUM(p.amount_sats), 0) FROM payouts p \
+                     WHERE {COUNTED}"
+                ),
+                params![since.to_rfc3339()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            ),
+            Some(recipient) => conn.query_row(
+                &format!(
+                    "SELECT COUNT(DISTINCT p.payout_id), COALESCE(SUM(o.amount_sats), 0) \
+                     FROM payouts p JOIN payout_outputs o ON o.payout_id = p.payout_id \
+                     WHERE {COUNTED} AND o.address = ?2"
+                ),
+                params![since.to_rfc3339(), stored_address(recipient, self.network)],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            ),
+        }
+        .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+
+        Ok(PayoutVolume {
+            count: count as u64,
+            sats: sats as u64,
+        })
+    }
+}
+
+impl ChangeIndexStore for SqliteParticipantRegistry {
+    fn next_change_index(&self) -> Result<u32, RegistryError> {
+        if !self.is_versioned {
+            return Ok(0);
+        }
+        self.read(|conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT value FROM pool_counters WHERE name = 'change_index'",
+                    [],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .unwrap_or(0))
+        })
+    }
+
+    fn reserve_change_index(&self) -> Result<u32, RegistryError> {
+        self.write(|tx| {
+            Ok(tx.query_row(
+                "INSERT INTO pool_counters (name, value) VALUES ('change_index', 1) \
+                 ON CONFLICT (name) DO UPDATE SET value = value + 1 \
+                 RETURNING value - 1",
+                [],
+                |row| row.get(0),
+            )?)
+        })
+    }
+}
+
+const PAYOUT_SELECT_COLUMNS: &str = "payout_id, recipient_address, amount_sats, fee_sats, status, \
+     psbt_base64, raw_tx_hex, txid, timestamp, trust_audit_json, is_dry_run, \
+     confirmations, signed_psbt_base64, final_tx_hex, fee_bump_of_json, fee_bumped_by_json, \
+     signing_json, batch_id";
+
+fn payout_from_row(row: &Row<'_>) -> rusqlite::Result<PayoutTransactionResult> {
+    let trust_audit_json: String = row.get(9).unwrap_or_default();
+    let trust_audit =
+        serde_json::from_str(&trust_audit_json).unwrap_or_else(|_| AileeTrustAudit::unrecorded());
+    let is_dry_run_i32: i32 = row.get(10).unwrap_or(0);
+
+    Ok(PayoutTransactionResult {
+        payout_id: row.get(0)?,
+        recipient_address: row.get(1)?,
+        amount_sats: row.get(2)?,
+        fee_sats: row.get(3)?,
+        status: parse_status(row, 4)?,
+        psbt_base64: row.get(5)?,
+        raw_tx_hex: row.get(6)?,
+        txid: row.get(7)?,
+        timestamp: row.get(8)?,
+        trust_audit,
+        is_dry_run: is_dry_run_i32 != 0,
+        confirmations: row.get(11)?,
+        signed_psbt_base64: row.get(12)?,
+        final_tx_hex: row.get(13)?,
+        fee_bump_of: json_column(row, 14)?,
+        fee_bumped_by: json_column(row, 15)?,
+        signing: json_column(row, 16)?,
+        batch_id: row.get(17)?,
+    })
+}
+
+fn json_column<T: serde::de::DeserializeOwned>(
+    row: &Row<'_>,
+    idx: usize,
+) -> rusqlite::Result<Option<T>> {
+    let json: Option<String> = row.get(idx)?;
+    json.map(|json| serde_json::from_str(&json))
+        .transpose()
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, e.into())
+        })
+}
+
+fn parse_status(row: &Row<'_>, idx: usize) -> rusqlite::Result<PayoutStatus> {
+    let status: String = row.get(idx)?;
+    status.parse().map_err(|e: String| {
+        rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, e.into())
+    })
+}
+
+/// Schema version this binary writes, stored in `PRAGMA user_version`.
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// One up-migration; its version is its position in [`MIGRATIONS`], 1-based.
+struct Migration {
+    description: &'static str,
+    up: fn(&Connection) -> Result<(), VelocityError>,
+}
+
+/// Ordered up-migrations. Append only: never edit or reorder a shipped entry.
+///
+/// Databases created before versioning report `user_version` 0 but may
+/// already contain any part of versions 1-4, so those steps are idempotent.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        description: "participants and payouts",
+        up: migrate_base_tables,
+    },
+    Migration {
+        description: "payout lifecycle",
+        up: migrate_payout_lifecycle,
+    },
+    Migration {
+        description: "fee bump links",
+        up: migrate_fee_bump_links,
+    },
+    Migration {
+        description: "cosigner signing progress",
+        up: migrate_signing_progress,
+    },
+    Migration {
+        description: "participant status and unique addresses",
+        up: migrate_participant_status,
+    },
+    Migration {
+        description: "stakes and trust metadata",
+        up: migrate_stakes,
+    },
+    Migration {
+        description: "hash-chained audit log",
+        up: migrate_audit_log,
+    },
+    Migration {
+        description: "pool UTXO set",
+        up: migrate_pool_utxos,
+    },
+    Migration {
+        description: "batch payout records",
+        up: migrate_batch_payouts,
+    },
+    Migration {
+        description: "pool counters",
+        up: migrate_pool_counters,
+    },
+    Migration {
+        description: "canonical addresses and unique positions",
+        up: migrate_address_positions,
+    },
+    Migration {
+        description: "participant deactivation height",
+        up: migrate_deactivation_height,
+    },
+    Migration {
+        description: "pool UTXO reservations",
+        up: migrate_pool_utxo_reservations,
+    },
+    Migration {
+        description: "payout outputs by recipient",
+        up: migrate_payout_outputs,
+    },
+];
+
+fn schema_version(conn: &Connection) -> Result<u32, VelocityError> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| VelocityError::DataSource(e.to_string()))
+}
+
+fn ensure_not_newer(version: u32) -> Result<(), VelocityError> {
+    if version > SCHEMA_VERSION {
+        return Err(VelocityError::InvalidData(format!(
+            "database schema version {version} is newer than supported version {SCHEMA_VERSION}"
+        )));
+    }
+    Ok(())
+}
+
+/// Applies every migration above the stored version, each in its own
+/// transaction together with the `user_version` bump.
+fn migrate(conn: &mut Connection) -> Result<(), VelocityError> {
+    let current = schema_version(conn)?;
+    ensure_not_newer(current)?;
+
+    for (version, migration) in (1..).zip(MIGRATIONS).skip(current as usize) {
+        let failed = |e: String| {
+            VelocityError::DataSource(format!(
+                "migration {version} ({}) failed: {e}",
+                migration.description
+            ))
+        };
+        let tx = conn.transaction().map_err(|e| failed(e.to_string()))?;
+        (migration.up)(&tx).map_err(|e| failed(e.to_string()))?;
+        tx.pragma_update(None, "user_version", version)
+            .map_err(|e| failed(e.to_string()))?;
+        tx.commit().map_err(|e| failed(e.to_string()))?;
+    }
+    Ok(())
+}
+
+fn migrate_base_tables(conn: &Connection) -> Result<(), VelocityError> {
+    conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS participants (
             participant_id TEXT PRIMARY KEY
-        )",
-        [],
-    )
-    .map_err(|e| VelocityError::DataSource(e.to_string()))?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS participant_addresses (
+        );
+        CREATE TABLE IF NOT EXISTS participant_addresses (
             participant_id TEXT NOT NULL,
             address TEXT NOT NULL,
             position INTEGER NOT NULL,
             PRIMARY KEY (participant_id, address),
             FOREIGN KEY (participant_id) REFERENCES participants(participant_id)
-        )",
-        [],
-    )
-    .map_err(|e| VelocityError::DataSource(e.to_string()))?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS payouts (
+        );
+        CREATE TABLE IF NOT EXISTS payouts (
             payout_id TEXT PRIMARY KEY,
             recipient_address TEXT NOT NULL,
             amount_sats INTEGER NOT NULL,
@@ -280,15 +1375,251 @@ fn init_schema(conn: &Connection) -> Result<(), VelocityError> {
             timestamp TEXT NOT NULL,
             trust_audit_json TEXT NOT NULL,
             is_dry_run INTEGER NOT NULL
+        );",
+    )
+    .map_err(|e| VelocityError::DataSource(e.to_string()))
+}
+
+fn migrate_payout_lifecycle(conn: &Connection) -> Result<(), VelocityError> {
+    add_column_if_missing(
+        conn,
+        "payouts",
+        "confirmations",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column_if_missing(conn, "payouts", "signed_psbt_base64", "TEXT")?;
+    add_column_if_missing(conn, "payouts", "final_tx_hex", "TEXT")?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS payout_transitions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            payout_id TEXT NOT NULL,
+            from_status TEXT NOT NULL,
+            to_status TEXT NOT NULL,
+            confirmations INTEGER NOT NULL,
+            detail TEXT,
+            timestamp TEXT NOT NULL
         )",
         [],
     )
     .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+    Ok(())
+}
+
+fn migrate_fee_bump_links(conn: &Connection) -> Result<(), VelocityError> {
+    add_column_if_missing(conn, "payouts", "fee_bump_of_json", "TEXT")?;
+    add_column_if_missing(conn, "payouts", "fee_bumped_by_json", "TEXT")
+}
+
+fn migrate_signing_progress(conn: &Connection) -> Result<(), VelocityError> {
+    add_column_if_missing(conn, "payouts", "signing_json", "TEXT")
+}
+
+/// Fails on databases that already reuse an address across participants;
+/// those must be repaired by hand before they can be migrated.
+fn migrate_participant_status(conn: &Connection) -> Result<(), VelocityError> {
+    conn.execute_batch(
+        "ALTER TABLE participants ADD COLUMN active INTEGER NOT NULL DEFAULT 1;
+         ALTER TABLE participants ADD COLUMN registered_at TEXT;
+         CREATE UNIQUE INDEX participant_addresses_address
+            ON participant_addresses (address);",
+    )
+    .map_err(|e| VelocityError::DataSource(e.to_string()))
+}
+
+fn migrate_stakes(conn: &Connection) -> Result<(), VelocityError> {
+    conn.execute_batch(
+        "CREATE TABLE stakes (
+            txid TEXT NOT NULL,
+            vout INTEGER NOT NULL,
+            participant_id TEXT NOT NULL REFERENCES participants(participant_id),
+            amount_sats INTEGER NOT NULL,
+            lock_height INTEGER NOT NULL,
+            witness_script_hex TEXT NOT NULL,
+            verification TEXT NOT NULL,
+            confirmed_height INTEGER,
+            spent_height INTEGER,
+            recorded_at TEXT NOT NULL,
+            PRIMARY KEY (txid, vout)
+         );
+         CREATE INDEX stakes_participant ON stakes (participant_id);
+         CREATE TABLE participant_trust (
+            participant_id TEXT NOT NULL REFERENCES participants(participant_id),
+            effective_height INTEGER NOT NULL,
+            trust_coefficient REAL NOT NULL,
+            recorded_at TEXT NOT NULL,
+            PRIMARY KEY (participant_id, effective_height)
+         );",
+    )
+    .map_err(|e| VelocityError::DataSource(e.to_string()))
+}
+
+/// Triggers refuse updates and deletes; the hash chain catches edits made
+/// around them.
+fn migrate_audit_log(conn: &Connection) -> Result<(), VelocityError> {
+    conn.execute_batch(
+        "CREATE TABLE audit_log (
+            seq INTEGER PRIMARY KEY,
+            recorded_at TEXT NOT NULL,
+            event_type TEXT NOT NULL,
+            payload_json TEXT NOT NULL,
+            prev_hash TEXT NOT NULL,
+            hash TEXT NOT NULL
+         );
+         CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+         BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END;
+         CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+         BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END;",
+    )
+    .map_err(|e| VelocityError::DataSource(e.to_string()))
+}
+
+fn migrate_pool_utxos(conn: &Connection) -> Result<(), VelocityError> {
+    conn.execute_batch(
+        "CREATE TABLE pool_utxos (
+            txid TEXT NOT NULL,
+            vout INTEGER NOT NULL,
+            utxo_json TEXT NOT NULL,
+            PRIMARY KEY (txid, vout)
+         );",
+    )
+    .map_err(|e| VelocityError::DataSource(e.to_string()))
+}
+
+fn migrate_batch_payouts(conn: &Connection) -> Result<(), VelocityError> {
+    add_column_if_missing(conn, "payouts", "batch_id", "TEXT")
+}
+
+fn migrate_pool_counters(conn: &Connection) -> Result<(), VelocityError> {
+    conn.execute_batch(
+        "CREATE TABLE pool_counters (
+            name TEXT PRIMARY KEY,
+            value INTEGER NOT NULL
+         );",
+    )
+    .map_err(|e| VelocityError::DataSource(e.to_string()))
+}
+
+/// Lower-cases bech32 addresses, the form they are now registered in, and
+/// renumbers the addresses of participants with shared positions in their
+/// existing order. Fails if two participants hold spellings of one
+/// address; those must be repaired by hand first.
+fn migrate_address_positions(conn: &Connection) -> Result<(), VelocityError> {
+    let failed = |e: rusqlite::Error| VelocityError::DataSource(e.to_string());
+    conn.execute(
+        "UPDATE participant_addresses SET address = lower(address) \
+         WHERE lower(address) GLOB 'bc1*' OR lower(address) GLOB 'tb1*' \
+            OR lower(address) GLOB 'bcrt1*'",
+        [],
+    )
+    .map_err(failed)?;
+
+    let shared: Vec<String> = conn
+        .prepare(
+            "SELECT DISTINCT participant_id FROM participant_addresses \
+             GROUP BY participant_id, position HAVING COUNT(*) > 1",
+        )
+        .map_err(failed)?
+        .query_map([], |row| row.get(0))
+        .map_err(failed)?
+        .collect::<Result<_, _>>()
+        .map_err(failed)?;
+    for participant_id in shared {
+        let addresses: Vec<(String, i64)> = conn
+            .prepare(
+                "SELECT address, position FROM participant_addresses \
+                 WHERE participant_id = ?1 ORDER BY position ASC, address ASC",
+            )
+            .map_err(failed)?
+            .query_map(params![participant_id], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .map_err(failed)?
+            .collect::<Result<_, _>>()
+            .map_err(failed)?;
+        let first = addresses.first().map_or(0, |(_, position)| *position);
+        for ((address, _), position) in addresses.iter().zip(first..) {
+            conn.execute(
+                "UPDATE participant_addresses SET position = ?3 \
+                 WHERE participant_id = ?1 AND address = ?2",
+                params![participant_id, address, position],
+            )
+            .map_err(failed)?;
+        }
+    }
+
+    conn.execute_batch(
+        "CREATE UNIQUE INDEX participant_addresses_position
+            ON participant_addresses (participant_id, position);",
+    )
+    .map_err(failed)
+}
+
+fn migrate_deactivation_height(conn: &Connection) -> Result<(), VelocityError> {
+    add_column_if_missing(conn, "participants", "deactivated_height", "INTEGER")
+}
 
+fn migrate_pool_utxo_reservations(conn: &Connection) -> Result<(), VelocityError> {
+    add_column_if_missing(conn, "pool_utxos", "reserved_by", "TEXT")
+}
+
+/// Backfills single payouts from their requested recipient, bech32
+/// lower-cased like registered addresses. Earlier batch records keep only
+/// their first recipient, so they stay out of per-recipient volume.
+fn migrate_payout_outputs(conn: &Connection) -> Result<(), VelocityError> {
+    conn.execute_batch(
+        "CREATE TABLE payout_outputs (
+            payout_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            address TEXT NOT NULL,
+            amount_sats INTEGER NOT NULL,
+            PRIMARY KEY (payout_id, position)
+         );
+         CREATE INDEX payout_outputs_address ON payout_outputs (address);
+         INSERT INTO payout_outputs (payout_id, position, address, amount_sats)
+            SELECT payout_id, 0,
+                   CASE WHEN lower(recipient_address) GLOB 'bc1*'
+                          OR lower(recipient_address) GLOB 'tb1*'
+                          OR lower(recipient_address) GLOB 'bcrt1*'
+                        THEN lower(recipient_address) ELSE recipient_address END,
+                   amount_sats
+            FROM payouts WHERE batch_id IS NULL;",
+    )
+    .map_err(|e| VelocityError::DataSource(e.to_string()))
+}
+
+fn add_column_if_missing(
+    conn: &Connection,
+    table_name: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), VelocityError> {
+    let exists: bool = conn
+        .query_row(
+            &format!("SELECT COUNT(*) > 0 FROM pragma_table_info('{table_name}') WHERE name = ?1"),
+            params![column],
+            |row| row.get(0),
+        )
+        .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {table_name} ADD COLUMN {column} {definition}"),
+            [],
+        )
+        .map_err(|e| VelocityError::DataSource(e.to_string()))?;
+    }
     Ok(())
 }
 
 fn validate_schema(conn: &Connection) -> Result<(), VelocityError> {
+    let version = schema_version(conn)?;
+    ensure_not_newer(version)?;
+    if version != 0 && version < SCHEMA_VERSION {
+        return Err(VelocityError::InvalidData(format!(
+            "database schema version {version} is older than {SCHEMA_VERSION}; \
+             open it read-write once to migrate"
+        )));
+    }
+
     let mut stmt = conn
         .prepare("SELECT name FROM sqlite_master WHERE type = 'table'")
         .map_err(|err| VelocityError::DataSource(err.to_string()))?;
@@ -312,6 +1643,23 @@ fn validate_schema(conn: &Connection) -> Result<(), VelocityError> {
 
     ensure_columns(conn, "participants", PARTICIPANTS_COLUMNS)?;
     ensure_columns(conn, "participant_addresses", ADDRESS_COLUMNS)?;
+    // Versioned databases always carry the payout tables; unversioned
+    // registries provisioned by hand may omit them.
+    if version == SCHEMA_VERSION || tables.contains("payouts") {
+        let payout_columns: Vec<&str> = PAYOUT_SELECT_COLUMNS.split(',').map(str::trim).collect();
+        ensure_columns(conn, "payouts", &payout_columns)?;
+    }
+    if version == SCHEMA_VERSION {
+        ensure_columns(conn, "participants", PARTICIPANT_STATUS_COLUMNS)?;
+        let stake_columns: Vec<&str> = STAKE_SELECT_COLUMNS.split(',').map(str::trim).collect();
+        ensure_columns(conn, "stakes", &stake_columns)?;
+        ensure_columns(conn, "participant_trust", TRUST_COLUMNS)?;
+        let audit_columns: Vec<&str> = AUDIT_SELECT_COLUMNS.split(',').map(str::trim).collect();
+        ensure_columns(conn, "audit_log", &audit_columns)?;
+    }
+    if version == SCHEMA_VERSION || tables.contains("payout_transitions") {
+        ensure_columns(conn, "payout_transitions", TRANSITION_COLUMNS)?;
+    }
     ensure_unique_addresses(conn)?;
     Ok(())
 }
@@ -321,6 +1669,19 @@ fn ensure_columns(
     table_name: &str,
     required_columns: &[&str],
 ) -> Result<(), VelocityError> {
+    let columns = table_columns(conn, table_name)?;
+    for required in required_columns {
+        if !columns.contains(*required) {
+            return Err(VelocityError::InvalidData(format!(
+                "missing column {required} on {table_name}"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+fn table_columns(conn: &Connection, table_name: &str) -> Result<HashSet<String>, VelocityError> {
     let pragma = format!("PRAGMA table_info({table_name})");
     let mut stmt = conn
         .prepare(&pragma)
@@ -334,16 +1695,7 @@ fn ensure_columns(
         let name = row.map_err(|err| VelocityError::DataSource(err.to_string()))?;
         columns.insert(name);
     }
-
-    for required in required_columns {
-        if !columns.contains(*required) {
-            return Err(VelocityError::InvalidData(format!(
-                "missing column {required} on {table_name}"
-            )));
-        }
-    }
-
-    Ok(())
+    Ok(columns)
 }
 
 fn ensure_unique_addresses(conn: &Connection) -> Result<(), VelocityError> {
@@ -0,0 +1,574 @@
+//! Storage backends for participants, addresses, stakes, payouts and the
+//! audit log.
+//!
+//! [`SqliteParticipantRegistry`](crate::sqlite_participant_registry::SqliteParticipantRegistry)
+//! is the default. With the `postgres` feature, `PostgresParticipantRegistry`
+//! stores the same data in a database several API nodes can share.
+
+use crate::audit_log::{
+    verify_chain, verify_chain_extends, AuditChainError, AuditChainStatus, AuditEntry, AuditEvent,
+};
+use crate::disbursement::{
+    parse_address, recipient_outputs, AddressRole, ChangeIndexStore, PayoutHistory,
+    PayoutTransactionResult, PayoutTransition, PoolUtxo, PoolUtxoRecord,
+};
+use crate::rbi_engine::ParticipantSnapshot;
+use crate::stake::{parse_stake_lock, StakePosition};
+use crate::velocity_analyzer::{ParticipantRegistry, VelocityError};
+use bitcoin::{Address, Network, OutPoint, Script};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Persistent registry state. Every write that changes economic state also
+/// appends its [`AuditEvent`] in the same transaction.
+pub trait RegistryStorage: ParticipantRegistry + PayoutHistory + ChangeIndexStore {
+    /// Stores the payout and appends its transition log entries atomically.
+    fn save_payout_transitions(
+        &self,
+        payout: &PayoutTransactionResult,
+        transitions: &[PayoutTransition],
+    ) -> Result<(), VelocityError>;
+
+    fn save_payout(&self, payout: &PayoutTransactionResult) -> Result<(), VelocityError> {
+        self.save_payout_transitions(payout, &[])
+    }
+
+    fn get_payout_by_id(
+        &self,
+        payout_id: &str,
+    ) -> Result<Option<PayoutTransactionResult>, VelocityError>;
+
+    /// Newest first.
+    fn get_all_payouts(&self) -> Result<Vec<PayoutTransactionResult>, VelocityError>;
+
+    /// Transition log of a payout, oldest first.
+    fn get_payout_transitions(
+        &self,
+        payout_id: &str,
+    ) -> Result<Vec<PayoutTransition>, VelocityError>;
+
+    /// Replaces the pool UTXO set. Outpoints that stay in it keep their
+    /// reservations.
+    fn replace_pool_utxos(&self, utxos: &[PoolUtxo]) -> Result<(), RegistryError>;
+
+    /// Spendable pool UTXOs, i.e. not reserved, in the order they were
+    /// stored.
+    fn pool_utxos(&self) -> Result<Vec<PoolUtxo>, RegistryError>;
+
+    /// Reserves the pool UTXOs a payout spends, hiding them from
+    /// [`Self::pool_utxos`]. Outpoints outside the set, such as the parent
+    /// output a CPFP child spends, are skipped. Fails without reserving
+    /// anything if one is reserved by another payout.
+    fn reserve_pool_utxos(
+        &self,
+        payout_id: &str,
+        outpoints: &[OutPoint],
+    ) -> Result<(), RegistryError>;
+
+    /// Makes the pool UTXOs reserved by `payout_id` spendable again, e.g.
+    /// once the payout failed. Returns how many were released.
+    fn release_pool_utxos(&self, payout_id: &str) -> Result<usize, RegistryError>;
+
+    /// Blocks until no other node sharing this registry is evaluating or
+    /// storing a payout, and holds that off until the guard is dropped.
+    /// The default holds nothing: one process writes a SQLite file.
+    fn lock_payouts(&self) -> Result<PayoutLock<'_>, RegistryError> {
+        Ok(PayoutLock::default())
+    }
+
+    /// Registers a participant with `addresses` at positions `0..`. Fails if
+    /// the id exists or any address belongs to another participant.
+    fn register_participant(
+        &self,
+        participant_id: &str,
+        addresses: &[String],
+    ) -> Result<ParticipantRecord, RegistryError>;
+
+    /// Adds an address at `position`, or after the last one when `None`.
+    fn add_participant_address(
+        &self,
+        participant_id: &str,
+        address: &str,
+        position: Option<u32>,
+    ) -> Result<ParticipantRecord, RegistryError>;
+
+    fn remove_participant_address(
+        &self,
+        participant_id: &str,
+        address: &str,
+    ) -> Result<ParticipantRecord, RegistryError>;
+
+    /// Stops resolving the participant's addresses. They stay reserved so
+    /// they cannot be re-registered under a new identity. Snapshots below
+    /// `height` still include the participant.
+    fn deactivate_participant(
+        &self,
+        participant_id: &str,
+        height: u64,
+    ) -> Result<ParticipantRecord, RegistryError>;
+
+    fn get_participant(
+        &self,
+        participant_id: &str,
+    ) -> Result<Option<ParticipantRecord>, RegistryError>;
+
+    fn list_participants(
+        &self,
+        page: u32,
+        page_size: u32,
+        include_inactive: bool,
+    ) -> Result<ParticipantPage, RegistryError>;
+
+    /// Records a pending stake. The lock height is read from
+    /// `witness_script`, which must be a stake lock.
+    fn record_stake(
+        &self,
+        participant_id: &str,
+        outpoint: OutPoint,
+        amount_sats: u64,
+        witness_script: &Script,
+    ) -> Result<StakePosition, RegistryError>;
+
+    /// Marks a stake verified as confirmed at `confirmed_height`.
+    fn verify_stake(
+        &self,
+        outpoint: &OutPoint,
+        confirmed_height: u64,
+    ) -> Result<StakePosition, RegistryError>;
+
+    /// Marks a stake rejected; it never counts toward the participant.
+    fn reject_stake(&self, outpoint: &OutPoint) -> Result<StakePosition, RegistryError>;
+
+    /// Records the height a stake output was spent at.
+    fn mark_stake_spent(
+        &self,
+        outpoint: &OutPoint,
+        spent_height: u64,
+    ) -> Result<StakePosition, RegistryError>;
+
+    /// A participant's stakes in the order they were recorded.
+    fn stakes_for(&self, participant_id: &str) -> Result<Vec<StakePosition>, RegistryError>;
+
+    /// Sets the participant's trust coefficient from `effective_height` on,
+    /// replacing any value recorded for that same height.
+    fn record_trust(
+        &self,
+        participant_id: &str,
+        effective_height: u64,
+        trust_coefficient: f64,
+    ) -> Result<(), RegistryError>;
+
+    /// Active participants with stake at `height`, ordered by id: the sum of
+    /// their verified stakes confirmed by and unspent at that height,
+    /// weighted by the latest trust coefficient in effect (1.0 if none).
+    fn participant_snapshots(&self, height: u64)
+        -> Result<Vec<ParticipantSnapshot>, RegistryError>;
+
+    /// Appends `event` to the audit log.
+    fn record_audit_event(&self, event: &AuditEvent) -> Result<AuditEntry, RegistryError>;
+
+    /// Logs a protocol parameter unless its last logged value is the same.
+    /// Returns whether an entry was appended.
+    fn record_parameter(&self, parameter: &str, value: &str) -> Result<bool, RegistryError>;
+
+    /// Up to `limit` audit entries after sequence number `after_seq`.
+    fn audit_entries(&self, after_seq: u64, limit: u32) -> Result<Vec<AuditEntry>, RegistryError>;
+
+    /// Recomputes the whole audit chain. Fails with
+    /// [`RegistryError::AuditChainBroken`] at the first entry that was
+    /// edited, removed or reordered.
+    fn verify_audit_chain(&self) -> Result<AuditChainStatus, RegistryError> {
+        let entries = self.audit_entries(0, u32::MAX)?;
+        verify_chain(&entries).map_err(RegistryError::AuditChainBroken)
+    }
+
+    /// [`Self::verify_audit_chain`], and the chain must still contain
+    /// `anchor`, a head recorded outside the log earlier.
+    fn verify_audit_chain_from(
+        &self,
+        anchor: &AuditChainStatus,
+    ) -> Result<AuditChainStatus, RegistryError> {
+        let entries = self.audit_entries(0, u32::MAX)?;
+        verify_chain_extends(&entries, anchor).map_err(RegistryError::AuditChainBroken)
+    }
+}
+
+/// Guard returned by [`RegistryStorage::lock_payouts`]; releases the lock
+/// when dropped.
+#[derive(Default)]
+pub struct PayoutLock<'a> {
+    release: Option<Box<dyn FnOnce() + 'a>>,
+}
+
+impl<'a> PayoutLock<'a> {
+    /// A held lock that `release` gives up.
+    pub fn new(release: impl FnOnce() + 'a) -> Self {
+        Self {
+            release: Some(Box::new(release)),
+        }
+    }
+}
+
+impl Drop for PayoutLock<'_> {
+    fn drop(&mut self) {
+        if let Some(release) = self.release.take() {
+            release();
+        }
+    }
+}
+
+/// Audit chain head kept in a file outside the database, so that cutting
+/// the log back past it, which leaves a valid chain, is still detected.
+/// Place it where the database's writers cannot reach.
+#[derive(Debug)]
+pub struct AuditAnchorFile {
+    path: PathBuf,
+    /// Serializes check-and-advance so the anchor never moves backwards.
+    lock: Mutex<()>,
+}
+
+impl AuditAnchorFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The recorded head; `None` before one was stored.
+    pub fn load(&self) -> Result<Option<AuditChainStatus>, RegistryError> {
+        match fs::read_to_string(&self.path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map(Some)
+                .map_err(|e| self.error(e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(self.error(e)),
+        }
+    }
+
+    /// Replaces the recorded head through a rename, so a crash leaves the
+    /// old or the new head.
+    pub fn store(&self, head: &AuditChainStatus) -> Result<(), RegistryError> {
+        let json = serde_json::to_string(head).map_err(|e| self.error(e))?;
+        let partial = self.path.with_extension("partial");
+        fs::write(&partial, json)
+            .and_then(|_| fs::rename(&partial, &self.path))
+            .map_err(|e| self.error(e))
+    }
+
+    /// Verifies `registry`'s audit chain still contains the recorded head,
+    /// then records the current one.
+    pub fn advance(
+        &self,
+        registry: &dyn RegistryStorage,
+    ) -> Result<AuditChainStatus, RegistryError> {
+        let _guard = self
+            .lock
+            .lock()
+            .map_err(|_| RegistryError::Storage("audit anchor lock poisoned".into()))?;
+        let status = match self.load()? {
+            Some(anchor) => registry.verify_audit_chain_from(&anchor)?,
+            None => registry.verify_audit_chain()?,
+        };
+        self.store(&status)?;
+        Ok(status)
+    }
+
+    fn error(&self, err: impl std::fmt::Display) -> RegistryError {
+        RegistryError::Storage(format!("audit anchor {}: {err}", self.path.display()))
+    }
+}
+
+/// A registered address and its position in the participant's list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParticipantAddress {
+    pub address: String,
+    pub position: u32,
+}
+
+/// A participant with its addresses in position order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParticipantRecord {
+    pub participant_id: String,
+    /// Deactivated participants keep their addresses reserved but are no
+    /// longer resolved by [`ParticipantRegistry::addresses_for`].
+    pub active: bool,
+    /// RFC 3339; `None` for participants inserted before registration was
+    /// recorded.
+    pub registered_at: Option<String>,
+    /// Block height the participant was deactivated at; `None` while
+    /// active, or for participants deactivated before it was recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deactivated_height: Option<u64>,
+    pub addresses: Vec<ParticipantAddress>,
+}
+
+/// One page of participants ordered by id.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParticipantPage {
+    pub participants: Vec<ParticipantRecord>,
+    pub total_count: usize,
+}
+
+/// Why a participant write was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    /// No registry is configured.
+    Unavailable,
+    /// Opened read-only; writes are refused.
+    ReadOnly,
+    ParticipantExists(String),
+    ParticipantNotFound(String),
+    /// Deactivated participants cannot be modified.
+    ParticipantInactive(String),
+    /// An address belongs to at most one participant, active or not.
+    AddressInUse {
+        address: String,
+        participant_id: String,
+    },
+    AddressNotFound {
+        participant_id: String,
+        address: String,
+    },
+    /// Each of a participant's addresses has its own position.
+    PositionInUse {
+        participant_id: String,
+        position: u32,
+    },
+    StakeExists(OutPoint),
+    StakeNotFound(OutPoint),
+    /// A pool UTXO is already reserved by another payout.
+    UtxoReserved {
+        outpoint: OutPoint,
+        payout_id: String,
+    },
+    /// The audit log was altered outside the registry.
+    AuditChainBroken(AuditChainError),
+    InvalidInput(String),
+    Storage(String),
+}
+
+impl RegistryError {
+    /// Stable machine-readable code for API error bodies.
+    pub fn code(&self) -> &'static str {
+        match self {
+            RegistryError::Unavailable => "registry_unavailable",
+            RegistryError::ReadOnly => "registry_read_only",
+            RegistryError::ParticipantExists(_) => "participant_exists",
+            RegistryError::ParticipantNotFound(_) => "participant_not_found",
+            RegistryError::ParticipantInactive(_) => "participant_inactive",
+            RegistryError::AddressInUse { .. } => "address_in_use",
+            RegistryError::AddressNotFound { .. } => "address_not_found",
+            RegistryError::PositionInUse { .. } => "position_in_use",
+            RegistryError::StakeExists(_) => "stake_exists",
+            RegistryError::StakeNotFound(_) => "stake_not_found",
+            RegistryError::UtxoReserved { .. } => "utxo_reserved",
+            RegistryError::AuditChainBroken(_) => "audit_chain_broken",
+            RegistryError::InvalidInput(_) => "invalid_input",
+            RegistryError::Storage(_) => "storage_error",
+        }
+    }
+}
+
+impl std::fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryError::Unavailable => write!(f, "no participant registry is configured"),
+            RegistryError::ReadOnly => write!(f, "participant registry is read-only"),
+            RegistryError::ParticipantExists(id) => {
+                write!(f, "participant {id} is already registered")
+            }
+            RegistryError::ParticipantNotFound(id) => write!(f, "participant {id} not found"),
+            RegistryError::ParticipantInactive(id) => {
+                write!(f, "participant {id} is deactivated")
+            }
+            RegistryError::AddressInUse {
+                address,
+                participant_id,
+            } => write!(
+                f,
+                "address {address} is already registered to participant {participant_id}"
+            ),
+            RegistryError::AddressNotFound {
+                participant_id,
+                address,
+            } => write!(
+                f,
+                "address {address} is not registered to participant {participant_id}"
+            ),
+            RegistryError::PositionInUse {
+                participant_id,
+                position,
+            } => write!(
+                f,
+                "participant {participant_id} already has an address at position {position}"
+            ),
+            RegistryError::StakeExists(outpoint) => {
+                write!(f, "stake {outpoint} is already recorded")
+            }
+            RegistryError::StakeNotFound(outpoint) => write!(f, "stake {outpoint} not found"),
+            RegistryError::UtxoReserved {
+                outpoint,
+                payout_id,
+            } => write!(f, "pool UTXO {outpoint} is reserved by payout {payout_id}"),
+            RegistryError::AuditChainBroken(err) => write!(f, "audit log tampered: {err}"),
+            RegistryError::InvalidInput(msg) => write!(f, "invalid input: {msg}"),
+            RegistryError::Storage(msg) => write!(f, "registry storage error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+/// Input checks shared by the backends, made before opening a transaction.
+/// Returns the addresses in canonical form.
+pub(crate) fn check_registration(
+    participant_id: &str,
+    addresses: &[String],
+    network: Network,
+) -> Result<Vec<String>, RegistryError> {
+    if participant_id.trim().is_empty() {
+        return Err(RegistryError::InvalidInput(
+            "participant_id must not be empty".into(),
+        ));
+    }
+    let addresses = addresses
+        .iter()
+        .map(|address| check_address(address, network))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut seen = HashSet::new();
+    if let Some(dup) = addresses.iter().find(|a| !seen.insert(a.as_str())) {
+        return Err(RegistryError::InvalidInput(format!(
+            "address {dup} listed twice"
+        )));
+    }
+    Ok(addresses)
+}
+
+/// Canonical form of a payout address on `network`, so that spellings of
+/// one address (e.g. upper-case bech32) cannot be registered twice.
+pub(crate) fn check_address(address: &str, network: Network) -> Result<String, RegistryError> {
+    parse_address(address.trim(), network, AddressRole::Recipient)
+        .map(|address| address.to_string())
+        .map_err(|e| RegistryError::InvalidInput(e.to_string()))
+}
+
+/// Stored form of an address being looked up: canonical where it parses,
+/// verbatim otherwise so rows predating validation stay reachable.
+pub(crate) fn stored_address(address: &str, network: Network) -> String {
+    check_address(address, network).unwrap_or_else(|_| address.to_string())
+}
+
+/// Lock height of a valid new stake.
+pub(crate) fn check_stake(amount_sats: u64, witness_script: &Script) -> Result<u32, RegistryError> {
+    if amount_sats == 0 {
+        return Err(RegistryError::InvalidInput(
+            "stake amount must be greater than 0".into(),
+        ));
+    }
+    parse_stake_lock(witness_script)
+        .map(|(lock_height, _)| lock_height)
+        .ok_or_else(|| RegistryError::InvalidInput("witness script is not a stake lock".into()))
+}
+
+pub(crate) fn check_spend(stake: &StakePosition, spent_height: u64) -> Result<(), RegistryError> {
+    if stake.confirmed_height.is_some_and(|h| spent_height < h) {
+        return Err(RegistryError::InvalidInput(format!(
+            "stake {} cannot be spent before it confirmed",
+            stake.outpoint
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_trust(trust_coefficient: f64) -> Result<(), RegistryError> {
+    if !trust_coefficient.is_finite() || trust_coefficient < 0.0 {
+        return Err(RegistryError::InvalidInput(format!(
+            "trust coefficient must be a non-negative number, got {trust_coefficient}"
+        )));
+    }
+    Ok(())
+}
+
+/// Canonical address and amount of each output `payout` pays a recipient,
+/// the rows per-recipient velocity is counted from.
+pub(crate) fn payout_outputs(
+    payout: &PayoutTransactionResult,
+    network: Network,
+) -> Vec<(String, u64)> {
+    recipient_outputs(payout)
+        .into_iter()
+        .filter_map(|o| {
+            Address::from_script(&o.script_pubkey, network)
+                .ok()
+                .map(|address| (address.to_string(), o.value))
+        })
+        .collect()
+}
+
+/// Audit events for storing `payout`: its creation or an update without a
+/// status change, then each transition.
+pub(crate) fn payout_events(
+    payout: &PayoutTransactionResult,
+    is_new: bool,
+    transitions: &[PayoutTransition],
+) -> Vec<AuditEvent> {
+    let mut events = Vec::new();
+    if is_new {
+        events.push(AuditEvent::PayoutCreated {
+            payout_id: payout.payout_id.clone(),
+            recipient_address: payout.recipient_address.clone(),
+            amount_sats: payout.amount_sats,
+            fee_sats: payout.fee_sats,
+            status: payout.status.to_string(),
+            is_dry_run: payout.is_dry_run,
+        });
+    } else if transitions.is_empty() {
+        events.push(AuditEvent::PayoutUpdated {
+            payout_id: payout.payout_id.clone(),
+            status: payout.status.to_string(),
+            txid: payout.txid.clone(),
+        });
+    }
+    events.extend(
+        transitions
+            .iter()
+            .map(|transition| AuditEvent::PayoutStatusChanged {
+                payout_id: transition.payout_id.clone(),
+                from: transition.from.to_string(),
+                to: transition.to.to_string(),
+                confirmations: transition.confirmations,
+                detail: transition.detail.clone(),
+            }),
+    );
+    events
+}
+
+pub(crate) fn pool_utxo_json(utxo: &PoolUtxo) -> Result<String, RegistryError> {
+    serde_json::to_string(&PoolUtxoRecord::from(utxo))
+        .map_err(|e| RegistryError::Storage(e.to_string()))
+}
+
+pub(crate) fn pool_utxo_from_json(json: &str) -> Result<PoolUtxo, RegistryError> {
+    serde_json::from_str::<PoolUtxoRecord>(json)
+        .map_err(|e| e.to_string())
+        .and_then(PoolUtxo::try_from)
+        .map_err(|e| RegistryError::Storage(format!("invalid pool UTXO: {e}")))
+}
+
+pub(crate) fn stake_updated_event(stake: &StakePosition) -> AuditEvent {
+    AuditEvent::StakeUpdated {
+        outpoint: stake.outpoint.to_string(),
+        verification: stake.verification.to_string(),
+        confirmed_height: stake.confirmed_height,
+        spent_height: stake.spent_height,
+    }
+}
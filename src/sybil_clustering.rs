@@ -0,0 +1,501 @@
+//! Sybil clustering by common-input ownership.
+//!
+//! Addresses spent together as inputs of one transaction are assumed to be
+//! controlled by the same owner. Those links, together with the addresses
+//! each participant registered, partition all known addresses into
+//! clusters. Participants whose addresses fall into the same cluster are
+//! linked and scored; flagged groups can be merged or capped before
+//! distribution.
+
+use crate::rbi_engine::ParticipantSnapshot;
+use crate::velocity_analyzer::VelocityError;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// A transaction's input addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoSpend {
+    pub txid: String,
+    pub input_addresses: Vec<String>,
+}
+
+/// Trait: chain/indexer interface for input co-spends.
+pub trait CoSpendSource: Send + Sync {
+    /// Transactions in [start_height, end_height] inclusive spending from any
+    /// of `addresses`, with all of their input addresses.
+    fn co_spends_for_addresses(
+        &self,
+        addresses: &[String],
+        start_height: u64,
+        end_height: u64,
+    ) -> Result<Vec<CoSpend>, VelocityError>;
+}
+
+/// How flagged groups are weighted in distribution.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SybilWeighting {
+    /// Report only; snapshots are left as they are.
+    #[default]
+    ReportOnly,
+    /// Replace each flagged group with one participant holding the group's
+    /// combined stake at its stake-weighted trust.
+    Merge,
+    /// Scale down the group's trust so its combined weighted stake is at
+    /// most this share of the total.
+    Cap { max_group_share: f64 },
+}
+
+impl std::fmt::Display for SybilWeighting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SybilWeighting::ReportOnly => f.write_str("report"),
+            SybilWeighting::Merge => f.write_str("merge"),
+            SybilWeighting::Cap { max_group_share } => write!(f, "cap:{max_group_share}"),
+        }
+    }
+}
+
+impl std::str::FromStr for SybilWeighting {
+    type Err = String;
+
+    /// `report`, `merge` or `cap:<max_group_share>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "report" => Ok(SybilWeighting::ReportOnly),
+            "merge" => Ok(SybilWeighting::Merge),
+            _ => match s.strip_prefix("cap:").map(str::parse::<f64>) {
+                Some(Ok(max_group_share)) => Ok(SybilWeighting::Cap { max_group_share }),
+                _ => Err(format!("unknown sybil weighting: {s}")),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SybilConfig {
+    /// Participants scoring at or above this are flagged.
+    pub flag_threshold: f64,
+    pub weighting: SybilWeighting,
+    /// Blocks back from the current height scanned for co-spends.
+    pub lookback_blocks: u64,
+}
+
+impl Default for SybilConfig {
+    fn default() -> Self {
+        Self {
+            flag_threshold: 0.5,
+            weighting: SybilWeighting::ReportOnly,
+            // About a year of blocks
+            lookback_blocks: 52_560,
+        }
+    }
+}
+
+impl SybilConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.flag_threshold) {
+            return Err("flag_threshold must be within [0, 1]".into());
+        }
+        if self.lookback_blocks == 0 {
+            return Err("lookback_blocks must be > 0".into());
+        }
+        if let SybilWeighting::Cap { max_group_share } = self.weighting {
+            if !(max_group_share > 0.0 && max_group_share <= 1.0) {
+                return Err("max_group_share must be within (0, 1]".into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Clustering result for one participant.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParticipantSybilRisk {
+    pub participant_id: String,
+    /// Participants sharing a cluster share this id.
+    pub cluster_id: usize,
+    /// Other participants in the cluster, by id.
+    pub linked_participants: Vec<String>,
+    /// This participant's addresses also registered by a linked participant.
+    pub shared_addresses: Vec<String>,
+    /// Co-spends within the cluster, when it is shared.
+    pub evidence_txids: Vec<String>,
+    /// 0 when unlinked, 1 on exact address reuse, else `1 - 1/group_size`.
+    pub risk_score: f64,
+    pub flagged: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SybilReport {
+    /// Sorted by participant id.
+    pub participants: Vec<ParticipantSybilRisk>,
+}
+
+impl SybilReport {
+    pub fn get(&self, participant_id: &str) -> Option<&ParticipantSybilRisk> {
+        self.participants
+            .binary_search_by(|p| p.participant_id.as_str().cmp(participant_id))
+            .ok()
+            .map(|i| &self.participants[i])
+    }
+
+    /// Flagged participants grouped by cluster, each group sorted by id.
+    pub fn flagged_groups(&self) -> Vec<Vec<String>> {
+        let mut groups: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+        for risk in self.participants.iter().filter(|p| p.flagged) {
+            groups
+                .entry(risk.cluster_id)
+                .or_default()
+                .push(risk.participant_id.clone());
+        }
+        groups.into_values().filter(|g| g.len() > 1).collect()
+    }
+}
+
+/// Union-find over address indices.
+struct DisjointSet {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+}
+
+impl DisjointSet {
+    fn new() -> Self {
+        Self {
+            parent: Vec::new(),
+            rank: Vec::new(),
+        }
+    }
+
+    fn add(&mut self) -> usize {
+        self.parent.push(self.parent.len());
+        self.rank.push(0);
+        self.parent.len() - 1
+    }
+
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        match self.rank[a].cmp(&self.rank[b]) {
+            std::cmp::Ordering::Less => self.parent[a] = b,
+            std::cmp::Ordering::Greater => self.parent[b] = a,
+            std::cmp::Ordering::Equal => {
+                self.parent[b] = a;
+                self.rank[a] += 1;
+            }
+        }
+    }
+}
+
+/// Cluster `participants` (id and registered addresses) using `co_spends`.
+pub fn analyze_clusters(
+    participants: &[(String, Vec<String>)],
+    co_spends: &[CoSpend],
+    config: &SybilConfig,
+) -> SybilReport {
+    let mut sets = DisjointSet::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+
+    let mut owners: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+    let mut roots_of: Vec<(&str, Option<usize>)> = Vec::new();
+    for (participant_id, addresses) in participants {
+        let mut first = None;
+        for address in addresses {
+            let n = *index.entry(address).or_insert_with(|| sets.add());
+            owners.entry(address).or_default().insert(participant_id);
+            match first {
+                Some(f) => sets.union(f, n),
+                None => first = Some(n),
+            }
+        }
+        roots_of.push((participant_id, first));
+    }
+    let mut spend_nodes = Vec::with_capacity(co_spends.len());
+    for spend in co_spends {
+        let mut first = None;
+        for address in &spend.input_addresses {
+            let n = *index.entry(address).or_insert_with(|| sets.add());
+            match first {
+                Some(f) => sets.union(f, n),
+                None => first = Some(n),
+            }
+        }
+        spend_nodes.push(first);
+    }
+
+    // Participants by cluster root; address-less participants stand alone
+    let mut members: BTreeMap<usize, BTreeSet<&str>> = BTreeMap::new();
+    let mut root_of: HashMap<&str, Option<usize>> = HashMap::new();
+    for (participant_id, first) in &roots_of {
+        let root = first.map(|f| sets.find(f));
+        if let Some(root) = root {
+            members.entry(root).or_default().insert(participant_id);
+        }
+        root_of.insert(participant_id, root);
+    }
+    let mut evidence: HashMap<usize, BTreeSet<&str>> = HashMap::new();
+    for (spend, first) in co_spends.iter().zip(spend_nodes) {
+        if let Some(first) = first {
+            evidence
+                .entry(sets.find(first))
+                .or_default()
+                .insert(spend.txid.as_str());
+        }
+    }
+
+    // Stable cluster ids: clusters numbered by their first participant id
+    let mut ordered: Vec<(&str, usize)> = members
+        .iter()
+        .map(|(root, ids)| (*ids.iter().next().expect("non-empty cluster"), *root))
+        .collect();
+    ordered.sort();
+    let cluster_ids: HashMap<usize, usize> = ordered
+        .iter()
+        .enumerate()
+        .map(|(id, (_, root))| (*root, id))
+        .collect();
+    let mut next_id = ordered.len();
+
+    let mut report: Vec<ParticipantSybilRisk> = participants
+        .iter()
+        .map(|(participant_id, addresses)| {
+            let root = root_of[participant_id.as_str()];
+            let cluster_id = match root {
+                Some(root) => cluster_ids[&root],
+                None => {
+                    next_id += 1;
+                    next_id - 1
+                }
+            };
+            let linked_participants: Vec<String> = root
+                .map(|root| {
+                    members[&root]
+                        .iter()
+                        .filter(|id| **id != participant_id.as_str())
+                        .map(|id| id.to_string())
+                        .collect()
+                })
+                .unwrap_or_default();
+            let mut shared_addresses: Vec<String> = addresses
+                .iter()
+                .filter(|a| owners[a.as_str()].len() > 1)
+                .cloned()
+                .collect();
+            shared_addresses.sort();
+            shared_addresses.dedup();
+            let evidence_txids = match root {
+                Some(root) if !linked_participants.is_empty() => evidence
+                    .get(&root)
+                    .map(|txids| txids.iter().map(|t| t.to_string()).collect())
+                    .unwrap_or_default(),
+                _ => Vec::new(),
+            };
+
+            let risk_score = if linked_participants.is_empty() {
+                0.0
+            } else if !shared_addresses.is_empty() {
+                1.0
+            } else {
+                1.0 - 1.0 / (linked_participants.len() + 1) as f64
+            };
+            ParticipantSybilRisk {
+                participant_id: participant_id.clone(),
+                cluster_id,
+                linked_participants,
+                shared_addresses,
+                evidence_txids,
+                risk_score,
+                flagged: risk_score > 0.0 && risk_score >= config.flag_threshold,
+            }
+        })
+        .collect();
+    report.sort_by(|a, b| a.participant_id.cmp(&b.participant_id));
+    SybilReport {
+        participants: report,
+    }
+}
+
+/// Apply `weighting` to the flagged groups of `report`. Other snapshots are
+/// returned unchanged and in order.
+pub fn apply_sybil_weighting(
+    snapshots: Vec<ParticipantSnapshot>,
+    report: &SybilReport,
+    weighting: SybilWeighting,
+) -> Vec<ParticipantSnapshot> {
+    let groups = report.flagged_groups();
+    if weighting == SybilWeighting::ReportOnly || groups.is_empty() {
+        return snapshots;
+    }
+    let group_of: HashMap<&str, usize> = groups
+        .iter()
+        .enumerate()
+        .flat_map(|(g, ids)| ids.iter().map(move |id| (id.as_str(), g)))
+        .collect();
+    let weight = |s: &ParticipantSnapshot| s.stake_amount_sats as f64 * s.trust_coefficient;
+
+    match weighting {
+        SybilWeighting::ReportOnly => snapshots,
+        SybilWeighting::Merge => {
+            let mut out: Vec<ParticipantSnapshot> = Vec::with_capacity(snapshots.len());
+            let mut slots: Vec<Option<usize>> = vec![None; groups.len()];
+            for snapshot in snapshots {
+                let Some(&g) = group_of.get(snapshot.participant_id.as_str()) else {
+                    out.push(snapshot);
+                    continue;
+                };
+                let Some(slot) = slots[g] else {
+                    slots[g] = Some(out.len());
+                    out.push(snapshot);
+                    continue;
+                };
+                let acc = &mut out[slot];
+                let stake = acc.stake_amount_sats + snapshot.stake_amount_sats;
+                acc.trust_coefficient = if stake == 0 {
+                    acc.trust_coefficient.max(snapshot.trust_coefficient)
+                } else {
+                    (weight(acc) + weight(&snapshot)) / stake as f64
+                };
+                acc.stake_amount_sats = stake;
+                acc.participant_id = format!("{}+{}", acc.participant_id, snapshot.participant_id);
+            }
+            out
+        }
+        SybilWeighting::Cap { max_group_share } => {
+            let total: f64 = snapshots.iter().map(weight).sum();
+            let mut group_weight = vec![0.0_f64; groups.len()];
+            for snapshot in &snapshots {
+                if let Some(&g) = group_of.get(snapshot.participant_id.as_str()) {
+                    group_weight[g] += weight(snapshot);
+                }
+            }
+            snapshots
+                .into_iter()
+                .map(|mut snapshot| {
+                    if let Some(&g) = group_of.get(snapshot.participant_id.as_str()) {
+                        let limit = max_group_share * total;
+                        if group_weight[g] > limit {
+                            snapshot.trust_coefficient *= limit / group_weight[g];
+                        }
+                    }
+                    snapshot
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn participant(id: &str, addresses: &[&str]) -> (String, Vec<String>) {
+        (
+            id.to_string(),
+            addresses.iter().map(|a| a.to_string()).collect(),
+        )
+    }
+
+    fn co_spend(txid: &str, inputs: &[&str]) -> CoSpend {
+        CoSpend {
+            txid: txid.to_string(),
+            input_addresses: inputs.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn co_spends_link_participants_transitively() {
+        let participants = [
+            participant("alice", &["a1", "a2"]),
+            participant("bob", &["b1"]),
+            participant("carol", &["c1"]),
+            participant("dave", &[]),
+        ];
+        // a2 and x co-spend, then x and b1: alice and bob share an owner
+        let spends = [
+            co_spend("tx1", &["a2", "x"]),
+            co_spend("tx2", &["x", "b1"]),
+            co_spend("tx3", &["c1", "y"]),
+        ];
+        let report = analyze_clusters(&participants, &spends, &SybilConfig::default());
+
+        let alice = report.get("alice").unwrap();
+        assert_eq!(alice.linked_participants, ["bob"]);
+        assert_eq!(alice.evidence_txids, ["tx1", "tx2"]);
+        assert_eq!(alice.risk_score, 0.5);
+        assert!(alice.flagged);
+        assert_eq!(report.get("bob").unwrap().cluster_id, alice.cluster_id);
+
+        let carol = report.get("carol").unwrap();
+        assert!(carol.linked_participants.is_empty());
+        assert!(carol.evidence_txids.is_empty());
+        assert!(!carol.flagged);
+        assert_ne!(report.get("dave").unwrap().cluster_id, carol.cluster_id);
+        assert_eq!(report.flagged_groups(), vec![vec!["alice", "bob"]]);
+    }
+
+    #[test]
+    fn exact_address_reuse_scores_highest() {
+        let participants = [
+            participant("mallory", &["addr-dup"]),
+            participant("mallory-clone", &["addr-dup", "m2"]),
+        ];
+        let report = analyze_clusters(&participants, &[], &SybilConfig::default());
+        let clone = report.get("mallory-clone").unwrap();
+        assert_eq!(clone.shared_addresses, ["addr-dup"]);
+        assert_eq!(clone.risk_score, 1.0);
+    }
+
+    #[test]
+    fn merge_combines_flagged_groups_in_place() {
+        let participants = [
+            participant("alice", &["a1"]),
+            participant("bob", &["b1"]),
+            participant("carol", &["c1"]),
+        ];
+        let report = analyze_clusters(
+            &participants,
+            &[co_spend("tx1", &["c1", "a1"])],
+            &SybilConfig::default(),
+        );
+        let snapshot = |id: &str, stake, trust| ParticipantSnapshot {
+            participant_id: id.to_string(),
+            stake_amount_sats: stake,
+            trust_coefficient: trust,
+        };
+        let merged = apply_sybil_weighting(
+            vec![
+                snapshot("carol", 30, 1.0),
+                snapshot("bob", 50, 1.0),
+                snapshot("alice", 10, 2.0),
+            ],
+            &report,
+            SybilWeighting::Merge,
+        );
+        let merged: Vec<_> = merged
+            .iter()
+            .map(|s| {
+                (
+                    s.participant_id.as_str(),
+                    s.stake_amount_sats,
+                    s.trust_coefficient,
+                )
+            })
+            .collect();
+        assert_eq!(merged, [("carol+alice", 40, 1.25), ("bob", 50, 1.0)]);
+        assert_eq!(
+            "cap:0.2".parse(),
+            Ok(SybilWeighting::Cap {
+                max_group_share: 0.2
+            })
+        );
+    }
+}
@@ -0,0 +1,159 @@
+#![cfg(feature = "rpc")]
+
+use bitcoin::hashes::Hash;
+use bitcoin::{absolute, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness};
+use bitcoin_digital_labor_derivative::bitcoin_core_chain::BitcoinCoreChainDataSource;
+use bitcoin_digital_labor_derivative::disbursement::{BroadcastError, Broadcaster};
+use bitcoincore_rpc::{Auth, Client};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+/// JSON-RPC methods the stub node was called with.
+type Calls = Arc<Mutex<Vec<String>>>;
+
+/// Answers each JSON-RPC call with `reply(method)`: `Ok(result)` or an RPC
+/// error `(code, message)`. Returns the node URL and the methods called.
+fn serve<F>(reply: F) -> (String, Calls)
+where
+    F: Fn(&str) -> Result<Value, (i64, &'static str)> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind stub node");
+    let addr = listener.local_addr().unwrap();
+    let calls = Calls::default();
+    let log = calls.clone();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            // The client keeps the connection open between calls
+            loop {
+                let mut request_line = String::new();
+                if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+                    break;
+                }
+                let mut content_length = 0;
+                let mut line = String::new();
+                while reader.read_line(&mut line).map(|n| n > 0).unwrap_or(false) {
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap_or(0);
+                        }
+                    }
+                    line.clear();
+                }
+                let mut body = vec![0; content_length];
+                if reader.read_exact(&mut body).is_err() {
+                    break;
+                }
+
+                let request: Value = serde_json::from_slice(&body).unwrap();
+                let method = request["method"].as_str().unwrap_or_default().to_string();
+                let response = match reply(&method) {
+                    Ok(result) => json!({ "result": result, "error": null, "id": request["id"] }),
+                    Err((code, message)) => json!({
+                        "result": null,
+                        "error": { "code": code, "message": message },
+                        "id": request["id"],
+                    }),
+                };
+                log.lock().unwrap().push(method);
+                let response = response.to_string();
+                let _ = stream.write_all(
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                        response.len(),
+                        response
+                    )
+                    .as_bytes(),
+                );
+            }
+        }
+    });
+    (format!("http://{addr}"), calls)
+}
+
+fn core(url: &str) -> BitcoinCoreChainDataSource {
+    BitcoinCoreChainDataSource::new(Client::new(url, Auth::None).unwrap())
+}
+
+fn payout_tx() -> Transaction {
+    Transaction {
+        version: 2,
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(Txid::from_slice(&[9; 32]).unwrap(), 0),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: 99_000,
+            script_pubkey: ScriptBuf::new_op_return(&[]),
+        }],
+    }
+}
+
+#[test]
+fn accepted_transactions_are_test_accepted_then_sent() {
+    let tx = payout_tx();
+    let txid = tx.txid();
+    let (url, calls) = serve(move |method| match method {
+        "testmempoolaccept" => Ok(json!([{ "txid": txid, "allowed": true, "vsize": 100 }])),
+        "sendrawtransaction" => Ok(json!(txid)),
+        _ => Err((-32601, "Method not found")),
+    });
+
+    assert_eq!(core(&url).broadcast(&tx), Ok(txid));
+    assert_eq!(
+        *calls.lock().unwrap(),
+        ["testmempoolaccept", "sendrawtransaction"]
+    );
+}
+
+#[test]
+fn policy_rejections_are_not_relayed() {
+    let tx = payout_tx();
+    let txid = tx.txid();
+    let (url, calls) = serve(move |method| match method {
+        "testmempoolaccept" => Ok(json!([{
+            "txid": txid,
+            "allowed": false,
+            "reject-reason": "min relay fee not met, 100 < 141",
+        }])),
+        _ => Err((-32601, "Method not found")),
+    });
+
+    assert_eq!(
+        core(&url).broadcast(&tx),
+        Err(BroadcastError::InsufficientFee(
+            "min relay fee not met, 100 < 141".into()
+        ))
+    );
+    assert_eq!(*calls.lock().unwrap(), ["testmempoolaccept"]);
+}
+
+#[test]
+fn rpc_errors_are_classified() {
+    let tx = payout_tx();
+    let txid = tx.txid();
+    let (url, _) = serve(move |method| match method {
+        "testmempoolaccept" => Ok(json!([{ "txid": txid, "allowed": true }])),
+        _ => Err((-25, "bad-txns-inputs-missingorspent")),
+    });
+    assert_eq!(
+        core(&url).broadcast(&tx),
+        Err(BroadcastError::MissingInputs)
+    );
+
+    let closed = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = closed.local_addr().unwrap();
+    drop(closed);
+    assert!(matches!(
+        core(&format!("http://{addr}")).broadcast(&tx),
+        Err(BroadcastError::Transport(_))
+    ));
+}
@@ -0,0 +1,41 @@
+//! Helpers shared by the integration tests. Each test crate uses a subset.
+#![allow(dead_code)]
+
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::{Secp256k1, SecretKey};
+use bitcoin::{Address, Network, OutPoint, PublicKey, ScriptBuf, Txid};
+use bitcoin_digital_labor_derivative::stake::stake_lock_script;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// SQLite file for test `name` in this test process.
+pub fn db_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("bdld_{name}_{}.db", std::process::id()))
+}
+
+/// [`db_path`], removed first so each run starts from an empty registry.
+pub fn temp_db(name: &str) -> PathBuf {
+    let path = db_path(name);
+    let _ = fs::remove_file(&path);
+    path
+}
+
+pub fn outpoint(n: u8) -> OutPoint {
+    OutPoint {
+        txid: Txid::from_slice(&[n; 32]).unwrap(),
+        vout: n as u32,
+    }
+}
+
+/// Stake lock script expiring at `height`, for a fixed test key.
+pub fn lock(height: u32) -> ScriptBuf {
+    let secp = Secp256k1::new();
+    let key = PublicKey::new(SecretKey::from_slice(&[9; 32]).unwrap().public_key(&secp));
+    stake_lock_script(height, &key)
+}
+
+/// Distinct mainnet payout address per `n`.
+pub fn address(n: u8) -> String {
+    Address::p2wsh(&ScriptBuf::from_bytes(vec![n]), Network::Bitcoin).to_string()
+}
@@ -0,0 +1,264 @@
+#![cfg(feature = "esplora")]
+
+use bitcoin::hashes::Hash;
+use bitcoin::{absolute, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness};
+use bitcoin_digital_labor_derivative::disbursement::{
+    BroadcastError, Broadcaster, ConfirmationSource, TxChainStatus,
+};
+use bitcoin_digital_labor_derivative::esplora_broadcaster::EsploraBroadcaster;
+use bitcoin_digital_labor_derivative::sybil_clustering::{CoSpend, CoSpendSource};
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Requests the stub server saw, as `"METHOD /path"` and body.
+type Seen = Arc<Mutex<Vec<(String, String)>>>;
+
+/// Serves canned `(status, body)` responses keyed by `"METHOD /path"` on a
+/// local port until the test ends; unknown routes get a 404. Returns the API
+/// root and the requests received.
+fn serve(routes: &[(&str, u16, &str)]) -> (String, Seen) {
+    let routes: HashMap<String, (u16, String)> = routes
+        .iter()
+        .map(|(route, status, body)| (route.to_string(), (*status, body.to_string())))
+        .collect();
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind stub server");
+    let addr = listener.local_addr().unwrap();
+    let seen = Seen::default();
+    let log = seen.clone();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            let _ = reader.read_line(&mut request_line);
+            let mut content_length = 0;
+            let mut line = String::new();
+            while reader.read_line(&mut line).map(|n| n > 0).unwrap_or(false) {
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap_or(0);
+                    }
+                }
+                line.clear();
+            }
+            let mut body = vec![0; content_length];
+            let _ = reader.read_exact(&mut body);
+
+            let route = request_line
+                .split_whitespace()
+                .take(2)
+                .collect::<Vec<_>>()
+                .join(" ");
+            log.lock()
+                .unwrap()
+                .push((route.clone(), String::from_utf8_lossy(&body).into_owned()));
+            let (status, body) = routes
+                .get(&route)
+                .cloned()
+                .unwrap_or((404, "Not Found".to_string()));
+            let response = format!(
+                "HTTP/1.1 {status} Stub\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    (format!("http://{addr}/api/"), seen)
+}
+
+fn txid(n: u8) -> Txid {
+    Txid::from_slice(&[n; 32]).unwrap()
+}
+
+fn payout_tx() -> Transaction {
+    Transaction {
+        version: 2,
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(txid(9), 0),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::new(),
+        }],
+        output: vec![TxOut {
+            value: 99_000,
+            script_pubkey: ScriptBuf::new_op_return(&[]),
+        }],
+    }
+}
+
+#[test]
+fn broadcasts_post_the_raw_transaction() {
+    let tx = payout_tx();
+    let (url, seen) = serve(&[("POST /api/tx", 200, &tx.txid().to_string())]);
+    let esplora = EsploraBroadcaster::new(url, Duration::from_secs(5));
+
+    assert_eq!(esplora.broadcast(&tx), Ok(tx.txid()));
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen[0].0, "POST /api/tx");
+    assert_eq!(seen[0].1, hex::encode(bitcoin::consensus::serialize(&tx)));
+}
+
+#[test]
+fn broadcast_rejections_are_classified() {
+    let tx = payout_tx();
+    let cases = [
+        (
+            400,
+            r#"sendrawtransaction RPC error: {"code":-25,"message":"bad-txns-inputs-missingorspent"}"#,
+            BroadcastError::MissingInputs,
+        ),
+        (
+            400,
+            r#"sendrawtransaction RPC error: {"code":-27,"message":"txn-already-in-mempool"}"#,
+            BroadcastError::AlreadyKnown,
+        ),
+        (
+            400,
+            r#"sendrawtransaction RPC error: {"code":-26,"message":"dust"}"#,
+            BroadcastError::NonStandard(
+                r#"sendrawtransaction RPC error: {"code":-26,"message":"dust"}"#.into(),
+            ),
+        ),
+    ];
+    for (status, body, expected) in cases {
+        let (url, _) = serve(&[("POST /api/tx", status, body)]);
+        assert_eq!(
+            EsploraBroadcaster::new(url, Duration::from_secs(5)).broadcast(&tx),
+            Err(expected)
+        );
+    }
+
+    // A server error or an unexpected reply decided nothing
+    for (status, body) in [(500, "upstream down"), (200, "not a txid")] {
+        let (url, _) = serve(&[("POST /api/tx", status, body)]);
+        assert!(matches!(
+            EsploraBroadcaster::new(url, Duration::from_secs(5)).broadcast(&tx),
+            Err(BroadcastError::Transport(_))
+        ));
+    }
+}
+
+#[test]
+fn confirmations_count_from_the_tip() {
+    let confirmed = format!("GET /api/tx/{}/status", txid(1));
+    let pending = format!("GET /api/tx/{}/status", txid(2));
+    let (url, _) = serve(&[
+        (
+            &confirmed,
+            200,
+            r#"{"confirmed":true,"block_height":100,"block_hash":"00","block_time":1}"#,
+        ),
+        (&pending, 200, r#"{"confirmed":false}"#),
+        ("GET /api/blocks/tip/height", 200, "105"),
+    ]);
+    let esplora = EsploraBroadcaster::new(url, Duration::from_secs(5));
+
+    assert_eq!(
+        esplora.transaction_status(&txid(1)).unwrap(),
+        TxChainStatus::Confirmed { confirmations: 6 }
+    );
+    assert_eq!(
+        esplora.transaction_status(&txid(2)).unwrap(),
+        TxChainStatus::InMempool
+    );
+    assert_eq!(
+        esplora.transaction_status(&txid(3)).unwrap(),
+        TxChainStatus::Unknown
+    );
+}
+
+#[test]
+fn missing_tip_or_backend_is_an_error() {
+    // A confirmed status without a tip height cannot be counted
+    let confirmed = format!("GET /api/tx/{}/status", txid(1));
+    let (url, _) = serve(&[(&confirmed, 200, r#"{"confirmed":true,"block_height":100}"#)]);
+    assert!(EsploraBroadcaster::new(url, Duration::from_secs(5))
+        .transaction_status(&txid(1))
+        .is_err());
+
+    let closed = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = closed.local_addr().unwrap();
+    drop(closed);
+    assert!(
+        EsploraBroadcaster::new(format!("http://{addr}"), Duration::from_secs(5))
+            .transaction_status(&txid(1))
+            .is_err()
+    );
+}
+
+/// Esplora's JSON for a confirmed transaction spending from `inputs`.
+fn history_tx(txid: &str, height: u64, inputs: &[&str]) -> serde_json::Value {
+    let vin: Vec<_> = inputs
+        .iter()
+        .map(|address| json!({"prevout": {"scriptpubkey_address": address}}))
+        .collect();
+    json!({
+        "txid": txid,
+        "vin": vin,
+        "status": {"confirmed": true, "block_height": height},
+    })
+}
+
+#[test]
+fn co_spends_page_through_confirmed_history() {
+    // A full first page: a co-spend, a lone spend, then receipts
+    let mut first = vec![
+        history_tx("t0", 120, &["alice", "change"]),
+        history_tx("t1", 119, &["alice", "alice"]),
+    ];
+    first.extend((2..25).map(|n| history_tx(&format!("t{n}"), 120 - n, &["payer", "other"])));
+    let second = json!([
+        history_tx("t25", 90, &["bob", "alice"]),
+        history_tx("t26", 50, &["alice", "carol"]),
+        {"txid": "coinbase", "vin": [{"prevout": null}], "status": {"confirmed": true, "block_height": 95}},
+    ]);
+    let (first, second) = (json!(first).to_string(), second.to_string());
+    let bob = json!([history_tx("t25", 90, &["bob", "alice"])]).to_string();
+    let (url, seen) = serve(&[
+        ("GET /api/address/alice/txs/chain", 200, &first),
+        ("GET /api/address/alice/txs/chain/t24", 200, &second),
+        ("GET /api/address/bob/txs/chain", 200, &bob),
+    ]);
+    let esplora = EsploraBroadcaster::new(url, Duration::from_secs(5));
+    let addresses = ["alice".to_string(), "bob".to_string()];
+
+    // Inside the window and linking at least two addresses, each once
+    assert_eq!(
+        esplora
+            .co_spends_for_addresses(&addresses, 60, 130)
+            .unwrap(),
+        vec![
+            CoSpend {
+                txid: "t0".into(),
+                input_addresses: vec!["alice".into(), "change".into()],
+            },
+            CoSpend {
+                txid: "t25".into(),
+                input_addresses: vec!["bob".into(), "alice".into()],
+            },
+        ]
+    );
+    assert_eq!(seen.lock().unwrap().len(), 3);
+
+    // A first page reaching below the window ends the walk
+    seen.lock().unwrap().clear();
+    let recent = esplora
+        .co_spends_for_addresses(&addresses[..1], 100, 130)
+        .unwrap();
+    assert_eq!(recent.len(), 1);
+    assert_eq!(seen.lock().unwrap().len(), 1);
+
+    // An unreachable backend is an error, not an empty history
+    let (url, _) = serve(&[("GET /api/address/alice/txs/chain", 500, "down")]);
+    assert!(EsploraBroadcaster::new(url, Duration::from_secs(5))
+        .co_spends_for_addresses(&addresses[..1], 60, 130)
+        .is_err());
+}
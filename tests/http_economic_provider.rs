@@ -0,0 +1,124 @@
+#![cfg(feature = "http-oracle")]
+
+use bitcoin::secp256k1::{KeyPair, Message, Secp256k1, SecretKey};
+use bitcoin_digital_labor_derivative::economic_oracle::EconomicDataProvider;
+use bitcoin_digital_labor_derivative::http_economic_provider::{
+    feed_message_digest, FeedPublisherKey, HttpEconomicProvider, HttpEconomicProviderConfig,
+    SignedFeedDocument, SignedFeedPayload,
+};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+
+/// Serves `body` once on a local port and returns the feed URL.
+fn serve_once(body: String) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind stub server");
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        if let Ok((mut stream, _)) = listener.accept() {
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).map(|n| n > 0).unwrap_or(false) {
+                if line == "\r\n" {
+                    break;
+                }
+                line.clear();
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    format!("http://{addr}/feed.json")
+}
+
+fn now() -> u64 {
+    chrono::Utc::now().timestamp() as u64
+}
+
+fn publisher() -> (Secp256k1<bitcoin::secp256k1::All>, SecretKey) {
+    (
+        Secp256k1::new(),
+        SecretKey::from_slice(&[7u8; 32]).expect("valid secret key"),
+    )
+}
+
+fn schnorr_document(payload: SignedFeedPayload) -> (FeedPublisherKey, SignedFeedDocument) {
+    let (secp, sk) = publisher();
+    let keypair = KeyPair::from_secret_key(&secp, &sk);
+    let msg = Message::from_slice(&feed_message_digest(&payload)).unwrap();
+    let sig = secp.sign_schnorr_no_aux_rand(&msg, &keypair);
+    (
+        FeedPublisherKey::Schnorr(keypair.x_only_public_key().0),
+        SignedFeedDocument {
+            payload,
+            signature: hex::encode(sig.as_ref()),
+        },
+    )
+}
+
+#[test]
+fn schnorr_signed_feed_is_accepted() {
+    let (key, document) = schnorr_document(SignedFeedPayload {
+        timestamp: now(),
+        demand_shock: 0.02,
+        productivity: 0.05,
+    });
+    let url = serve_once(serde_json::to_string(&document).unwrap());
+    let provider = HttpEconomicProvider::new(HttpEconomicProviderConfig::new(url, key));
+
+    let observation = provider.observe().expect("verified feed");
+    assert_eq!(observation.demand_shock, 0.02);
+    assert_eq!(observation.productivity, 0.05);
+}
+
+#[test]
+fn ecdsa_signed_feed_is_accepted() {
+    let (secp, sk) = publisher();
+    let payload = SignedFeedPayload {
+        timestamp: now(),
+        demand_shock: 0.03,
+        productivity: 0.04,
+    };
+    let msg = Message::from_slice(&feed_message_digest(&payload)).unwrap();
+    let document = SignedFeedDocument {
+        payload,
+        signature: hex::encode(secp.sign_ecdsa(&msg, &sk).serialize_der()),
+    };
+    let url = serve_once(serde_json::to_string(&document).unwrap());
+    let key = FeedPublisherKey::Ecdsa(sk.public_key(&secp));
+    let provider = HttpEconomicProvider::new(HttpEconomicProviderConfig::new(url, key));
+
+    assert_eq!(provider.demand_shock_rate().unwrap(), 0.03);
+}
+
+#[test]
+fn tampered_payload_is_rejected() {
+    let (key, mut document) = schnorr_document(SignedFeedPayload {
+        timestamp: now(),
+        demand_shock: 0.02,
+        productivity: 0.05,
+    });
+    document.payload.demand_shock = 0.0001;
+    let url = serve_once(serde_json::to_string(&document).unwrap());
+    let provider = HttpEconomicProvider::new(HttpEconomicProviderConfig::new(url, key));
+
+    let err = provider.observe().unwrap_err().to_string();
+    assert!(err.contains("signature verification failed"), "{err}");
+}
+
+#[test]
+fn stale_payload_is_rejected() {
+    let (key, document) = schnorr_document(SignedFeedPayload {
+        timestamp: now() - 7_200,
+        demand_shock: 0.02,
+        productivity: 0.05,
+    });
+    let url = serve_once(serde_json::to_string(&document).unwrap());
+    let provider = HttpEconomicProvider::new(HttpEconomicProviderConfig::new(url, key));
+
+    let err = provider.observe().unwrap_err().to_string();
+    assert!(err.contains("stale"), "{err}");
+}
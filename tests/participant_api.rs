@@ -0,0 +1,819 @@
+#![cfg(feature = "api")]
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use bitcoin_digital_labor_derivative::api::node::NodeConfiguration;
+use bitcoin_digital_labor_derivative::api::{create_router, GlobalNode};
+use bitcoin_digital_labor_derivative::disbursement::{
+    BroadcastError, Broadcaster, ConfirmationSource, PayoutRequest, PayoutStatus, PoolUtxo,
+    TxChainStatus,
+};
+use bitcoin_digital_labor_derivative::simulation::state::SimulationParticipant;
+use bitcoin_digital_labor_derivative::sqlite_participant_registry::SqliteParticipantRegistry;
+use bitcoin_digital_labor_derivative::stake::stake_lock_script;
+use bitcoin_digital_labor_derivative::sybil_clustering::{
+    CoSpend, CoSpendSource, SybilConfig, SybilWeighting,
+};
+use bitcoin_digital_labor_derivative::velocity_analyzer::VelocityError;
+use common::{db_path, temp_db};
+use serde_json::{json, Value};
+use std::fs;
+use std::str::FromStr;
+use std::sync::Arc;
+use tower::Service;
+
+const TOKEN: &str = "test-admin-token";
+const ALICE: &str = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+const BOB: &str = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+
+fn node(admin_token: Option<&str>, db: &str) -> GlobalNode {
+    let path = temp_db(db);
+    GlobalNode::new()
+        .with_config(NodeConfiguration {
+            admin_token: admin_token.map(str::to_string),
+            ..Default::default()
+        })
+        .with_registry(SqliteParticipantRegistry::open_read_write(&path).unwrap())
+}
+
+async fn send(
+    node: &GlobalNode,
+    method: &str,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut req = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        req = req.header(header::AUTHORIZATION, format!("Bearer {token}"));
+    }
+    let req = match body {
+        Some(body) => req
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => req.body(Body::empty()),
+    }
+    .unwrap();
+
+    let mut router = create_router(node.clone());
+    std::future::poll_fn(|cx| Service::<Request<Body>>::poll_ready(&mut router, cx))
+        .await
+        .unwrap();
+    let res = router.call(req).await.unwrap();
+    let status = res.status();
+    let bytes = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+#[tokio::test]
+async fn participant_endpoints_require_the_admin_token() {
+    let disabled = node(None, "disabled");
+    let (status, body) = send(&disabled, "GET", "/api/v1/participants", Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "forbidden");
+
+    let node = node(Some(TOKEN), "auth");
+    for token in [None, Some("wrong-token")] {
+        let (status, body) = send(&node, "GET", "/api/v1/participants", token, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["code"], "unauthorized");
+    }
+    let (status, _) = send(&node, "GET", "/api/v1/participants", Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn register_rejects_reused_and_off_network_addresses() {
+    let node = node(Some(TOKEN), "register");
+    let register =
+        |id: &str, address: &str| json!({ "participant_id": id, "addresses": [address] });
+
+    let (status, body) = send(
+        &node,
+        "POST",
+        "/api/v1/participants",
+        Some(TOKEN),
+        Some(register("alice", ALICE)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["addresses"][0]["address"], ALICE);
+
+    let (status, body) = send(
+        &node,
+        "POST",
+        "/api/v1/participants",
+        Some(TOKEN),
+        Some(register("bob", ALICE)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "address_in_use");
+
+    let testnet = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+    let (status, body) = send(
+        &node,
+        "POST",
+        "/api/v1/participants/alice/addresses",
+        Some(TOKEN),
+        Some(json!({ "address": testnet })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_address");
+
+    let (status, body) = send(
+        &node,
+        "POST",
+        "/api/v1/participants/alice/deactivate",
+        Some(TOKEN),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["active"], false);
+    assert_eq!(body["deactivated_height"], node.get_block_height());
+    let (status, _) = send(&node, "GET", "/api/v1/participants/bob", Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn labor_state_reads_persisted_stakes() {
+    let node = node(Some(TOKEN), "stakes");
+    let registry = node.registry().unwrap();
+    registry.register_participant("alice", &[]).unwrap();
+    let secp = bitcoin::secp256k1::Secp256k1::new();
+    let key = bitcoin::PublicKey::new(
+        bitcoin::secp256k1::SecretKey::from_slice(&[5; 32])
+            .unwrap()
+            .public_key(&secp),
+    );
+    let outpoint = bitcoin::OutPoint::null();
+    registry
+        .record_stake(
+            "alice",
+            outpoint,
+            40_000_000,
+            &stake_lock_script(900_000, &key),
+        )
+        .unwrap();
+    registry
+        .verify_stake(&outpoint, node.get_block_height())
+        .unwrap();
+    registry
+        .record_trust("alice", node.get_block_height(), 1.6)
+        .unwrap();
+
+    let (status, body) = send(&node, "GET", "/labor/state", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total_participants"], 1);
+    assert_eq!(body["participants"][0]["stake_sats"], 40_000_000);
+    assert_eq!(body["participants"][0]["trust_coefficient"], 1.6);
+}
+
+/// Co-spends on chain; records the addresses and window looked up.
+#[derive(Default)]
+struct ChainCoSpends {
+    co_spends: Vec<CoSpend>,
+    lookups: std::sync::Mutex<Vec<(Vec<String>, u64, u64)>>,
+}
+
+impl CoSpendSource for ChainCoSpends {
+    fn co_spends_for_addresses(
+        &self,
+        addresses: &[String],
+        start_height: u64,
+        end_height: u64,
+    ) -> Result<Vec<CoSpend>, VelocityError> {
+        self.lookups
+            .lock()
+            .unwrap()
+            .push((addresses.to_vec(), start_height, end_height));
+        Ok(self.co_spends.clone())
+    }
+}
+
+#[tokio::test]
+async fn co_spending_participants_are_reported_and_capped() {
+    let node = node(Some(TOKEN), "sybil").with_sybil_config(SybilConfig {
+        weighting: SybilWeighting::Cap {
+            max_group_share: 0.5,
+        },
+        ..Default::default()
+    });
+    let registry = node.registry().unwrap();
+    registry
+        .register_participant("alice", &[ALICE.to_string()])
+        .unwrap();
+    registry
+        .register_participant("bob", &[BOB.to_string()])
+        .unwrap();
+    registry.register_participant("carol", &[]).unwrap();
+    for (id, stake) in [
+        ("alice", 40_000_000),
+        ("bob", 40_000_000),
+        ("carol", 20_000_000),
+    ] {
+        node.add_participant(SimulationParticipant {
+            participant_id: id.to_string(),
+            stake_sats: stake,
+            trust_coefficient: 1.0,
+            addresses: vec![],
+        });
+    }
+
+    let (_, body) = send(
+        &node,
+        "GET",
+        "/api/v1/participants/alice/sybil",
+        Some(TOKEN),
+        None,
+    )
+    .await;
+    assert_eq!(body["flagged"], false);
+
+    // Both fund one transaction, through an unregistered address
+    let chain = ChainCoSpends {
+        co_spends: vec![
+            CoSpend {
+                txid: "tx-1".into(),
+                input_addresses: vec![ALICE.into(), "bc1q-change".into()],
+            },
+            CoSpend {
+                txid: "tx-2".into(),
+                input_addresses: vec!["bc1q-change".into(), BOB.into()],
+            },
+        ],
+        ..Default::default()
+    };
+    assert_eq!(node.refresh_co_spends(&chain).unwrap(), 2);
+    assert_eq!(node.refresh_co_spends(&chain).unwrap(), 0);
+    let height = node.get_block_height();
+    assert_eq!(
+        chain.lookups.lock().unwrap()[0],
+        (
+            vec![ALICE.to_string(), BOB.to_string()],
+            height - SybilConfig::default().lookback_blocks,
+            height
+        )
+    );
+    let (status, body) = send(
+        &node,
+        "GET",
+        "/api/v1/participants/alice/sybil",
+        Some(TOKEN),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["linked_participants"], json!(["bob"]));
+    assert_eq!(body["evidence_txids"], json!(["tx-1", "tx-2"]));
+    assert_eq!(body["risk_score"], 0.5);
+    assert_eq!(body["flagged"], true);
+    let (status, _) = send(
+        &node,
+        "GET",
+        "/api/v1/participants/erin/sybil",
+        Some(TOKEN),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // The pair's 80% of the weight is capped at half
+    let weights: Vec<f64> = node
+        .get_participant_snapshots()
+        .iter()
+        .map(|s| s.stake_amount_sats as f64 * s.trust_coefficient)
+        .collect();
+    assert_eq!(weights, [25_000_000.0, 25_000_000.0, 20_000_000.0]);
+}
+
+#[tokio::test]
+async fn audit_log_is_served_and_verified() {
+    let node = node(Some(TOKEN), "audit");
+    node.registry()
+        .unwrap()
+        .register_participant("alice", &[ALICE.to_string()])
+        .unwrap();
+    node.record_parameters().unwrap();
+    // Unchanged parameters are not logged twice
+    node.record_parameters().unwrap();
+
+    let (status, body) = send(&node, "GET", "/api/v1/audit?limit=2", Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body[0]["event_type"], "participant_registered");
+    assert_eq!(body[1]["seq"], 2);
+
+    let (status, body) = send(&node, "GET", "/api/v1/audit/verify", Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["entries"], 7);
+    let (status, _) = send(&node, "GET", "/api/v1/audit/verify", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // A head published earlier must still be in the chain
+    let head = body["head_hash"].as_str().unwrap().to_string();
+    let verify_from = |entries: u64, head: &str| {
+        format!("/api/v1/audit/verify?entries={entries}&head_hash={head}")
+    };
+    let (status, _) = send(&node, "GET", &verify_from(7, &head), Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::OK);
+    for uri in [verify_from(8, &head), verify_from(7, &"0".repeat(64))] {
+        let (status, body) = send(&node, "GET", &uri, Some(TOKEN), None).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["code"], "audit_chain_broken");
+    }
+    let (status, _) = send(
+        &node,
+        "GET",
+        "/api/v1/audit/verify?entries=7",
+        Some(TOKEN),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn pool_utxos_fund_rest_payouts_and_survive_restart() {
+    let path = temp_db("pool");
+    let open = || {
+        GlobalNode::new()
+            .with_config(NodeConfiguration {
+                admin_token: Some(TOKEN.to_string()),
+                ..Default::default()
+            })
+            .with_registry(SqliteParticipantRegistry::open_read_write(&path).unwrap())
+    };
+    let node = open();
+    let pool_script = bitcoin::Address::from_str(BOB)
+        .unwrap()
+        .assume_checked()
+        .script_pubkey();
+    let utxo = |n: u8, value_sats: u64| {
+        json!({
+            "txid": format!("{n:02x}").repeat(32),
+            "vout": 0,
+            "value_sats": value_sats,
+            "script_pubkey_hex": hex::encode(pool_script.as_bytes()),
+        })
+    };
+    let payout = json!({
+        "recipient_address": ALICE,
+        "amount_sats": 100_000,
+        "change_address": BOB,
+        "fee_rate_sats_per_vbyte": 2,
+        "dry_run": false,
+    });
+
+    // Payouts reserve pool funds, so only the operator creates them
+    let (status, _) = send(
+        &node,
+        "POST",
+        "/api/v1/payouts/execute",
+        None,
+        Some(payout.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = send(
+        &node,
+        "POST",
+        "/api/v1/payouts/execute",
+        Some(TOKEN),
+        Some(payout.clone()),
+    )
+    .await;
+    // Nothing funds the pool yet
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["findings"][0]["rule_id"], "pool_share_1d");
+
+    let set = json!({ "utxos": [utxo(1, 1_000_000), utxo(2, 50_000)] });
+    let (status, _) = send(&node, "PUT", "/api/v1/pool/utxos", None, Some(set.clone())).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = send(&node, "PUT", "/api/v1/pool/utxos", Some(TOKEN), Some(set)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total_sats"], 1_050_000);
+    assert_eq!(node.get_pool_balance(), 1_050_000);
+
+    let (status, body) = send(
+        &node,
+        "POST",
+        "/api/v1/payouts/execute",
+        Some(TOKEN),
+        Some(payout),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["status"], "unsignedcreated");
+    // Signatures are submitted by the operator only
+    let signed = format!(
+        "/api/v1/payouts/{}/signed",
+        body["payout_id"].as_str().unwrap()
+    );
+    let psbt = json!({ "psbt_base64": body["psbt_base64"] });
+    let (status, _) = send(&node, "POST", &signed, None, Some(psbt)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let bump = signed.replace("/signed", "/bump");
+    let rate = json!({ "fee_rate_sats_per_vbyte": 20 });
+    let (status, _) = send(&node, "POST", &bump, None, Some(rate)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // The spent input left the stored set
+    drop(node);
+    let restarted = open();
+    let (status, body) = send(&restarted, "GET", "/api/v1/pool/utxos", Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["utxos"].as_array().unwrap().len(), 1);
+    assert_eq!(
+        restarted.get_pool_balance(),
+        body["total_sats"].as_u64().unwrap()
+    );
+
+    // Batch transactions are stored as payouts too
+    let set = json!({ "utxos": [utxo(3, 2_000_000)] });
+    let (status, _) = send(
+        &restarted,
+        "PUT",
+        "/api/v1/pool/utxos",
+        Some(TOKEN),
+        Some(set),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let batch = json!({
+        "allocations": [
+            { "participant_id": "alice", "recipient_address": ALICE, "amount_sats": 100_000 },
+            { "participant_id": "bob", "recipient_address": BOB, "amount_sats": 100_000 },
+        ],
+        "change_address": BOB,
+        "fee_rate_sats_per_vbyte": 2,
+        "dry_run": false,
+    });
+    let (status, _) = send(
+        &restarted,
+        "POST",
+        "/api/v1/payouts/batch",
+        None,
+        Some(batch.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = send(
+        &restarted,
+        "POST",
+        "/api/v1/payouts/batch",
+        Some(TOKEN),
+        Some(batch),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let payout_id = body["transactions"][0]["payout_id"].as_str().unwrap();
+    let (status, stored) = send(
+        &restarted,
+        "GET",
+        &format!("/api/v1/payouts/{payout_id}"),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(stored["batch_id"], body["batch_id"]);
+    assert_eq!(stored["amount_sats"], 200_000);
+
+    let bad = json!({ "utxos": [{ "txid": "zz", "vout": 0, "value_sats": 1, "script_pubkey_hex": "00" }] });
+    let (status, body) = send(
+        &restarted,
+        "PUT",
+        "/api/v1/pool/utxos",
+        Some(TOKEN),
+        Some(bad),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_utxo");
+    let _ = fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn payouts_that_cannot_be_stored_keep_their_inputs() {
+    let node = node(Some(TOKEN), "unstored");
+    let pool_script = bitcoin::Address::from_str(BOB)
+        .unwrap()
+        .assume_checked()
+        .script_pubkey();
+    let set = json!({ "utxos": [{
+        "txid": "07".repeat(32),
+        "vout": 0,
+        "value_sats": 2_000_000,
+        "script_pubkey_hex": hex::encode(pool_script.as_bytes()),
+    }] });
+    let (status, _) = send(&node, "PUT", "/api/v1/pool/utxos", Some(TOKEN), Some(set)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let path = db_path("unstored");
+    rusqlite::Connection::open(&path)
+        .unwrap()
+        .execute_batch("DROP TABLE payouts")
+        .unwrap();
+    let payout = json!({
+        "recipient_address": ALICE,
+        "amount_sats": 100_000,
+        "change_address": BOB,
+        "dry_run": false,
+    });
+    let (status, body) = send(
+        &node,
+        "POST",
+        "/api/v1/payouts/execute",
+        Some(TOKEN),
+        Some(payout),
+    )
+    .await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body["code"], "storage_failed");
+    assert_eq!(node.get_pool_utxos().len(), 1);
+    let _ = fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn batches_that_cannot_be_audited_are_failed() {
+    let node = node(Some(TOKEN), "unaudited");
+    let pool_script = bitcoin::Address::from_str(BOB)
+        .unwrap()
+        .assume_checked()
+        .script_pubkey();
+    let set = json!({ "utxos": [{
+        "txid": "0b".repeat(32),
+        "vout": 0,
+        "value_sats": 2_000_000,
+        "script_pubkey_hex": hex::encode(pool_script.as_bytes()),
+    }] });
+    let (status, _) = send(&node, "PUT", "/api/v1/pool/utxos", Some(TOKEN), Some(set)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let path = db_path("unaudited");
+    rusqlite::Connection::open(&path)
+        .unwrap()
+        .execute_batch(
+            "CREATE TRIGGER no_epochs BEFORE INSERT ON audit_log
+             WHEN NEW.event_type = 'epoch_closed'
+             BEGIN SELECT RAISE(ABORT, 'no epochs'); END;",
+        )
+        .unwrap();
+    let batch = json!({
+        "allocations": [
+            { "participant_id": "alice", "recipient_address": ALICE, "amount_sats": 100_000 },
+        ],
+        "change_address": BOB,
+        "dry_run": false,
+    });
+    let (status, body) = send(
+        &node,
+        "POST",
+        "/api/v1/payouts/batch",
+        Some(TOKEN),
+        Some(batch),
+    )
+    .await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(body["message"].as_str().unwrap().contains("no epochs"));
+    let stored = node.list_payouts();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].status, PayoutStatus::Failed);
+    assert_eq!(node.get_pool_utxos().len(), 1);
+    let _ = fs::remove_file(&path);
+}
+
+struct ChainAt(TxChainStatus);
+
+impl ConfirmationSource for ChainAt {
+    fn transaction_status(&self, _: &bitcoin::Txid) -> Result<TxChainStatus, VelocityError> {
+        Ok(self.0)
+    }
+}
+
+#[test]
+fn track_confirmations_completes_broadcast_payouts() {
+    let node = GlobalNode::new();
+    let pool_script = bitcoin::Address::from_str(BOB)
+        .unwrap()
+        .assume_checked()
+        .script_pubkey();
+    node.set_pool_utxos(vec![PoolUtxo::new(
+        bitcoin::OutPoint::from_str(&format!("{}:0", "08".repeat(32))).unwrap(),
+        2_000_000,
+        pool_script,
+    )])
+    .unwrap();
+    let payout = node
+        .execute_payout(&PayoutRequest {
+            recipient_address: ALICE.to_string(),
+            amount_sats: 100_000,
+            funding_utxo_txid: None,
+            funding_utxo_vout: None,
+            funding_utxo_value_sats: None,
+            change_address: Some(BOB.to_string()),
+            fee_rate_sats_per_vbyte: Some(2),
+            dry_run: Some(false),
+        })
+        .unwrap();
+    node.in_memory_payouts
+        .write()
+        .unwrap()
+        .get_mut(&payout.payout_id)
+        .unwrap()
+        .status = PayoutStatus::Broadcast;
+
+    let taken = node.track_confirmations(&ChainAt(TxChainStatus::Confirmed { confirmations: 2 }));
+    assert_eq!(taken.len(), 1);
+    assert_eq!(
+        node.get_payout(&payout.payout_id).unwrap().status,
+        PayoutStatus::Confirmed
+    );
+
+    node.track_confirmations(&ChainAt(TxChainStatus::Confirmed { confirmations: 6 }));
+    let stored = node.get_payout(&payout.payout_id).unwrap();
+    assert_eq!(stored.status, PayoutStatus::Completed);
+    assert_eq!(stored.confirmations, 6);
+}
+
+#[test]
+fn failed_payouts_return_their_inputs_to_the_pool() {
+    let pool_script = bitcoin::Address::from_str(BOB)
+        .unwrap()
+        .assume_checked()
+        .script_pubkey();
+    let pool = vec![PoolUtxo::new(
+        bitcoin::OutPoint::from_str(&format!("{}:0", "0a".repeat(32))).unwrap(),
+        2_000_000,
+        pool_script,
+    )];
+    let request = PayoutRequest {
+        recipient_address: ALICE.to_string(),
+        amount_sats: 100_000,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: Some(BOB.to_string()),
+        fee_rate_sats_per_vbyte: Some(2),
+        dry_run: Some(false),
+    };
+
+    // Reservations live in the registry, or in memory without one
+    for node in [GlobalNode::new(), node(None, "release")] {
+        node.set_pool_utxos(pool.clone()).unwrap();
+        let payout = node.execute_payout(&request).unwrap();
+        assert!(node.get_pool_utxos().is_empty());
+        assert!(node.execute_payout(&request).is_err());
+
+        // Replacing the set keeps the reservation of an unfinished payout
+        node.set_pool_utxos(pool.clone()).unwrap();
+        assert!(node.get_pool_utxos().is_empty());
+
+        node.transition_payout(&payout.payout_id, PayoutStatus::Failed, None)
+            .unwrap();
+        assert_eq!(node.get_pool_utxos(), pool);
+        assert_eq!(node.get_pool_balance(), 2_000_000);
+        node.execute_payout(&request).unwrap();
+    }
+    let _ = fs::remove_file(db_path("release"));
+}
+
+/// Rejects the first submission with `first`, then accepts.
+struct FlakyRelay(std::sync::Mutex<Option<BroadcastError>>);
+
+impl Broadcaster for FlakyRelay {
+    fn broadcast(&self, tx: &bitcoin::Transaction) -> Result<bitcoin::Txid, BroadcastError> {
+        match self.0.lock().unwrap().take() {
+            Some(err) => Err(err),
+            None => Ok(tx.txid()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn finalized_payouts_are_broadcast_through_the_backend() {
+    let node = node(Some(TOKEN), "broadcast");
+    let pool_script = bitcoin::Address::from_str(BOB)
+        .unwrap()
+        .assume_checked()
+        .script_pubkey();
+    node.set_pool_utxos(vec![PoolUtxo::new(
+        bitcoin::OutPoint::from_str(&format!("{}:0", "09".repeat(32))).unwrap(),
+        2_000_000,
+        pool_script,
+    )])
+    .unwrap();
+    let mut payout = node
+        .execute_payout(&PayoutRequest {
+            recipient_address: ALICE.to_string(),
+            amount_sats: 100_000,
+            funding_utxo_txid: None,
+            funding_utxo_vout: None,
+            funding_utxo_value_sats: None,
+            change_address: Some(BOB.to_string()),
+            fee_rate_sats_per_vbyte: Some(2),
+            dry_run: Some(false),
+        })
+        .unwrap();
+    let uri = format!("/api/v1/payouts/{}/broadcast", payout.payout_id);
+
+    // Only finalized payouts can be broadcast
+    let (status, body) = send(&node, "POST", &uri, Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+
+    // The backend only sees the extracted transaction, signed or not
+    payout.status = PayoutStatus::Finalized;
+    payout.final_tx_hex = Some(payout.raw_tx_hex.clone());
+    node.participant_registry
+        .as_ref()
+        .unwrap()
+        .save_payout(&payout)
+        .unwrap();
+
+    let (status, _) = send(&node, "POST", &uri, None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = send(&node, "POST", &uri, Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["code"], "unavailable");
+
+    let node = node.with_broadcaster(Arc::new(FlakyRelay(std::sync::Mutex::new(Some(
+        BroadcastError::MissingInputs,
+    )))));
+    let (status, body) = send(&node, "POST", &uri, Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(body["message"].as_str().unwrap().contains("inputs missing"));
+    assert_eq!(
+        node.get_payout(&payout.payout_id).unwrap().status,
+        PayoutStatus::Finalized
+    );
+
+    let (status, body) = send(&node, "POST", &uri, Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["status"], "broadcast");
+
+    // Fee bumps are capped at the configured maximum rate
+    let bump = uri.replace("/broadcast", "/bump");
+    let rate = json!({ "fee_rate_sats_per_vbyte": 5_000 });
+    let (status, body) = send(&node, "POST", &bump, Some(TOKEN), Some(rate)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"]
+        .as_str()
+        .unwrap()
+        .contains("exceeds the maximum"));
+    let rate = json!({ "fee_rate_sats_per_vbyte": 20 });
+    let (status, body) = send(&node, "POST", &bump, Some(TOKEN), Some(rate)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["fee_bump_of"]["kind"], "rbf");
+    // The original links to the stored replacement
+    let link = node
+        .get_payout(&payout.payout_id)
+        .unwrap()
+        .fee_bumped_by
+        .unwrap();
+    assert_eq!(link.payout_id, body["payout_id"].as_str().unwrap());
+    let mut replacement = node.get_payout(&link.payout_id).unwrap();
+
+    // Broadcasting the replacement marks the original replaced
+    let registry = node.participant_registry.as_ref().unwrap();
+    replacement.status = PayoutStatus::Finalized;
+    replacement.final_tx_hex = Some(replacement.raw_tx_hex.clone());
+    registry.save_payout(&replacement).unwrap();
+    let uri = format!("/api/v1/payouts/{}/broadcast", replacement.payout_id);
+    let (status, body) = send(&node, "POST", &uri, Some(TOKEN), None).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        node.get_payout(&payout.payout_id).unwrap().status,
+        PayoutStatus::Replaced
+    );
+
+    // and says so when it cannot
+    replacement.payout_id = "payout-orphan".to_string();
+    replacement.fee_bump_of.as_mut().unwrap().payout_id = "payout-missing".to_string();
+    registry.save_payout(&replacement).unwrap();
+    let (status, body) = send(
+        &node,
+        "POST",
+        "/api/v1/payouts/payout-orphan/broadcast",
+        Some(TOKEN),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(body["message"]
+        .as_str()
+        .unwrap()
+        .contains("'payout-missing' could not be marked replaced"));
+    assert_eq!(
+        node.get_payout("payout-orphan").unwrap().status,
+        PayoutStatus::Broadcast
+    );
+}
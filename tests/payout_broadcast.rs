@@ -0,0 +1,190 @@
+use bitcoin::hashes::Hash;
+use bitcoin::{
+    absolute, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, WPubkeyHash, Witness,
+};
+use bitcoin_digital_labor_derivative::disbursement::{
+    classify_rejection, AileeTrustAudit, BroadcastError, Broadcaster, LifecycleError, PayoutStatus,
+    PayoutTransactionResult, TrustPolicy,
+};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Regtest-like node: a UTXO set and a mempool, with Core's reject reasons.
+struct RegtestStub {
+    utxos: Mutex<HashMap<OutPoint, TxOut>>,
+    mempool: Mutex<HashMap<Txid, Transaction>>,
+    min_relay_sats_per_vbyte: u64,
+}
+
+impl RegtestStub {
+    fn new(utxos: Vec<(OutPoint, TxOut)>) -> Self {
+        Self {
+            utxos: Mutex::new(utxos.into_iter().collect()),
+            mempool: Mutex::new(HashMap::new()),
+            min_relay_sats_per_vbyte: 1,
+        }
+    }
+
+    /// `testmempoolaccept`: the reject reason, if any.
+    fn test_accept(&self, tx: &Transaction) -> Option<String> {
+        let mempool = self.mempool.lock().unwrap();
+        if mempool.contains_key(&tx.txid()) {
+            return Some("txn-already-in-mempool".into());
+        }
+        let utxos = self.utxos.lock().unwrap();
+        let mut input_value = 0;
+        for input in &tx.input {
+            let Some(prevout) = utxos.get(&input.previous_output) else {
+                let conflict = mempool.values().any(|other| {
+                    other
+                        .input
+                        .iter()
+                        .any(|i| i.previous_output == input.previous_output)
+                });
+                return Some(if conflict {
+                    "txn-mempool-conflict".into()
+                } else {
+                    "bad-txns-inputs-missingorspent".into()
+                });
+            };
+            input_value += prevout.value;
+        }
+        let output_value: u64 = tx.output.iter().map(|o| o.value).sum();
+        let fee = input_value.saturating_sub(output_value);
+        let min_fee = tx.vsize() as u64 * self.min_relay_sats_per_vbyte;
+        (fee < min_fee).then(|| format!("min relay fee not met, {fee} < {min_fee}"))
+    }
+}
+
+impl Broadcaster for RegtestStub {
+    fn broadcast(&self, tx: &Transaction) -> Result<Txid, BroadcastError> {
+        if let Some(reason) = self.test_accept(tx) {
+            return Err(classify_rejection(&reason));
+        }
+        let mut utxos = self.utxos.lock().unwrap();
+        for input in &tx.input {
+            utxos.remove(&input.previous_output);
+        }
+        self.mempool.lock().unwrap().insert(tx.txid(), tx.clone());
+        Ok(tx.txid())
+    }
+}
+
+fn funding(n: u8, value: u64) -> (OutPoint, TxOut) {
+    (
+        OutPoint {
+            txid: Txid::from_slice(&[n; 32]).unwrap(),
+            vout: 0,
+        },
+        TxOut {
+            value,
+            script_pubkey: ScriptBuf::new_v0_p2wpkh(&WPubkeyHash::from_slice(&[n; 20]).unwrap()),
+        },
+    )
+}
+
+fn finalized_payout(
+    payout_id: &str,
+    spends: &[OutPoint],
+    output_value: u64,
+) -> PayoutTransactionResult {
+    let tx = Transaction {
+        version: 2,
+        lock_time: absolute::LockTime::ZERO,
+        input: spends
+            .iter()
+            .map(|outpoint| TxIn {
+                previous_output: *outpoint,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::from_slice(&[vec![1u8; 72], vec![2u8; 33]]),
+            })
+            .collect(),
+        output: vec![TxOut {
+            value: output_value,
+            script_pubkey: ScriptBuf::new_v0_p2wpkh(&WPubkeyHash::from_slice(&[9; 20]).unwrap()),
+        }],
+    };
+    PayoutTransactionResult {
+        payout_id: payout_id.to_string(),
+        recipient_address: String::new(),
+        amount_sats: output_value,
+        fee_sats: 0,
+        status: PayoutStatus::Finalized,
+        psbt_base64: String::new(),
+        raw_tx_hex: String::new(),
+        txid: tx.txid().to_string(),
+        timestamp: String::new(),
+        trust_audit: AileeTrustAudit {
+            passed: true,
+            risk_score: 0.0,
+            policy_version: TrustPolicy::standard().version().to_string(),
+            warnings: Vec::new(),
+            max_payout_sats_limit: 0,
+            findings: Vec::new(),
+        },
+        is_dry_run: false,
+        confirmations: 0,
+        signed_psbt_base64: None,
+        final_tx_hex: Some(hex::encode(bitcoin::consensus::serialize(&tx))),
+        fee_bump_of: None,
+        fee_bumped_by: None,
+        signing: None,
+        batch_id: None,
+    }
+}
+
+#[test]
+fn test_broadcast_moves_payout_to_broadcast_and_is_idempotent() {
+    let (outpoint, prevout) = funding(1, 100_000);
+    let node = RegtestStub::new(vec![(outpoint, prevout)]);
+
+    let mut payout = finalized_payout("payout-a", &[outpoint], 99_000);
+    let transition = payout.broadcast_with(&node).unwrap();
+    assert_eq!(transition.to, PayoutStatus::Broadcast);
+    assert_eq!(
+        transition.detail,
+        Some(format!("accepted as {}", payout.txid))
+    );
+    assert!(payout.broadcast_with(&node).is_err());
+
+    // A second submission of the same transaction, e.g. after a restart.
+    let mut resubmitted = finalized_payout("payout-a", &[outpoint], 99_000);
+    resubmitted.broadcast_with(&node).unwrap();
+    assert_eq!(resubmitted.status, PayoutStatus::Broadcast);
+}
+
+#[test]
+fn test_broadcast_rejections_are_typed() {
+    let (funded, prevout) = funding(1, 100_000);
+    let node = RegtestStub::new(vec![(funded, prevout)]);
+
+    // Fee of 10 sats is below the relay minimum: retryable, payout unchanged.
+    let mut low_fee = finalized_payout("payout-low", &[funded], 99_990);
+    assert!(matches!(
+        low_fee.broadcast_with(&node),
+        Err(LifecycleError::Broadcast(BroadcastError::InsufficientFee(
+            _
+        )))
+    ));
+    assert_eq!(low_fee.status, PayoutStatus::Finalized);
+
+    // Conflicts with a transaction already in the mempool.
+    finalized_payout("payout-first", &[funded], 99_000)
+        .broadcast_with(&node)
+        .unwrap();
+    let mut conflicting = finalized_payout("payout-conflict", &[funded], 98_000);
+    assert_eq!(
+        conflicting.broadcast_with(&node),
+        Err(LifecycleError::Broadcast(BroadcastError::MempoolConflict))
+    );
+
+    // An unknown output may be a parent still propagating: retry later.
+    let (unknown, _) = funding(2, 100_000);
+    let mut missing = finalized_payout("payout-missing", &[unknown], 99_000);
+    assert_eq!(
+        missing.broadcast_with(&node),
+        Err(LifecycleError::Broadcast(BroadcastError::MissingInputs))
+    );
+    assert_eq!(missing.status, PayoutStatus::Finalized);
+}
@@ -1,11 +1,58 @@
+mod common;
+
+use base64::Engine;
+use bitcoin::bip32::{DerivationPath, ExtendedPrivKey, ExtendedPubKey};
+use bitcoin::hashes::Hash;
+use bitcoin::key::TapTweak;
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::secp256k1::{KeyPair, Message, Secp256k1, SecretKey};
+use bitcoin::sighash::{EcdsaSighashType, Prevouts, SighashCache, TapSighashType};
+use bitcoin::{Address, Network, OutPoint, ScriptBuf, Transaction, TxOut, Txid, Witness};
 use bitcoin_digital_labor_derivative::disbursement::{
-    DisbursementConfig, DisbursementEngine, PayoutRequest, PayoutStatus,
+    estimate_vsize, finalize_psbt, signing_progress, AddressError, AddressRole, BatchFeePolicy,
+    BatchPayoutRequest, DisbursementConfig, DisbursementEngine, DisbursementError, FeeBumpKind,
+    FeeBumpLink, InputScriptType, KeyChain, KeyChainIndex, PayoutAllocation, PayoutHistory,
+    PayoutRequest, PayoutStatus, PayoutTransactionResult, PoolDescriptor, PoolUtxo, SubDustPolicy,
+    TrustPolicy, TrustPolicyConfig, TxChainStatus,
 };
 use bitcoin_digital_labor_derivative::sqlite_participant_registry::SqliteParticipantRegistry;
+use bitcoin_digital_labor_derivative::storage::RegistryStorage;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+const POOL_ADDRESS: &str = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+
+fn pool_utxo(n: u8, value_sats: u64) -> PoolUtxo {
+    PoolUtxo::new(
+        OutPoint {
+            txid: Txid::from_slice(&[n; 32]).unwrap(),
+            vout: 0,
+        },
+        value_sats,
+        Address::from_str(POOL_ADDRESS)
+            .unwrap()
+            .assume_checked()
+            .script_pubkey(),
+    )
+}
+
+/// Change goes back to the pool address the test UTXOs pay.
+fn pool_config() -> DisbursementConfig {
+    DisbursementConfig {
+        change_address: Some(POOL_ADDRESS.to_string()),
+        ..Default::default()
+    }
+}
+
+fn decode_tx(raw_tx_hex: &str) -> Transaction {
+    bitcoin::consensus::deserialize(&hex::decode(raw_tx_hex).unwrap()).unwrap()
+}
 
 #[test]
 fn test_payout_psbt_and_raw_tx_generation() {
-    let engine = DisbursementEngine::new(DisbursementConfig::default());
+    let engine = DisbursementEngine::new(pool_config());
     let req = PayoutRequest {
         recipient_address: "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh".to_string(),
         amount_sats: 100_000,
@@ -18,7 +65,11 @@ fn test_payout_psbt_and_raw_tx_generation() {
     };
 
     let result = engine
-        .create_unsigned_payout("payout-test-1".to_string(), &req)
+        .create_unsigned_payout(
+            "payout-test-1".to_string(),
+            &req,
+            &[pool_utxo(1, 1_000_000)],
+        )
         .expect("Failed to create payout");
 
     assert_eq!(result.payout_id, "payout-test-1");
@@ -30,8 +81,133 @@ fn test_payout_psbt_and_raw_tx_generation() {
 }
 
 #[test]
-fn test_ailee_trust_layer_safeguard_policy() {
+fn test_payout_selects_multiple_pool_inputs_with_change() {
+    let engine = DisbursementEngine::new(pool_config());
+    let req = PayoutRequest {
+        recipient_address: "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh".to_string(),
+        amount_sats: 250_000,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(5),
+        dry_run: Some(false),
+    };
+    let pool = vec![
+        pool_utxo(1, 120_000),
+        pool_utxo(2, 150_000),
+        pool_utxo(3, 40_000),
+    ];
+
+    let result = engine
+        .create_unsigned_payout("payout-multi".to_string(), &req, &pool)
+        .expect("Failed to create payout");
+    let tx = decode_tx(&result.raw_tx_hex);
+
+    assert_eq!(tx.input.len(), 2);
+    let input_total = 150_000 + 120_000;
+    let output_total: u64 = tx.output.iter().map(|o| o.value).sum();
+    assert_eq!(input_total - output_total, result.fee_sats);
+    // Change goes back to the pool script.
+    assert_eq!(tx.output.len(), 2);
+    assert_eq!(tx.output[1].script_pubkey, pool[0].script_pubkey);
+
+    let empty = engine.create_unsigned_payout("payout-empty".to_string(), &req, &[]);
+    let err = empty.unwrap_err();
+    assert!(matches!(
+        err,
+        DisbursementError::InsufficientFunds {
+            available_sats: 0,
+            ..
+        }
+    ));
+    assert_eq!(err.code(), "insufficient_funds");
+    assert!(err.to_string().contains("insufficient pool funds"));
+}
+
+#[test]
+fn test_payout_fee_is_exact_for_mixed_input_types() {
+    let engine = DisbursementEngine::new(pool_config());
+    let req = PayoutRequest {
+        recipient_address: "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh".to_string(),
+        amount_sats: 100_000,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: Some(
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr".to_string(),
+        ),
+        fee_rate_sats_per_vbyte: Some(3),
+        dry_run: Some(true),
+    };
+    let script_of = |addr: &str| {
+        Address::from_str(addr)
+            .unwrap()
+            .assume_checked()
+            .script_pubkey()
+    };
+    let mut taproot = pool_utxo(1, 60_000);
+    taproot.script_pubkey =
+        script_of("bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr");
+    let mut legacy = pool_utxo(2, 50_000);
+    legacy.script_pubkey = script_of("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2");
+
+    let result = engine
+        .create_unsigned_payout("payout-mixed".to_string(), &req, &[taproot, legacy])
+        .expect("Failed to create payout");
+    let tx = decode_tx(&result.raw_tx_hex);
+
+    // header 40 + marker 2 + P2TR in 230 + P2PKH in 592 (+1 empty witness)
+    // + P2WPKH out 124 + P2TR change 172 = 1161 WU -> 291 vB.
+    assert_eq!(result.fee_sats, 3 * 291);
+    assert_eq!(tx.output.len(), 2);
+    assert_eq!(tx.output[1].value, 110_000 - 100_000 - 3 * 291);
+}
+
+#[test]
+fn test_change_requires_a_pool_destination() {
+    let mut req = PayoutRequest {
+        recipient_address: "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh".to_string(),
+        amount_sats: 100_000,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(2),
+        dry_run: Some(false),
+    };
+    let utxos = [pool_utxo(1, 1_000_000)];
+
+    // Change never silently returns to whatever script funded the payout
     let engine = DisbursementEngine::new(DisbursementConfig::default());
+    let err = engine
+        .create_unsigned_payout("payout-no-change".to_string(), &req, &utxos)
+        .unwrap_err();
+    assert_eq!(err.code(), "invalid_request");
+
+    // Nor to an address of the caller's choosing
+    req.change_address = Some("bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh".to_string());
+    let err = engine
+        .create_unsigned_payout("payout-foreign-change".to_string(), &req, &utxos)
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("does not belong to the pool"),
+        "{err}"
+    );
+
+    req.change_address = Some(POOL_ADDRESS.to_string());
+    let result = engine
+        .create_unsigned_payout("payout-change".to_string(), &req, &utxos)
+        .unwrap();
+    assert_eq!(
+        decode_tx(&result.raw_tx_hex).output[1].script_pubkey,
+        utxos[0].script_pubkey
+    );
+}
+
+#[test]
+fn test_ailee_trust_layer_safeguard_policy() {
+    let engine = DisbursementEngine::new(pool_config());
 
     // Exceed max payout limit
     let req_exceed = PayoutRequest {
@@ -45,20 +221,275 @@ fn test_ailee_trust_layer_safeguard_policy() {
         dry_run: None,
     };
 
-    let err = engine.create_unsigned_payout("payout-test-2".to_string(), &req_exceed);
-    assert!(err.is_err());
+    let err = engine.create_unsigned_payout(
+        "payout-test-2".to_string(),
+        &req_exceed,
+        &[pool_utxo(1, 1_000_000_000)],
+    );
+    let err = err.unwrap_err();
     assert!(err
-        .unwrap_err()
+        .to_string()
         .contains("AILEE Trust Layer policy check failed"));
+    match err {
+        DisbursementError::PolicyRejected { findings, .. } => {
+            assert!(findings
+                .iter()
+                .any(|f| f.rule_id == "amount_limit" && f.blocking));
+        }
+        other => panic!("expected a policy rejection, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_network_mismatches_are_refused_for_every_role() {
+    // Enforced even when trust failures would not block the payout.
+    let engine = DisbursementEngine::new(DisbursementConfig {
+        require_ailee_trust_pass: false,
+        ..pool_config()
+    });
+    let testnet = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+    let mut req = PayoutRequest {
+        recipient_address: testnet.to_string(),
+        amount_sats: 100_000,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: None,
+        dry_run: None,
+    };
+    let pool = [pool_utxo(1, 1_000_000)];
+
+    let audit = engine.evaluate_ailee_trust_policy(&req);
+    assert!(!audit.passed);
+    let err = engine
+        .create_unsigned_payout("payout-recipient".into(), &req, &pool)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        DisbursementError::InvalidAddress(AddressError::NetworkMismatch {
+            role: AddressRole::Recipient,
+            address: testnet.to_string(),
+            expected: Network::Bitcoin,
+        })
+    );
+    assert_eq!(
+        err.to_string(),
+        format!("recipient address {testnet} is not valid for network bitcoin")
+    );
+
+    req.recipient_address = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh".to_string();
+    req.change_address = Some(testnet.to_string());
+    let err = engine
+        .create_unsigned_payout("payout-change".into(), &req, &pool)
+        .unwrap_err();
+    assert!(err.to_string().starts_with("change address"));
+
+    // A testnet pool descriptor cannot fund or receive mainnet change.
+    let secp = Secp256k1::new();
+    let master = ExtendedPrivKey::new_master(Network::Testnet, &[9u8; 32]).unwrap();
+    let tpub = ExtendedPubKey::from_priv(&secp, &master);
+    let engine = DisbursementEngine::new(DisbursementConfig {
+        pool_descriptor: Some(PoolDescriptor::from_str(&format!("wpkh({tpub}/<0;1>/*)")).unwrap()),
+        ..pool_config()
+    });
+    req.change_address = None;
+    let err = engine
+        .create_unsigned_payout("payout-change-key".into(), &req, &pool)
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with(&format!("pool change key {tpub}")));
+    req.change_address = Some(POOL_ADDRESS.to_string());
+    let err = engine
+        .create_unsigned_payout("payout-funding-key".into(), &req, &pool)
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with(&format!("pool funding key {tpub}")));
+}
+
+/// A payout to `recipient` broadcast `age` ago.
+fn paid_payout(
+    payout_id: &str,
+    recipient: &str,
+    amount_sats: u64,
+    age: chrono::Duration,
+) -> PayoutTransactionResult {
+    let req = PayoutRequest {
+        recipient_address: recipient.to_string(),
+        amount_sats,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: None,
+        dry_run: None,
+    };
+    let mut payout = DisbursementEngine::new(pool_config())
+        .create_unsigned_payout(payout_id.to_string(), &req, &[pool_utxo(1, 10_000_000)])
+        .unwrap();
+    payout.status = PayoutStatus::Broadcast;
+    payout.timestamp = (Utc::now() - age).to_rfc3339();
+    payout
+}
+
+#[test]
+fn test_trust_policy_rule_set_records_fired_rules() {
+    let recipient = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+    let policy: TrustPolicyConfig = serde_json::from_value(serde_json::json!({
+        "version": "pool-policy-3",
+        "rules": [
+            { "rule": "amount_limit", "max_sats": 1_000_000 },
+            { "rule": "deny_list", "addresses": [POOL_ADDRESS] },
+            { "rule": "new_recipient_cooling", "period_secs": 86_400 }
+        ]
+    }))
+    .unwrap();
+    let history = RwLock::new(HashMap::from([(
+        "payout-earlier".to_string(),
+        paid_payout(
+            "payout-earlier",
+            recipient,
+            50_000,
+            chrono::Duration::hours(2),
+        ),
+    )]));
+    let engine = DisbursementEngine::new(DisbursementConfig {
+        trust_policy: Arc::new(TrustPolicy::from_config(&policy).unwrap()),
+        ..pool_config()
+    })
+    .with_payout_history(Arc::new(history));
+
+    let mut req = PayoutRequest {
+        recipient_address: recipient.to_string(),
+        amount_sats: 100_000,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: None,
+        dry_run: None,
+    };
+
+    // Cooling findings are warnings: the payout proceeds with raised risk.
+    let result = engine
+        .create_unsigned_payout("payout-cooling".into(), &req, &[pool_utxo(1, 1_000_000)])
+        .unwrap();
+    let audit = &result.trust_audit;
+    assert!(audit.passed);
+    assert_eq!(audit.policy_version, "pool-policy-3");
+    assert_eq!(audit.findings.len(), 1);
+    assert_eq!(audit.findings[0].rule_id, "new_recipient_cooling");
+    assert!((audit.risk_score - (1.0 - 0.95 * 0.5)).abs() < 1e-9);
+
+    req.recipient_address = POOL_ADDRESS.to_string();
+    req.amount_sats = 2_000_000;
+    let audit = engine.evaluate_ailee_trust_policy(&req);
+    assert!(!audit.passed);
+    let fired: Vec<&str> = audit.findings.iter().map(|f| f.rule_id.as_str()).collect();
+    assert_eq!(
+        fired,
+        vec!["amount_limit", "deny_list", "new_recipient_cooling"]
+    );
+}
+
+#[test]
+fn test_velocity_limits_use_stored_payouts_and_reject() {
+    let temp_db = common::temp_db("velocity");
+    let registry = Arc::new(SqliteParticipantRegistry::open_read_write(&temp_db).unwrap());
+    let frequent = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+    let other = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    let hour = chrono::Duration::hours(1);
+    registry
+        .save_payout(&paid_payout("recent", frequent, 400_000, hour * 2))
+        .unwrap();
+    registry
+        .save_payout(&paid_payout("outside-window", frequent, 900_000, hour * 30))
+        .unwrap();
+    let mut dry_run = paid_payout("dry-run", frequent, 900_000, hour);
+    dry_run.is_dry_run = true;
+    registry.save_payout(&dry_run).unwrap();
+    let mut failed = paid_payout("failed", frequent, 900_000, hour);
+    failed.status = PayoutStatus::Failed;
+    registry.save_payout(&failed).unwrap();
+
+    let policy: TrustPolicyConfig = serde_json::from_value(serde_json::json!({
+        "version": "velocity-test",
+        "rules": [
+            { "rule": "velocity_limit", "scope": "recipient", "window_secs": 86_400,
+              "max_sats": 1_000_000, "max_count": 3 },
+            { "rule": "pool_share", "window_secs": 86_400, "max_percent": 10.0 }
+        ]
+    }))
+    .unwrap();
+    let pool_balance = Arc::new(RwLock::new(20_000_000));
+    let engine = DisbursementEngine::new(DisbursementConfig {
+        trust_policy: Arc::new(TrustPolicy::from_config(&policy).unwrap()),
+        ..pool_config()
+    })
+    .with_payout_history(registry.clone())
+    .with_pool_balance(pool_balance.clone());
+
+    let request = |recipient: &str, amount_sats: u64| PayoutRequest {
+        recipient_address: recipient.to_string(),
+        amount_sats,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: None,
+        dry_run: None,
+    };
+
+    // 400k already paid today: another 700k exceeds the 1M daily limit.
+    let audit = engine.evaluate_ailee_trust_policy(&request(frequent, 700_000));
+    assert!(!audit.passed);
+    assert_eq!(audit.findings[0].rule_id, "recipient_velocity_1d");
+    // Another spelling of the address is the same recipient.
+    let audit = engine.evaluate_ailee_trust_policy(&request(&frequent.to_uppercase(), 700_000));
+    assert_eq!(audit.findings[0].rule_id, "recipient_velocity_1d");
+    assert!(
+        engine
+            .evaluate_ailee_trust_policy(&request(other, 700_000))
+            .passed
+    );
+
+    // 10% of a 5M pool leaves 100k after the 400k paid today.
+    *pool_balance.write().unwrap() = 5_000_000;
+    let audit = engine.evaluate_ailee_trust_policy(&request(other, 200_000));
+    assert!(!audit.passed);
+    assert_eq!(audit.findings[0].rule_id, "pool_share_1d");
+
+    let (rejected, transition) =
+        engine.rejected_payout("payout-rejected".into(), &request(other, 200_000), audit);
+    registry
+        .save_payout_transitions(&rejected, &[transition])
+        .unwrap();
+    let stored = registry
+        .get_payout_by_id("payout-rejected")
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.status, PayoutStatus::Rejected);
+    let log = registry.get_payout_transitions("payout-rejected").unwrap();
+    assert_eq!(log[0].from, PayoutStatus::Pending);
+    assert!(log[0]
+        .detail
+        .as_ref()
+        .unwrap()
+        .starts_with("pool_share_1d: "));
+
+    let _ = std::fs::remove_file(&temp_db);
 }
 
 #[test]
 fn test_payout_sqlite_persistence() {
-    let temp_db = std::env::temp_dir().join(format!("test_payouts_{}.db", uuid::Uuid::new_v4()));
+    let temp_db = common::temp_db("payouts");
     let registry =
         SqliteParticipantRegistry::open_read_write(&temp_db).expect("Failed to open read-write DB");
 
-    let engine = DisbursementEngine::new(DisbursementConfig::default());
+    let engine = DisbursementEngine::new(pool_config());
     let req = PayoutRequest {
         recipient_address: "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh".to_string(),
         amount_sats: 50_000,
@@ -71,7 +502,7 @@ fn test_payout_sqlite_persistence() {
     };
 
     let payout = engine
-        .create_unsigned_payout("payout-db-1".to_string(), &req)
+        .create_unsigned_payout("payout-db-1".to_string(), &req, &[pool_utxo(1, 500_000)])
         .expect("Failed payout generation");
 
     registry.save_payout(&payout).expect("Save payout failed");
@@ -93,3 +524,1000 @@ fn test_payout_sqlite_persistence() {
 
     let _ = std::fs::remove_file(temp_db);
 }
+
+#[test]
+fn test_payout_lifecycle_transitions_are_logged() {
+    let temp_db = common::temp_db("lifecycle");
+    let registry =
+        SqliteParticipantRegistry::open_read_write(&temp_db).expect("Failed to open read-write DB");
+
+    let secp = Secp256k1::new();
+    let key = SecretKey::from_slice(&[6u8; 32]).unwrap();
+    let pubkey = bitcoin::PublicKey::new(key.public_key(&secp));
+    let utxo = PoolUtxo::new(
+        OutPoint {
+            txid: Txid::from_slice(&[1; 32]).unwrap(),
+            vout: 0,
+        },
+        80_000,
+        ScriptBuf::new_v0_p2wpkh(&pubkey.wpubkey_hash().unwrap()),
+    );
+
+    let engine = DisbursementEngine::new(pool_config());
+    let req = PayoutRequest {
+        recipient_address: "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh".to_string(),
+        amount_sats: 50_000,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(1),
+        dry_run: Some(false),
+    };
+    let mut payout = engine
+        .create_unsigned_payout("payout-life".to_string(), &req, &[utxo])
+        .expect("Failed payout generation");
+    registry.save_payout(&payout).unwrap();
+
+    // Broadcasting an unsigned payout is not allowed.
+    assert!(payout.transition(PayoutStatus::Broadcast, None).is_err());
+
+    let psbt_bytes = base64::engine::general_purpose::STANDARD
+        .decode(&payout.psbt_base64)
+        .unwrap();
+    let mut psbt = PartiallySignedTransaction::deserialize(&psbt_bytes).unwrap();
+    let sign =
+        |msg: Message| bitcoin::ecdsa::Signature::sighash_all(secp.sign_ecdsa(&msg, &key)).to_vec();
+
+    // A final witness is checked like a partial signature.
+    let mut forged = psbt.clone();
+    forged.inputs[0].final_script_witness = Some(Witness::from_slice(&[
+        sign(Message::from_slice(&[3u8; 32]).unwrap()),
+        pubkey.to_bytes(),
+    ]));
+    assert!(payout.apply_signed_psbt(&forged).is_err());
+    assert_eq!(payout.status, PayoutStatus::UnsignedCreated);
+
+    // A signer that finalizes itself is accepted.
+    let script_code = ScriptBuf::new_v0_p2wpkh(&pubkey.wpubkey_hash().unwrap())
+        .p2wpkh_script_code()
+        .unwrap();
+    let sighash = SighashCache::new(&psbt.unsigned_tx)
+        .segwit_signature_hash(0, &script_code, 80_000, EcdsaSighashType::All)
+        .unwrap();
+    psbt.inputs[0].partial_sigs.insert(
+        pubkey,
+        bitcoin::ecdsa::Signature::from_slice(&sign(Message::from(sighash))).unwrap(),
+    );
+    assert!(finalize_psbt(&mut psbt).unwrap());
+    let finalized = payout.apply_signed_psbt(&psbt).unwrap();
+    let broadcast = payout.transition(PayoutStatus::Broadcast, None).unwrap();
+    let mut confirmed = payout
+        .observe_chain(TxChainStatus::Confirmed { confirmations: 6 }, 6)
+        .unwrap();
+    let mut transitions = vec![finalized, broadcast];
+    transitions.append(&mut confirmed);
+    registry
+        .save_payout_transitions(&payout, &transitions)
+        .unwrap();
+    drop(registry);
+
+    let registry = SqliteParticipantRegistry::open_read_write(&temp_db).unwrap();
+    let loaded = registry.get_payout_by_id("payout-life").unwrap().unwrap();
+    assert_eq!(loaded.status, PayoutStatus::Completed);
+    assert_eq!(loaded.confirmations, 6);
+    assert!(loaded.final_tx_hex.is_some());
+
+    let log: Vec<(PayoutStatus, PayoutStatus)> = registry
+        .get_payout_transitions("payout-life")
+        .unwrap()
+        .into_iter()
+        .map(|t| (t.from, t.to))
+        .collect();
+    assert_eq!(
+        log,
+        vec![
+            (PayoutStatus::UnsignedCreated, PayoutStatus::Finalized),
+            (PayoutStatus::Finalized, PayoutStatus::Broadcast),
+            (PayoutStatus::Broadcast, PayoutStatus::Confirmed),
+            (PayoutStatus::Confirmed, PayoutStatus::Completed),
+        ]
+    );
+
+    let _ = std::fs::remove_file(temp_db);
+}
+
+fn allocation(participant_id: &str, recipient_address: &str, amount_sats: u64) -> PayoutAllocation {
+    PayoutAllocation {
+        participant_id: participant_id.to_string(),
+        recipient_address: recipient_address.to_string(),
+        amount_sats,
+    }
+}
+
+#[test]
+fn test_batch_payout_merges_chunks_and_rolls_over_dust() {
+    let engine = DisbursementEngine::new(pool_config());
+    let alice = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+    let bob = "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr";
+    let carol = "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2";
+    let req = BatchPayoutRequest {
+        allocations: vec![
+            allocation("alice-1", alice, 40_000),
+            allocation("bob", bob, 30_000),
+            allocation("alice-2", alice, 20_000),
+            allocation("carol", carol, 25_000),
+            allocation("dave", POOL_ADDRESS, 300),
+        ],
+        fee_policy: BatchFeePolicy::PoolPays,
+        sub_dust_policy: SubDustPolicy::RollOver,
+        max_outputs_per_tx: Some(2),
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(2),
+        dry_run: Some(false),
+    };
+    let pool = vec![pool_utxo(1, 100_000), pool_utxo(2, 40_000)];
+
+    let result = engine
+        .create_batch_payout("batch-1".to_string(), &req, &pool)
+        .expect("Failed to create batch payout");
+
+    assert_eq!(result.transactions.len(), 2);
+    let first = &result.transactions[0];
+    assert_eq!(first.outputs[0].participant_ids, vec!["alice-1", "alice-2"]);
+    assert_eq!(first.outputs[0].amount_sats, 60_000);
+    assert_eq!(
+        result.rolled_over,
+        vec![allocation("dave", POOL_ADDRESS, 300)]
+    );
+    assert_eq!(result.total_paid_sats, 115_000);
+
+    // Each chunk spends distinct pool UTXOs.
+    let spent: Vec<_> = result
+        .transactions
+        .iter()
+        .flat_map(|t| decode_tx(&t.raw_tx_hex).input)
+        .map(|i| i.previous_output)
+        .collect();
+    assert_eq!(spent.len(), 2);
+    assert_ne!(spent[0], spent[1]);
+}
+
+#[test]
+fn test_batch_payout_splits_fee_pro_rata() {
+    let engine = DisbursementEngine::new(pool_config());
+    let req = BatchPayoutRequest {
+        allocations: vec![
+            allocation(
+                "alice",
+                "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh",
+                75_000,
+            ),
+            allocation("bob", "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", 25_000),
+        ],
+        fee_policy: BatchFeePolicy::SplitProRata,
+        sub_dust_policy: SubDustPolicy::Drop,
+        max_outputs_per_tx: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(4),
+        dry_run: Some(true),
+    };
+
+    let result = engine
+        .create_batch_payout("batch-2".to_string(), &req, &[pool_utxo(1, 150_000)])
+        .expect("Failed to create batch payout");
+    let batch_tx = &result.transactions[0];
+    let tx = decode_tx(&batch_tx.raw_tx_hex);
+
+    // Recipients carry the whole fee; change returns the untouched remainder.
+    assert_eq!(batch_tx.change_sats, Some(50_000));
+    let shares: Vec<u64> = batch_tx.outputs.iter().map(|o| o.fee_share_sats).collect();
+    assert_eq!(shares.iter().sum::<u64>(), batch_tx.fee_sats);
+    assert_eq!(shares[0], 3 * shares[1]);
+    let output_total: u64 = tx.output.iter().map(|o| o.value).sum();
+    assert_eq!(150_000 - output_total, batch_tx.fee_sats);
+}
+
+#[test]
+fn test_batch_payout_refuses_fee_shares_above_the_allocation() {
+    let engine = DisbursementEngine::new(pool_config());
+    let req = BatchPayoutRequest {
+        allocations: vec![allocation(
+            "alice",
+            "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh",
+            10_000,
+        )],
+        fee_policy: BatchFeePolicy::SplitProRata,
+        sub_dust_policy: SubDustPolicy::Drop,
+        max_outputs_per_tx: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(500),
+        dry_run: Some(true),
+    };
+
+    match engine.create_batch_payout("batch-fee".into(), &req, &[pool_utxo(1, 100_000_000)]) {
+        Err(DisbursementError::InvalidRequest(message)) => {
+            assert!(
+                message.contains("exceeds the 10000 sats allocated"),
+                "{message}"
+            )
+        }
+        other => panic!("expected an invalid request, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_batch_payout_caps_the_batch_total_and_stores_records() {
+    let temp_db = common::temp_db("batch");
+    let registry = Arc::new(SqliteParticipantRegistry::open_read_write(&temp_db).unwrap());
+    let alice = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+    let bob = "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2";
+    let policy: TrustPolicyConfig = serde_json::from_value(serde_json::json!({
+        "version": "batch-test",
+        "rules": [
+            { "rule": "pool_share", "window_secs": 86_400, "max_percent": 10.0 }
+        ]
+    }))
+    .unwrap();
+    let engine = DisbursementEngine::new(DisbursementConfig {
+        trust_policy: Arc::new(TrustPolicy::from_config(&policy).unwrap()),
+        ..pool_config()
+    })
+    .with_payout_history(registry.clone())
+    .with_pool_balance(Arc::new(RwLock::new(1_000_000)));
+    let batch = |amount_sats: u64| BatchPayoutRequest {
+        allocations: vec![
+            allocation("alice", alice, amount_sats),
+            allocation("bob", bob, amount_sats),
+        ],
+        fee_policy: BatchFeePolicy::PoolPays,
+        sub_dust_policy: SubDustPolicy::Drop,
+        max_outputs_per_tx: Some(1),
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(2),
+        dry_run: Some(false),
+    };
+    let pool = vec![pool_utxo(1, 500_000), pool_utxo(2, 500_000)];
+
+    // Each 60k payout fits under 10% of the pool; the 120k batch does not.
+    match engine.create_batch_payout("batch-over".into(), &batch(60_000), &pool) {
+        Err(DisbursementError::PolicyRejected {
+            recipient_address,
+            findings,
+        }) => {
+            assert_eq!(recipient_address, bob);
+            assert_eq!(findings[0].rule_id, "pool_share_1d");
+        }
+        other => panic!("expected a policy rejection, got {other:?}"),
+    }
+
+    let result = engine
+        .create_batch_payout("batch-ok".into(), &batch(40_000), &pool)
+        .unwrap();
+    let records = result.payout_records();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].payout_id, "batch-ok-1");
+    assert_eq!(records[0].trust_audit.policy_version, "batch-test");
+    assert_eq!(records[1].recipient_address, bob);
+    assert_eq!(records[1].amount_sats, 40_000);
+    assert_eq!(records[1].batch_id.as_deref(), Some("batch-ok"));
+    for record in &records {
+        registry.save_payout(record).unwrap();
+    }
+
+    // Batch outputs count toward the pool-wide volume and each recipient's.
+    let since = Utc::now() - chrono::Duration::hours(1);
+    let memory = RwLock::new(
+        records
+            .iter()
+            .map(|r| (r.payout_id.clone(), r.clone()))
+            .collect::<HashMap<_, _>>(),
+    );
+    for history in [&*registry as &dyn PayoutHistory, &memory] {
+        assert_eq!(history.volume_since(None, since).unwrap().sats, 80_000);
+        let volume = history.volume_since(Some(alice), since).unwrap();
+        assert_eq!((volume.count, volume.sats), (1, 40_000));
+        let volume = history
+            .volume_since(Some(&alice.to_uppercase()), since)
+            .unwrap();
+        assert_eq!(volume.sats, 40_000);
+    }
+    let stored = registry.get_payout_by_id("batch-ok-2").unwrap().unwrap();
+    assert_eq!(stored.batch_id.as_deref(), Some("batch-ok"));
+
+    // So a recipient's cap cannot be sidestepped through the batch endpoint.
+    let policy: TrustPolicyConfig = serde_json::from_value(serde_json::json!({
+        "version": "batch-test",
+        "rules": [
+            { "rule": "velocity_limit", "scope": "recipient", "window_secs": 86_400,
+              "max_sats": 50_000 }
+        ]
+    }))
+    .unwrap();
+    let engine = DisbursementEngine::new(DisbursementConfig {
+        trust_policy: Arc::new(TrustPolicy::from_config(&policy).unwrap()),
+        ..pool_config()
+    })
+    .with_payout_history(registry.clone());
+    let audit = engine.evaluate_ailee_trust_policy(&PayoutRequest {
+        recipient_address: alice.to_string(),
+        amount_sats: 20_000,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: None,
+        dry_run: None,
+    });
+    assert_eq!(audit.findings[0].rule_id, "recipient_velocity_1d");
+
+    let _ = std::fs::remove_file(&temp_db);
+}
+
+#[test]
+fn test_batch_outputs_and_transactions_stay_within_configured_limits() {
+    let temp_db = common::temp_db("batch_limits");
+    let registry = Arc::new(SqliteParticipantRegistry::open_read_write(&temp_db).unwrap());
+    let recipients = [
+        "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh",
+        "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2",
+        "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+    ];
+    let policy: TrustPolicyConfig = serde_json::from_value(serde_json::json!({
+        "version": "batch-test",
+        "rules": [
+            { "rule": "velocity_limit", "scope": "global", "window_secs": 86_400,
+              "max_count": 3 }
+        ]
+    }))
+    .unwrap();
+    let engine = DisbursementEngine::new(DisbursementConfig {
+        trust_policy: Arc::new(TrustPolicy::from_config(&policy).unwrap()),
+        max_batch_outputs: 1,
+        ..pool_config()
+    })
+    .with_payout_history(registry.clone());
+    let batch = |recipients: &[&str]| BatchPayoutRequest {
+        allocations: recipients
+            .iter()
+            .map(|address| allocation(address, address, 40_000))
+            .collect(),
+        fee_policy: BatchFeePolicy::PoolPays,
+        sub_dust_policy: SubDustPolicy::Drop,
+        max_outputs_per_tx: Some(100),
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(2),
+        dry_run: Some(false),
+    };
+    let pool: Vec<PoolUtxo> = (1..=3).map(|n| pool_utxo(n, 500_000)).collect();
+
+    // The request cannot raise the configured output cap.
+    let result = engine
+        .create_batch_payout("batch-1".into(), &batch(&recipients[..2]), &pool)
+        .unwrap();
+    assert_eq!(result.transactions.len(), 2);
+    for record in result.payout_records() {
+        registry.save_payout(&record).unwrap();
+    }
+
+    // Two transactions already paid today: a batch of two more is a fourth.
+    match engine.create_batch_payout("batch-2".into(), &batch(&recipients[1..]), &pool) {
+        Err(DisbursementError::PolicyRejected {
+            recipient_address,
+            findings,
+        }) => {
+            assert_eq!(recipient_address, recipients[2]);
+            assert_eq!(findings[0].rule_id, "global_velocity_1d");
+        }
+        other => panic!("expected a policy rejection, got {other:?}"),
+    }
+
+    let _ = std::fs::remove_file(&temp_db);
+}
+
+#[test]
+fn test_payout_psbt_carries_utxos_and_key_origins() {
+    let secp = Secp256k1::new();
+    let master = ExtendedPrivKey::new_master(Network::Bitcoin, &[9u8; 32]).unwrap();
+    let account_path = DerivationPath::from_str("m/84'/0'/0'").unwrap();
+    let xpub = ExtendedPubKey::from_priv(&secp, &master.derive_priv(&secp, &account_path).unwrap());
+    let descriptor = PoolDescriptor::from_str(&format!(
+        "wpkh([{}/84h/0h/0h]{xpub}/<0;1>/*)",
+        master.fingerprint(&secp)
+    ))
+    .unwrap();
+
+    let receive = KeyChainIndex {
+        chain: KeyChain::External,
+        index: 3,
+    };
+    let funding_key = descriptor.derive(receive).unwrap();
+    let previous_tx = Transaction {
+        version: 2,
+        lock_time: bitcoin::absolute::LockTime::ZERO,
+        input: vec![],
+        output: vec![TxOut {
+            value: 200_000,
+            script_pubkey: funding_key.script_pubkey.clone(),
+        }],
+    };
+    let utxo = PoolUtxo::new(
+        OutPoint {
+            txid: previous_tx.txid(),
+            vout: 0,
+        },
+        200_000,
+        funding_key.script_pubkey.clone(),
+    )
+    .with_derivation(receive)
+    .with_previous_tx(previous_tx.clone());
+
+    let engine = DisbursementEngine::new(DisbursementConfig {
+        pool_descriptor: Some(descriptor.clone()),
+        ..pool_config()
+    })
+    .with_next_change_index(7);
+    let req = PayoutRequest {
+        recipient_address: "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh".to_string(),
+        amount_sats: 50_000,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(2),
+        dry_run: Some(false),
+    };
+
+    let result = engine
+        .create_unsigned_payout("payout-signable".to_string(), &req, &[utxo])
+        .expect("Failed to create payout");
+    let psbt_bytes = base64::engine::general_purpose::STANDARD
+        .decode(&result.psbt_base64)
+        .unwrap();
+    let psbt = PartiallySignedTransaction::deserialize(&psbt_bytes).unwrap();
+
+    let input = &psbt.inputs[0];
+    assert_eq!(input.witness_utxo.as_ref().map(|o| o.value), Some(200_000));
+    assert_eq!(input.non_witness_utxo.as_ref(), Some(&previous_tx));
+    assert_eq!(
+        input.bip32_derivation.get(&funding_key.keys[0].0),
+        Some(&funding_key.keys[0].1)
+    );
+
+    let change_key = descriptor
+        .derive(KeyChainIndex {
+            chain: KeyChain::Internal,
+            index: 7,
+        })
+        .unwrap();
+    assert_eq!(
+        psbt.unsigned_tx.output[1].script_pubkey,
+        change_key.script_pubkey
+    );
+    assert_eq!(
+        psbt.outputs[1].bip32_derivation.get(&change_key.keys[0].0),
+        Some(&change_key.keys[0].1)
+    );
+    assert_eq!(engine.next_change_index().unwrap(), 8);
+}
+
+#[test]
+fn test_change_index_survives_restart() {
+    let temp_db = common::temp_db("change_index");
+    let secp = Secp256k1::new();
+    let master = ExtendedPrivKey::new_master(Network::Bitcoin, &[4u8; 32]).unwrap();
+    let descriptor = PoolDescriptor::from_str(&format!(
+        "wpkh({}/<0;1>/*)",
+        ExtendedPubKey::from_priv(&secp, &master)
+    ))
+    .unwrap();
+    let open = || {
+        DisbursementEngine::new(DisbursementConfig {
+            pool_descriptor: Some(descriptor.clone()),
+            ..pool_config()
+        })
+        .with_change_index_store(Arc::new(
+            SqliteParticipantRegistry::open_read_write(&temp_db).unwrap(),
+        ))
+    };
+    let payout = |engine: &DisbursementEngine, dry_run: bool| {
+        let req = PayoutRequest {
+            recipient_address: "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh".to_string(),
+            amount_sats: 50_000,
+            funding_utxo_txid: None,
+            funding_utxo_vout: None,
+            funding_utxo_value_sats: None,
+            change_address: None,
+            fee_rate_sats_per_vbyte: Some(2),
+            dry_run: Some(dry_run),
+        };
+        let result = engine
+            .create_unsigned_payout("payout-change".to_string(), &req, &[pool_utxo(1, 200_000)])
+            .unwrap();
+        decode_tx(&result.raw_tx_hex).output[1]
+            .script_pubkey
+            .clone()
+    };
+    let change_key = |index| {
+        descriptor
+            .derive(KeyChainIndex {
+                chain: KeyChain::Internal,
+                index,
+            })
+            .unwrap()
+            .script_pubkey
+    };
+
+    let engine = open();
+    assert_eq!(payout(&engine, false), change_key(0));
+    assert_eq!(payout(&engine, false), change_key(1));
+    drop(engine);
+
+    // A restarted engine continues after the last reserved key; dry runs
+    // only preview it.
+    let engine = open();
+    assert_eq!(engine.next_change_index().unwrap(), 2);
+    assert_eq!(payout(&engine, true), change_key(2));
+    assert_eq!(payout(&engine, false), change_key(2));
+    assert_eq!(engine.next_change_index().unwrap(), 3);
+
+    // An unreadable counter is an error, not index 0
+    rusqlite::Connection::open(&temp_db)
+        .unwrap()
+        .execute_batch("DROP TABLE pool_counters")
+        .unwrap();
+    assert!(matches!(
+        engine.next_change_index(),
+        Err(DisbursementError::Storage(_))
+    ));
+
+    let _ = std::fs::remove_file(&temp_db);
+}
+
+#[test]
+fn test_signed_psbts_are_combined_and_finalized() {
+    let secp = Secp256k1::new();
+    let master = ExtendedPrivKey::new_master(Network::Bitcoin, &[5u8; 32]).unwrap();
+    let descriptor = PoolDescriptor::from_str(&format!(
+        "wpkh({}/<0;1>/*)",
+        ExtendedPubKey::from_priv(&secp, &master)
+    ))
+    .unwrap();
+    let receive = KeyChainIndex {
+        chain: KeyChain::External,
+        index: 0,
+    };
+    let wpkh_key = master
+        .derive_priv(&secp, &DerivationPath::from_str("m/0/0").unwrap())
+        .unwrap()
+        .private_key;
+    let wpkh_utxo = PoolUtxo::new(
+        OutPoint {
+            txid: Txid::from_slice(&[1; 32]).unwrap(),
+            vout: 0,
+        },
+        200_000,
+        descriptor.derive(receive).unwrap().script_pubkey,
+    )
+    .with_derivation(receive);
+
+    let tr_keypair = KeyPair::from_seckey_slice(&secp, &[7u8; 32]).unwrap();
+    let tr_utxo = PoolUtxo::new(
+        OutPoint {
+            txid: Txid::from_slice(&[2; 32]).unwrap(),
+            vout: 0,
+        },
+        200_000,
+        ScriptBuf::new_v1_p2tr(&secp, tr_keypair.x_only_public_key().0, None),
+    );
+
+    let engine = DisbursementEngine::new(DisbursementConfig {
+        pool_descriptor: Some(descriptor),
+        ..pool_config()
+    });
+    let req = PayoutRequest {
+        recipient_address: "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh".to_string(),
+        amount_sats: 300_000,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(2),
+        dry_run: Some(false),
+    };
+    let mut payout = engine
+        .create_unsigned_payout("payout-cosign".to_string(), &req, &[wpkh_utxo, tr_utxo])
+        .expect("Failed payout generation");
+    let psbt_bytes = base64::engine::general_purpose::STANDARD
+        .decode(&payout.psbt_base64)
+        .unwrap();
+    let unsigned = PartiallySignedTransaction::deserialize(&psbt_bytes).unwrap();
+    let prevouts: Vec<TxOut> = unsigned
+        .inputs
+        .iter()
+        .map(|i| i.witness_utxo.clone().unwrap())
+        .collect();
+    let wpkh_index = prevouts
+        .iter()
+        .position(|o| o.script_pubkey.is_v0_p2wpkh())
+        .unwrap();
+    let tr_index = 1 - wpkh_index;
+
+    // First signer: P2WPKH input only.
+    let mut first = unsigned.clone();
+    let script_code = prevouts[wpkh_index]
+        .script_pubkey
+        .p2wpkh_script_code()
+        .unwrap();
+    let sighash = SighashCache::new(&unsigned.unsigned_tx)
+        .segwit_signature_hash(wpkh_index, &script_code, 200_000, EcdsaSighashType::All)
+        .unwrap();
+    first.inputs[wpkh_index].partial_sigs.insert(
+        bitcoin::PublicKey::new(wpkh_key.public_key(&secp)),
+        bitcoin::ecdsa::Signature::sighash_all(secp.sign_ecdsa(&Message::from(sighash), &wpkh_key)),
+    );
+    payout.apply_signed_psbt(&first).unwrap();
+    assert_eq!(payout.status, PayoutStatus::PartiallySigned);
+    assert!(payout.final_tx_hex.is_none());
+
+    // A PSBT for a different transaction is rejected.
+    let mut tampered = first.clone();
+    tampered.unsigned_tx.input[0].sequence = bitcoin::Sequence::MAX;
+    assert!(payout.apply_signed_psbt(&tampered).is_err());
+
+    // A signature over the wrong message is rejected.
+    let mut forged = unsigned.clone();
+    forged.inputs[tr_index].tap_key_sig = Some(bitcoin::taproot::Signature {
+        sig: secp.sign_schnorr_no_aux_rand(&Message::from_slice(&[3u8; 32]).unwrap(), &tr_keypair),
+        hash_ty: TapSighashType::Default,
+    });
+    assert!(payout.apply_signed_psbt(&forged).is_err());
+
+    // Second signer works from the unsigned PSBT: key-path taproot input.
+    let mut second = unsigned.clone();
+    let sighash = SighashCache::new(&unsigned.unsigned_tx)
+        .taproot_key_spend_signature_hash(
+            tr_index,
+            &Prevouts::All(&prevouts),
+            TapSighashType::Default,
+        )
+        .unwrap();
+    let tweaked = tr_keypair.tap_tweak(&secp, None).to_inner();
+    second.inputs[tr_index].tap_key_sig = Some(bitcoin::taproot::Signature {
+        sig: secp.sign_schnorr_no_aux_rand(&Message::from(sighash), &tweaked),
+        hash_ty: TapSighashType::Default,
+    });
+    payout.apply_signed_psbt(&second).unwrap();
+    assert_eq!(payout.status, PayoutStatus::Finalized);
+
+    let tx = decode_tx(payout.final_tx_hex.as_deref().unwrap());
+    assert_eq!(tx.txid().to_string(), payout.txid);
+    assert_eq!(tx.input[wpkh_index].witness.len(), 2);
+    assert_eq!(tx.input[tr_index].witness.len(), 1);
+    assert_eq!(tx.input[tr_index].witness.to_vec()[0].len(), 64);
+}
+
+#[test]
+fn test_multisig_pool_stays_partially_signed_until_threshold() {
+    let secp = Secp256k1::new();
+    let masters: Vec<ExtendedPrivKey> = (1u8..=3)
+        .map(|seed| ExtendedPrivKey::new_master(Network::Bitcoin, &[seed; 32]).unwrap())
+        .collect();
+    let account_path = DerivationPath::from_str("m/48'/0'/0'/2'").unwrap();
+    let keys: Vec<String> = masters
+        .iter()
+        .map(|m| {
+            let xpub =
+                ExtendedPubKey::from_priv(&secp, &m.derive_priv(&secp, &account_path).unwrap());
+            format!("[{}/48h/0h/0h/2h]{xpub}/<0;1>/*", m.fingerprint(&secp))
+        })
+        .collect();
+    let descriptor =
+        PoolDescriptor::from_str(&format!("wsh(sortedmulti(2,{}))", keys.join(","))).unwrap();
+    assert_eq!(descriptor.threshold(), 2);
+
+    let receive = KeyChainIndex {
+        chain: KeyChain::External,
+        index: 0,
+    };
+    let funding_key = descriptor.derive(receive).unwrap();
+    assert!(funding_key.script_pubkey.is_v0_p2wsh());
+    // The engine fills in the witness script from the descriptor.
+    let utxo = PoolUtxo::new(
+        OutPoint {
+            txid: Txid::from_slice(&[4; 32]).unwrap(),
+            vout: 0,
+        },
+        300_000,
+        funding_key.script_pubkey.clone(),
+    )
+    .with_derivation(receive);
+
+    let engine = DisbursementEngine::new(DisbursementConfig {
+        pool_descriptor: Some(descriptor),
+        ..pool_config()
+    });
+    let req = PayoutRequest {
+        recipient_address: "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh".to_string(),
+        amount_sats: 100_000,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(2),
+        dry_run: Some(false),
+    };
+    let mut payout = engine
+        .create_unsigned_payout("payout-multisig".to_string(), &req, &[utxo])
+        .expect("Failed payout generation");
+    let fingerprints: Vec<String> = masters
+        .iter()
+        .map(|m| m.fingerprint(&secp).to_string())
+        .collect();
+    let signing = payout.signing.clone().unwrap();
+    assert_eq!(signing.threshold, 2);
+    let mut awaiting: Vec<&str> = signing.awaiting().collect();
+    awaiting.sort();
+    let mut expected: Vec<&str> = fingerprints.iter().map(String::as_str).collect();
+    expected.sort();
+    assert_eq!(awaiting, expected);
+
+    let psbt_bytes = base64::engine::general_purpose::STANDARD
+        .decode(&payout.psbt_base64)
+        .unwrap();
+    let unsigned = PartiallySignedTransaction::deserialize(&psbt_bytes).unwrap();
+    let witness_script = unsigned.inputs[0].witness_script.clone().unwrap();
+    assert_eq!(Some(&witness_script), funding_key.witness_script.as_ref());
+    assert_eq!(unsigned.inputs[0].bip32_derivation.len(), 3);
+    assert_eq!(unsigned.outputs[1].bip32_derivation.len(), 3);
+    assert!(unsigned.outputs[1].witness_script.is_some());
+
+    let sighash = SighashCache::new(&unsigned.unsigned_tx)
+        .segwit_signature_hash(0, &witness_script, 300_000, EcdsaSighashType::All)
+        .unwrap();
+    let cosigner_key = |cosigner: usize| {
+        let path = account_path.extend([
+            bitcoin::bip32::ChildNumber::from_normal_idx(0).unwrap(),
+            bitcoin::bip32::ChildNumber::from_normal_idx(0).unwrap(),
+        ]);
+        masters[cosigner]
+            .derive_priv(&secp, &path)
+            .unwrap()
+            .private_key
+    };
+    let sign = |cosigner: usize| {
+        let mut psbt = unsigned.clone();
+        let key = cosigner_key(cosigner);
+        psbt.inputs[0].partial_sigs.insert(
+            bitcoin::PublicKey::new(key.public_key(&secp)),
+            bitcoin::ecdsa::Signature::sighash_all(secp.sign_ecdsa(&Message::from(sighash), &key)),
+        );
+        psbt
+    };
+
+    // A signature under a cosigner's key that does not verify is not counted
+    let outsider = SecretKey::from_slice(&[9u8; 32]).unwrap();
+    let mut forged = sign(2);
+    forged.inputs[0].partial_sigs.insert(
+        bitcoin::PublicKey::new(cosigner_key(1).public_key(&secp)),
+        bitcoin::ecdsa::Signature::sighash_all(secp.sign_ecdsa(&Message::from(sighash), &outsider)),
+    );
+    let progress = signing_progress(&forged).unwrap();
+    assert_eq!(progress.signed_count(), 1);
+    assert!(progress.awaiting().any(|f| f == fingerprints[1]));
+
+    let unsigned_payout = payout.clone();
+    payout.apply_signed_psbt(&sign(2)).unwrap();
+    assert_eq!(payout.status, PayoutStatus::PartiallySigned);
+    let signing = payout.signing.clone().unwrap();
+    assert_eq!(signing.signed_count(), 1);
+    assert!(!signing.awaiting().any(|f| f == fingerprints[2]));
+
+    payout.apply_signed_psbt(&sign(0)).unwrap();
+    assert_eq!(payout.status, PayoutStatus::Finalized);
+    assert_eq!(payout.signing.as_ref().unwrap().signed_count(), 2);
+
+    // <> sig sig script, and the fee covers the signed size.
+    let tx = decode_tx(payout.final_tx_hex.as_deref().unwrap());
+    assert_eq!(tx.input[0].witness.len(), 4);
+    assert!(tx.input[0].witness.to_vec()[0].is_empty());
+    assert!(payout.fee_sats >= 2 * tx.vsize() as u64);
+
+    // A PSBT the cosigners finalized themselves is re-verified key by key.
+    let mut finalized = sign(0);
+    finalized.combine(sign(1)).unwrap();
+    assert!(finalize_psbt(&mut finalized).unwrap());
+    let mut accepted = unsigned_payout.clone();
+    accepted.apply_signed_psbt(&finalized).unwrap();
+    assert_eq!(accepted.status, PayoutStatus::Finalized);
+
+    let outsider = SecretKey::from_slice(&[9u8; 32]).unwrap();
+    let mut items = finalized.inputs[0]
+        .final_script_witness
+        .clone()
+        .unwrap()
+        .to_vec();
+    items[1] =
+        bitcoin::ecdsa::Signature::sighash_all(secp.sign_ecdsa(&Message::from(sighash), &outsider))
+            .to_vec();
+    finalized.inputs[0].final_script_witness = Some(Witness::from_slice(&items));
+    let mut rejected = unsigned_payout;
+    assert!(rejected.apply_signed_psbt(&finalized).is_err());
+    assert_eq!(rejected.status, PayoutStatus::UnsignedCreated);
+}
+
+fn broadcast_payout(engine: &DisbursementEngine, pool: &[PoolUtxo]) -> PayoutTransactionResult {
+    let req = PayoutRequest {
+        recipient_address: "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh".to_string(),
+        amount_sats: 60_000,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(1),
+        dry_run: Some(false),
+    };
+    let mut payout = engine
+        .create_unsigned_payout("payout-stuck".to_string(), &req, pool)
+        .expect("Failed payout generation");
+    payout.transition(PayoutStatus::Finalized, None).unwrap();
+    payout.transition(PayoutStatus::Broadcast, None).unwrap();
+    payout
+}
+
+#[test]
+fn test_rbf_replacement_meets_bip125_rules() {
+    let engine = DisbursementEngine::new(pool_config());
+    let original = broadcast_payout(&engine, &[pool_utxo(1, 100_000)]);
+    let original_tx = decode_tx(&original.raw_tx_hex);
+
+    // Draining the 100k input at 400 sat/vB needs a second pool input.
+    let replacement = engine
+        .replace_by_fee(
+            "payout-rbf".to_string(),
+            &original,
+            400,
+            &[pool_utxo(2, 50_000)],
+        )
+        .unwrap();
+    let tx = decode_tx(&replacement.raw_tx_hex);
+
+    assert_eq!(tx.output[0], original_tx.output[0]);
+    assert_eq!(
+        tx.input[0].previous_output,
+        original_tx.input[0].previous_output
+    );
+    assert_eq!(tx.input.len(), 2);
+    assert!(tx.is_explicitly_rbf());
+
+    let output_value: u64 = tx.output.iter().map(|o| o.value).sum();
+    assert_eq!(150_000 - output_value, replacement.fee_sats);
+    let vsize = estimate_vsize(
+        &[InputScriptType::P2wpkh; 2],
+        &[&tx.output[0].script_pubkey, &tx.output[1].script_pubkey],
+    );
+    assert_eq!(replacement.fee_sats, 400 * vsize);
+    assert!(replacement.fee_sats >= original.fee_sats + vsize);
+    assert_eq!(
+        replacement.fee_bump_of,
+        Some(FeeBumpLink {
+            payout_id: "payout-stuck".to_string(),
+            kind: FeeBumpKind::Rbf,
+        })
+    );
+
+    // Not a fee increase.
+    assert!(matches!(
+        engine.replace_by_fee("payout-rbf-2".to_string(), &original, 1, &[]),
+        Err(DisbursementError::InvalidRequest(_))
+    ));
+}
+
+#[test]
+fn test_fee_bumps_are_capped_and_keep_typed_errors() {
+    let engine = DisbursementEngine::new(DisbursementConfig {
+        max_fee_rate: 500,
+        ..pool_config()
+    });
+    let original = broadcast_payout(&engine, &[pool_utxo(1, 100_000)]);
+
+    assert!(matches!(
+        engine.bump_fee("payout-bump".to_string(), &original, 501, &[]),
+        Err(DisbursementError::InvalidRequest(_))
+    ));
+    // Neither a replacement nor a child can pay 500 sat/vB from what is left
+    assert!(matches!(
+        engine.bump_fee("payout-bump".to_string(), &original, 500, &[]),
+        Err(DisbursementError::InsufficientFunds { .. })
+    ));
+}
+
+#[test]
+fn test_payouts_and_batches_refuse_fee_rates_above_the_cap() {
+    let engine = DisbursementEngine::new(pool_config());
+    let alice = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+    let pool = [pool_utxo(1, 100_000_000)];
+    let req = PayoutRequest {
+        recipient_address: alice.to_string(),
+        amount_sats: 1_000,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(500_000),
+        dry_run: Some(true),
+    };
+    let err = engine
+        .create_unsigned_payout("payout-fee".to_string(), &req, &pool)
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("exceeds the maximum of 1000"),
+        "{err}"
+    );
+
+    let batch = BatchPayoutRequest {
+        allocations: vec![allocation("alice", alice, 10_000)],
+        fee_policy: BatchFeePolicy::PoolPays,
+        sub_dust_policy: SubDustPolicy::Drop,
+        max_outputs_per_tx: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(1_001),
+        dry_run: Some(true),
+    };
+    assert!(matches!(
+        engine.create_batch_payout("batch-fee".into(), &batch, &pool),
+        Err(DisbursementError::InvalidRequest(_))
+    ));
+}
+
+#[test]
+fn test_cpfp_child_spends_change_at_package_rate() {
+    let temp_db = common::temp_db("cpfp");
+    let registry = SqliteParticipantRegistry::open_read_write(&temp_db).unwrap();
+    let engine = DisbursementEngine::new(pool_config());
+    let mut original = broadcast_payout(&engine, &[pool_utxo(1, 100_000)]);
+    let parent_tx = decode_tx(&original.raw_tx_hex);
+
+    let child = engine
+        .child_pays_for_parent("payout-cpfp".to_string(), &original, 20, &[])
+        .unwrap();
+    let tx = decode_tx(&child.raw_tx_hex);
+    assert_eq!(tx.input.len(), 1);
+    assert_eq!(
+        tx.input[0].previous_output,
+        OutPoint {
+            txid: parent_tx.txid(),
+            vout: 1,
+        }
+    );
+
+    let parent_vsize = estimate_vsize(
+        &[InputScriptType::P2wpkh],
+        &[
+            &parent_tx.output[0].script_pubkey,
+            &parent_tx.output[1].script_pubkey,
+        ],
+    );
+    let child_vsize = estimate_vsize(&[InputScriptType::P2wpkh], &[&tx.output[0].script_pubkey]);
+    assert_eq!(
+        original.fee_sats + child.fee_sats,
+        20 * (parent_vsize + child_vsize)
+    );
+    assert_eq!(
+        parent_tx.output[1].value - child.fee_sats,
+        tx.output[0].value
+    );
+
+    original.fee_bumped_by = Some(FeeBumpLink {
+        payout_id: child.payout_id.clone(),
+        kind: FeeBumpKind::Cpfp,
+    });
+    registry.save_payout(&original).unwrap();
+    registry.save_payout(&child).unwrap();
+    let loaded = registry.get_payout_by_id("payout-stuck").unwrap().unwrap();
+    assert_eq!(loaded.fee_bumped_by, original.fee_bumped_by);
+    let loaded = registry.get_payout_by_id("payout-cpfp").unwrap().unwrap();
+    assert_eq!(loaded.fee_bump_of.map(|l| l.kind), Some(FeeBumpKind::Cpfp));
+
+    let _ = std::fs::remove_file(temp_db);
+}
@@ -0,0 +1,376 @@
+#![cfg(feature = "test-signer")]
+//! Signed payouts are checked twice: by `verify_spends`, and by running each
+//! witness through the small script interpreter below, which shares no code
+//! with the crate. libbitcoinconsensus (`bitcoin`'s `bitcoinconsensus`
+//! feature) would be the reference check, but it cannot be resolved in the
+//! offline build these tests run in.
+
+use base64::Engine;
+use bitcoin::bip32::DerivationPath;
+use bitcoin::blockdata::opcodes::all::*;
+use bitcoin::blockdata::script::Instruction;
+use bitcoin::hashes::{hash160, Hash};
+use bitcoin::psbt::PartiallySignedTransaction;
+use bitcoin::secp256k1::{Message, Secp256k1, XOnlyPublicKey};
+use bitcoin::sighash::{Prevouts, SighashCache};
+use bitcoin::{
+    ecdsa, taproot, Network, OutPoint, PublicKey, Script, ScriptBuf, Transaction, TxOut, Txid,
+};
+use bitcoin_digital_labor_derivative::disbursement::test_signer::{verify_spends, TestSigner};
+use bitcoin_digital_labor_derivative::disbursement::{
+    finalize_psbt, BatchPayoutRequest, DisbursementConfig, DisbursementEngine, KeyChain,
+    KeyChainIndex, PayoutAllocation, PayoutRequest, PayoutStatus, PayoutTransactionResult,
+    PoolDescriptor, PoolUtxo,
+};
+use std::str::FromStr;
+
+const RECIPIENT: &str = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+
+fn decode_psbt(psbt_base64: &str) -> PartiallySignedTransaction {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(psbt_base64)
+        .unwrap();
+    PartiallySignedTransaction::deserialize(&bytes).unwrap()
+}
+
+fn decode_tx(raw_hex: &str) -> Transaction {
+    bitcoin::consensus::deserialize(&hex::decode(raw_hex).unwrap()).unwrap()
+}
+
+/// Engine for `descriptor` plus `count` pool UTXOs on its receive chain.
+fn pool(descriptor: &str, values: &[u64]) -> (DisbursementEngine, Vec<PoolUtxo>) {
+    let descriptor = PoolDescriptor::from_str(descriptor).unwrap();
+    let utxos = values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let at = KeyChainIndex {
+                chain: KeyChain::External,
+                index: i as u32,
+            };
+            PoolUtxo::new(
+                OutPoint {
+                    txid: Txid::from_slice(&[i as u8 + 1; 32]).unwrap(),
+                    vout: 0,
+                },
+                *value,
+                descriptor.derive(at).unwrap().script_pubkey,
+            )
+            .with_derivation(at)
+        })
+        .collect();
+    let engine = DisbursementEngine::new(DisbursementConfig {
+        pool_descriptor: Some(descriptor),
+        ..Default::default()
+    });
+    (engine, utxos)
+}
+
+fn payout(engine: &DisbursementEngine, utxos: &[PoolUtxo], amount: u64) -> PayoutTransactionResult {
+    let req = PayoutRequest {
+        recipient_address: RECIPIENT.to_string(),
+        amount_sats: amount,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(5),
+        dry_run: Some(false),
+    };
+    engine
+        .create_unsigned_payout("payout-e2e".to_string(), &req, utxos)
+        .expect("Failed payout generation")
+}
+
+/// Outputs spent by `tx`, looked up in the pool.
+fn spent_outputs(tx: &Transaction, utxos: &[PoolUtxo]) -> Vec<TxOut> {
+    tx.input
+        .iter()
+        .map(|txin| {
+            let utxo = utxos
+                .iter()
+                .find(|u| u.outpoint == txin.previous_output)
+                .unwrap();
+            TxOut {
+                value: utxo.value_sats,
+                script_pubkey: utxo.script_pubkey.clone(),
+            }
+        })
+        .collect()
+}
+
+/// Executes every input of `tx` against the output it spends: P2WPKH and
+/// P2WSH scripts are run on their witness stack (BIP141/143), P2TR key-path
+/// signatures checked against the output key (BIP341). Fails closed on
+/// anything else.
+fn execute_spends(tx: &Transaction, spent: &[TxOut]) -> Result<(), String> {
+    let secp = Secp256k1::verification_only();
+    let mut cache = SighashCache::new(tx);
+    for (index, (txin, prevout)) in tx.input.iter().zip(spent).enumerate() {
+        let fail = |reason: &str| format!("input {index}: {reason}");
+        if !txin.script_sig.is_empty() {
+            return Err(fail("non-empty scriptSig"));
+        }
+        let program = &prevout.script_pubkey;
+        let mut stack = txin.witness.to_vec();
+
+        if program.is_v1_p2tr() {
+            let [sig] = &stack[..] else {
+                return Err(fail("key path spends carry one signature"));
+            };
+            let sig = taproot::Signature::from_slice(sig).map_err(|e| fail(&e.to_string()))?;
+            let sighash = cache
+                .taproot_key_spend_signature_hash(index, &Prevouts::All(spent), sig.hash_ty)
+                .map_err(|e| fail(&e.to_string()))?;
+            let output_key = XOnlyPublicKey::from_slice(&program.as_bytes()[2..])
+                .map_err(|e| fail(&e.to_string()))?;
+            secp.verify_schnorr(&sig.sig, &Message::from(sighash), &output_key)
+                .map_err(|_| fail("Schnorr signature does not verify"))?;
+            continue;
+        }
+        let script = if program.is_v0_p2wpkh() {
+            if stack.len() != 2 {
+                return Err(fail("P2WPKH witness must hold two items"));
+            }
+            program.p2wpkh_script_code().unwrap()
+        } else if program.is_v0_p2wsh() {
+            let script = ScriptBuf::from_bytes(stack.pop().ok_or_else(|| fail("empty witness"))?);
+            if ScriptBuf::new_v0_p2wsh(&script.wscript_hash()) != *program {
+                return Err(fail("witness script does not hash to the program"));
+            }
+            script
+        } else {
+            return Err(fail("unsupported output type"));
+        };
+
+        let mut check_sig = |sig: &[u8], key: &[u8]| -> bool {
+            let (Ok(sig), Ok(key)) = (
+                ecdsa::Signature::from_slice(sig),
+                PublicKey::from_slice(key),
+            ) else {
+                return false;
+            };
+            key.compressed
+                && cache
+                    .segwit_signature_hash(index, &script, prevout.value, sig.hash_ty)
+                    .is_ok_and(|sighash| {
+                        secp.verify_ecdsa(&Message::from(sighash), &sig.sig, &key.inner)
+                            .is_ok()
+                    })
+        };
+        run_script(&script, &mut stack, &mut check_sig).map_err(|e| fail(&e))?;
+        // Witness scripts must leave exactly one true element (CLEANSTACK)
+        if stack.len() != 1 || !is_true(&stack[0]) {
+            return Err(fail("script did not succeed"));
+        }
+    }
+    Ok(())
+}
+
+/// The opcodes pool scripts use: pushes, `OP_DUP`, `OP_HASH160`,
+/// `OP_EQUAL(VERIFY)`, `OP_VERIFY` and `OP_CHECK(MULTI)SIG(VERIFY)`.
+/// Failed non-empty signatures abort (NULLFAIL), as does a non-empty
+/// `OP_CHECKMULTISIG` dummy (NULLDUMMY).
+fn run_script(
+    script: &Script,
+    stack: &mut Vec<Vec<u8>>,
+    check_sig: &mut dyn FnMut(&[u8], &[u8]) -> bool,
+) -> Result<(), String> {
+    fn pop(stack: &mut Vec<Vec<u8>>) -> Result<Vec<u8>, String> {
+        stack.pop().ok_or_else(|| "stack underflow".to_string())
+    }
+    fn pop_count(stack: &mut Vec<Vec<u8>>, max: usize) -> Result<usize, String> {
+        let item = pop(stack)?;
+        match item[..] {
+            [] => Ok(0),
+            [n] if (n as usize) <= max => Ok(n as usize),
+            _ => Err(format!("count {item:?} out of range")),
+        }
+    }
+    fn verify(stack: &mut Vec<Vec<u8>>, op: &str) -> Result<(), String> {
+        if is_true(&pop(stack)?) {
+            Ok(())
+        } else {
+            Err(format!("{op} failed"))
+        }
+    }
+    let bool_item = |b: bool| if b { vec![1] } else { Vec::new() };
+
+    for instruction in script.instructions_minimal() {
+        match instruction.map_err(|e| e.to_string())? {
+            Instruction::PushBytes(bytes) => stack.push(bytes.as_bytes().to_vec()),
+            Instruction::Op(op) => {
+                let code = op.to_u8();
+                if (OP_PUSHNUM_1.to_u8()..=OP_PUSHNUM_16.to_u8()).contains(&code) {
+                    stack.push(vec![code - OP_PUSHNUM_1.to_u8() + 1]);
+                } else if op == OP_DUP {
+                    let top = stack.last().cloned().ok_or("stack underflow")?;
+                    stack.push(top);
+                } else if op == OP_HASH160 {
+                    let item = pop(stack)?;
+                    stack.push(hash160::Hash::hash(&item).to_byte_array().to_vec());
+                } else if op == OP_EQUAL || op == OP_EQUALVERIFY {
+                    let (a, b) = (pop(stack)?, pop(stack)?);
+                    stack.push(bool_item(a == b));
+                    if op == OP_EQUALVERIFY {
+                        verify(stack, "OP_EQUALVERIFY")?;
+                    }
+                } else if op == OP_VERIFY {
+                    verify(stack, "OP_VERIFY")?;
+                } else if op == OP_CHECKSIG || op == OP_CHECKSIGVERIFY {
+                    let (key, sig) = (pop(stack)?, pop(stack)?);
+                    let valid = !sig.is_empty() && check_sig(&sig, &key);
+                    if !valid && !sig.is_empty() {
+                        return Err("signature does not verify".into());
+                    }
+                    stack.push(bool_item(valid));
+                    if op == OP_CHECKSIGVERIFY {
+                        verify(stack, "OP_CHECKSIGVERIFY")?;
+                    }
+                } else if op == OP_CHECKMULTISIG || op == OP_CHECKMULTISIGVERIFY {
+                    let key_count = pop_count(stack, 20)?;
+                    let mut keys = (0..key_count)
+                        .map(|_| pop(stack))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    keys.reverse();
+                    let sig_count = pop_count(stack, key_count)?;
+                    let mut sigs = (0..sig_count)
+                        .map(|_| pop(stack))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    sigs.reverse();
+                    if !pop(stack)?.is_empty() {
+                        return Err("non-empty OP_CHECKMULTISIG dummy".into());
+                    }
+                    // Signatures match keys in script order, each key once
+                    let mut remaining_keys = keys.iter();
+                    let valid = sigs.iter().all(|sig| {
+                        !sig.is_empty() && remaining_keys.by_ref().any(|key| check_sig(sig, key))
+                    });
+                    if !valid && sigs.iter().any(|sig| !sig.is_empty()) {
+                        return Err("signatures do not verify".into());
+                    }
+                    stack.push(bool_item(valid));
+                    if op == OP_CHECKMULTISIGVERIFY {
+                        verify(stack, "OP_CHECKMULTISIGVERIFY")?;
+                    }
+                } else {
+                    return Err(format!("unsupported opcode {op}"));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Script truth: any non-zero byte, except a lone sign bit (negative zero).
+fn is_true(item: &[u8]) -> bool {
+    match item.split_last() {
+        None => false,
+        Some((last, rest)) => rest.iter().any(|b| *b != 0) || (*last & 0x7f) != 0,
+    }
+}
+
+#[test]
+fn test_single_key_payouts_sign_finalize_and_verify() {
+    let signer = TestSigner::from_seed(&[11u8; 32], Network::Bitcoin);
+    for (wrapper, account) in [("wpkh", "m/84'/0'/0'"), ("tr", "m/86'/0'/0'")] {
+        let key = signer.descriptor_key(&DerivationPath::from_str(account).unwrap());
+        let (engine, utxos) = pool(&format!("{wrapper}({key})"), &[70_000, 90_000]);
+        let mut payout = payout(&engine, &utxos, 120_000);
+
+        let mut psbt = decode_psbt(&payout.psbt_base64);
+        assert_eq!(signer.sign_psbt(&mut psbt).unwrap(), 2, "{wrapper}");
+        payout.apply_signed_psbt(&psbt).unwrap();
+        assert_eq!(payout.status, PayoutStatus::Finalized, "{wrapper}");
+
+        let tx = decode_tx(payout.final_tx_hex.as_deref().unwrap());
+        let spent = spent_outputs(&tx, &utxos);
+        verify_spends(&tx, &spent).unwrap_or_else(|e| panic!("{wrapper}: {e}"));
+        execute_spends(&tx, &spent).unwrap_or_else(|e| panic!("{wrapper}: {e}"));
+        // The estimate the fee was paid on never undershoots the signed size.
+        assert!(payout.fee_sats >= 5 * tx.vsize() as u64, "{wrapper}");
+
+        let mut tampered = tx.clone();
+        tampered.output[0].value -= 1;
+        assert!(verify_spends(&tampered, &spent).is_err(), "{wrapper}");
+        assert!(execute_spends(&tampered, &spent).is_err(), "{wrapper}");
+    }
+}
+
+#[test]
+fn test_multisig_payout_verifies_once_threshold_signed() {
+    let account = DerivationPath::from_str("m/48'/0'/0'/2'").unwrap();
+    let signers: Vec<TestSigner> = (21u8..=23)
+        .map(|seed| TestSigner::from_seed(&[seed; 32], Network::Bitcoin))
+        .collect();
+    let keys: Vec<String> = signers.iter().map(|s| s.descriptor_key(&account)).collect();
+    let (engine, utxos) = pool(
+        &format!("wsh(sortedmulti(2,{}))", keys.join(",")),
+        &[150_000],
+    );
+    let mut payout = payout(&engine, &utxos, 100_000);
+    let unsigned = decode_psbt(&payout.psbt_base64);
+
+    let mut first = unsigned.clone();
+    assert_eq!(signers[1].sign_psbt(&mut first).unwrap(), 1);
+    payout.apply_signed_psbt(&first).unwrap();
+    assert_eq!(payout.status, PayoutStatus::PartiallySigned);
+    assert!(payout.final_tx_hex.is_none());
+
+    let mut second = unsigned.clone();
+    signers[2].sign_psbt(&mut second).unwrap();
+    payout.apply_signed_psbt(&second).unwrap();
+    assert_eq!(payout.status, PayoutStatus::Finalized);
+
+    let tx = decode_tx(payout.final_tx_hex.as_deref().unwrap());
+    let spent = spent_outputs(&tx, &utxos);
+    verify_spends(&tx, &spent).unwrap();
+    execute_spends(&tx, &spent).unwrap();
+    assert!(payout.fee_sats >= 5 * tx.vsize() as u64);
+
+    // Dropping a signature leaves CHECKMULTISIG one short
+    let mut short = tx.clone();
+    let mut items = short.input[0].witness.to_vec();
+    items.remove(1);
+    short.input[0].witness = bitcoin::Witness::from_slice(&items);
+    assert!(execute_spends(&short, &spent).is_err());
+}
+
+#[test]
+fn test_batch_payout_psbt_signs_and_verifies() {
+    let signer = TestSigner::from_seed(&[31u8; 32], Network::Bitcoin);
+    let key = signer.descriptor_key(&DerivationPath::from_str("m/84'/0'/0'").unwrap());
+    let (engine, utxos) = pool(&format!("wpkh({key})"), &[200_000]);
+    let req = BatchPayoutRequest {
+        allocations: vec![
+            PayoutAllocation {
+                participant_id: "alice".into(),
+                recipient_address: RECIPIENT.into(),
+                amount_sats: 40_000,
+            },
+            PayoutAllocation {
+                participant_id: "bob".into(),
+                recipient_address: "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+                    .into(),
+                amount_sats: 60_000,
+            },
+        ],
+        fee_policy: Default::default(),
+        sub_dust_policy: Default::default(),
+        max_outputs_per_tx: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(3),
+        dry_run: Some(false),
+    };
+    let batch = engine
+        .create_batch_payout("batch-e2e".to_string(), &req, &utxos)
+        .unwrap();
+
+    let mut psbt = decode_psbt(&batch.transactions[0].psbt_base64);
+    signer.sign_psbt(&mut psbt).unwrap();
+    assert!(finalize_psbt(&mut psbt).unwrap());
+    let tx = psbt.extract_tx();
+    let spent = spent_outputs(&tx, &utxos);
+    verify_spends(&tx, &spent).unwrap();
+    execute_spends(&tx, &spent).unwrap();
+}
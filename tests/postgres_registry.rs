@@ -0,0 +1,522 @@
+#![cfg(feature = "postgres")]
+
+//! Runs against the server in `BDLD_TEST_POSTGRES_URL` (a `postgres://` URL
+//! or `key=value` string for a role allowed to create databases), with a
+//! fresh database per test. Ignored by default; run with
+//! `cargo test --features api,postgres --test postgres_registry -- --include-ignored`.
+
+mod common;
+
+use bitcoin::Address;
+use bitcoin_digital_labor_derivative::disbursement::{
+    ChangeIndexStore, DisbursementConfig, DisbursementEngine, PayoutRequest, PayoutStatus,
+    PayoutTransactionResult, PoolUtxo,
+};
+use bitcoin_digital_labor_derivative::postgres_registry::PostgresParticipantRegistry;
+use bitcoin_digital_labor_derivative::sqlite_participant_registry::SqliteParticipantRegistry;
+use bitcoin_digital_labor_derivative::storage::{RegistryError, RegistryStorage};
+use bitcoin_digital_labor_derivative::velocity_analyzer::ParticipantRegistry;
+use chrono::{Duration, Utc};
+use common::{address, lock, outpoint, temp_db};
+use postgres::{Config, NoTls};
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::str::FromStr;
+use std::sync::{mpsc, Arc};
+use std::time::Duration as StdDuration;
+
+const POOL_ADDRESS: &str = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+const ALICE: &str = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+const BOB: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+/// Recreates database `bdld_test_<name>` and returns its config.
+fn fresh_database(name: &str) -> Config {
+    let url = env::var("BDLD_TEST_POSTGRES_URL")
+        .expect("BDLD_TEST_POSTGRES_URL must name a PostgreSQL server for these tests");
+    let mut config = Config::from_str(&url).unwrap();
+    let dbname = format!("bdld_test_{name}");
+    // The blocking client must not run on an async test's runtime thread
+    let admin = config.clone();
+    let create = dbname.clone();
+    std::thread::spawn(move || {
+        let mut client = admin.connect(NoTls).unwrap();
+        for statement in [
+            format!("DROP DATABASE IF EXISTS {create} WITH (FORCE)"),
+            format!("CREATE DATABASE {create}"),
+        ] {
+            client.batch_execute(&statement).unwrap();
+        }
+    })
+    .join()
+    .unwrap();
+    config.dbname(&dbname);
+    config
+}
+
+/// Participants, stakes, trust and a failed payout; returns what the
+/// backend reads back.
+fn exercise(storage: &dyn RegistryStorage) -> Vec<String> {
+    storage
+        .register_participant("alice", &[ALICE.to_string()])
+        .unwrap();
+    storage.register_participant("bob", &[]).unwrap();
+    storage.add_participant_address("bob", BOB, None).unwrap();
+    storage
+        .record_stake("alice", outpoint(1), 50_000_000, &lock(850_000))
+        .unwrap();
+    storage
+        .record_stake("bob", outpoint(2), 20_000_000, &lock(850_000))
+        .unwrap();
+    storage.verify_stake(&outpoint(1), 800_000).unwrap();
+    storage.verify_stake(&outpoint(2), 800_010).unwrap();
+    storage.mark_stake_spent(&outpoint(2), 800_020).unwrap();
+    storage.record_trust("alice", 800_000, 1.5).unwrap();
+    storage.record_trust("alice", 800_000, 1.25).unwrap();
+    storage.register_participant("dave", &[address(3)]).unwrap();
+    storage
+        .record_stake("dave", outpoint(3), 10_000_000, &lock(850_000))
+        .unwrap();
+    storage.verify_stake(&outpoint(3), 800_000).unwrap();
+    storage.deactivate_participant("dave", 800_015).unwrap();
+
+    let pool_utxo = PoolUtxo::new(
+        outpoint(7),
+        1_000_000,
+        Address::from_str(POOL_ADDRESS)
+            .unwrap()
+            .assume_checked()
+            .script_pubkey(),
+    );
+    let req = PayoutRequest {
+        recipient_address: ALICE.to_string(),
+        amount_sats: 50_000,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(1),
+        dry_run: Some(false),
+    };
+    let mut payout = DisbursementEngine::new(DisbursementConfig {
+        change_address: Some(POOL_ADDRESS.to_string()),
+        ..Default::default()
+    })
+    .create_unsigned_payout(
+        "payout-1".to_string(),
+        &req,
+        std::slice::from_ref(&pool_utxo),
+    )
+    .unwrap();
+    let pool = [
+        pool_utxo.clone(),
+        PoolUtxo {
+            outpoint: outpoint(8),
+            ..pool_utxo
+        },
+    ];
+    storage.replace_pool_utxos(&pool).unwrap();
+    storage
+        .reserve_pool_utxos("payout-1", &[outpoint(7), outpoint(9)])
+        .unwrap();
+    assert_eq!(
+        storage.reserve_pool_utxos("payout-2", &[outpoint(8), outpoint(7)]),
+        Err(RegistryError::UtxoReserved {
+            outpoint: outpoint(7),
+            payout_id: "payout-1".to_string(),
+        })
+    );
+    // Reservations outlive replacing the set
+    storage.replace_pool_utxos(&pool).unwrap();
+    storage.save_payout(&payout).unwrap();
+    let failed = payout.transition(PayoutStatus::Failed, None).unwrap();
+    storage.save_payout_transitions(&payout, &[failed]).unwrap();
+    let batch_record = PayoutTransactionResult {
+        payout_id: "batch-1-1".to_string(),
+        status: PayoutStatus::UnsignedCreated,
+        batch_id: Some("batch-1".to_string()),
+        ..payout.clone()
+    };
+    storage.save_payout(&batch_record).unwrap();
+
+    // Timestamps differ between runs
+    let mut stored = storage.get_payout_by_id("payout-1").unwrap().unwrap();
+    stored.timestamp.clear();
+    let batch = storage.get_payout_by_id("batch-1-1").unwrap().unwrap();
+    let mut transitions = storage.get_payout_transitions("payout-1").unwrap();
+    for transition in &mut transitions {
+        transition.timestamp.clear();
+    }
+    let mut participants = storage.list_participants(0, 10, true).unwrap().participants;
+    for participant in &mut participants {
+        participant.registered_at = None;
+    }
+    let snapshots: Vec<String> = [800_000, 800_015, 800_020]
+        .into_iter()
+        .flat_map(|height| storage.participant_snapshots(height).unwrap())
+        .map(|s| {
+            format!(
+                "{} {} {}",
+                s.participant_id, s.stake_amount_sats, s.trust_coefficient
+            )
+        })
+        .collect();
+    let since = Utc::now() - Duration::hours(1);
+    vec![
+        format!("{participants:?}"),
+        format!("{:?}", storage.addresses_for("bob").unwrap()),
+        format!("{:?}", storage.stakes_for("bob").unwrap()),
+        format!("{snapshots:?}"),
+        serde_json::to_string(&stored).unwrap(),
+        format!("{:?}", storage.get_all_payouts().unwrap().len()),
+        format!("{transitions:?}"),
+        format!("{:?}", storage.first_seen(ALICE).unwrap()),
+        format!("{:?}", storage.volume_since(None, since).unwrap()),
+        format!("{:?}", storage.volume_since(Some(ALICE), since).unwrap()),
+        format!("{:?}", batch.batch_id),
+        format!("{:?}", storage.pool_utxos().unwrap()),
+        format!(
+            "{:?}",
+            [
+                storage.next_change_index().unwrap(),
+                storage.reserve_change_index().unwrap(),
+                storage.reserve_change_index().unwrap(),
+                storage.next_change_index().unwrap(),
+            ]
+        ),
+        format!(
+            "{:?}",
+            storage
+                .audit_entries(0, 100)
+                .unwrap()
+                .iter()
+                .map(|e| e.event().unwrap())
+                .collect::<Vec<_>>()
+        ),
+    ]
+}
+
+#[test]
+#[ignore = "needs BDLD_TEST_POSTGRES_URL"]
+fn postgres_reads_back_what_sqlite_does() {
+    let config = fresh_database("parity");
+    let postgres = PostgresParticipantRegistry::from_config(config).unwrap();
+    let path = temp_db("postgres_parity");
+    let sqlite = SqliteParticipantRegistry::open_read_write(&path).unwrap();
+
+    assert_eq!(exercise(&postgres), exercise(&sqlite));
+    assert_eq!(postgres.verify_audit_chain().unwrap().entries, 17);
+
+    for storage in [&postgres as &dyn RegistryStorage, &sqlite] {
+        // Another spelling of a registered address is the same address
+        assert_eq!(
+            storage.register_participant("carol", &[ALICE.to_uppercase()]),
+            Err(RegistryError::AddressInUse {
+                address: ALICE.to_string(),
+                participant_id: "alice".to_string(),
+            })
+        );
+        assert!(matches!(
+            storage.add_participant_address("alice", "addr-alice", None),
+            Err(RegistryError::InvalidInput(_))
+        ));
+        assert_eq!(
+            storage.add_participant_address("alice", &address(1), Some(0)),
+            Err(RegistryError::PositionInUse {
+                participant_id: "alice".to_string(),
+                position: 0,
+            })
+        );
+    }
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+#[ignore = "needs BDLD_TEST_POSTGRES_URL"]
+fn legacy_addresses_are_canonicalized_and_positions_renumbered() {
+    let config = fresh_database("positions");
+    PostgresParticipantRegistry::from_config(config.clone()).unwrap();
+    // Back to schema version 4, with rows it allowed
+    let mut client = config.connect(NoTls).unwrap();
+    client
+        .batch_execute(&format!(
+            "DROP INDEX participant_addresses_position;
+             ALTER TABLE participants DROP COLUMN deactivated_height;
+             ALTER TABLE pool_utxos DROP COLUMN reserved_by;
+             DROP TABLE payout_outputs;
+             INSERT INTO participants (participant_id) VALUES ('alice');
+             INSERT INTO participant_addresses (participant_id, address, position)
+                VALUES ('alice', 'addr-alice', 0), ('alice', '{}', 0),
+                       ('alice', '1BvBMSEYstWetqTMn5Au4m4GFg7xJaNVN2', 4);
+             UPDATE schema_version SET version = 4;",
+            BOB.to_uppercase()
+        ))
+        .unwrap();
+    drop(client);
+
+    let registry = PostgresParticipantRegistry::from_config(config).unwrap();
+    let alice = registry.get_participant("alice").unwrap().unwrap();
+    let positions: Vec<(&str, u32)> = alice
+        .addresses
+        .iter()
+        .map(|a| (a.address.as_str(), a.position))
+        .collect();
+    assert_eq!(
+        positions,
+        [
+            ("addr-alice", 0),
+            (BOB, 1),
+            ("1BvBMSEYstWetqTMn5Au4m4GFg7xJaNVN2", 2),
+        ]
+    );
+}
+
+#[test]
+#[ignore = "needs BDLD_TEST_POSTGRES_URL"]
+fn replicas_share_state_and_one_audit_chain() {
+    let config = fresh_database("replicas");
+    // Replicas starting together migrate one at a time
+    let replicas: Vec<Arc<PostgresParticipantRegistry>> = (0..2)
+        .map(|_| {
+            let config = config.clone();
+            std::thread::spawn(move || PostgresParticipantRegistry::from_config(config))
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|handle| Arc::new(handle.join().unwrap().unwrap()))
+        .collect();
+
+    let writers: Vec<_> = (0..8)
+        .map(|i| {
+            let replica = replicas[i % 2].clone();
+            std::thread::spawn(move || {
+                replica
+                    .register_participant(&format!("p{i}"), &[address(i as u8)])
+                    .unwrap();
+                replica.record_parameter("network", "bitcoin").unwrap()
+            })
+        })
+        .collect();
+    let logged = writers
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .filter(|appended| *appended)
+        .count();
+    assert_eq!(logged, 1);
+
+    let page = replicas[1].list_participants(0, 100, false).unwrap();
+    assert_eq!(page.total_count, 8);
+    assert_eq!(replicas[0].addresses_for("p5").unwrap(), [address(5)]);
+    assert_eq!(
+        replicas[1].register_participant("q", &[address(2)]),
+        Err(RegistryError::AddressInUse {
+            address: address(2),
+            participant_id: "p2".to_string(),
+        })
+    );
+    let status = replicas[0].verify_audit_chain().unwrap();
+    assert_eq!(status.entries, 9);
+    assert_eq!(replicas[1].verify_audit_chain().unwrap(), status);
+}
+
+#[test]
+#[ignore = "needs BDLD_TEST_POSTGRES_URL"]
+fn replicas_reserve_distinct_change_indexes() {
+    let config = fresh_database("change_index");
+    let replicas: Vec<Arc<PostgresParticipantRegistry>> = (0..2)
+        .map(|_| Arc::new(PostgresParticipantRegistry::from_config(config.clone()).unwrap()))
+        .collect();
+
+    let reservers: Vec<_> = (0..8)
+        .map(|i| {
+            let replica = replicas[i % 2].clone();
+            std::thread::spawn(move || {
+                (0..5)
+                    .map(|_| replica.reserve_change_index().unwrap())
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let reserved: BTreeSet<u32> = reservers
+        .into_iter()
+        .flat_map(|handle| handle.join().unwrap())
+        .collect();
+    assert_eq!(reserved, (0..40).collect());
+    assert_eq!(replicas[1].next_change_index().unwrap(), 40);
+}
+
+#[test]
+#[ignore = "needs BDLD_TEST_POSTGRES_URL"]
+fn replicas_take_turns_holding_the_payout_lock() {
+    let config = fresh_database("payout_lock");
+    let first = PostgresParticipantRegistry::from_config(config.clone()).unwrap();
+    let second = Arc::new(PostgresParticipantRegistry::from_config(config).unwrap());
+
+    let held = first.lock_payouts().unwrap();
+    // The lock has its own session; the registry still reads and writes
+    first.register_participant("alice", &[]).unwrap();
+    let (acquired, waiting) = mpsc::channel();
+    let waiter = {
+        let second = second.clone();
+        std::thread::spawn(move || {
+            let _held = second.lock_payouts().unwrap();
+            acquired.send(()).unwrap();
+        })
+    };
+    assert!(waiting.recv_timeout(StdDuration::from_millis(300)).is_err());
+    drop(held);
+    waiting.recv_timeout(StdDuration::from_secs(10)).unwrap();
+    waiter.join().unwrap();
+
+    // Released on drop, so it can be taken again
+    drop(first.lock_payouts().unwrap());
+}
+
+#[cfg(feature = "api")]
+#[test]
+#[ignore = "needs BDLD_TEST_POSTGRES_URL"]
+fn api_nodes_never_spend_the_same_pool_utxo() {
+    use bitcoin_digital_labor_derivative::api::GlobalNode;
+
+    let config = fresh_database("payouts");
+    let node = || {
+        GlobalNode::new().with_storage(Arc::new(
+            PostgresParticipantRegistry::from_config(config.clone()).unwrap(),
+        ))
+    };
+    let first = node();
+    let pool_script = Address::from_str(POOL_ADDRESS)
+        .unwrap()
+        .assume_checked()
+        .script_pubkey();
+    first
+        .set_pool_utxos(vec![PoolUtxo::new(outpoint(7), 10_000_000, pool_script)])
+        .unwrap();
+    // Both nodes start out caching the one UTXO
+    let nodes = [first, node()];
+    assert_eq!(nodes[1].get_pool_utxos().len(), 1);
+
+    let payers: Vec<_> = (0..4)
+        .map(|i| {
+            let node = nodes[i % 2].clone();
+            std::thread::spawn(move || {
+                node.execute_payout(&PayoutRequest {
+                    recipient_address: ALICE.to_string(),
+                    amount_sats: 100_000,
+                    funding_utxo_txid: None,
+                    funding_utxo_vout: None,
+                    funding_utxo_value_sats: None,
+                    change_address: Some(POOL_ADDRESS.to_string()),
+                    fee_rate_sats_per_vbyte: Some(1),
+                    dry_run: Some(false),
+                })
+                .is_ok()
+            })
+        })
+        .collect();
+    let paid = payers
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .filter(|paid| *paid)
+        .count();
+    assert_eq!(paid, 1);
+
+    let registry = nodes[1].participant_registry.as_ref().unwrap();
+    assert!(registry.pool_utxos().unwrap().is_empty());
+    let unsigned = registry
+        .get_all_payouts()
+        .unwrap()
+        .into_iter()
+        .filter(|payout| payout.status == PayoutStatus::UnsignedCreated)
+        .count();
+    assert_eq!(unsigned, 1);
+}
+
+#[test]
+#[ignore = "needs BDLD_TEST_POSTGRES_URL"]
+fn audit_log_refuses_rewrites() {
+    let config = fresh_database("tamper");
+    let registry = PostgresParticipantRegistry::from_config(config.clone()).unwrap();
+    registry.register_participant("alice", &[]).unwrap();
+    registry.deactivate_participant("alice", 800_000).unwrap();
+
+    let mut client = config.connect(NoTls).unwrap();
+    for statement in [
+        "UPDATE audit_log SET payload_json = '{}' WHERE seq = 1",
+        "DELETE FROM audit_log WHERE seq = 2",
+        "TRUNCATE audit_log",
+    ] {
+        let err = client.batch_execute(statement).unwrap_err();
+        let message = err.as_db_error().map(|e| e.message().to_string());
+        assert_eq!(message.as_deref(), Some("audit_log is append-only"));
+    }
+    assert_eq!(registry.verify_audit_chain().unwrap().entries, 2);
+
+    // Reconnecting to an up-to-date database leaves it as it is
+    drop(registry);
+    let registry = PostgresParticipantRegistry::from_config(config).unwrap();
+    assert!(!registry.get_participant("alice").unwrap().unwrap().active);
+}
+
+#[cfg(feature = "api")]
+#[tokio::test]
+#[ignore = "needs BDLD_TEST_POSTGRES_URL"]
+async fn api_nodes_share_a_postgres_registry() {
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use bitcoin_digital_labor_derivative::api::node::NodeConfiguration;
+    use bitcoin_digital_labor_derivative::api::{create_router, GlobalNode};
+    use serde_json::{json, Value};
+    use tower::Service;
+
+    let config = fresh_database("api");
+    let node = || {
+        GlobalNode::new()
+            .with_config(NodeConfiguration {
+                admin_token: Some("token".into()),
+                ..Default::default()
+            })
+            .with_storage(Arc::new(
+                PostgresParticipantRegistry::from_config(config.clone()).unwrap(),
+            ))
+    };
+    let (first, second) = (node(), node());
+
+    let send = |node: &GlobalNode, method: &str, uri: &str, body: Option<Value>| {
+        let req = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::AUTHORIZATION, "Bearer token")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+            .unwrap();
+        let mut router = create_router(node.clone());
+        async move {
+            std::future::poll_fn(|cx| Service::<Request<Body>>::poll_ready(&mut router, cx))
+                .await
+                .unwrap();
+            let res = router.call(req).await.unwrap();
+            let status = res.status();
+            let bytes = axum::body::to_bytes(res.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            (status, serde_json::from_slice::<Value>(&bytes).unwrap())
+        }
+    };
+
+    let (status, _) = send(
+        &first,
+        "POST",
+        "/api/v1/participants",
+        Some(json!({ "participant_id": "alice", "addresses": [ALICE] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, body) = send(&second, "GET", "/api/v1/participants/alice", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["addresses"][0]["address"], ALICE);
+    let (status, body) = send(&second, "GET", "/api/v1/audit/verify", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["entries"], 1);
+}
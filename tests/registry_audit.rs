@@ -0,0 +1,196 @@
+mod common;
+
+use bitcoin::hashes::Hash;
+use bitcoin::{Address, OutPoint, Txid};
+use bitcoin_digital_labor_derivative::audit_log::AuditEvent;
+use bitcoin_digital_labor_derivative::disbursement::{
+    DisbursementConfig, DisbursementEngine, PayoutRequest, PayoutStatus, PoolUtxo,
+};
+use bitcoin_digital_labor_derivative::sqlite_participant_registry::{
+    RegistryError, SqliteParticipantRegistry,
+};
+use bitcoin_digital_labor_derivative::storage::{AuditAnchorFile, RegistryStorage};
+use common::{db_path, temp_db};
+use rusqlite::Connection;
+use std::fs;
+use std::str::FromStr;
+
+const POOL_ADDRESS: &str = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+const RECIPIENT: &str = "bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh";
+
+/// Registers a participant, sets its trust, and creates a payout that fails.
+fn populate(registry: &SqliteParticipantRegistry) {
+    registry
+        .register_participant("alice", &[RECIPIENT.to_string()])
+        .unwrap();
+    registry.record_trust("alice", 800_000, 1.2).unwrap();
+
+    let pool_utxo = PoolUtxo::new(
+        OutPoint {
+            txid: Txid::from_slice(&[1; 32]).unwrap(),
+            vout: 0,
+        },
+        1_000_000,
+        Address::from_str(POOL_ADDRESS)
+            .unwrap()
+            .assume_checked()
+            .script_pubkey(),
+    );
+    let req = PayoutRequest {
+        recipient_address: RECIPIENT.to_string(),
+        amount_sats: 50_000,
+        funding_utxo_txid: None,
+        funding_utxo_vout: None,
+        funding_utxo_value_sats: None,
+        change_address: None,
+        fee_rate_sats_per_vbyte: Some(1),
+        dry_run: Some(false),
+    };
+    let mut payout = DisbursementEngine::new(DisbursementConfig {
+        change_address: Some(POOL_ADDRESS.to_string()),
+        ..Default::default()
+    })
+    .create_unsigned_payout("payout-audit".to_string(), &req, &[pool_utxo])
+    .unwrap();
+    registry.save_payout(&payout).unwrap();
+    let failed = payout.transition(PayoutStatus::Failed, None).unwrap();
+    registry
+        .save_payout_transitions(&payout, &[failed])
+        .unwrap();
+}
+
+#[test]
+fn economic_changes_are_logged_in_a_verifiable_chain() {
+    let path = temp_db("chain");
+    let registry = SqliteParticipantRegistry::open_read_write(&path).unwrap();
+    populate(&registry);
+
+    // Only a changed parameter value is logged again
+    assert!(registry.record_parameter("network", "bitcoin").unwrap());
+    assert!(!registry.record_parameter("network", "bitcoin").unwrap());
+    assert!(registry.record_parameter("network", "signet").unwrap());
+
+    let kinds: Vec<String> = registry
+        .audit_entries(0, 100)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.event_type)
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            "participant_registered",
+            "parameter_changed",
+            "payout_created",
+            "payout_status_changed",
+            "parameter_changed",
+            "parameter_changed",
+        ]
+    );
+    let entries = registry.audit_entries(3, 1).unwrap();
+    assert_eq!(entries[0].seq, 4);
+    assert_eq!(
+        entries[0].event().unwrap(),
+        AuditEvent::PayoutStatusChanged {
+            payout_id: "payout-audit".into(),
+            from: "unsigned_created".into(),
+            to: "failed".into(),
+            confirmations: 0,
+            detail: None,
+        }
+    );
+
+    let status = registry.verify_audit_chain().unwrap();
+    assert_eq!(status.entries, 6);
+    drop(registry);
+
+    // Verifiable read-only as well
+    let read_only = SqliteParticipantRegistry::open(&path).unwrap();
+    assert_eq!(read_only.verify_audit_chain().unwrap(), status);
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn tampering_with_the_log_is_refused_or_detected() {
+    let path = temp_db("tamper");
+    let registry = SqliteParticipantRegistry::open_read_write(&path).unwrap();
+    populate(&registry);
+    drop(registry);
+
+    let conn = Connection::open(&path).unwrap();
+    let rewrite = "UPDATE audit_log SET payload_json = \
+                   replace(payload_json, '50000', '5000000') WHERE seq = 3";
+    assert!(conn.execute(rewrite, []).is_err());
+    assert!(conn
+        .execute("DELETE FROM audit_log WHERE seq = 2", [])
+        .is_err());
+
+    // With the guards dropped the edit goes through, but breaks the chain
+    conn.execute_batch(
+        "DROP TRIGGER audit_log_no_update;
+         DROP TRIGGER audit_log_no_delete;",
+    )
+    .unwrap();
+    conn.execute(rewrite, []).unwrap();
+    drop(conn);
+
+    let registry = SqliteParticipantRegistry::open(&path).unwrap();
+    match registry.verify_audit_chain() {
+        Err(RegistryError::AuditChainBroken(err)) => assert_eq!(err.seq, 3),
+        other => panic!("expected a broken chain, got {other:?}"),
+    }
+    drop(registry);
+
+    let conn = Connection::open(&path).unwrap();
+    conn.execute("DELETE FROM audit_log WHERE seq IN (2, 3)", [])
+        .unwrap();
+    drop(conn);
+    let registry = SqliteParticipantRegistry::open(&path).unwrap();
+    match registry.verify_audit_chain() {
+        Err(RegistryError::AuditChainBroken(err)) => assert_eq!(err.seq, 4),
+        other => panic!("expected a broken chain, got {other:?}"),
+    }
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn an_anchor_outside_the_database_catches_truncation() {
+    let path = temp_db("anchor");
+    let anchor = AuditAnchorFile::new(db_path("anchor_head"));
+    let _ = fs::remove_file(anchor.path());
+    let registry = SqliteParticipantRegistry::open_read_write(&path).unwrap();
+    populate(&registry);
+
+    let status = anchor.advance(&registry).unwrap();
+    assert_eq!(anchor.load().unwrap(), Some(status.clone()));
+    registry
+        .register_participant("bob", &[POOL_ADDRESS.to_string()])
+        .unwrap();
+    assert_eq!(
+        anchor.advance(&registry).unwrap().entries,
+        status.entries + 1
+    );
+    drop(registry);
+
+    // Cutting the tail leaves a valid chain, but not one reaching the anchor
+    let conn = Connection::open(&path).unwrap();
+    conn.execute_batch(
+        "DROP TRIGGER audit_log_no_delete;
+         DELETE FROM audit_log WHERE seq > 2;",
+    )
+    .unwrap();
+    drop(conn);
+    let registry = SqliteParticipantRegistry::open(&path).unwrap();
+    assert_eq!(registry.verify_audit_chain().unwrap().entries, 2);
+    match anchor.advance(&registry) {
+        Err(RegistryError::AuditChainBroken(err)) => assert_eq!(err.seq, 3),
+        other => panic!("expected a broken chain, got {other:?}"),
+    }
+    // A failed check leaves the anchor where it was
+    assert_eq!(anchor.load().unwrap().unwrap().entries, status.entries + 1);
+
+    let _ = fs::remove_file(anchor.path());
+    let _ = fs::remove_file(&path);
+}
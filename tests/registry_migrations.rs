@@ -0,0 +1,217 @@
+mod common;
+
+use bitcoin_digital_labor_derivative::disbursement::AileeTrustAudit;
+use bitcoin_digital_labor_derivative::sqlite_participant_registry::{
+    SqliteParticipantRegistry, SCHEMA_VERSION,
+};
+use bitcoin_digital_labor_derivative::storage::RegistryStorage;
+use bitcoin_digital_labor_derivative::velocity_analyzer::ParticipantRegistry;
+use common::temp_db;
+use rusqlite::Connection;
+use std::fs;
+use std::path::PathBuf;
+
+fn user_version(path: &PathBuf) -> u32 {
+    Connection::open(path)
+        .unwrap()
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .unwrap()
+}
+
+/// Schema version 1: participants and the original payouts columns.
+fn write_v1_fixture(path: &PathBuf) {
+    let conn = Connection::open(path).expect("open sqlite");
+    conn.execute_batch(
+        "CREATE TABLE participants (participant_id TEXT PRIMARY KEY);
+         CREATE TABLE participant_addresses (
+            participant_id TEXT NOT NULL,
+            address TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            PRIMARY KEY (participant_id, address),
+            FOREIGN KEY (participant_id) REFERENCES participants(participant_id)
+         );
+         CREATE TABLE payouts (
+            payout_id TEXT PRIMARY KEY,
+            recipient_address TEXT NOT NULL,
+            amount_sats INTEGER NOT NULL,
+            fee_sats INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            psbt_base64 TEXT NOT NULL,
+            raw_tx_hex TEXT NOT NULL,
+            txid TEXT NOT NULL,
+            timestamp TEXT NOT NULL,
+            trust_audit_json TEXT NOT NULL,
+            is_dry_run INTEGER NOT NULL
+         );
+         INSERT INTO participants (participant_id) VALUES ('alice');
+         INSERT INTO participant_addresses (participant_id, address, position)
+            VALUES ('alice', 'addr-alice', 0);
+         INSERT INTO payouts VALUES (
+            'payout-v1', 'addr-alice', 50000, 300, 'broadcast', 'cHNidP8=', '00', 'ab',
+            '2024-01-01T00:00:00+00:00', '{}', 0
+         );
+         PRAGMA user_version = 1;",
+    )
+    .expect("setup v1 fixture");
+}
+
+#[test]
+fn v1_fixture_migrates_forward_and_keeps_data() {
+    let path = temp_db("v1");
+    write_v1_fixture(&path);
+
+    // A read-only handle cannot migrate, so a stale schema is refused.
+    let err = SqliteParticipantRegistry::open(&path).unwrap_err();
+    assert!(err.to_string().contains("older than"), "{err}");
+
+    let registry = SqliteParticipantRegistry::open_read_write(&path).unwrap();
+    assert_eq!(
+        registry.addresses_for("alice").unwrap(),
+        vec!["addr-alice".to_string()]
+    );
+    let payout = registry.get_payout_by_id("payout-v1").unwrap().unwrap();
+    assert_eq!(payout.amount_sats, 50_000);
+    assert_eq!(payout.confirmations, 0);
+    assert_eq!(
+        payout.trust_audit.policy_version,
+        AileeTrustAudit::UNRECORDED_VERSION
+    );
+    assert!(!payout.trust_audit.passed);
+    assert!(payout.signing.is_none());
+    assert!(registry
+        .get_payout_transitions("payout-v1")
+        .unwrap()
+        .is_empty());
+    drop(registry);
+
+    assert_eq!(user_version(&path), SCHEMA_VERSION);
+    // Re-opening is a no-op, and read-only opens now validate.
+    SqliteParticipantRegistry::open_read_write(&path).unwrap();
+    SqliteParticipantRegistry::open(&path).unwrap();
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn unversioned_database_with_later_columns_is_adopted() {
+    let path = temp_db("unversioned");
+    // Created by a pre-versioning binary that already added every column.
+    write_v1_fixture(&path);
+    Connection::open(&path)
+        .unwrap()
+        .execute_batch(
+            "ALTER TABLE payouts ADD COLUMN confirmations INTEGER NOT NULL DEFAULT 0;
+             ALTER TABLE payouts ADD COLUMN signed_psbt_base64 TEXT;
+             ALTER TABLE payouts ADD COLUMN final_tx_hex TEXT;
+             ALTER TABLE payouts ADD COLUMN fee_bump_of_json TEXT;
+             ALTER TABLE payouts ADD COLUMN fee_bumped_by_json TEXT;
+             ALTER TABLE payouts ADD COLUMN signing_json TEXT;
+             CREATE TABLE payout_transitions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                payout_id TEXT NOT NULL,
+                from_status TEXT NOT NULL,
+                to_status TEXT NOT NULL,
+                confirmations INTEGER NOT NULL,
+                detail TEXT,
+                timestamp TEXT NOT NULL
+             );
+             PRAGMA user_version = 0;",
+        )
+        .unwrap();
+
+    SqliteParticipantRegistry::open_read_write(&path).unwrap();
+    assert_eq!(user_version(&path), SCHEMA_VERSION);
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn newer_schema_is_refused_in_both_modes() {
+    let path = temp_db("newer");
+    drop(SqliteParticipantRegistry::open_read_write(&path).unwrap());
+    Connection::open(&path)
+        .unwrap()
+        .pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+        .unwrap();
+
+    for result in [
+        SqliteParticipantRegistry::open(&path),
+        SqliteParticipantRegistry::open_read_write(&path),
+    ] {
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("newer than"), "{err}");
+    }
+    // Nothing was rewritten.
+    assert_eq!(user_version(&path), SCHEMA_VERSION + 1);
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn read_only_open_validates_payout_columns() {
+    let path = temp_db("payout_columns");
+    write_v1_fixture(&path);
+    // Unversioned, but with a payouts table the queries cannot read.
+    Connection::open(&path)
+        .unwrap()
+        .pragma_update(None, "user_version", 0)
+        .unwrap();
+
+    let err = SqliteParticipantRegistry::open(&path).unwrap_err();
+    assert!(err.to_string().contains("on payouts"), "{err}");
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn address_reuse_blocks_the_unique_address_migration() {
+    let path = temp_db("reuse");
+    write_v1_fixture(&path);
+    Connection::open(&path)
+        .unwrap()
+        .execute_batch(
+            "INSERT INTO participants (participant_id) VALUES ('bob');
+             INSERT INTO participant_addresses (participant_id, address, position)
+                VALUES ('bob', 'addr-alice', 0);",
+        )
+        .unwrap();
+
+    let err = SqliteParticipantRegistry::open_read_write(&path).unwrap_err();
+    assert!(err.to_string().contains("unique addresses"), "{err}");
+    // Migrations 2-4 committed; migration 5 rolled back.
+    assert_eq!(user_version(&path), 4);
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn legacy_addresses_are_canonicalized_and_positions_renumbered() {
+    let path = temp_db("positions");
+    write_v1_fixture(&path);
+    Connection::open(&path)
+        .unwrap()
+        .execute_batch(
+            "INSERT INTO participant_addresses (participant_id, address, position)
+                VALUES ('alice', 'BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4', 0),
+                       ('alice', '1BvBMSEYstWetqTMn5Au4m4GFg7xJaNVN2', 4);",
+        )
+        .unwrap();
+
+    let registry = SqliteParticipantRegistry::open_read_write(&path).unwrap();
+    let alice = registry.get_participant("alice").unwrap().unwrap();
+    let positions: Vec<(&str, u32)> = alice
+        .addresses
+        .iter()
+        .map(|a| (a.address.as_str(), a.position))
+        .collect();
+    assert_eq!(
+        positions,
+        [
+            ("addr-alice", 0),
+            ("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", 1),
+            ("1BvBMSEYstWetqTMn5Au4m4GFg7xJaNVN2", 2),
+        ]
+    );
+
+    let _ = fs::remove_file(&path);
+}
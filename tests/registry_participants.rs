@@ -0,0 +1,207 @@
+mod common;
+
+use bitcoin::Network;
+use bitcoin_digital_labor_derivative::sqlite_participant_registry::{
+    ParticipantAddress, RegistryError, SqliteParticipantRegistry,
+};
+use bitcoin_digital_labor_derivative::storage::RegistryStorage;
+use bitcoin_digital_labor_derivative::velocity_analyzer::{ParticipantRegistry, VelocityError};
+use common::{address, temp_db};
+use std::fs;
+
+fn addresses(list: &[u8]) -> Vec<String> {
+    list.iter().map(|n| address(*n)).collect()
+}
+
+#[test]
+fn register_and_edit_addresses_in_position_order() {
+    let path = temp_db("edit");
+    let registry = SqliteParticipantRegistry::open_read_write(&path).unwrap();
+
+    let alice = registry
+        .register_participant("alice", &addresses(&[0, 1]))
+        .unwrap();
+    assert!(alice.active);
+    assert!(alice.registered_at.is_some());
+
+    // Appended after the last position, or placed explicitly at a free one
+    registry
+        .add_participant_address("alice", &address(2), None)
+        .unwrap();
+    assert_eq!(
+        registry.add_participant_address("alice", &address(3), Some(0)),
+        Err(RegistryError::PositionInUse {
+            participant_id: "alice".into(),
+            position: 0,
+        })
+    );
+    let alice = registry
+        .add_participant_address("alice", &address(3), Some(7))
+        .unwrap();
+    assert_eq!(
+        alice.addresses[2],
+        ParticipantAddress {
+            address: address(2),
+            position: 2
+        }
+    );
+    assert_eq!(
+        registry.addresses_for("alice").unwrap(),
+        addresses(&[0, 1, 2, 3])
+    );
+
+    let alice = registry
+        .remove_participant_address("alice", &address(1))
+        .unwrap();
+    assert_eq!(alice.addresses.len(), 3);
+    assert_eq!(
+        registry.remove_participant_address("alice", &address(1)),
+        Err(RegistryError::AddressNotFound {
+            participant_id: "alice".into(),
+            address: address(1),
+        })
+    );
+    // The freed position can be reused
+    registry
+        .add_participant_address("alice", &address(4), Some(1))
+        .unwrap();
+    assert_eq!(
+        registry.register_participant("alice", &[]),
+        Err(RegistryError::ParticipantExists("alice".into()))
+    );
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn addresses_stay_unique_across_participants_even_after_deactivation() {
+    let path = temp_db("unique");
+    let registry = SqliteParticipantRegistry::open_read_write(&path).unwrap();
+    registry
+        .register_participant("alice", &addresses(&[0]))
+        .unwrap();
+
+    let in_use = RegistryError::AddressInUse {
+        address: address(0),
+        participant_id: "alice".into(),
+    };
+    assert_eq!(
+        registry.register_participant("bob", &addresses(&[1, 0])),
+        Err(in_use.clone())
+    );
+    // The failed registration left nothing behind
+    assert_eq!(registry.get_participant("bob").unwrap(), None);
+
+    registry
+        .register_participant("bob", &addresses(&[1]))
+        .unwrap();
+    assert_eq!(
+        registry.add_participant_address("bob", &address(0), None),
+        Err(in_use.clone())
+    );
+
+    let alice = registry.deactivate_participant("alice", 800_000).unwrap();
+    assert!(!alice.active);
+    assert!(matches!(
+        registry.addresses_for("alice"),
+        Err(VelocityError::ParticipantNotFound)
+    ));
+    assert_eq!(
+        registry.add_participant_address("bob", &address(0), None),
+        Err(in_use)
+    );
+    assert_eq!(
+        registry.remove_participant_address("alice", &address(0)),
+        Err(RegistryError::ParticipantInactive("alice".into()))
+    );
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn addresses_are_stored_canonically_for_the_registry_network() {
+    let path = temp_db("canonical");
+    let registry = SqliteParticipantRegistry::open_read_write(&path).unwrap();
+
+    // Bech32 is case-insensitive; the lower-case form is stored
+    let alice = registry
+        .register_participant("alice", &[address(0).to_uppercase()])
+        .unwrap();
+    assert_eq!(alice.addresses[0].address, address(0));
+    assert_eq!(
+        registry.add_participant_address("alice", &address(0), None),
+        Err(RegistryError::AddressInUse {
+            address: address(0),
+            participant_id: "alice".into(),
+        })
+    );
+    assert!(matches!(
+        registry.register_participant("bob", &[address(1), address(1).to_uppercase()]),
+        Err(RegistryError::InvalidInput(_))
+    ));
+    let alice = registry
+        .remove_participant_address("alice", &address(0).to_uppercase())
+        .unwrap();
+    assert!(alice.addresses.is_empty());
+
+    // Malformed addresses and addresses for another network are refused
+    let testnet = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+    for invalid in ["addr-alice", testnet] {
+        assert!(matches!(
+            registry.add_participant_address("alice", invalid, None),
+            Err(RegistryError::InvalidInput(_))
+        ));
+    }
+    drop(registry);
+
+    let registry = SqliteParticipantRegistry::open_read_write(&path)
+        .unwrap()
+        .with_network(Network::Testnet);
+    registry
+        .add_participant_address("alice", testnet, None)
+        .unwrap();
+    assert!(matches!(
+        registry.add_participant_address("alice", &address(0), None),
+        Err(RegistryError::InvalidInput(_))
+    ));
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn list_pages_by_id_and_read_only_refuses_writes() {
+    let path = temp_db("list");
+    let registry = SqliteParticipantRegistry::open_read_write(&path).unwrap();
+    for (n, id) in (0..).zip(["carol", "alice", "dave", "bob"]) {
+        registry.register_participant(id, &addresses(&[n])).unwrap();
+    }
+    registry.deactivate_participant("bob", 800_000).unwrap();
+
+    let page = registry.list_participants(0, 2, false).unwrap();
+    assert_eq!(page.total_count, 3);
+    let ids: Vec<&str> = page
+        .participants
+        .iter()
+        .map(|p| p.participant_id.as_str())
+        .collect();
+    assert_eq!(ids, ["alice", "carol"]);
+    let page = registry.list_participants(1, 2, true).unwrap();
+    assert_eq!(page.total_count, 4);
+    assert_eq!(page.participants[0].participant_id, "carol");
+    drop(registry);
+
+    let read_only = SqliteParticipantRegistry::open(&path).unwrap();
+    assert_eq!(
+        read_only
+            .list_participants(0, 10, true)
+            .unwrap()
+            .total_count,
+        4
+    );
+    assert_eq!(
+        read_only.register_participant("erin", &[]),
+        Err(RegistryError::ReadOnly)
+    );
+
+    let _ = fs::remove_file(&path);
+}
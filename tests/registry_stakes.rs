@@ -0,0 +1,115 @@
+mod common;
+
+use bitcoin::ScriptBuf;
+use bitcoin_digital_labor_derivative::sqlite_participant_registry::{
+    RegistryError, SqliteParticipantRegistry,
+};
+use bitcoin_digital_labor_derivative::stake::StakeVerification;
+use bitcoin_digital_labor_derivative::storage::RegistryStorage;
+use common::{lock, outpoint, temp_db};
+use std::fs;
+use std::path::PathBuf;
+
+fn registry_with(name: &str, participants: &[&str]) -> (SqliteParticipantRegistry, PathBuf) {
+    let path = temp_db(name);
+    let registry = SqliteParticipantRegistry::open_read_write(&path).unwrap();
+    for id in participants {
+        registry.register_participant(id, &[]).unwrap();
+    }
+    (registry, path)
+}
+
+#[test]
+fn stake_lifecycle_is_persisted() {
+    let (registry, path) = registry_with("lifecycle", &["alice"]);
+
+    let stake = registry
+        .record_stake("alice", outpoint(1), 50_000_000, &lock(850_000))
+        .unwrap();
+    assert_eq!(stake.lock_height, 850_000);
+    assert_eq!(stake.verification, StakeVerification::Pending);
+    assert_eq!(
+        registry.record_stake("alice", outpoint(1), 1, &lock(850_000)),
+        Err(RegistryError::StakeExists(outpoint(1)))
+    );
+    assert!(matches!(
+        registry.record_stake("alice", outpoint(2), 1, &ScriptBuf::new()),
+        Err(RegistryError::InvalidInput(_))
+    ));
+
+    registry.verify_stake(&outpoint(1), 800_010).unwrap();
+    assert!(matches!(
+        registry.mark_stake_spent(&outpoint(1), 800_000),
+        Err(RegistryError::InvalidInput(_))
+    ));
+    registry.mark_stake_spent(&outpoint(1), 860_000).unwrap();
+    drop(registry);
+
+    let reopened = SqliteParticipantRegistry::open(&path).unwrap();
+    let stakes = reopened.stakes_for("alice").unwrap();
+    assert_eq!(stakes.len(), 1);
+    assert_eq!(stakes[0].witness_script, lock(850_000));
+    assert_eq!(stakes[0].verification, StakeVerification::Verified);
+    assert_eq!(stakes[0].confirmed_height, Some(800_010));
+    assert_eq!(stakes[0].spent_height, Some(860_000));
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn snapshots_reflect_stakes_and_trust_at_height() {
+    let (registry, path) = registry_with("snapshots", &["alice", "bob", "carol"]);
+    registry
+        .record_stake("alice", outpoint(1), 30_000_000, &lock(900_000))
+        .unwrap();
+    registry
+        .record_stake("alice", outpoint(2), 20_000_000, &lock(900_000))
+        .unwrap();
+    registry
+        .record_stake("bob", outpoint(3), 70_000_000, &lock(900_000))
+        .unwrap();
+    registry
+        .record_stake("carol", outpoint(4), 10_000_000, &lock(900_000))
+        .unwrap();
+    registry.verify_stake(&outpoint(1), 800_000).unwrap();
+    registry.verify_stake(&outpoint(2), 800_100).unwrap();
+    registry.verify_stake(&outpoint(3), 800_000).unwrap();
+    registry.mark_stake_spent(&outpoint(3), 800_200).unwrap();
+    // Carol's stake never verifies
+    registry.reject_stake(&outpoint(4)).unwrap();
+    registry.record_trust("alice", 800_050, 1.3).unwrap();
+    registry.record_trust("alice", 800_150, 1.6).unwrap();
+
+    let at = |height| {
+        registry
+            .participant_snapshots(height)
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.participant_id, s.stake_amount_sats, s.trust_coefficient))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(at(799_999), vec![]);
+    assert_eq!(
+        at(800_000),
+        vec![
+            ("alice".to_string(), 30_000_000, 1.0),
+            ("bob".to_string(), 70_000_000, 1.0)
+        ]
+    );
+    assert_eq!(
+        at(800_100),
+        vec![
+            ("alice".to_string(), 50_000_000, 1.3),
+            ("bob".to_string(), 70_000_000, 1.0)
+        ]
+    );
+    assert_eq!(at(800_200), vec![("alice".to_string(), 50_000_000, 1.6)]);
+
+    // Deactivation leaves earlier snapshots as they were
+    let alice = registry.deactivate_participant("alice", 800_300).unwrap();
+    assert_eq!(alice.deactivated_height, Some(800_300));
+    assert_eq!(at(800_200), vec![("alice".to_string(), 50_000_000, 1.6)]);
+    assert_eq!(at(800_300), vec![]);
+
+    let _ = fs::remove_file(&path);
+}
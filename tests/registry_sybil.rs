@@ -1,15 +1,13 @@
+mod common;
+
 use bitcoin_digital_labor_derivative::sqlite_participant_registry::SqliteParticipantRegistry;
+use common::temp_db;
 use rusqlite::Connection;
-use std::env;
 use std::fs;
 
 #[test]
 fn address_reuse_fails_open() {
-    let path = env::temp_dir().join(format!(
-        "participant_registry_sybil_{}.db",
-        std::process::id()
-    ));
-    let _ = fs::remove_file(&path);
+    let path = temp_db("sybil");
 
     let conn = Connection::open(&path).expect("open sqlite");
     conn.execute_batch(